@@ -0,0 +1,76 @@
+use crate::ProfileAccounts;
+use prettytable::{format, Cell, Row, Table};
+use serde::Serialize;
+
+/// A discovered account as the `accounts` subcommand lists it.
+#[derive(Serialize, Debug)]
+pub struct ListedAccount {
+    pub profile: String,
+    pub account_id: String,
+    pub account_name: String,
+    /// `ACTIVE`, `SUSPENDED` or `PENDING_CLOSURE`; unknown for mapped and STS-discovered accounts.
+    pub status: Option<String>,
+    /// Date the account joined the organization (`YYYY-MM-DD`).
+    pub joined: Option<String>,
+    /// OU path with `--ou-id` or `--group-by-ou`.
+    pub ou_path: Option<String>,
+    /// Other profiles that found the account; it is only reported under `profile`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub also_via: Vec<String>,
+}
+
+/// Flattens the discovered accounts, in discovery order.
+pub fn build(discovered: &[ProfileAccounts]) -> Vec<ListedAccount> {
+    discovered
+        .iter()
+        .flat_map(|p| {
+            p.accounts.iter().map(move |account| {
+                let account_id = account.id.clone().unwrap_or_default();
+                ListedAccount {
+                    profile: p.profile.clone(),
+                    account_name: account.name.clone().unwrap_or("N/A".to_string()),
+                    status: account.status().map(|status| status.as_str().to_string()),
+                    joined: account
+                        .joined_timestamp()
+                        .and_then(|joined| chrono::DateTime::from_timestamp(joined.secs(), 0))
+                        .map(|joined| joined.format("%Y-%m-%d").to_string()),
+                    ou_path: p.ou_paths.get(&account_id).cloned(),
+                    also_via: p.also_via.get(&account_id).cloned().unwrap_or_default(),
+                    account_id,
+                }
+            })
+        })
+        .collect()
+}
+
+/// Prints the accounts as one table, with a column for other profiles when any account has them.
+pub fn print(accounts: &[ListedAccount]) {
+    let shared = accounts.iter().filter(|account| !account.also_via.is_empty()).count();
+    let mut titles = vec!["Profile", "Account ID", "Account Name", "Status", "Joined", "OU Path"];
+    if shared > 0 {
+        titles.push("Also Via");
+    }
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_DEFAULT);
+    table.set_titles(Row::new(titles.into_iter().map(|title| Cell::new(title).style_spec("bFc")).collect()));
+    for account in accounts {
+        let mut cells = vec![
+            Cell::new(&account.profile),
+            Cell::new(&account.account_id),
+            Cell::new(&account.account_name),
+            Cell::new(account.status.as_deref().unwrap_or("N/A")),
+            Cell::new(account.joined.as_deref().unwrap_or("N/A")),
+            Cell::new(account.ou_path.as_deref().unwrap_or("")),
+        ];
+        if shared > 0 {
+            cells.push(Cell::new(&account.also_via.join(", ")).style_spec("Fy"));
+        }
+        table.add_row(Row::new(cells));
+    }
+    println!("\nDiscovered Accounts:");
+    table.printstd();
+    println!("{} accounts", accounts.len());
+    if shared > 0 {
+        println!("{} accounts are reachable via several profiles; each is listed under the profile --dedupe-strategy keeps", shared);
+    }
+}
@@ -0,0 +1,77 @@
+use crate::UnifiedViewData;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A cost over `--alert-threshold` (the total across accounts) or `--account-alert-threshold` (one account).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Alert {
+    /// `global` or `account`.
+    pub scope: String,
+    pub profile: Option<String>,
+    pub account_id: Option<String>,
+    pub account_name: Option<String>,
+    /// The latest complete period, whose cost is compared.
+    pub period: String,
+    pub cost: f64,
+    pub threshold: f64,
+}
+
+impl Alert {
+    /// One line for the ALERT section and notifications.
+    pub fn message(&self) -> String {
+        match (&self.profile, &self.account_id, &self.account_name) {
+            (Some(profile), Some(account_id), Some(account_name)) => format!(
+                "Profile {} Account {} ({}) cost ${:.2} in {}, over --account-alert-threshold ${:.2}",
+                profile, account_id, account_name, self.cost, self.period, self.threshold
+            ),
+            _ => format!("Total cost ${:.2} in {}, over --alert-threshold ${:.2}", self.cost, self.period, self.threshold),
+        }
+    }
+}
+
+/// Compares the latest complete period against the thresholds. The in-progress period is never
+/// compared, so alerts don't fire on a half-finished month.
+pub fn evaluate(
+    global_threshold: Option<f64>,
+    account_threshold: Option<f64>,
+    periods: &[String],
+    partial_period: Option<&str>,
+    global_period_totals: &BTreeMap<String, f64>,
+    unified_view: &[UnifiedViewData],
+) -> Vec<Alert> {
+    let Some(period) = periods.iter().rev().find(|period| Some(period.as_str()) != partial_period) else {
+        return Vec::new();
+    };
+    let mut alerts = Vec::new();
+    if let Some(threshold) = global_threshold {
+        let cost = global_period_totals.get(period).copied().unwrap_or(0.0);
+        if cost > threshold {
+            alerts.push(Alert {
+                scope: "global".to_string(),
+                profile: None,
+                account_id: None,
+                account_name: None,
+                period: period.clone(),
+                cost,
+                threshold,
+            });
+        }
+    }
+    if let Some(threshold) = account_threshold {
+        for account in unified_view {
+            let cost = account.monthly_costs.get(period).copied().unwrap_or(0.0);
+            if cost > threshold {
+                alerts.push(Alert {
+                    scope: "account".to_string(),
+                    profile: Some(account.profile.clone()),
+                    account_id: Some(account.account_id.clone()),
+                    account_name: Some(account.account_name.clone()),
+                    period: period.clone(),
+                    cost,
+                    threshold,
+                });
+            }
+        }
+    }
+    alerts
+}
@@ -0,0 +1,354 @@
+use crate::AccountCostData;
+use chrono::{DateTime, Datelike, NaiveDate, Timelike, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Average number of Saturdays and Sundays in a month.
+const WEEKEND_DAYS_PER_MONTH: f64 = 365.25 / 12.0 * 2.0 / 7.0;
+
+/// Weekday versus weekend daily spend for one account.
+#[derive(Serialize, Debug)]
+pub struct WeekdaySplit {
+    pub profile: String,
+    pub account_id: String,
+    pub account_name: String,
+    pub weekday_average: f64,
+    pub weekend_average: f64,
+    /// Weekend average as a fraction of the weekday average; absent when there is no weekday spend.
+    pub weekend_ratio: Option<f64>,
+    /// Monthly savings if weekend days cost `target_percent` of a weekday.
+    pub estimated_monthly_savings: f64,
+}
+
+/// Splits an account's daily trend into weekday and weekend days, leaving out the in-progress day
+/// unless `include_partial`. Returns `None` when the trend has no dated daily periods.
+pub fn weekday_split(account: &AccountCostData, target_percent: f64, include_partial: bool) -> Option<WeekdaySplit> {
+    let mut weekday = (0.0, 0usize);
+    let mut weekend = (0.0, 0usize);
+    for entry in account.cost_trend.iter().filter(|e| include_partial || !e.is_partial) {
+        let Ok(day) = NaiveDate::parse_from_str(&entry.month, "%Y-%m-%d") else {
+            continue;
+        };
+        let bucket = if matches!(day.weekday(), Weekday::Sat | Weekday::Sun) { &mut weekend } else { &mut weekday };
+        bucket.0 += entry.total_cost;
+        bucket.1 += 1;
+    }
+    if weekday.1 == 0 && weekend.1 == 0 {
+        return None;
+    }
+
+    let average = |(total, days): (f64, usize)| if days == 0 { 0.0 } else { total / days as f64 };
+    let weekday_average = average(weekday);
+    let weekend_average = average(weekend);
+    let target_weekend_average = weekday_average * target_percent / 100.0;
+    Some(WeekdaySplit {
+        profile: account.profile.clone(),
+        account_id: account.account_id.clone(),
+        account_name: account.account_name.clone(),
+        weekday_average,
+        weekend_average,
+        weekend_ratio: (weekday_average > 0.0).then(|| weekend_average / weekday_average),
+        estimated_monthly_savings: ((weekend_average - target_weekend_average) * WEEKEND_DAYS_PER_MONTH).max(0.0),
+    })
+}
+
+/// Hours of the day (start inclusive, end exclusive) compared by `--hourly-profile`.
+const OVERNIGHT_HOURS: std::ops::Range<usize> = 0..6;
+const BUSINESS_HOURS: std::ops::Range<usize> = 9..18;
+/// Overnight spend within this fraction of business-hours spend means the account never scales down.
+const ALWAYS_ON_TOLERANCE: f64 = 0.10;
+
+/// Average cost for each hour of the day for one account.
+#[derive(Serialize, Debug)]
+pub struct HourlyProfile {
+    pub profile: String,
+    pub account_id: String,
+    pub account_name: String,
+    /// 24 averages, index 0 being 00:00-01:00 in the report's time zone.
+    pub hourly_averages: Vec<f64>,
+    pub overnight_average: f64,
+    pub business_hours_average: f64,
+    /// Overnight (00:00-06:00) spend is within 10% of business-hours (09:00-18:00) spend.
+    pub always_on: bool,
+}
+
+/// Buckets an account's hourly trend by hour of day, in the offset each period label carries
+/// (UTC, or the `--timezone` zone). Returns `None` when the trend has no hourly periods.
+pub fn hourly_profile(account: &AccountCostData) -> Option<HourlyProfile> {
+    let mut buckets = [(0.0, 0usize); 24];
+    for entry in &account.cost_trend {
+        let Ok(start) = DateTime::parse_from_rfc3339(&entry.month) else {
+            continue;
+        };
+        let bucket = &mut buckets[start.hour() as usize];
+        bucket.0 += entry.total_cost;
+        bucket.1 += 1;
+    }
+    if buckets.iter().all(|(_, samples)| *samples == 0) {
+        return None;
+    }
+
+    let hourly_averages: Vec<f64> = buckets
+        .iter()
+        .map(|(total, samples)| if *samples == 0 { 0.0 } else { total / *samples as f64 })
+        .collect();
+    let mean = |hours: std::ops::Range<usize>| {
+        let len = hours.len() as f64;
+        hourly_averages[hours].iter().sum::<f64>() / len
+    };
+    let overnight_average = mean(OVERNIGHT_HOURS);
+    let business_hours_average = mean(BUSINESS_HOURS);
+    Some(HourlyProfile {
+        profile: account.profile.clone(),
+        account_id: account.account_id.clone(),
+        account_name: account.account_name.clone(),
+        always_on: business_hours_average > 0.0
+            && (overnight_average - business_hours_average).abs() <= business_hours_average * ALWAYS_ON_TOLERANCE,
+        hourly_averages,
+        overnight_average,
+        business_hours_average,
+    })
+}
+
+/// Spread of an account's period totals over the averaging window.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CostStats {
+    pub min: f64,
+    pub max: f64,
+    pub median: f64,
+    /// Sample standard deviation; undefined for a single period.
+    pub std_dev: Option<f64>,
+    /// `std_dev` relative to the mean, a volatility signal; undefined when the mean is zero.
+    pub coefficient_of_variation: Option<f64>,
+}
+
+/// Summarizes period totals, or `None` when there are no periods.
+pub fn cost_stats(costs: &[f64]) -> Option<CostStats> {
+    if costs.is_empty() {
+        return None;
+    }
+    let mut sorted = costs.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let n = sorted.len();
+    let median = if n.is_multiple_of(2) { (sorted[n / 2 - 1] + sorted[n / 2]) / 2.0 } else { sorted[n / 2] };
+    let mean = sorted.iter().sum::<f64>() / n as f64;
+    let std_dev = (n > 1).then(|| (sorted.iter().map(|c| (c - mean).powi(2)).sum::<f64>() / (n - 1) as f64).sqrt());
+    Some(CostStats {
+        min: sorted[0],
+        max: sorted[n - 1],
+        median,
+        std_dev,
+        coefficient_of_variation: std_dev.filter(|_| mean != 0.0).map(|sd| sd / mean),
+    })
+}
+
+/// Unblended cost above amortized cost by more than this fraction of it marks an upfront purchase.
+const UPFRONT_PURCHASE_TOLERANCE: f64 = 0.10;
+
+/// One period of `--amortization-delta`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AmortizationPeriod {
+    pub period: String,
+    pub unblended_cost: f64,
+    pub amortized_cost: f64,
+    /// Unblended minus amortized: positive when upfront fees are billed, negative while they are spread
+    /// over later periods.
+    pub delta: f64,
+    /// `delta` relative to the amortized cost; undefined when there is no amortized cost.
+    pub delta_percent: Option<f64>,
+    /// Unblended cost spikes above amortized cost, as upfront RI and Savings Plans purchases make it.
+    pub upfront_purchase: bool,
+}
+
+/// Unblended versus amortized cost for one account, which is why the console's amortized view differs.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AmortizationDelta {
+    pub periods: Vec<AmortizationPeriod>,
+    pub unblended_total: f64,
+    pub amortized_total: f64,
+    /// Sample standard deviation of each series; a lower amortized one is the smoothing of purchases.
+    pub unblended_std_dev: Option<f64>,
+    pub amortized_std_dev: Option<f64>,
+}
+
+/// Compares an account's unblended and amortized totals over `periods`.
+pub fn amortization_delta(periods: &[String], unblended: &BTreeMap<String, f64>, amortized: &BTreeMap<String, f64>) -> AmortizationDelta {
+    let periods: Vec<AmortizationPeriod> = periods
+        .iter()
+        .map(|period| {
+            let unblended_cost = unblended.get(period).copied().unwrap_or(0.0);
+            let amortized_cost = amortized.get(period).copied().unwrap_or(0.0);
+            let delta = unblended_cost - amortized_cost;
+            AmortizationPeriod {
+                period: period.clone(),
+                unblended_cost,
+                amortized_cost,
+                delta,
+                delta_percent: (amortized_cost != 0.0).then(|| delta / amortized_cost.abs() * 100.0),
+                upfront_purchase: delta >= 0.01 && delta > amortized_cost.abs() * UPFRONT_PURCHASE_TOLERANCE,
+            }
+        })
+        .collect();
+    let std_dev = |costs: Vec<f64>| cost_stats(&costs).and_then(|stats| stats.std_dev);
+    AmortizationDelta {
+        unblended_total: periods.iter().map(|p| p.unblended_cost).sum(),
+        amortized_total: periods.iter().map(|p| p.amortized_cost).sum(),
+        unblended_std_dev: std_dev(periods.iter().map(|p| p.unblended_cost).collect()),
+        amortized_std_dev: std_dev(periods.iter().map(|p| p.amortized_cost).collect()),
+        periods,
+    }
+}
+
+/// Costs by record type and period, from `--show-credits`.
+pub type RecordTypeBreakdown = BTreeMap<String, BTreeMap<String, f64>>;
+
+/// Record types `--show-credits` lists first, in this order (by prefix, so `Support` covers support
+/// fees); the others follow alphabetically.
+const RECORD_TYPE_ORDER: &[&str] = &["Usage", "Credit", "Refund", "Tax", "Support"];
+
+/// Record types of a `record_type_breakdown` in display order.
+pub fn ordered_record_types(breakdown: &RecordTypeBreakdown) -> Vec<&String> {
+    let rank = |record_type: &str| {
+        RECORD_TYPE_ORDER
+            .iter()
+            .position(|prefix| record_type.to_lowercase().starts_with(&prefix.to_lowercase()))
+            .unwrap_or(RECORD_TYPE_ORDER.len())
+    };
+    let mut record_types: Vec<&String> = breakdown.keys().collect();
+    record_types.sort_by_key(|record_type| rank(record_type));
+    record_types
+}
+
+/// One period of `--compare-metrics`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MetricComparisonPeriod {
+    pub period: String,
+    pub first_cost: f64,
+    pub second_cost: f64,
+    /// Second metric minus first.
+    pub delta: f64,
+}
+
+/// Two cost metrics of one account side by side, e.g. unblended and blended cost.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MetricComparison {
+    pub first_metric: String,
+    pub second_metric: String,
+    pub periods: Vec<MetricComparisonPeriod>,
+    pub first_total: f64,
+    pub second_total: f64,
+    pub delta: f64,
+    /// `delta` relative to the first metric's total; undefined when that is zero.
+    pub delta_percent: Option<f64>,
+    /// The totals differ by more than `--compare-threshold` percent.
+    pub exceeds_threshold: bool,
+}
+
+/// Compares an account's totals of two metrics over `periods`.
+pub fn metric_comparison(
+    periods: &[String],
+    (first_metric, first): (&str, &BTreeMap<String, f64>),
+    (second_metric, second): (&str, &BTreeMap<String, f64>),
+    threshold_percent: f64,
+) -> MetricComparison {
+    let periods: Vec<MetricComparisonPeriod> = periods
+        .iter()
+        .map(|period| {
+            let first_cost = first.get(period).copied().unwrap_or(0.0);
+            let second_cost = second.get(period).copied().unwrap_or(0.0);
+            MetricComparisonPeriod { period: period.clone(), first_cost, second_cost, delta: second_cost - first_cost }
+        })
+        .collect();
+    let first_total: f64 = periods.iter().map(|p| p.first_cost).sum();
+    let second_total: f64 = periods.iter().map(|p| p.second_cost).sum();
+    let delta = second_total - first_total;
+    let delta_percent = (first_total != 0.0).then(|| delta / first_total.abs() * 100.0);
+    MetricComparison {
+        first_metric: first_metric.to_string(),
+        second_metric: second_metric.to_string(),
+        periods,
+        first_total,
+        second_total,
+        delta,
+        delta_percent,
+        // Without a first total any difference is a shift
+        exceeds_threshold: delta_percent.map_or(delta.abs() >= 0.01, |percent| percent.abs() > threshold_percent),
+    }
+}
+
+/// Record types of ordinary spend. Any other record type (a refund, credit, upfront fee, tax, ...)
+/// making up a large share of a period gets a composition annotation.
+const USAGE_RECORD_TYPES: &[&str] = &[
+    "Usage",
+    "DiscountedUsage",
+    "SavingsPlanCoveredUsage",
+    "SavingsPlanNegation",
+    "SavingsPlanRecurringFee",
+    "RIFee",
+];
+
+/// A record type's share of a period, in the composition annotation of a trend entry.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecordTypeShare {
+    pub record_type: String,
+    pub cost: f64,
+    /// Share of the period's gross activity: the record type's absolute cost over the sum of every
+    /// record type's absolute cost, so a refund counts as much as a charge of the same size.
+    pub share_percent: f64,
+}
+
+impl RecordTypeShare {
+    /// Table annotation such as `refund 34%`.
+    pub fn label(&self) -> String {
+        format!("{} {:.0}%", self.record_type.to_lowercase(), self.share_percent)
+    }
+}
+
+/// Non-usage record types above `threshold_percent` of a period whose costs by record type are
+/// `costs`, largest share first.
+pub fn unusual_record_types(costs: &[(&String, f64)], threshold_percent: f64) -> Vec<RecordTypeShare> {
+    let gross: f64 = costs.iter().map(|(_, cost)| cost.abs()).sum();
+    if gross == 0.0 {
+        return Vec::new();
+    }
+    let mut shares: Vec<RecordTypeShare> = costs
+        .iter()
+        .filter(|(record_type, _)| !USAGE_RECORD_TYPES.contains(&record_type.as_str()))
+        .map(|(record_type, cost)| RecordTypeShare { record_type: record_type.to_string(), cost: *cost, share_percent: cost.abs() / gross * 100.0 })
+        .filter(|share| share.share_percent > threshold_percent)
+        .collect();
+    shares.sort_by(|a, b| b.share_percent.total_cmp(&a.share_percent).then_with(|| a.record_type.cmp(&b.record_type)));
+    shares
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cost_stats_summarize_the_spread() {
+        let stats = cost_stats(&[30.0, 10.0, 20.0, 40.0]).unwrap();
+        assert_eq!((stats.min, stats.max, stats.median), (10.0, 40.0, 25.0));
+        // Sample standard deviation of 10, 20, 30, 40
+        let std_dev = stats.std_dev.unwrap();
+        assert!((std_dev - 12.909944).abs() < 1e-6, "{}", std_dev);
+        assert!((stats.coefficient_of_variation.unwrap() - std_dev / 25.0).abs() < 1e-12);
+        assert_eq!(cost_stats(&[5.0, 1.0, 3.0]).unwrap().median, 3.0);
+    }
+
+    #[test]
+    fn a_single_period_has_no_standard_deviation() {
+        let stats = cost_stats(&[42.0]).unwrap();
+        assert_eq!((stats.min, stats.max, stats.median), (42.0, 42.0, 42.0));
+        assert_eq!(stats.std_dev, None);
+        assert_eq!(stats.coefficient_of_variation, None);
+    }
+
+    #[test]
+    fn an_account_without_cost_has_no_coefficient_of_variation() {
+        let stats = cost_stats(&[0.0, 0.0, 0.0]).unwrap();
+        assert_eq!(stats.std_dev, Some(0.0));
+        assert_eq!(stats.coefficient_of_variation, None);
+        assert!(cost_stats(&[]).is_none());
+    }
+}
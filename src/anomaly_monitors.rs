@@ -0,0 +1,198 @@
+use crate::{cost_explorer_client, load_discovery_config, Cli};
+use aws_sdk_costexplorer::error::ProvideErrorMetadata;
+use aws_sdk_costexplorer::types::{AnomalyMonitor, AnomalySubscription, Dimension, Expression, MonitorType};
+use aws_sdk_costexplorer::Client as CostExplorerClient;
+use futures::future::join_all;
+use prettytable::{format, Cell, Row, Table};
+use serde::Serialize;
+
+/// An alert subscription of a Cost Anomaly Detection monitor.
+#[derive(Serialize, Debug, Clone)]
+pub struct MonitorSubscription {
+    pub name: String,
+    /// `DAILY`, `IMMEDIATE` or `WEEKLY`.
+    pub frequency: String,
+    /// Impact an anomaly needs before it is sent, such as `impact >= $100 and impact >= 20%`.
+    pub threshold: Option<String>,
+    /// Email addresses and SNS topic ARNs.
+    pub recipients: Vec<String>,
+}
+
+/// A Cost Anomaly Detection monitor and the subscriptions that alert on it.
+#[derive(Serialize, Debug)]
+pub struct MonitorSummary {
+    pub name: String,
+    pub monitor_arn: Option<String>,
+    /// `service`, `linked account`, `cost category`, `tag` or `custom`.
+    pub monitor_type: String,
+    pub last_evaluated: Option<String>,
+    pub subscriptions: Vec<MonitorSubscription>,
+}
+
+/// The monitors of one profile, or why they couldn't be listed.
+#[derive(Serialize, Debug)]
+pub struct ProfileMonitors {
+    pub profile: String,
+    pub monitors: Vec<MonitorSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Lists the monitors and subscriptions of every profile concurrently. A failing profile gets an
+/// error entry instead of stopping the others.
+pub async fn list(cli: &Cli, profiles: &[String]) -> Vec<ProfileMonitors> {
+    join_all(profiles.iter().map(|profile| list_profile(cli, profile))).await
+}
+
+async fn list_profile(cli: &Cli, profile: &str) -> ProfileMonitors {
+    let config = load_discovery_config(cli, profile).await;
+    let client = cost_explorer_client(cli, &config);
+    let fetched = match fetch_monitors(cli, &client).await {
+        Ok(monitors) => fetch_subscriptions(cli, &client).await.map(|subscriptions| (monitors, subscriptions)),
+        Err(e) => Err(e),
+    };
+    let (monitors, subscriptions) = match fetched {
+        Ok(fetched) => fetched,
+        Err(error) => return ProfileMonitors { profile: profile.to_string(), monitors: Vec::new(), error: Some(error) },
+    };
+    let monitors = monitors
+        .into_iter()
+        .map(|monitor| {
+            let arn = monitor.monitor_arn().map(str::to_string);
+            MonitorSummary {
+                name: monitor.monitor_name().to_string(),
+                monitor_type: monitor_type(&monitor),
+                last_evaluated: monitor.last_evaluated_date().map(str::to_string),
+                subscriptions: subscriptions
+                    .iter()
+                    .filter(|subscription| arn.as_ref().is_some_and(|arn| subscription.monitor_arn_list().contains(arn)))
+                    .map(summarize_subscription)
+                    .collect(),
+                monitor_arn: arn,
+            }
+        })
+        .collect();
+    ProfileMonitors { profile: profile.to_string(), monitors, error: None }
+}
+
+async fn fetch_monitors(cli: &Cli, client: &CostExplorerClient) -> Result<Vec<AnomalyMonitor>, String> {
+    let mut monitors = Vec::new();
+    let mut next_page_token = None;
+    loop {
+        cli.ce_limiter.acquire().await;
+        let response = client.get_anomaly_monitors().set_next_page_token(next_page_token).send().await.map_err(|e| error_message(&e))?;
+        monitors.extend(response.anomaly_monitors);
+        next_page_token = response.next_page_token;
+        if next_page_token.is_none() {
+            return Ok(monitors);
+        }
+    }
+}
+
+async fn fetch_subscriptions(cli: &Cli, client: &CostExplorerClient) -> Result<Vec<AnomalySubscription>, String> {
+    let mut subscriptions = Vec::new();
+    let mut next_page_token = None;
+    loop {
+        cli.ce_limiter.acquire().await;
+        let response = client.get_anomaly_subscriptions().set_next_page_token(next_page_token).send().await.map_err(|e| error_message(&e))?;
+        subscriptions.extend(response.anomaly_subscriptions);
+        next_page_token = response.next_page_token;
+        if next_page_token.is_none() {
+            return Ok(subscriptions);
+        }
+    }
+}
+
+fn error_message<E: ProvideErrorMetadata + std::error::Error>(error: &E) -> String {
+    match (error.code(), error.message()) {
+        (Some(code), Some(message)) => format!("{}: {}", code, message),
+        _ => error.to_string(),
+    }
+}
+
+/// Dimensional monitors track every value of their dimension; custom ones a specification of their own.
+fn monitor_type(monitor: &AnomalyMonitor) -> String {
+    match (monitor.monitor_type(), monitor.monitor_dimension()) {
+        (MonitorType::Custom, _) => "custom".to_string(),
+        (_, Some(dimension)) => dimension.as_str().to_lowercase().replace('_', " "),
+        (other, None) => other.as_str().to_lowercase(),
+    }
+}
+
+// Subscriptions created before threshold expressions only have the deprecated `threshold`
+#[allow(deprecated)]
+fn summarize_subscription(subscription: &AnomalySubscription) -> MonitorSubscription {
+    MonitorSubscription {
+        name: subscription.subscription_name().to_string(),
+        frequency: subscription.frequency().as_str().to_string(),
+        threshold: subscription
+            .threshold_expression()
+            .and_then(describe_threshold)
+            .or_else(|| subscription.threshold().map(|threshold| format!("impact >= ${}", threshold))),
+        recipients: subscription.subscribers().iter().filter_map(|subscriber| subscriber.address().map(str::to_string)).collect(),
+    }
+}
+
+/// `impact >= $100`, `impact >= 20%`, or both joined by `and` / `or`.
+fn describe_threshold(expression: &Expression) -> Option<String> {
+    if let Some(dimension) = expression.dimensions() {
+        let value = dimension.values().first()?;
+        return match dimension.key()? {
+            Dimension::AnomalyTotalImpactAbsolute => Some(format!("impact >= ${}", value)),
+            Dimension::AnomalyTotalImpactPercentage => Some(format!("impact >= {}%", value)),
+            other => Some(format!("{} >= {}", other.as_str(), value)),
+        };
+    }
+    let (operands, joiner) = match (expression.and(), expression.or()) {
+        (and, _) if !and.is_empty() => (and, " and "),
+        (_, or) if !or.is_empty() => (or, " or "),
+        _ => return None,
+    };
+    let parts: Vec<String> = operands.iter().filter_map(describe_threshold).collect();
+    (!parts.is_empty()).then(|| parts.join(joiner))
+}
+
+/// Prints one table of monitors per profile.
+pub fn print(results: &[ProfileMonitors]) {
+    for result in results {
+        if let Some(error) = &result.error {
+            println!("\nAnomaly Monitors for Profile {}: unavailable - {}", result.profile, error);
+            continue;
+        }
+        if result.monitors.is_empty() {
+            println!("\nAnomaly Monitors for Profile {}: none", result.profile);
+            continue;
+        }
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_DEFAULT);
+        table.set_titles(Row::new(vec![
+            Cell::new("Monitor").style_spec("bFc"),
+            Cell::new("Type").style_spec("bFc"),
+            Cell::new("Last Evaluated").style_spec("bFc"),
+            Cell::new("Subscriptions").style_spec("bFc"),
+        ]));
+        for monitor in &result.monitors {
+            let subscriptions: Vec<String> = monitor
+                .subscriptions
+                .iter()
+                .map(|subscription| {
+                    format!(
+                        "{} ({}, {}): {}",
+                        subscription.name,
+                        subscription.frequency.to_lowercase(),
+                        subscription.threshold.as_deref().unwrap_or("no threshold"),
+                        subscription.recipients.join(", ")
+                    )
+                })
+                .collect();
+            table.add_row(Row::new(vec![
+                Cell::new(&monitor.name),
+                Cell::new(&monitor.monitor_type),
+                Cell::new(monitor.last_evaluated.as_deref().unwrap_or("never")),
+                if subscriptions.is_empty() { Cell::new("none").style_spec("Fy") } else { Cell::new(&subscriptions.join("\n")) },
+            ]));
+        }
+        println!("\nAnomaly Monitors for Profile {}:", result.profile);
+        table.printstd();
+    }
+}
@@ -0,0 +1,68 @@
+use aws_sdk_costexplorer::config::interceptors::BeforeTransmitInterceptorContextRef;
+use aws_sdk_costexplorer::config::{ConfigBag, Intercept, RuntimeComponents};
+use aws_sdk_costexplorer::error::BoxError;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Cost Explorer API price per request, in USD; every page of a paginated response is billed.
+pub const COST_PER_REQUEST: f64 = 0.01;
+
+/// Counts Cost Explorer requests across every client it is attached to. Each attempt is counted,
+/// so pages and retries are included, and clones share the count for concurrent queries.
+#[derive(Debug, Default, Clone)]
+pub struct RequestCounter {
+    attempts: Arc<AtomicUsize>,
+    /// Requests callers have started through `start_within`, including those not yet sent.
+    started: Arc<AtomicUsize>,
+}
+
+impl RequestCounter {
+    pub fn count(&self) -> usize {
+        self.attempts.load(Ordering::Relaxed)
+    }
+
+    /// Claims one request against a budget of `max`, returning false once the budget is used up.
+    /// Claims are atomic, so concurrent queries can't all start the last request; only retries of
+    /// requests already in flight can still go over.
+    pub fn start_within(&self, max: usize) -> bool {
+        self.started
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |started| (started.max(self.count()) < max).then_some(started + 1))
+            .is_ok()
+    }
+
+    pub fn usage(&self) -> ApiUsage {
+        let requests = self.count();
+        ApiUsage { cost_explorer_requests: requests, estimated_cost_usd: requests as f64 * COST_PER_REQUEST }
+    }
+}
+
+impl Intercept for RequestCounter {
+    fn name(&self) -> &'static str {
+        "RequestCounter"
+    }
+
+    fn read_before_attempt(
+        &self,
+        _context: &BeforeTransmitInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        self.attempts.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Cost Explorer requests made while producing a report, and what they cost.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct ApiUsage {
+    pub cost_explorer_requests: usize,
+    pub estimated_cost_usd: f64,
+}
+
+impl ApiUsage {
+    /// Final status line, e.g. `Made 183 Cost Explorer requests (~$1.83)`.
+    pub fn summary(&self) -> String {
+        format!("Made {} Cost Explorer requests (~${:.2})", self.cost_explorer_requests, self.estimated_cost_usd)
+    }
+}
@@ -0,0 +1,440 @@
+use crate::cur::{CurCosts, Selection, ACCOUNT_COLUMN, COST_COLUMN, LINE_ITEM_TYPE_COLUMN, SERVICE_COLUMN, START_COLUMN, TAGS_MAP_COLUMN};
+use crate::{Cli, GranularityOption, TAX};
+use aws_config::SdkConfig;
+use aws_sdk_athena::error::ProvideErrorMetadata;
+use aws_sdk_athena::types::{QueryExecution, QueryExecutionContext, QueryExecutionState, ResultConfiguration};
+use aws_sdk_athena::Client as AthenaClient;
+use chrono::{Datelike, Duration, NaiveDateTime};
+use std::error::Error;
+use tokio::time::Instant;
+
+/// Glue Data Catalog, where the CUR's Athena integration creates its tables.
+const CATALOG: &str = "AwsDataCatalog";
+
+/// Athena's price per TB scanned, for the log.
+const PRICE_PER_TB: f64 = 5.0;
+
+/// Hint added to access denied errors, which Athena reports from several services.
+const PERMISSIONS_HINT: &str =
+    "the profile needs athena:StartQueryExecution, athena:GetQueryExecution, athena:GetQueryResults, athena:GetTableMetadata, glue:GetTable, glue:GetPartitions, s3:GetObject on the CUR data and s3:PutObject on --athena-output";
+
+/// The table columns the query is built from.
+struct Table {
+    database: String,
+    table: String,
+    /// `year` and `month` partitions of the legacy CUR integration, or the `billing_period` one of CUR 2.0.
+    partitions: Partitions,
+    /// SQL expression of the `--tag-key` value.
+    tag: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Partitions {
+    YearMonth,
+    BillingPeriod,
+    None,
+}
+
+/// Sums the line items of `--athena-table` for the accounts in `[start, end)` with one Athena
+/// query, grouped as the Cost Explorer query groups them.
+pub async fn load(cli: &Cli, config: &SdkConfig, account_ids: &[String], start: &str, end: &str) -> Result<CurCosts, Box<dyn Error>> {
+    let (Some(database), Some(table_name), Some(output)) = (&cli.athena_database, &cli.athena_table, &cli.athena_output) else {
+        return Err("--source athena requires --athena-database, --athena-table and --athena-output".into());
+    };
+    let selection = Selection::new(cli, start, end)?;
+    let client = AthenaClient::new(config);
+    let table = describe_table(&client, database, table_name, selection.tag_key).await?;
+    let mut costs = CurCosts::default();
+    if account_ids.is_empty() {
+        return Ok(costs);
+    }
+
+    let sql = query_sql(&table, &selection, account_ids);
+    let started = client
+        .start_query_execution()
+        .query_string(&sql)
+        .query_execution_context(QueryExecutionContext::builder().catalog(CATALOG).database(database).build())
+        .result_configuration(ResultConfiguration::builder().output_location(output).build())
+        .send()
+        .await
+        .map_err(|e| format!("Cannot start the Athena query: {}", error_message(&e)))?;
+    let id = started.query_execution_id().ok_or("Athena returned no query execution ID")?.to_string();
+    let execution = wait(&client, &id, cli.athena_timeout).await?;
+    let scanned = execution.statistics().and_then(|statistics| statistics.data_scanned_in_bytes()).unwrap_or(0) as f64;
+    info!("Athena query {} scanned {:.2} GB (~${:.2})", id, scanned / 1e9, scanned / 1e12 * PRICE_PER_TB);
+
+    let mut next_token = None;
+    let mut header = true;
+    loop {
+        let response = client
+            .get_query_results()
+            .query_execution_id(&id)
+            .set_next_token(next_token)
+            .send()
+            .await
+            .map_err(|e| format!("Cannot read the results of Athena query {}: {}", id, error_message(&e)))?;
+        for row in response.result_set().map(|set| set.rows()).unwrap_or_default() {
+            // The first row holds the column names
+            if std::mem::take(&mut header) {
+                continue;
+            }
+            let values: Vec<Option<&str>> = row.data().iter().map(|datum| datum.var_char_value()).collect();
+            let [Some(account_id), product_code, line_type, Some(period), Some(cost), tag @ ..] = values.as_slice() else {
+                continue;
+            };
+            let period = NaiveDateTime::parse_from_str(period, "%Y-%m-%d %H:%M:%S%.f").map_err(|e| format!("Athena query {} returned period {}: {}", id, period, e))?;
+            let cost: f64 = cost.parse().map_err(|e| format!("Athena query {} returned cost {}: {}", id, cost, e))?;
+            let tag = tag.first().copied().flatten();
+            let line_type = line_type.unwrap_or("Usage");
+            costs.add(account_id, selection.service(*product_code, line_type, tag), line_type, selection.period(period.and_utc()), cost);
+            costs.line_items += 1;
+        }
+        next_token = response.next_token;
+        if next_token.is_none() {
+            return Ok(costs);
+        }
+    }
+}
+
+/// Checks the table has the CUR columns and finds its partitions and the `--tag-key` column.
+async fn describe_table(client: &AthenaClient, database: &str, table: &str, tag_key: Option<&str>) -> Result<Table, Box<dyn Error>> {
+    let response = client
+        .get_table_metadata()
+        .catalog_name(CATALOG)
+        .database_name(database)
+        .table_name(table)
+        .send()
+        .await
+        .map_err(|e| format!("Cannot read Athena table {}.{}: {}", database, table, error_message(&e)))?;
+    let metadata = response.table_metadata().ok_or_else(|| format!("Athena returned no metadata for table {}.{}", database, table))?;
+    let columns: Vec<&str> = metadata.columns().iter().chain(metadata.partition_keys()).map(|column| column.name()).collect();
+    let has = |name: &str| columns.iter().any(|column| column.eq_ignore_ascii_case(name));
+    for column in [ACCOUNT_COLUMN, SERVICE_COLUMN, COST_COLUMN, START_COLUMN, LINE_ITEM_TYPE_COLUMN] {
+        if !has(column) {
+            return Err(format!("Athena table {}.{} is not a CUR table: it has no {} column", database, table, column).into());
+        }
+    }
+    let partition_keys: Vec<&str> = metadata.partition_keys().iter().map(|column| column.name()).collect();
+    let partitions = if partition_keys.contains(&"year") && partition_keys.contains(&"month") {
+        Partitions::YearMonth
+    } else if partition_keys.contains(&"billing_period") {
+        Partitions::BillingPeriod
+    } else {
+        Partitions::None
+    };
+    let tag = match tag_key {
+        None => None,
+        Some(key) => {
+            let names = crate::cur::tag_columns(key);
+            if let Some(name) = names.iter().find(|name| has(name)) {
+                Some(format!("NULLIF({}, '')", identifier(name)))
+            } else if has(TAGS_MAP_COLUMN) {
+                // CUR 2.0 map keys are the legacy column names without `resource_tags_`
+                let lookups: Vec<String> = std::iter::once(format!("user_{}", key))
+                    .chain(names.iter().map(|name| name.trim_start_matches("resource_tags_").to_string()))
+                    .map(|map_key| format!("element_at({}, {})", TAGS_MAP_COLUMN, literal(&map_key)))
+                    .collect();
+                Some(format!("NULLIF(COALESCE({}), '')", lookups.join(", ")))
+            } else {
+                return Err(format!("Athena table {}.{} has no {} column or {} map for --tag-key {}", database, table, names[0], TAGS_MAP_COLUMN, key).into());
+            }
+        }
+    };
+    Ok(Table { database: database.to_string(), table: table.to_string(), partitions, tag })
+}
+
+/// SQL summing the line items by account, product code, line item type, period and, with
+/// `--tag-key`, tag value. Columns come back in that order, the cost before the tag.
+fn query_sql(table: &Table, selection: &Selection, account_ids: &[String]) -> String {
+    let unit = match selection.granularity {
+        GranularityOption::Monthly | GranularityOption::Quarterly => "month",
+        GranularityOption::Daily | GranularityOption::Weekly => "day",
+        GranularityOption::Hourly => "hour",
+    };
+    let mut columns = vec![
+        ACCOUNT_COLUMN.to_string(),
+        SERVICE_COLUMN.to_string(),
+        LINE_ITEM_TYPE_COLUMN.to_string(),
+        format!("date_trunc('{}', {}) AS period", unit, START_COLUMN),
+        format!("SUM({}) AS cost", COST_COLUMN),
+    ];
+    let mut conditions = vec![
+        format!("{} >= TIMESTAMP '{}'", START_COLUMN, selection.start.format("%Y-%m-%d %H:%M:%S")),
+        format!("{} < TIMESTAMP '{}'", START_COLUMN, selection.end.format("%Y-%m-%d %H:%M:%S")),
+        format!("{} IN ({})", ACCOUNT_COLUMN, account_ids.iter().map(|id| literal(id)).collect::<Vec<_>>().join(", ")),
+    ];
+    // Partition predicates keep Athena from scanning months outside the range
+    let first = selection.start.date_naive();
+    let last = (selection.end - Duration::seconds(1)).date_naive();
+    match table.partitions {
+        Partitions::YearMonth => conditions.push(format!(
+            "CAST(year AS integer) * 100 + CAST(month AS integer) BETWEEN {} AND {}",
+            first.year() * 100 + first.month() as i32,
+            last.year() * 100 + last.month() as i32
+        )),
+        Partitions::BillingPeriod => conditions.push(format!("billing_period BETWEEN '{}' AND '{}'", first.format("%Y-%m"), last.format("%Y-%m"))),
+        Partitions::None => {}
+    }
+    if selection.exclude_tax {
+        conditions.push(format!("{} <> {}", LINE_ITEM_TYPE_COLUMN, literal(TAX)));
+    }
+    let mut group_by = "1, 2, 3, 4".to_string();
+    if let Some(tag) = &table.tag {
+        columns.push(format!("{} AS tag", tag));
+        group_by.push_str(", 6");
+        if let Some(value) = selection.tag_value {
+            conditions.push(format!("{} = {}", tag, literal(value)));
+        }
+    }
+    format!(
+        "SELECT {}\nFROM {}.{}\nWHERE {}\nGROUP BY {}",
+        columns.join(", "),
+        identifier(&table.database),
+        identifier(&table.table),
+        conditions.join("\n  AND "),
+        group_by
+    )
+}
+
+/// Polls the query until it finishes, backing off to every 5 seconds, and stops it after `timeout`.
+async fn wait(client: &AthenaClient, id: &str, timeout: std::time::Duration) -> Result<QueryExecution, Box<dyn Error>> {
+    let deadline = Instant::now() + timeout;
+    let mut delay = std::time::Duration::from_millis(500);
+    loop {
+        let response = client
+            .get_query_execution()
+            .query_execution_id(id)
+            .send()
+            .await
+            .map_err(|e| format!("Cannot read the status of Athena query {}: {}", id, error_message(&e)))?;
+        let execution = response.query_execution.ok_or_else(|| format!("Athena returned no status for query {}", id))?;
+        let status = execution.status();
+        match status.and_then(|status| status.state()) {
+            Some(QueryExecutionState::Succeeded) => return Ok(execution),
+            Some(QueryExecutionState::Failed) | Some(QueryExecutionState::Cancelled) => {
+                let reason = status
+                    .and_then(|status| status.athena_error().and_then(|error| error.error_message()).or(status.state_change_reason()))
+                    .unwrap_or("no reason given");
+                let hint = if reason.to_lowercase().contains("access denied") { format!(" ({})", PERMISSIONS_HINT) } else { String::new() };
+                return Err(format!("Athena query {} failed: {}{}", id, reason, hint).into());
+            }
+            _ => {}
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            // Best effort: a query left running keeps scanning, and billing
+            let _ = client.stop_query_execution().query_execution_id(id).send().await;
+            return Err(format!("Athena query {} did not finish within {}s and was stopped; raise --athena-timeout", id, timeout.as_secs()).into());
+        }
+        tokio::time::sleep(delay.min(deadline - now)).await;
+        delay = (delay * 2).min(std::time::Duration::from_secs(5));
+    }
+}
+
+fn error_message<E: ProvideErrorMetadata + std::error::Error>(error: &E) -> String {
+    match (error.code(), error.message()) {
+        (Some(code @ "AccessDeniedException"), Some(message)) => format!("{}: {} ({})", code, message, PERMISSIONS_HINT),
+        (Some(code), Some(message)) => format!("{}: {}", code, message),
+        _ => error.to_string(),
+    }
+}
+
+/// Double-quoted SQL identifier.
+fn identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Single-quoted SQL string literal.
+fn literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_athena::config::{BehaviorVersion, Credentials, Region};
+    use aws_smithy_runtime_api::client::http::{http_client_fn, HttpConnector, HttpConnectorFuture, SharedHttpConnector};
+    use aws_smithy_runtime_api::client::orchestrator::HttpRequest;
+    use aws_smithy_runtime_api::http::{Response, StatusCode};
+    use aws_smithy_types::body::SdkBody;
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    /// Answers Athena requests from fixed table metadata and query state, recording each operation.
+    #[derive(Debug, Clone)]
+    struct MockAthena {
+        columns: Vec<&'static str>,
+        partition_keys: Vec<&'static str>,
+        state: &'static str,
+        reason: Option<&'static str>,
+        operations: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl MockAthena {
+        fn new(columns: &[&'static str], partition_keys: &[&'static str]) -> Self {
+            MockAthena {
+                columns: columns.to_vec(),
+                partition_keys: partition_keys.to_vec(),
+                state: "SUCCEEDED",
+                reason: None,
+                operations: Arc::default(),
+            }
+        }
+
+        fn client(&self) -> AthenaClient {
+            let mock = self.clone();
+            let config = aws_sdk_athena::Config::builder()
+                .behavior_version(BehaviorVersion::latest())
+                .region(Region::new("us-east-1"))
+                .credentials_provider(Credentials::new("AKIDMOCK", "secret", None, None, "test"))
+                .http_client(http_client_fn(move |_, _| SharedHttpConnector::new(mock.clone())))
+                .build();
+            AthenaClient::from_conf(config)
+        }
+
+        fn count(&self, operation: &str) -> usize {
+            self.operations.lock().unwrap().iter().filter(|received| received.as_str() == operation).count()
+        }
+    }
+
+    impl HttpConnector for MockAthena {
+        fn call(&self, request: HttpRequest) -> HttpConnectorFuture {
+            let target = request.headers().get("x-amz-target").unwrap_or_default();
+            let operation = target.rsplit('.').next().unwrap_or_default().to_string();
+            let column = |name: &&str| json!({"Name": name, "Type": "string"});
+            let body = match operation.as_str() {
+                "GetTableMetadata" => json!({"TableMetadata": {
+                    "Name": "cur",
+                    "Columns": self.columns.iter().map(column).collect::<Vec<_>>(),
+                    "PartitionKeys": self.partition_keys.iter().map(column).collect::<Vec<_>>(),
+                }}),
+                "GetQueryExecution" => json!({"QueryExecution": {
+                    "QueryExecutionId": "query-1",
+                    "Status": {"State": self.state, "StateChangeReason": self.reason},
+                }}),
+                _ => json!({}),
+            };
+            self.operations.lock().unwrap().push(operation);
+            let response = Response::new(StatusCode::try_from(200).unwrap(), SdkBody::from(body.to_string()));
+            HttpConnectorFuture::ready(Ok(response))
+        }
+    }
+
+    const CUR_COLUMNS: [&str; 5] = [ACCOUNT_COLUMN, SERVICE_COLUMN, COST_COLUMN, START_COLUMN, LINE_ITEM_TYPE_COLUMN];
+
+    fn table(partitions: Partitions, tag: Option<&str>) -> Table {
+        Table { database: "billing".to_string(), table: "cur".to_string(), partitions, tag: tag.map(str::to_string) }
+    }
+
+    fn selection(start: (i32, u32, u32), end: (i32, u32, u32), granularity: GranularityOption) -> Selection<'static> {
+        let day = |(year, month, day): (i32, u32, u32)| Utc.with_ymd_and_hms(year, month, day, 0, 0, 0).unwrap();
+        Selection { start: day(start), end: day(end), granularity, tag_key: None, tag_value: None, exclude_tax: false }
+    }
+
+    #[test]
+    fn monthly_query_groups_by_month_and_prunes_year_month_partitions() {
+        let sql = query_sql(
+            &table(Partitions::YearMonth, None),
+            &selection((2024, 11, 1), (2025, 2, 1), GranularityOption::Monthly),
+            &["111111111111".to_string(), "222222222222".to_string()],
+        );
+        assert_eq!(
+            sql,
+            "SELECT line_item_usage_account_id, line_item_product_code, line_item_line_item_type, \
+             date_trunc('month', line_item_usage_start_date) AS period, SUM(line_item_unblended_cost) AS cost\n\
+             FROM \"billing\".\"cur\"\n\
+             WHERE line_item_usage_start_date >= TIMESTAMP '2024-11-01 00:00:00'\n  \
+             AND line_item_usage_start_date < TIMESTAMP '2025-02-01 00:00:00'\n  \
+             AND line_item_usage_account_id IN ('111111111111', '222222222222')\n  \
+             AND CAST(year AS integer) * 100 + CAST(month AS integer) BETWEEN 202411 AND 202501\n\
+             GROUP BY 1, 2, 3, 4"
+        );
+    }
+
+    #[test]
+    fn daily_query_prunes_billing_periods_and_filters_tax_and_tag() {
+        let mut selection = selection((2024, 12, 30), (2025, 1, 2), GranularityOption::Daily);
+        selection.exclude_tax = true;
+        selection.tag_value = Some("o'brien");
+        let sql = query_sql(&table(Partitions::BillingPeriod, Some("NULLIF(\"resource_tags_user_owner\", '')")), &selection, &["111111111111".to_string()]);
+
+        assert!(sql.contains("date_trunc('day', line_item_usage_start_date) AS period"), "{}", sql);
+        assert!(sql.contains("\n  AND billing_period BETWEEN '2024-12' AND '2025-01'"), "{}", sql);
+        assert!(sql.contains("\n  AND line_item_line_item_type <> 'Tax'"), "{}", sql);
+        assert!(sql.contains(", NULLIF(\"resource_tags_user_owner\", '') AS tag\n"), "{}", sql);
+        assert!(sql.contains("\n  AND NULLIF(\"resource_tags_user_owner\", '') = 'o''brien'"), "{}", sql);
+        assert!(sql.ends_with("GROUP BY 1, 2, 3, 4, 6"), "{}", sql);
+    }
+
+    #[test]
+    fn hourly_query_without_partitions_has_no_partition_predicate() {
+        let sql = query_sql(&table(Partitions::None, None), &selection((2024, 3, 1), (2024, 3, 2), GranularityOption::Hourly), &["111111111111".to_string()]);
+        assert!(sql.contains("date_trunc('hour', line_item_usage_start_date)"), "{}", sql);
+        assert!(!sql.contains("BETWEEN"), "{}", sql);
+    }
+
+    #[test]
+    fn identifiers_and_literals_are_quoted_and_escaped() {
+        assert_eq!(identifier("cur_2024"), "\"cur_2024\"");
+        assert_eq!(identifier("my\"table"), "\"my\"\"table\"");
+        assert_eq!(literal("team"), "'team'");
+        assert_eq!(literal("'; DROP TABLE cur; --"), "'''; DROP TABLE cur; --'");
+    }
+
+    #[tokio::test]
+    async fn legacy_cur_tables_use_the_tag_column_and_year_month_partitions() {
+        let mock = MockAthena::new(&[&CUR_COLUMNS[..], &["resource_tags_user_cost_center"]].concat(), &["year", "month"]);
+        let table = describe_table(&mock.client(), "billing", "cur", Some("CostCenter")).await.unwrap();
+        assert_eq!(table.partitions, Partitions::YearMonth);
+        assert_eq!(table.tag.as_deref(), Some("NULLIF(\"resource_tags_user_cost_center\", '')"));
+    }
+
+    #[tokio::test]
+    async fn cur_2_tables_look_tags_up_in_the_map_under_every_key_spelling() {
+        let mock = MockAthena::new(&[&CUR_COLUMNS[..], &[TAGS_MAP_COLUMN]].concat(), &["billing_period"]);
+        let table = describe_table(&mock.client(), "billing", "cur", Some("CostCenter")).await.unwrap();
+        assert_eq!(table.partitions, Partitions::BillingPeriod);
+        assert_eq!(
+            table.tag.as_deref(),
+            Some("NULLIF(COALESCE(element_at(resource_tags, 'user_CostCenter'), element_at(resource_tags, 'user_cost_center'), element_at(resource_tags, 'user_costcenter')), '')")
+        );
+        let untagged = describe_table(&mock.client(), "billing", "cur", None).await.unwrap();
+        assert_eq!(untagged.tag, None);
+    }
+
+    #[tokio::test]
+    async fn tables_without_cur_or_tag_columns_are_rejected() {
+        let mock = MockAthena::new(&CUR_COLUMNS[..4], &[]);
+        let error = describe_table(&mock.client(), "billing", "cur", None).await.err().unwrap();
+        assert_eq!(error.to_string(), "Athena table billing.cur is not a CUR table: it has no line_item_line_item_type column");
+
+        let mock = MockAthena::new(&CUR_COLUMNS, &[]);
+        let error = describe_table(&mock.client(), "billing", "cur", Some("team")).await.err().unwrap();
+        assert_eq!(error.to_string(), "Athena table billing.cur has no resource_tags_user_team column or resource_tags map for --tag-key team");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn queries_running_past_the_timeout_are_stopped() {
+        let mock = MockAthena { state: "RUNNING", ..MockAthena::new(&CUR_COLUMNS, &[]) };
+        let started = Instant::now();
+        let error = wait(&mock.client(), "query-1", std::time::Duration::from_secs(10)).await.err().unwrap();
+
+        assert_eq!(error.to_string(), "Athena query query-1 did not finish within 10s and was stopped; raise --athena-timeout");
+        assert_eq!(started.elapsed().as_secs(), 10);
+        // Polled after 0, 0.5, 1.5, 3.5, 7.5 and 10 seconds
+        assert_eq!(mock.count("GetQueryExecution"), 6);
+        assert_eq!(mock.count("StopQueryExecution"), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failed_queries_report_their_reason_with_the_permissions_hint() {
+        let mock = MockAthena { state: "FAILED", reason: Some("Access Denied when writing to location: s3://results/"), ..MockAthena::new(&CUR_COLUMNS, &[]) };
+        let error = wait(&mock.client(), "query-1", std::time::Duration::from_secs(10)).await.err().unwrap().to_string();
+        assert!(error.starts_with("Athena query query-1 failed: Access Denied when writing to location: s3://results/ (the profile needs"), "{}", error);
+        assert_eq!(mock.count("StopQueryExecution"), 0);
+
+        let mock = MockAthena { state: "SUCCEEDED", ..mock };
+        assert!(wait(&mock.client(), "query-1", std::time::Duration::from_secs(10)).await.is_ok());
+    }
+}
@@ -0,0 +1,60 @@
+use chrono::{Datelike, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+
+/// Month-to-date spend of an account against its `--budget-file` budget.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BudgetBurn {
+    /// First day of the current month.
+    pub month: String,
+    pub budget: f64,
+    pub month_to_date_cost: f64,
+    pub budget_used_percent: f64,
+    /// Days of the month up to and including today, as a percentage of the month.
+    pub month_elapsed_percent: f64,
+    /// `budget_used_percent` minus `month_elapsed_percent`: positive when the account is burning
+    /// through its budget faster than linear.
+    pub overage_percent: f64,
+    /// Month-end cost if spending continues at the month-to-date pace.
+    pub projected_cost: f64,
+    /// `overage_percent` is over `--burn-rate-threshold`.
+    pub exceeds_threshold: bool,
+}
+
+impl BudgetBurn {
+    pub fn new(budget: f64, month_to_date_cost: f64, today: NaiveDate, threshold: f64) -> Self {
+        let month_start = today.with_day(1).expect("every month has a first day");
+        let month_days = ((month_start + Months::new(1)) - month_start).num_days() as f64;
+        let elapsed = f64::from(today.day()) / month_days;
+        let budget_used_percent = month_to_date_cost / budget * 100.0;
+        let overage_percent = budget_used_percent - elapsed * 100.0;
+        BudgetBurn {
+            month: month_start.format("%Y-%m-%d").to_string(),
+            budget,
+            month_to_date_cost,
+            budget_used_percent,
+            month_elapsed_percent: elapsed * 100.0,
+            overage_percent,
+            projected_cost: month_to_date_cost / elapsed,
+            exceeds_threshold: overage_percent > threshold,
+        }
+    }
+}
+
+/// Reads an account ID to monthly budget (USD) map, as TOML when the path ends in `.toml` and JSON otherwise.
+pub fn load(path: &str) -> Result<HashMap<String, f64>, Box<dyn Error>> {
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("Failed to read --budget-file {}: {}", path, e))?;
+    let budgets: HashMap<String, f64> = if path.ends_with(".toml") {
+        toml::from_str(&contents).map_err(|e| format!("Invalid --budget-file {}: {}", path, e))?
+    } else {
+        serde_json::from_str(&contents).map_err(|e| format!("Invalid --budget-file {}: {}", path, e))?
+    };
+    for (account_id, budget) in &budgets {
+        crate::parse_account_id(account_id).map_err(|e| format!("{} in {}", e, path))?;
+        if !(budget.is_finite() && *budget > 0.0) {
+            return Err(format!("Budget for account {} in {} must be a positive amount", account_id, path).into());
+        }
+    }
+    Ok(budgets)
+}
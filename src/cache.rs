@@ -0,0 +1,106 @@
+use crate::GranularityOption;
+use chrono::{Datelike, Duration, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::PathBuf;
+
+/// One Cost Explorer period of an account, as stored in the cache.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CachedPeriod {
+    /// Exclusive end; a period cut short by the end of an earlier range is only reused for that same range end.
+    pub end: String,
+    pub total: f64,
+    pub services: BTreeMap<String, f64>,
+    /// Totals of the metrics requested besides the report's (`--amortization-delta`, `--compare-metrics`).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metrics: BTreeMap<String, f64>,
+    /// Costs by record type, in caches written with `--show-credits`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub record_types: Option<BTreeMap<String, f64>>,
+    /// Cost Explorer flagged the period as estimated, so it is always fetched again.
+    pub estimated: bool,
+}
+
+/// Cached periods of one account, keyed by period start.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct AccountCache {
+    pub periods: BTreeMap<String, CachedPeriod>,
+}
+
+/// On-disk cache of per-account Cost Explorer results (`--cache-dir`), one JSON file per account and query shape.
+pub struct CostCache {
+    dir: PathBuf,
+    /// Periods ending within this many days of today are re-fetched, since AWS restates recent costs.
+    recent_days: i64,
+}
+
+impl CostCache {
+    pub fn new(dir: &str, recent_days: u32) -> io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        Ok(CostCache { dir: PathBuf::from(dir), recent_days: i64::from(recent_days) })
+    }
+
+    /// Cache file for an account's results at `granularity` with the given metric and tag filter or grouping,
+    /// since each of those changes what Cost Explorer returns.
+    fn path(&self, account_id: &str, granularity: &GranularityOption, metric: &str, tag: &str) -> PathBuf {
+        let mut name = format!("{}_{}_{}", account_id, granularity.name(), metric);
+        if !tag.is_empty() {
+            name.push_str("_tag-");
+            name.extend(tag.chars().map(|c| if c.is_ascii_alphanumeric() || c == '=' { c } else { '-' }));
+        }
+        self.dir.join(format!("{}.json", name))
+    }
+
+    /// The account's cached periods; empty when there is no cache file or it can't be read.
+    pub fn load(&self, account_id: &str, granularity: &GranularityOption, metric: &str, tag: &str) -> AccountCache {
+        let path = self.path(account_id, granularity, metric, tag);
+        let Ok(contents) = std::fs::read_to_string(&path) else {
+            return AccountCache::default();
+        };
+        serde_json::from_str(&contents).unwrap_or_else(|e| {
+            warn!("Warning: ignoring unreadable cache file {}: {}", path.display(), e);
+            AccountCache::default()
+        })
+    }
+
+    /// Writes the account's periods, replacing the file atomically.
+    pub fn save(&self, account_id: &str, granularity: &GranularityOption, metric: &str, tag: &str, cache: &AccountCache) -> io::Result<()> {
+        let path = self.path(account_id, granularity, metric, tag);
+        let temp_path = path.with_extension(format!("json.tmp-{}", std::process::id()));
+        std::fs::write(&temp_path, serde_json::to_string(cache)?)?;
+        std::fs::rename(&temp_path, &path)
+    }
+
+    /// Leading periods of `[start, end)` that can be taken from `cache` — final, covering the same
+    /// dates, and ended at least `recent_days` before `today` — and the date to query Cost Explorer from.
+    pub fn reusable_prefix(
+        &self,
+        cache: &AccountCache,
+        granularity: &GranularityOption,
+        start: NaiveDate,
+        end: NaiveDate,
+        today: NaiveDate,
+    ) -> (Vec<String>, NaiveDate) {
+        let settled_before = today - Duration::days(self.recent_days);
+        let mut reused = Vec::new();
+        let mut period_start = start;
+        while period_start < end {
+            let next = match granularity {
+                GranularityOption::Daily => period_start + Duration::days(1),
+                _ => period_start.with_day0(0).unwrap_or(period_start) + Months::new(1),
+            }
+            .min(end);
+            let key = period_start.format("%Y-%m-%d").to_string();
+            let reusable = cache.periods.get(&key).is_some_and(|cached| {
+                !cached.estimated && cached.end == next.format("%Y-%m-%d").to_string() && next <= settled_before
+            });
+            if !reusable {
+                break;
+            }
+            reused.push(key);
+            period_start = next;
+        }
+        (reused, period_start)
+    }
+}
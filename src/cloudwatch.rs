@@ -0,0 +1,82 @@
+use crate::AccountCostData;
+use aws_config::SdkConfig;
+use aws_sdk_cloudwatch::primitives::DateTime;
+use aws_sdk_cloudwatch::types::{Dimension, MetricDatum, StandardUnit};
+use aws_sdk_cloudwatch::Client as CloudWatchClient;
+use chrono::NaiveDate;
+use std::error::Error;
+
+/// PutMetricData accepts at most 20 metric datums per request.
+const MAX_DATUMS_PER_REQUEST: usize = 20;
+
+/// Converts a Cost Explorer period start (`2025-06-01` or `2025-06-01T00:00:00Z`) into a metric timestamp.
+fn period_timestamp(period: &str) -> Option<DateTime> {
+    if let Ok(date) = NaiveDate::parse_from_str(period, "%Y-%m-%d") {
+        return Some(DateTime::from_secs(date.and_hms_opt(0, 0, 0)?.and_utc().timestamp()));
+    }
+    chrono::DateTime::parse_from_rfc3339(period)
+        .ok()
+        .map(|dt| DateTime::from_secs(dt.timestamp()))
+}
+
+fn dimension(name: &str, value: &str) -> Dimension {
+    Dimension::builder().name(name).value(value).build()
+}
+
+/// One `TotalCost` datum per account and one `ServiceCost` datum per service, for each account's latest period.
+pub fn build_datums(accounts: &[AccountCostData]) -> Vec<MetricDatum> {
+    let mut datums = Vec::new();
+    for account in accounts {
+        let Some(latest) = account.cost_trend.last() else {
+            continue;
+        };
+        let timestamp = period_timestamp(&latest.month);
+        let account_dimensions = [
+            dimension("AccountId", &account.account_id),
+            dimension("AccountName", &account.account_name),
+        ];
+
+        datums.push(
+            MetricDatum::builder()
+                .metric_name("TotalCost")
+                .set_dimensions(Some(account_dimensions.to_vec()))
+                .set_timestamp(timestamp)
+                .value(latest.total_cost)
+                .unit(StandardUnit::None)
+                .build(),
+        );
+
+        for service in &account.service_consumption {
+            let cost = service.monthly_costs.get(&latest.month).copied().unwrap_or(0.0);
+            let mut dimensions = account_dimensions.to_vec();
+            dimensions.push(dimension("Service", &service.service));
+            datums.push(
+                MetricDatum::builder()
+                    .metric_name("ServiceCost")
+                    .set_dimensions(Some(dimensions))
+                    .set_timestamp(timestamp)
+                    .value(cost)
+                    .unit(StandardUnit::None)
+                    .build(),
+            );
+        }
+    }
+    datums
+}
+
+/// Publishes the datums in request-sized batches, returning how many were accepted.
+pub async fn publish(config: &SdkConfig, namespace: &str, datums: Vec<MetricDatum>) -> Result<usize, Box<dyn Error>> {
+    let client = CloudWatchClient::new(config);
+    let mut published = 0;
+    for batch in datums.chunks(MAX_DATUMS_PER_REQUEST) {
+        client
+            .put_metric_data()
+            .namespace(namespace)
+            .set_metric_data(Some(batch.to_vec()))
+            .send()
+            .await
+            .map_err(|e| format!("PutMetricData failed after {} datapoints: {}", published, e))?;
+        published += batch.len();
+    }
+    Ok(published)
+}
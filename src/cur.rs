@@ -0,0 +1,563 @@
+use crate::{Cli, GranularityOption, TaxHandling, TAX};
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Float64Type, TimestampSecondType};
+use arrow_array::{Array, ArrayRef, RecordBatch};
+use arrow_cast::cast;
+use arrow_schema::{DataType, TimeUnit};
+use aws_config::SdkConfig;
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::Client as S3Client;
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::ValueEnum;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ProjectionMask;
+use parquet::file::reader::ChunkReader;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// Where costs are read from.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum CostSource {
+    /// Cost Explorer.
+    Api,
+    /// Cost and Usage Report parquet files under `--cur-path`.
+    Cur,
+    /// The Cost and Usage Report table `--athena-table`, queried with Athena.
+    Athena,
+}
+
+pub const ACCOUNT_COLUMN: &str = "line_item_usage_account_id";
+pub const SERVICE_COLUMN: &str = "line_item_product_code";
+pub const COST_COLUMN: &str = "line_item_unblended_cost";
+pub const START_COLUMN: &str = "line_item_usage_start_date";
+/// `Usage`, `Tax`, `Credit`, ...; Cost Explorer's record types under the same names.
+pub const LINE_ITEM_TYPE_COLUMN: &str = "line_item_line_item_type";
+/// CUR 2.0 keeps every tag in one map column, with keys such as `user_team`.
+pub const TAGS_MAP_COLUMN: &str = "resource_tags";
+
+/// Costs of one account read from the CUR, in the shape the Cost Explorer query builds.
+#[derive(Default)]
+struct AccountCur {
+    services: BTreeMap<String, BTreeMap<String, f64>>,
+    record_types: BTreeMap<String, BTreeMap<String, f64>>,
+}
+
+/// `--source cur` costs of every account in the files, by period.
+#[derive(Default)]
+pub struct CurCosts {
+    accounts: HashMap<String, AccountCur>,
+    /// Line items read, for the log.
+    pub line_items: usize,
+    pub files: usize,
+}
+
+impl CurCosts {
+    /// Adds an account's costs to the maps the Cost Explorer query fills: totals, services and
+    /// record types by period.
+    pub fn fill(
+        &self,
+        account_id: &str,
+        totals: &mut BTreeMap<String, f64>,
+        services: &mut BTreeMap<String, BTreeMap<String, f64>>,
+        record_types: &mut BTreeMap<String, BTreeMap<String, f64>>,
+    ) {
+        let Some(account) = self.accounts.get(account_id) else {
+            return;
+        };
+        for (service, costs) in &account.services {
+            for (period, cost) in costs {
+                *totals.entry(period.clone()).or_insert(0.0) += cost;
+                *services.entry(service.clone()).or_default().entry(period.clone()).or_insert(0.0) += cost;
+            }
+        }
+        for (record_type, costs) in &account.record_types {
+            for (period, cost) in costs {
+                *record_types.entry(record_type.clone()).or_default().entry(period.clone()).or_insert(0.0) += cost;
+            }
+        }
+    }
+
+    /// Adds the cost of line items of one account, service, line item type and period, which the
+    /// caller has already checked against the selection.
+    pub fn add(&mut self, account_id: &str, service: String, line_type: &str, period: String, cost: f64) {
+        let account = self.accounts.entry(account_id.to_string()).or_default();
+        *account.services.entry(service).or_default().entry(period.clone()).or_insert(0.0) += cost;
+        *account.record_types.entry(line_type.to_string()).or_default().entry(period).or_insert(0.0) += cost;
+    }
+}
+
+/// What to keep of the line items: the report range, the `--tag-key`/`--tag-value` filter or
+/// grouping, and tax with `--tax-handling exclude`.
+pub struct Selection<'a> {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub granularity: GranularityOption,
+    pub tag_key: Option<&'a str>,
+    pub tag_value: Option<&'a str>,
+    pub exclude_tax: bool,
+}
+
+impl<'a> Selection<'a> {
+    /// The report's selection for the `YYYY-MM-DD` range `[start, end)`.
+    pub fn new(cli: &'a Cli, start: &str, end: &str) -> Result<Self, Box<dyn Error>> {
+        let day = |date: &str| -> Result<DateTime<Utc>, Box<dyn Error>> {
+            Ok(NaiveDate::parse_from_str(date, "%Y-%m-%d")?.and_hms_opt(0, 0, 0).ok_or("invalid date")?.and_utc())
+        };
+        Ok(Selection {
+            start: day(start)?,
+            end: day(end)?,
+            granularity: cli.query_granularity(),
+            tag_key: cli.tag_key.as_deref(),
+            tag_value: cli.tag_value.as_deref(),
+            exclude_tax: cli.tax_handling == TaxHandling::Exclude,
+        })
+    }
+
+    /// Service row of a line item: its product code, labelled with the tag when grouping by `--tag-key`.
+    pub fn service(&self, product_code: Option<&str>, line_type: &str, tag: Option<&str>) -> String {
+        // Cost Explorer puts tax under a `Tax` service of its own
+        let service = match (line_type, product_code) {
+            (TAX, _) => TAX.to_string(),
+            (_, Some(code)) if !code.is_empty() => code.to_string(),
+            _ => "N/A".to_string(),
+        };
+        match (self.tag_key, self.tag_value) {
+            (Some(key), None) => format!("{}, {}${}", service, key, tag.unwrap_or_default()),
+            _ => service,
+        }
+    }
+
+    /// Period a line item starting at `start` falls in, keyed like Cost Explorer's period starts.
+    pub fn period(&self, start: DateTime<Utc>) -> String {
+        match self.granularity {
+            GranularityOption::Monthly | GranularityOption::Quarterly => start.format("%Y-%m-01").to_string(),
+            GranularityOption::Daily | GranularityOption::Weekly => start.format("%Y-%m-%d").to_string(),
+            GranularityOption::Hourly => start.format("%Y-%m-%dT%H:00:00Z").to_string(),
+        }
+    }
+}
+
+/// Reads every parquet file under `--cur-path`, a directory, a file or an `s3://bucket/prefix`, and
+/// sums the line items starting in `[start, end)` by account, period and service.
+pub async fn load(cli: &Cli, config: &SdkConfig, start: &str, end: &str) -> Result<CurCosts, Box<dyn Error>> {
+    let path = cli.cur_path.as_deref().ok_or("--source cur requires --cur-path")?;
+    let selection = Selection::new(cli, start, end)?;
+
+    let mut costs = CurCosts::default();
+    if let Some((bucket, prefix)) = s3_location(path) {
+        let client = S3Client::new(config);
+        for key in list_s3_files(&client, bucket, prefix).await? {
+            let object = client.get_object().bucket(bucket).key(&key).send().await.map_err(|e| format!("Cannot read s3://{}/{}: {}", bucket, key, error_message(&e)))?;
+            let body = object.body.collect().await.map_err(|e| format!("Cannot read s3://{}/{}: {}", bucket, key, e))?;
+            read_file(body.into_bytes(), &selection, &mut costs).map_err(|e| format!("s3://{}/{}: {}", bucket, key, e))?;
+        }
+    } else {
+        let mut files = Vec::new();
+        collect_local_files(Path::new(path), &mut files).map_err(|e| format!("Cannot read --cur-path {}: {}", path, e))?;
+        files.sort();
+        for file in files {
+            let reader = std::fs::File::open(&file).map_err(|e| format!("Cannot open {}: {}", file.display(), e))?;
+            read_file(reader, &selection, &mut costs).map_err(|e| format!("{}: {}", file.display(), e))?;
+        }
+    }
+    if costs.files == 0 {
+        return Err(format!("No .parquet files found under --cur-path {}", path).into());
+    }
+    Ok(costs)
+}
+
+/// Bucket and key prefix of an `s3://bucket/prefix` path; `None` for a local path.
+fn s3_location(path: &str) -> Option<(&str, &str)> {
+    let location = path.strip_prefix("s3://")?;
+    Some(location.split_once('/').unwrap_or((location, "")))
+}
+
+fn collect_local_files(path: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    if path.is_file() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(path)? {
+        let entry_path = entry?.path();
+        if entry_path.is_dir() {
+            collect_local_files(&entry_path, files)?;
+        } else if entry_path.extension().is_some_and(|extension| extension == "parquet") {
+            files.push(entry_path);
+        }
+    }
+    Ok(())
+}
+
+async fn list_s3_files(client: &S3Client, bucket: &str, prefix: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut keys = Vec::new();
+    let mut continuation_token = None;
+    loop {
+        let response = client
+            .list_objects_v2()
+            .bucket(bucket)
+            .prefix(prefix)
+            .set_continuation_token(continuation_token)
+            .send()
+            .await
+            .map_err(|e| format!("Cannot list s3://{}/{}: {}", bucket, prefix, error_message(&e)))?;
+        keys.extend(response.contents().iter().filter_map(|object| object.key()).filter(|key| key.ends_with(".parquet")).map(str::to_string));
+        continuation_token = response.next_continuation_token;
+        if continuation_token.is_none() {
+            return Ok(keys);
+        }
+    }
+}
+
+fn error_message<E: ProvideErrorMetadata + std::error::Error>(error: &E) -> String {
+    match (error.code(), error.message()) {
+        (Some(code), Some(message)) => format!("{}: {}", code, message),
+        _ => error.to_string(),
+    }
+}
+
+/// Legacy CUR column names of a tag key: `resource_tags_user_` and the key in snake case, as the
+/// Athena-compatible export writes it (`CostCenter` becomes `cost_center`), or just lowercased.
+pub fn tag_columns(key: &str) -> [String; 2] {
+    let mut snake = String::new();
+    let mut previous_lower = false;
+    for c in key.chars() {
+        if c.is_ascii_uppercase() && previous_lower {
+            snake.push('_');
+        }
+        snake.push(if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' });
+        previous_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+    }
+    let lower: String = key.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' }).collect();
+    [format!("resource_tags_user_{}", snake), format!("resource_tags_user_{}", lower)]
+}
+
+fn read_file<R: ChunkReader + 'static>(reader: R, selection: &Selection, costs: &mut CurCosts) -> Result<(), Box<dyn Error>> {
+    let builder = ParquetRecordBatchReaderBuilder::try_new(reader)?;
+    let schema = builder.schema().clone();
+    let tag_column = selection.tag_key.map(|key| {
+        tag_columns(key)
+            .into_iter()
+            .find(|name| schema.index_of(name).is_ok())
+            .unwrap_or_else(|| TAGS_MAP_COLUMN.to_string())
+    });
+    let mut wanted = vec![ACCOUNT_COLUMN, SERVICE_COLUMN, COST_COLUMN, START_COLUMN];
+    for column in &wanted {
+        if schema.index_of(column).is_err() {
+            return Err(format!("not a CUR file: it has no {} column", column).into());
+        }
+    }
+    wanted.push(LINE_ITEM_TYPE_COLUMN);
+    if let Some(column) = &tag_column {
+        wanted.push(column);
+    }
+    let roots: Vec<usize> = wanted.iter().filter_map(|column| schema.index_of(column).ok()).collect();
+    let mask = ProjectionMask::roots(builder.parquet_schema(), roots);
+    for batch in builder.with_projection(mask).build()? {
+        read_batch(&batch?, selection, tag_column.as_deref(), costs)?;
+    }
+    costs.files += 1;
+    Ok(())
+}
+
+fn column(batch: &RecordBatch, name: &str, data_type: &DataType) -> Result<Option<ArrayRef>, Box<dyn Error>> {
+    match batch.column_by_name(name) {
+        Some(array) => Ok(Some(cast(array, data_type).map_err(|e| format!("column {}: {}", name, e))?)),
+        None => Ok(None),
+    }
+}
+
+fn read_batch(batch: &RecordBatch, selection: &Selection, tag_column: Option<&str>, costs: &mut CurCosts) -> Result<(), Box<dyn Error>> {
+    let required = |name: &str, data_type: &DataType| -> Result<ArrayRef, Box<dyn Error>> {
+        column(batch, name, data_type)?.ok_or_else(|| format!("no {} column", name).into())
+    };
+    let accounts: ArrayRef = required(ACCOUNT_COLUMN, &DataType::Utf8)?;
+    let services: ArrayRef = required(SERVICE_COLUMN, &DataType::Utf8)?;
+    let amounts: ArrayRef = required(COST_COLUMN, &DataType::Float64)?;
+    let starts: ArrayRef = required(START_COLUMN, &DataType::Timestamp(TimeUnit::Second, None))?;
+    let line_types = column(batch, LINE_ITEM_TYPE_COLUMN, &DataType::Utf8)?;
+    let (accounts, services) = (accounts.as_string::<i32>(), services.as_string::<i32>());
+    let (amounts, starts) = (amounts.as_primitive::<Float64Type>(), starts.as_primitive::<TimestampSecondType>());
+    let line_types = line_types.as_ref().map(|types| types.as_string::<i32>());
+    let tags = tag_column.map(|name| tag_values(batch, name, selection.tag_key.unwrap_or_default())).transpose()?;
+
+    for row in 0..batch.num_rows() {
+        if accounts.is_null(row) || amounts.is_null(row) || starts.is_null(row) {
+            continue;
+        }
+        let Some(start) = DateTime::from_timestamp(starts.value(row), 0) else {
+            continue;
+        };
+        if start < selection.start || start >= selection.end {
+            continue;
+        }
+        let line_type = line_types.filter(|types| !types.is_null(row)).map_or("Usage", |types| types.value(row));
+        if selection.exclude_tax && line_type == TAX {
+            continue;
+        }
+        let tag = tags.as_ref().and_then(|tags| tags[row].as_deref());
+        if selection.tag_value.is_some_and(|value| tag != Some(value)) {
+            continue;
+        }
+        let product_code = (!services.is_null(row)).then(|| services.value(row));
+        costs.add(accounts.value(row), selection.service(product_code, line_type, tag), line_type, selection.period(start), amounts.value(row));
+        costs.line_items += 1;
+    }
+    Ok(())
+}
+
+/// The tag's value on each row, from its legacy column or the CUR 2.0 tag map.
+fn tag_values(batch: &RecordBatch, column_name: &str, key: &str) -> Result<Vec<Option<String>>, Box<dyn Error>> {
+    let Some(array) = batch.column_by_name(column_name) else {
+        return Ok(vec![None; batch.num_rows()]);
+    };
+    if column_name != TAGS_MAP_COLUMN {
+        let values = cast(array, &DataType::Utf8)?;
+        let values = values.as_string::<i32>();
+        return Ok((0..batch.num_rows()).map(|row| (!values.is_null(row)).then(|| values.value(row).to_string()).filter(|v| !v.is_empty())).collect());
+    }
+    let DataType::Map(..) = array.data_type() else {
+        return Err(format!("column {} is not a map", TAGS_MAP_COLUMN).into());
+    };
+    let map = array.as_map();
+    let wanted: Vec<String> = tag_columns(key).iter().map(|name| name.trim_start_matches("resource_tags_").to_string()).collect();
+    let mut values = Vec::with_capacity(batch.num_rows());
+    for row in 0..batch.num_rows() {
+        if map.is_null(row) {
+            values.push(None);
+            continue;
+        }
+        let entries = map.value(row);
+        let keys = cast(entries.column(0), &DataType::Utf8)?;
+        let tag_values = cast(entries.column(1), &DataType::Utf8)?;
+        let (keys, tag_values) = (keys.as_string::<i32>(), tag_values.as_string::<i32>());
+        let value = (0..keys.len())
+            .find(|&i| !keys.is_null(i) && (keys.value(i) == format!("user_{}", key) || wanted.iter().any(|name| name == keys.value(i))))
+            .filter(|&i| !tag_values.is_null(i))
+            .map(|i| tag_values.value(i).to_string());
+        values.push(value);
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::builder::{MapBuilder, StringBuilder};
+    use arrow_array::{Float64Array, StringArray, TimestampMillisecondArray};
+    use aws_config::BehaviorVersion;
+    use chrono::{NaiveDateTime, TimeZone};
+    use parquet::arrow::ArrowWriter;
+    use std::sync::Arc;
+
+    /// A line item: account, product code, line item type, usage start and cost.
+    type LineItem = (&'static str, &'static str, &'static str, &'static str, f64);
+
+    const MANAGEMENT: &str = "111111111111";
+    const MEMBER: &str = "222222222222";
+
+    fn selection(start: (i32, u32, u32), end: (i32, u32, u32), granularity: GranularityOption) -> Selection<'static> {
+        let day = |(year, month, day): (i32, u32, u32)| Utc.with_ymd_and_hms(year, month, day, 0, 0, 0).unwrap();
+        Selection { start: day(start), end: day(end), granularity, tag_key: None, tag_value: None, exclude_tax: false }
+    }
+
+    /// The CUR columns of `items`, with usage starts as millisecond timestamps like the export writes them.
+    fn line_item_columns(items: &[LineItem]) -> Vec<(&'static str, ArrayRef)> {
+        let starts = items.iter().map(|item| NaiveDateTime::parse_from_str(item.3, "%Y-%m-%d %H:%M").unwrap().and_utc().timestamp_millis());
+        vec![
+            (ACCOUNT_COLUMN, Arc::new(StringArray::from_iter_values(items.iter().map(|item| item.0))) as ArrayRef),
+            (SERVICE_COLUMN, Arc::new(StringArray::from_iter_values(items.iter().map(|item| item.1)))),
+            (LINE_ITEM_TYPE_COLUMN, Arc::new(StringArray::from_iter_values(items.iter().map(|item| item.2)))),
+            (START_COLUMN, Arc::new(TimestampMillisecondArray::from_iter_values(starts))),
+            (COST_COLUMN, Arc::new(Float64Array::from_iter_values(items.iter().map(|item| item.4)))),
+        ]
+    }
+
+    /// A CUR 2.0 `resource_tags` map column with the given tags on each row.
+    fn tag_map(rows: &[&[(&str, &str)]]) -> ArrayRef {
+        let mut builder = MapBuilder::new(None, StringBuilder::new(), StringBuilder::new());
+        for tags in rows {
+            for (key, value) in *tags {
+                builder.keys().append_value(key);
+                builder.values().append_value(value);
+            }
+            builder.append(true).unwrap();
+        }
+        Arc::new(builder.finish())
+    }
+
+    /// Writes `columns` as a parquet file at `path`.
+    fn write_parquet(path: &Path, columns: Vec<(&str, ArrayRef)>) {
+        let batch = RecordBatch::try_from_iter(columns).unwrap();
+        let mut writer = ArrowWriter::try_new(std::fs::File::create(path).unwrap(), batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+    }
+
+    /// Reads the parquet file with `columns` through `selection`.
+    fn read(name: &str, columns: Vec<(&str, ArrayRef)>, selection: &Selection) -> Result<CurCosts, Box<dyn Error>> {
+        let dir = crate::tests::temp_dir(name);
+        let path = dir.join("cur.parquet");
+        write_parquet(&path, columns);
+        let mut costs = CurCosts::default();
+        let result = read_file(std::fs::File::open(&path).unwrap(), selection, &mut costs);
+        std::fs::remove_dir_all(&dir).unwrap();
+        result.map(|()| costs)
+    }
+
+    /// An account's totals and service costs by period.
+    fn account(costs: &CurCosts, account_id: &str) -> (BTreeMap<String, f64>, BTreeMap<String, BTreeMap<String, f64>>) {
+        let (mut totals, mut services, mut record_types) = (BTreeMap::new(), BTreeMap::new(), BTreeMap::new());
+        costs.fill(account_id, &mut totals, &mut services, &mut record_types);
+        (totals, services)
+    }
+
+    fn periods(costs: &[(&str, f64)]) -> BTreeMap<String, f64> {
+        costs.iter().map(|(period, cost)| (period.to_string(), *cost)).collect()
+    }
+
+    const LINE_ITEMS: [LineItem; 7] = [
+        (MANAGEMENT, "AmazonEC2", "Usage", "2024-01-03 10:00", 10.0),
+        (MANAGEMENT, "AmazonEC2", "Usage", "2024-01-20 00:00", 5.0),
+        (MANAGEMENT, "AmazonS3", "Usage", "2024-02-29 23:00", 2.0),
+        (MANAGEMENT, "AmazonEC2", TAX, "2024-02-01 00:00", 1.5),
+        (MEMBER, "AmazonRDS", "Usage", "2024-03-31 12:00", 7.0),
+        // Outside [2024-01-01, 2024-04-01)
+        (MEMBER, "AmazonRDS", "Usage", "2023-12-31 23:00", 100.0),
+        (MEMBER, "AmazonRDS", "Usage", "2024-04-01 00:00", 100.0),
+    ];
+
+    #[test]
+    fn line_items_are_summed_by_account_period_and_service() {
+        let costs = read("cur-sums", line_item_columns(&LINE_ITEMS), &selection((2024, 1, 1), (2024, 4, 1), GranularityOption::Monthly)).unwrap();
+        assert_eq!((costs.files, costs.line_items), (1, 5));
+
+        let (totals, services) = account(&costs, MANAGEMENT);
+        assert_eq!(totals, periods(&[("2024-01-01", 15.0), ("2024-02-01", 3.5)]));
+        assert_eq!(services["AmazonEC2"], periods(&[("2024-01-01", 15.0)]));
+        assert_eq!(services["AmazonS3"], periods(&[("2024-02-01", 2.0)]));
+        // Tax gets a service of its own, as in Cost Explorer
+        assert_eq!(services[TAX], periods(&[("2024-02-01", 1.5)]));
+
+        let (totals, _) = account(&costs, MEMBER);
+        assert_eq!(totals, periods(&[("2024-03-01", 7.0)]));
+    }
+
+    #[test]
+    fn excluded_tax_is_dropped_and_daily_periods_are_days() {
+        let selection = Selection { exclude_tax: true, ..selection((2024, 1, 1), (2024, 4, 1), GranularityOption::Daily) };
+        let costs = read("cur-daily", line_item_columns(&LINE_ITEMS), &selection).unwrap();
+        let (totals, services) = account(&costs, MANAGEMENT);
+        assert_eq!(totals, periods(&[("2024-01-03", 10.0), ("2024-01-20", 5.0), ("2024-02-29", 2.0)]));
+        assert!(!services.contains_key(TAX));
+    }
+
+    #[test]
+    fn files_without_the_cur_columns_are_rejected() {
+        let mut columns = line_item_columns(&LINE_ITEMS);
+        columns.retain(|(name, _)| *name != COST_COLUMN);
+        let error = read("cur-not-cur", columns, &selection((2024, 1, 1), (2024, 4, 1), GranularityOption::Monthly)).err().unwrap();
+        assert_eq!(error.to_string(), "not a CUR file: it has no line_item_unblended_cost column");
+    }
+
+    const TAGGED: [LineItem; 3] = [
+        (MANAGEMENT, "AmazonEC2", "Usage", "2024-01-03 10:00", 10.0),
+        (MANAGEMENT, "AmazonEC2", "Usage", "2024-01-04 10:00", 20.0),
+        (MANAGEMENT, "AmazonEC2", "Usage", "2024-01-05 10:00", 40.0),
+    ];
+
+    #[test]
+    fn legacy_tag_columns_filter_and_label_line_items() {
+        let mut columns = line_item_columns(&TAGGED);
+        let tags: ArrayRef = Arc::new(StringArray::from(vec![Some("payments"), Some("search"), None]));
+        columns.push(("resource_tags_user_cost_center", tags));
+
+        let filtered = Selection { tag_key: Some("CostCenter"), tag_value: Some("payments"), ..selection((2024, 1, 1), (2024, 2, 1), GranularityOption::Monthly) };
+        let costs = read("cur-legacy-filter", columns.clone(), &filtered).unwrap();
+        assert_eq!(account(&costs, MANAGEMENT).1["AmazonEC2"], periods(&[("2024-01-01", 10.0)]));
+
+        let grouped = Selection { tag_key: Some("CostCenter"), ..selection((2024, 1, 1), (2024, 2, 1), GranularityOption::Monthly) };
+        let costs = read("cur-legacy-group", columns, &grouped).unwrap();
+        let services: Vec<String> = account(&costs, MANAGEMENT).1.into_keys().collect();
+        assert_eq!(services, ["AmazonEC2, CostCenter$", "AmazonEC2, CostCenter$payments", "AmazonEC2, CostCenter$search"]);
+    }
+
+    #[test]
+    fn cur_2_tags_are_looked_up_in_the_resource_tags_map() {
+        let mut columns = line_item_columns(&TAGGED);
+        columns.push((TAGS_MAP_COLUMN, tag_map(&[&[("user_cost_center", "payments")], &[("user_costcenter", "payments"), ("user_team", "a")], &[("user_team", "payments")]])));
+        let filtered = Selection { tag_key: Some("CostCenter"), tag_value: Some("payments"), ..selection((2024, 1, 1), (2024, 2, 1), GranularityOption::Monthly) };
+        let costs = read("cur-tag-map", columns, &filtered).unwrap();
+        assert_eq!(costs.line_items, 2);
+        assert_eq!(account(&costs, MANAGEMENT).0, periods(&[("2024-01-01", 30.0)]));
+    }
+
+    #[test]
+    fn tag_keys_map_to_snake_case_and_lowercased_columns() {
+        assert_eq!(tag_columns("CostCenter"), ["resource_tags_user_cost_center", "resource_tags_user_costcenter"]);
+        assert_eq!(tag_columns("team"), ["resource_tags_user_team", "resource_tags_user_team"]);
+        assert_eq!(tag_columns("aws:Env-2"), ["resource_tags_user_aws_env_2", "resource_tags_user_aws_env_2"]);
+    }
+
+    #[test]
+    fn periods_are_keyed_like_cost_explorer_period_starts() {
+        let start = Utc.with_ymd_and_hms(2024, 2, 14, 9, 30, 0).unwrap();
+        let period = |granularity| selection((2024, 1, 1), (2024, 4, 1), granularity).period(start);
+        assert_eq!(period(GranularityOption::Monthly), "2024-02-01");
+        assert_eq!(period(GranularityOption::Quarterly), "2024-02-01");
+        assert_eq!(period(GranularityOption::Daily), "2024-02-14");
+        assert_eq!(period(GranularityOption::Weekly), "2024-02-14");
+        assert_eq!(period(GranularityOption::Hourly), "2024-02-14T09:00:00Z");
+    }
+
+    #[test]
+    fn services_are_labelled_with_tax_and_tag_values() {
+        let plain = selection((2024, 1, 1), (2024, 4, 1), GranularityOption::Monthly);
+        assert_eq!(plain.service(Some("AmazonEC2"), "Usage", None), "AmazonEC2");
+        assert_eq!(plain.service(Some("AmazonEC2"), TAX, None), TAX);
+        assert_eq!(plain.service(Some(""), "Usage", None), "N/A");
+        assert_eq!(plain.service(None, "Credit", None), "N/A");
+
+        let grouped = Selection { tag_key: Some("Team"), ..selection((2024, 1, 1), (2024, 4, 1), GranularityOption::Monthly) };
+        assert_eq!(grouped.service(Some("AmazonEC2"), "Usage", Some("payments")), "AmazonEC2, Team$payments");
+        assert_eq!(grouped.service(Some("AmazonEC2"), TAX, None), "Tax, Team$");
+        let filtered = Selection { tag_value: Some("payments"), ..grouped };
+        assert_eq!(filtered.service(Some("AmazonEC2"), "Usage", Some("payments")), "AmazonEC2");
+    }
+
+    #[test]
+    fn s3_paths_are_split_into_bucket_and_prefix() {
+        assert_eq!(s3_location("s3://billing/cur/export"), Some(("billing", "cur/export")));
+        assert_eq!(s3_location("s3://billing"), Some(("billing", "")));
+        assert_eq!(s3_location("/data/cur"), None);
+        assert_eq!(s3_location("cur/s3://billing"), None);
+    }
+
+    #[tokio::test]
+    async fn local_paths_read_every_parquet_file_below_them() {
+        let dir = crate::tests::temp_dir("cur-load");
+        std::fs::create_dir_all(dir.join("2024/01")).unwrap();
+        write_parquet(&dir.join("2024/01/part-0.parquet"), line_item_columns(&LINE_ITEMS[..2]));
+        write_parquet(&dir.join("part-1.parquet"), line_item_columns(&LINE_ITEMS[4..]));
+        std::fs::write(dir.join("manifest.json"), "{}").unwrap();
+        let config = SdkConfig::builder().behavior_version(BehaviorVersion::latest()).build();
+
+        let cli = crate::tests::cli(&["--source", "cur", "--cur-path", dir.to_str().unwrap(), "--granularity", "monthly"]);
+        let costs = load(&cli, &config, "2024-01-01", "2024-04-01").await.unwrap();
+        assert_eq!((costs.files, costs.line_items), (2, 3));
+        assert_eq!(account(&costs, MANAGEMENT).0, periods(&[("2024-01-01", 15.0)]));
+        assert_eq!(account(&costs, MEMBER).0, periods(&[("2024-03-01", 7.0)]));
+
+        // A single file
+        let file = dir.join("part-1.parquet");
+        let cli = crate::tests::cli(&["--source", "cur", "--cur-path", file.to_str().unwrap(), "--granularity", "monthly"]);
+        assert_eq!(load(&cli, &config, "2024-01-01", "2024-04-01").await.unwrap().files, 1);
+
+        std::fs::create_dir_all(dir.join("empty")).unwrap();
+        let empty = dir.join("empty");
+        let cli = crate::tests::cli(&["--source", "cur", "--cur-path", empty.to_str().unwrap()]);
+        let error = load(&cli, &config, "2024-01-01", "2024-04-01").await.err().unwrap();
+        assert_eq!(error.to_string(), format!("No .parquet files found under --cur-path {}", empty.display()));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,217 @@
+use chrono::{Datelike, Duration, Months, NaiveDate};
+
+/// Parses a `YYYY-MM-DD` date, rejecting unpadded forms like `2025-1-1` that chrono would accept.
+pub fn parse_date(label: &str, value: &str) -> Result<NaiveDate, String> {
+    let well_formed = value.len() == 10
+        && value
+            .char_indices()
+            .all(|(i, c)| if i == 4 || i == 7 { c == '-' } else { c.is_ascii_digit() });
+    if !well_formed {
+        return Err(format!("Invalid {} '{}': expected YYYY-MM-DD (e.g. 2025-01-01)", label, value));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|e| format!("Invalid {} '{}': {}", label, value, e))
+}
+
+/// Checks a Cost Explorer range, where `end` is exclusive and may be at most tomorrow
+/// (which includes today's costs so far).
+pub fn validate_range(start: NaiveDate, end: NaiveDate, today: NaiveDate) -> Result<(), String> {
+    if start >= end {
+        return Err(format!(
+            "Start date {} must be before end date {} (the end date is exclusive; to report on a single day use --start-date {} --end-date {})",
+            start,
+            end,
+            start,
+            start + Duration::days(1)
+        ));
+    }
+    let tomorrow = today + Duration::days(1);
+    if end > tomorrow {
+        return Err(format!(
+            "End date {} is in the future; use --end-date {} or earlier (the end date is exclusive, so {} includes today)",
+            end, tomorrow, tomorrow
+        ));
+    }
+    Ok(())
+}
+
+/// The exclusive end date sent to Cost Explorer: the day after `end` with `--inclusive-end`.
+pub fn query_end(end: NaiveDate, inclusive: bool) -> NaiveDate {
+    if inclusive {
+        end + Duration::days(1)
+    } else {
+        end
+    }
+}
+
+/// Expands `--month` (`YYYY-MM` or `last`) into the month's first day and the first day of the
+/// following month, clamped to tomorrow for the month in progress.
+pub fn month_range(value: &str, today: NaiveDate) -> Result<(NaiveDate, NaiveDate), String> {
+    let start = if value.eq_ignore_ascii_case("last") {
+        let first_of_this_month = today.with_day(1).expect("day 1 exists in every month");
+        first_of_this_month - Months::new(1)
+    } else {
+        if value.len() != 7 {
+            return Err(format!("Invalid month '{}': expected YYYY-MM (e.g. 2025-06) or 'last'", value));
+        }
+        parse_date("month", &format!("{}-01", value))
+            .map_err(|_| format!("Invalid month '{}': expected YYYY-MM (e.g. 2025-06) or 'last'", value))?
+    };
+    let end = (start + Months::new(1)).min(today + Duration::days(1));
+    Ok((start, end))
+}
+
+/// First day of the current month through today (`--mtd`).
+pub fn month_to_date(today: NaiveDate) -> (NaiveDate, NaiveDate) {
+    (today.with_day(1).expect("day 1 exists in every month"), today + Duration::days(1))
+}
+
+/// Start of the current calendar (or fiscal, when `fiscal_year_start` is set) year through today (`--ytd`).
+pub fn year_to_date(today: NaiveDate, fiscal_year_start: Option<u32>) -> (NaiveDate, NaiveDate) {
+    let start = crate::periods::fiscal_year_start_date(today, fiscal_year_start.unwrap_or(1));
+    (start, today + Duration::days(1))
+}
+
+/// Cost Explorer returns at most 14 days of hourly data per request.
+pub const MAX_HOURLY_DAYS_PER_REQUEST: i64 = 14;
+
+/// Splits `[start, end)` into consecutive intervals of at most `max_days` days.
+pub fn split_range(start: NaiveDate, end: NaiveDate, max_days: i64) -> Vec<(NaiveDate, NaiveDate)> {
+    let mut intervals = Vec::new();
+    let mut chunk_start = start;
+    while chunk_start < end {
+        let chunk_end = (chunk_start + Duration::days(max_days)).min(end);
+        intervals.push((chunk_start, chunk_end));
+        chunk_start = chunk_end;
+    }
+    intervals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn parse_date_accepts_padded_dates() {
+        assert_eq!(parse_date("start date", "2024-02-29"), Ok(date("2024-02-29")));
+    }
+
+    #[test]
+    fn parse_date_rejects_other_formats_with_a_hint() {
+        for value in ["2025-1-1", "2025/01/01", "01-01-2025", "20250101", "2025-01-01T00:00", ""] {
+            let error = parse_date("start date", value).unwrap_err();
+            assert!(error.contains("expected YYYY-MM-DD"), "{}: {}", value, error);
+        }
+    }
+
+    #[test]
+    fn parse_date_rejects_days_that_dont_exist() {
+        let error = parse_date("end date", "2025-02-29").unwrap_err();
+        assert!(error.starts_with("Invalid end date '2025-02-29'"), "{}", error);
+    }
+
+    #[test]
+    fn validate_range_rejects_empty_and_reversed_ranges() {
+        let today = date("2025-06-15");
+        for (start, end) in [("2025-06-01", "2025-06-01"), ("2025-06-02", "2025-06-01")] {
+            let error = validate_range(date(start), date(end), today).unwrap_err();
+            assert!(error.contains(&format!("must be before end date {}", end)), "{}", error);
+            // Suggests the single-day range
+            assert!(error.contains(&format!("--start-date {} --end-date {}", start, date(start) + Duration::days(1))), "{}", error);
+        }
+    }
+
+    #[test]
+    fn validate_range_allows_through_tomorrow_only() {
+        let today = date("2025-06-15");
+        assert_eq!(validate_range(date("2025-06-01"), date("2025-06-16"), today), Ok(()));
+        let error = validate_range(date("2025-06-01"), date("2025-06-17"), today).unwrap_err();
+        assert!(error.contains("is in the future; use --end-date 2025-06-16 or earlier"), "{}", error);
+    }
+
+    #[test]
+    fn validate_range_accepts_a_single_day() {
+        assert_eq!(validate_range(date("2025-06-14"), date("2025-06-15"), date("2025-06-15")), Ok(()));
+    }
+
+    #[test]
+    fn query_end_moves_inclusive_ends_across_month_and_year_boundaries() {
+        for (end, expected) in [
+            ("2025-06-30", "2025-07-01"),
+            ("2024-02-28", "2024-02-29"),
+            ("2024-02-29", "2024-03-01"),
+            ("2025-02-28", "2025-03-01"),
+            ("2024-12-31", "2025-01-01"),
+        ] {
+            assert_eq!(query_end(date(end), true), date(expected), "{}", end);
+            assert_eq!(query_end(date(end), false), date(end));
+        }
+    }
+
+    #[test]
+    fn fiscal_year_to_date_starts_in_the_previous_calendar_year_before_the_start_month() {
+        assert_eq!(year_to_date(date("2025-03-15"), Some(4)), (date("2024-04-01"), date("2025-03-16")));
+        assert_eq!(year_to_date(date("2025-04-01"), Some(4)), (date("2025-04-01"), date("2025-04-02")));
+    }
+
+    #[test]
+    fn split_range_cuts_14_day_chunks_and_keeps_the_remainder() {
+        let chunks = split_range(date("2025-01-01"), date("2025-02-01"), MAX_HOURLY_DAYS_PER_REQUEST);
+        assert_eq!(
+            chunks,
+            [
+                (date("2025-01-01"), date("2025-01-15")),
+                (date("2025-01-15"), date("2025-01-29")),
+                (date("2025-01-29"), date("2025-02-01")),
+            ]
+        );
+        // Exactly one chunk, then one day more
+        assert_eq!(split_range(date("2025-01-01"), date("2025-01-15"), 14), [(date("2025-01-01"), date("2025-01-15"))]);
+        assert_eq!(split_range(date("2025-01-01"), date("2025-01-16"), 14).len(), 2);
+        assert!(split_range(date("2025-01-01"), date("2025-01-01"), 14).is_empty());
+    }
+
+    #[test]
+    fn split_range_chunks_are_contiguous_across_leap_day() {
+        let chunks = split_range(date("2024-02-20"), date("2024-03-20"), 14);
+        assert_eq!(chunks.first().unwrap().0, date("2024-02-20"));
+        assert_eq!(chunks.last().unwrap().1, date("2024-03-20"));
+        assert!(chunks.windows(2).all(|pair| pair[0].1 == pair[1].0));
+        assert_eq!(chunks[0].1, date("2024-03-05"));
+    }
+
+    #[test]
+    fn month_range_ends_on_the_first_of_the_next_month() {
+        let today = date("2025-06-15");
+        assert_eq!(month_range("2024-02", today), Ok((date("2024-02-01"), date("2024-03-01"))));
+        assert_eq!(month_range("2025-02", today), Ok((date("2025-02-01"), date("2025-03-01"))));
+        assert_eq!(month_range("2024-12", today), Ok((date("2024-12-01"), date("2025-01-01"))));
+        // The month in progress runs through today
+        assert_eq!(month_range("2025-06", today), Ok((date("2025-06-01"), date("2025-06-16"))));
+    }
+
+    #[test]
+    fn month_range_last_is_the_previous_month() {
+        assert_eq!(month_range("last", date("2025-01-31")), Ok((date("2024-12-01"), date("2025-01-01"))));
+        assert_eq!(month_range("LAST", date("2024-03-01")), Ok((date("2024-02-01"), date("2024-03-01"))));
+    }
+
+    #[test]
+    fn month_range_rejects_other_forms() {
+        for value in ["2025-6", "2025-13", "2025/06", "2025-06-01", "june"] {
+            let error = month_range(value, date("2025-06-15")).unwrap_err();
+            assert!(error.contains("expected YYYY-MM"), "{}: {}", value, error);
+        }
+    }
+
+    #[test]
+    fn to_date_ranges_include_today() {
+        assert_eq!(month_to_date(date("2024-02-29")), (date("2024-02-01"), date("2024-03-01")));
+        assert_eq!(month_to_date(date("2025-06-01")), (date("2025-06-01"), date("2025-06-02")));
+        assert_eq!(year_to_date(date("2024-12-31"), None), (date("2024-01-01"), date("2025-01-01")));
+        assert_eq!(year_to_date(date("2025-01-01"), None), (date("2025-01-01"), date("2025-01-02")));
+    }
+}
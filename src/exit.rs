@@ -0,0 +1,124 @@
+use std::error::Error;
+use std::fmt;
+use std::process::ExitCode;
+
+/// Why a run didn't end in a complete report. Each variant maps onto the documented exit codes so
+/// automation can tell them apart:
+///
+/// | Code | Meaning |
+/// |------|---------|
+/// | 0 | Complete report |
+/// | 1 | Unexpected error |
+/// | 2 | A cost alert threshold was exceeded |
+/// | 3 | No cost data retrieved |
+/// | 4 | Partial report: some profiles or accounts were skipped |
+/// | 5 | Invalid arguments |
+/// | 6 | No usable profiles: none found, or all failed the credential check |
+/// | 130 | Interrupted |
+pub enum RunFailure {
+    /// Flags failed to parse or validate.
+    InvalidArguments(clap::Error),
+    /// An hourly range needing many requests was not confirmed with `--yes`.
+    HourlyRangeUnconfirmed(String),
+    NoProfiles,
+    /// Every profile failed the credential check.
+    AuthFailed,
+    NoCostData,
+    /// The report was produced and this many `--alert-threshold` / `--account-alert-threshold` alerts fired.
+    AlertTriggered(usize),
+    /// A report was produced, but this many profiles or accounts were left out of it.
+    Partial(usize),
+    /// Ctrl-C stopped the run; whatever was collected has been written.
+    Interrupted,
+    Other(Box<dyn Error>),
+}
+
+impl RunFailure {
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            RunFailure::Other(_) => ExitCode::from(1),
+            RunFailure::AlertTriggered(_) => ExitCode::from(2),
+            RunFailure::NoCostData => ExitCode::from(3),
+            RunFailure::Partial(_) => ExitCode::from(4),
+            RunFailure::InvalidArguments(_) | RunFailure::HourlyRangeUnconfirmed(_) => ExitCode::from(5),
+            RunFailure::NoProfiles | RunFailure::AuthFailed => ExitCode::from(6),
+            RunFailure::Interrupted => ExitCode::from(130),
+        }
+    }
+
+    /// Value for the `phase` field of the JSON error entry.
+    pub fn phase(&self) -> &'static str {
+        match self {
+            RunFailure::InvalidArguments(_) | RunFailure::HourlyRangeUnconfirmed(_) => "validation",
+            RunFailure::NoProfiles => "profile_discovery",
+            RunFailure::AuthFailed => "credential_check",
+            RunFailure::NoCostData | RunFailure::Partial(_) => "cost_query",
+            RunFailure::AlertTriggered(_) => "alert",
+            RunFailure::Interrupted => "interrupted",
+            RunFailure::Other(_) => "run",
+        }
+    }
+
+    /// Prints the failure to stderr; clap formats its own errors, and alerts and partial reports are only warnings.
+    pub fn print(&self) {
+        match self {
+            RunFailure::InvalidArguments(e) => {
+                let _ = e.print();
+            }
+            RunFailure::AlertTriggered(_) | RunFailure::Partial(_) => warn!("Warning: {}", self),
+            _ => warn!("Error: {}", self),
+        }
+    }
+}
+
+impl fmt::Display for RunFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunFailure::InvalidArguments(e) => write!(f, "{}", e.render().to_string().trim_end()),
+            RunFailure::HourlyRangeUnconfirmed(message) => write!(f, "{}", message),
+            RunFailure::NoProfiles => write!(f, "No AWS profiles found in the shared credentials or config file (~/.aws/credentials, ~/.aws/config, or AWS_SHARED_CREDENTIALS_FILE / AWS_CONFIG_FILE); use --no-profile for the default credential chain."),
+            RunFailure::AuthFailed => write!(f, "Every profile failed the credential check; see the table above."),
+            RunFailure::NoCostData => write!(f, "No cost data retrieved for any accounts across specified profiles."),
+            RunFailure::AlertTriggered(count) => write!(f, "{} cost alert thresholds were exceeded.", count),
+            RunFailure::Partial(skipped) => write!(f, "The report is incomplete: {} profiles or accounts were skipped.", skipped),
+            RunFailure::Interrupted => write!(f, "Interrupted by Ctrl-C; any report written covers only the accounts queried before then."),
+            RunFailure::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl<E: Into<Box<dyn Error>>> From<E> for RunFailure {
+    fn from(e: E) -> Self {
+        RunFailure::Other(e.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_failure_has_its_documented_exit_code() {
+        let invalid = clap::Error::raw(clap::error::ErrorKind::InvalidValue, "bad value");
+        for (failure, code, phase) in [
+            (RunFailure::Other("boom".into()), 1, "run"),
+            (RunFailure::AlertTriggered(1), 2, "alert"),
+            (RunFailure::NoCostData, 3, "cost_query"),
+            (RunFailure::Partial(2), 4, "cost_query"),
+            (RunFailure::InvalidArguments(invalid), 5, "validation"),
+            (RunFailure::HourlyRangeUnconfirmed("confirm".to_string()), 5, "validation"),
+            (RunFailure::NoProfiles, 6, "profile_discovery"),
+            (RunFailure::AuthFailed, 6, "credential_check"),
+            (RunFailure::Interrupted, 130, "interrupted"),
+        ] {
+            assert_eq!(failure.exit_code(), ExitCode::from(code), "{}", failure);
+            assert_eq!(failure.phase(), phase);
+        }
+    }
+
+    #[test]
+    fn invalid_arguments_display_the_whole_message() {
+        let failure = RunFailure::InvalidArguments(clap::Error::raw(clap::error::ErrorKind::InvalidValue, "Start date 2025-06-02 must be before end date 2025-06-01\n"));
+        assert_eq!(failure.to_string(), "error: Start date 2025-06-02 must be before end date 2025-06-01");
+    }
+}
@@ -0,0 +1,88 @@
+use crate::partition::Partition;
+use aws_config::{Region, SdkConfig};
+use aws_sdk_freetier::error::ProvideErrorMetadata;
+use aws_sdk_freetier::Client as FreeTierClient;
+use aws_sdk_sts::Client as StsClient;
+use serde::{Deserialize, Serialize};
+
+/// One Free Tier allowance and how much of it this month's usage has consumed.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FreeTierItem {
+    pub service: String,
+    pub usage_type: String,
+    pub region: Option<String>,
+    /// AWS's description of the offer, such as `750 hours of Amazon EC2 Linux t2.micro`.
+    pub description: Option<String>,
+    pub unit: Option<String>,
+    pub limit: f64,
+    pub actual_usage: f64,
+    pub forecasted_usage: f64,
+    /// `None` for allowances without a limit.
+    pub percent_used: Option<f64>,
+    /// Over `--free-tier-threshold`, so close to turning into charges.
+    pub exceeds_threshold: bool,
+}
+
+/// `--free-tier` usage of the account a profile's credentials belong to.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct FreeTierUsage {
+    /// Most consumed first.
+    pub items: Vec<FreeTierItem>,
+    /// Why usage couldn't be read, e.g. in partitions without the Free Tier API.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The Free Tier API only reports on the caller's own account (for an organization, its management
+/// account, which holds the shared allowance), so this returns that account's ID with its usage.
+/// `None` when the caller can't be identified.
+pub async fn query(config: &SdkConfig, threshold_percent: f64) -> Option<(String, FreeTierUsage)> {
+    let account_id = StsClient::new(config).get_caller_identity().send().await.ok()?.account?;
+    let builder = aws_sdk_freetier::config::Builder::from(config).region(Region::new(Partition::of(config).endpoint_region()));
+    let client = FreeTierClient::from_conf(builder.build());
+    let mut usages = Vec::new();
+    let mut next_token = None;
+    loop {
+        let response = match client.get_free_tier_usage().set_next_token(next_token).send().await {
+            Ok(response) => response,
+            Err(e) => {
+                let error = match (e.code(), e.message()) {
+                    (Some(code), Some(message)) => format!("{}: {}", code, message),
+                    _ => e.to_string(),
+                };
+                return Some((account_id, FreeTierUsage { items: Vec::new(), error: Some(error) }));
+            }
+        };
+        usages.extend(response.free_tier_usages);
+        next_token = response.next_token;
+        if next_token.is_none() {
+            break;
+        }
+    }
+
+    let mut items: Vec<FreeTierItem> = usages
+        .iter()
+        .map(|usage| {
+            let percent_used = (usage.limit() > 0.0).then(|| usage.actual_usage_amount() / usage.limit() * 100.0);
+            FreeTierItem {
+                service: usage.service().unwrap_or("N/A").to_string(),
+                usage_type: usage.usage_type().unwrap_or("N/A").to_string(),
+                region: usage.region().map(str::to_string),
+                description: usage.description().map(str::to_string),
+                unit: usage.unit().map(str::to_string),
+                limit: usage.limit(),
+                actual_usage: usage.actual_usage_amount(),
+                forecasted_usage: usage.forecasted_usage_amount(),
+                percent_used,
+                exceeds_threshold: percent_used.is_some_and(|percent| percent > threshold_percent),
+            }
+        })
+        .collect();
+    items.sort_by(|a, b| {
+        b.percent_used
+            .unwrap_or(0.0)
+            .total_cmp(&a.percent_used.unwrap_or(0.0))
+            .then_with(|| (&a.service, &a.usage_type).cmp(&(&b.service, &b.usage_type)))
+    });
+    Some((account_id, FreeTierUsage { items, error: None }))
+}
@@ -0,0 +1,107 @@
+use crate::AccountCostData;
+use aws_config::SdkConfig;
+use aws_sdk_dynamodb::error::ProvideErrorMetadata;
+use aws_sdk_dynamodb::types::AttributeValue;
+use aws_sdk_dynamodb::Client as DynamoDbClient;
+use futures::future::join_all;
+use std::collections::HashMap;
+use std::error::Error;
+use std::time::Duration;
+
+/// Services stored per item, keeping items well below DynamoDB's 400 KB limit.
+const MAX_SERVICES_PER_ITEM: usize = 50;
+/// Items written concurrently per batch.
+const WRITE_BATCH_SIZE: usize = 25;
+const MAX_WRITE_ATTEMPTS: u32 = 5;
+
+/// Outcome of writing the run history.
+pub struct HistoryWriteSummary {
+    pub written: usize,
+    pub skipped_newer: usize,
+}
+
+/// One item per (account_id, period_start) with the period total and its top services.
+pub fn build_items(accounts: &[AccountCostData], metric: &str, run_timestamp: &str) -> Vec<HashMap<String, AttributeValue>> {
+    let mut items = Vec::new();
+    for account in accounts {
+        for period in &account.cost_trend {
+            let mut services: Vec<(&str, f64)> = account
+                .service_consumption
+                .iter()
+                .filter_map(|s| s.monthly_costs.get(&period.month).map(|cost| (s.service.as_str(), *cost)))
+                .filter(|(_, cost)| *cost != 0.0)
+                .collect();
+            services.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal).then_with(|| a.0.cmp(b.0)));
+            let services: HashMap<String, AttributeValue> = services
+                .into_iter()
+                .take(MAX_SERVICES_PER_ITEM)
+                .map(|(service, cost)| (service.to_string(), AttributeValue::N(cost.to_string())))
+                .collect();
+
+            let mut item = HashMap::new();
+            item.insert("account_id".to_string(), AttributeValue::S(account.account_id.clone()));
+            item.insert("period_start".to_string(), AttributeValue::S(period.month.clone()));
+            item.insert("account_name".to_string(), AttributeValue::S(account.account_name.clone()));
+            item.insert("profile".to_string(), AttributeValue::S(account.profile.clone()));
+            item.insert("total_cost".to_string(), AttributeValue::N(period.total_cost.to_string()));
+            item.insert("services".to_string(), AttributeValue::M(services));
+            item.insert("metric".to_string(), AttributeValue::S(metric.to_string()));
+            item.insert("run_timestamp".to_string(), AttributeValue::S(run_timestamp.to_string()));
+            items.push(item);
+        }
+    }
+    items
+}
+
+fn is_throughput_error(code: Option<&str>) -> bool {
+    matches!(
+        code,
+        Some("ProvisionedThroughputExceededException" | "ThrottlingException" | "RequestLimitExceeded")
+    )
+}
+
+/// Writes an item unless the table already holds the same period from a newer run.
+/// Returns `Ok(false)` when the condition rejected the write.
+async fn put_item(client: &DynamoDbClient, table: &str, item: HashMap<String, AttributeValue>) -> Result<bool, Box<dyn Error>> {
+    let run_timestamp = item.get("run_timestamp").cloned().unwrap_or(AttributeValue::Null(true));
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let result = client
+            .put_item()
+            .table_name(table)
+            .set_item(Some(item.clone()))
+            .condition_expression("attribute_not_exists(run_timestamp) OR run_timestamp <= :run_timestamp")
+            .expression_attribute_values(":run_timestamp", run_timestamp.clone())
+            .send()
+            .await;
+        match result {
+            Ok(_) => return Ok(true),
+            Err(e) if e.code() == Some("ConditionalCheckFailedException") => return Ok(false),
+            Err(e) if is_throughput_error(e.code()) && attempt < MAX_WRITE_ATTEMPTS => {
+                tokio::time::sleep(Duration::from_millis(100 * 2u64.pow(attempt))).await;
+            }
+            Err(e) => return Err(format!("Failed to write history item to {}: {}", table, e).into()),
+        }
+    }
+}
+
+pub async fn write_history(
+    config: &SdkConfig,
+    table: &str,
+    items: Vec<HashMap<String, AttributeValue>>,
+) -> Result<HistoryWriteSummary, Box<dyn Error>> {
+    let client = DynamoDbClient::new(config);
+    let mut summary = HistoryWriteSummary { written: 0, skipped_newer: 0 };
+    for batch in items.chunks(WRITE_BATCH_SIZE) {
+        let results = join_all(batch.iter().map(|item| put_item(&client, table, item.clone()))).await;
+        for result in results {
+            if result? {
+                summary.written += 1;
+            } else {
+                summary.skipped_newer += 1;
+            }
+        }
+    }
+    Ok(summary)
+}
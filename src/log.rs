@@ -0,0 +1,17 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Number of times `--quiet` was given.
+static QUIET: AtomicU8 = AtomicU8::new(0);
+
+/// Applies `--quiet`: once hides informational output, twice also hides warnings and errors.
+pub fn set_quiet(level: u8) {
+    QUIET.store(level, Ordering::Relaxed);
+}
+
+pub fn info_enabled() -> bool {
+    QUIET.load(Ordering::Relaxed) == 0
+}
+
+pub fn warn_enabled() -> bool {
+    QUIET.load(Ordering::Relaxed) < 2
+}
@@ -1,44 +1,534 @@
 
-use aws_config::meta::region::RegionProviderChain;
-use aws_sdk_costexplorer::{Client as CostExplorerClient, types::{DateInterval, Granularity, GroupDefinition, GroupDefinitionType, Dimension}};
-use aws_sdk_organizations::Client as OrganizationsClient;
+use aws_sdk_costexplorer::{Client as CostExplorerClient, types::{DateInterval, Granularity, GroupDefinition, GroupDefinitionType, Dimension, DimensionValues, Expression, TagValues}};
+use aws_sdk_organizations::{types::{AccountStatus, ParentType}, Client as OrganizationsClient};
+use aws_sdk_sts::config::{Credentials, SharedCredentialsProvider};
 use aws_sdk_sts::Client as StsClient;
-use clap::{Parser, ValueEnum};
-use std::collections::{HashMap, HashSet};
+use aws_sdk_iam::Client as IamClient;
+use aws_sdk_costexplorer::error::{ProvideErrorMetadata, SdkError};
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::error::Error;
-use tokio;
 use prettytable::{Table, Row, Cell, format};
-use chrono::{NaiveDate, Duration};
-use csv::Writer;
+use chrono::{NaiveDate, Months};
 use serde::{Serialize, Deserialize};
 use plotters::prelude::*;
-use std::cmp::min;
+use aws_config::retry::RetryConfig;
+use aws_config::{Region, SdkConfig};
+use std::io::{self, BufWriter, Write};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use chrono::Datelike;
+use tokio::sync::Notify;
+use futures::StreamExt;
+
+/// Informational progress on stderr, hidden by `--quiet`.
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if $crate::log::info_enabled() {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+/// Warnings and errors on stderr, hidden only by `--quiet --quiet`.
+macro_rules! warn {
+    ($($arg:tt)*) => {
+        if $crate::log::warn_enabled() {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+mod account_list;
+mod alerts;
+mod analysis;
+mod athena;
+mod anomaly_monitors;
+mod api_usage;
+mod budgets;
+mod cache;
+mod cloudwatch;
+mod compute_optimizer;
+mod cur;
+mod dates;
+mod exit;
+mod free_tier;
+mod history;
+mod log;
+mod mfa;
+mod optimization_hub;
+mod output;
+mod periods;
+mod plan;
+mod rate_limit;
+mod redact;
+mod resources;
+mod service_costs;
+mod service_list;
+mod service_names;
+mod pager;
+mod partition;
+mod picker;
+mod presets;
+mod pricing;
+mod preflight;
+mod profile_list;
+mod profiles;
+mod slack;
+mod tag_list;
+mod sp_recommendations;
+mod sns;
+mod timings;
+mod trusted_advisor;
+mod tui;
+mod unit_rates;
+mod upload;
+mod validate;
+
+use alerts::Alert;
+use analysis::{AmortizationDelta, CostStats, HourlyProfile, MetricComparison, RecordTypeBreakdown, RecordTypeShare, WeekdaySplit};
+use api_usage::{ApiUsage, RequestCounter};
+use budgets::BudgetBurn;
+use cache::{CachedPeriod, CostCache};
+use compute_optimizer::ComputeOptimizerSummary;
+use cur::{CostSource, CurCosts};
+use exit::RunFailure;
+use free_tier::{FreeTierItem, FreeTierUsage};
+use optimization_hub::OptimizationHub;
+use output::{local_output_path, publish_output, CsvExports, OutputSink};
+use pager::ReportBuffer;
+use partition::Partition;
+use periods::PeriodBuckets;
+use timings::{Timing, Timings};
+use trusted_advisor::TrustedAdvisor;
+use profiles::FileKind;
+use rate_limit::RateLimiter;
+use sp_recommendations::{SpLookback, SpPaymentOption, SpRecommendation, SpTerm};
+use presets::{Breakdown, Preset};
+use redact::Redactor;
+use resources::TopResources;
+use service_names::{ServiceAliases, ShortNames};
+use service_costs::ServiceCosts;
+use unit_rates::{UnitRate, UsageSamples};
+use upload::{is_s3_uri, S3Uploader};
+
+/// Version of the JSON report structure. Bump whenever fields are added, removed or renamed.
+const SCHEMA_VERSION: u32 = 38;
+
+const COST_METRIC: &str = "UnblendedCost";
+/// Fetched alongside `COST_METRIC` by `--amortization-delta`.
+const AMORTIZED_METRIC: &str = CostMetric::Amortized.api_name();
+/// Service and record type Cost Explorer reports tax under.
+const TAX: &str = "Tax";
+/// Profile name shown for `--no-profile` runs, which use the default credential chain.
+const DEFAULT_CHAIN_PROFILE: &str = "default-chain";
+/// Hourly ranges needing more requests per account than this must be confirmed with `--yes`.
+const MAX_HOURLY_CHUNKS_UNCONFIRMED: usize = 2;
+/// Unified view section for accounts whose OU couldn't be resolved.
+const UNKNOWN_OU: &str = "Unknown OU";
+/// Attempts per Organizations call while walking OUs, which are rate limited to a few requests per second.
+const ORGANIZATIONS_MAX_ATTEMPTS: u32 = 8;
+
+/// Read-only listings that run instead of a cost report. Options such as `--profiles` and `--json`
+/// go before the subcommand.
+#[derive(Subcommand, Clone, Debug, PartialEq)]
+enum Command {
+    /// List the Cost Anomaly Detection monitors of each profile with their alert subscriptions
+    AnomalyMonitors,
+    /// List the accounts discovery finds for each profile, without querying costs
+    Accounts,
+    /// List the profiles a report would run with, where each is defined and how it gets credentials; with --check, test them too
+    Profiles,
+    /// Look up the Cost Explorer service names of each profile
+    Services {
+        #[command(subcommand)]
+        command: ServicesCommand,
+    },
+    /// Look up the cost allocation tags of each profile
+    Tags {
+        #[command(subcommand)]
+        command: TagsCommand,
+    },
+}
+
+#[derive(Subcommand, Clone, Debug, PartialEq)]
+enum ServicesCommand {
+    /// List the services with usage between --start-date and --end-date with their total cost, highest first
+    List {
+        /// Only list services whose name contains this text, ignoring case
+        #[arg(long)]
+        search: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Clone, Debug, PartialEq)]
+enum TagsCommand {
+    /// List the cost allocation tag keys with usage between --start-date and --end-date
+    List,
+    /// List the values of a tag key between --start-date and --end-date with their total cost, highest first
+    Values {
+        /// Tag key, as --tag-key takes it
+        key: String,
+    },
+}
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "CLI tool to fetch AWS cost trend analysis and service consumption for multiple profiles", long_about = None)]
 struct Cli {
-    #[arg(long, default_value = "2025-01-01")]
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[arg(long, env = "AWS_COST_CLI_START_DATE", default_value = "2025-01-01")]
     start_date: String,
-    #[arg(long, default_value = "2025-07-04")]
+    #[arg(long, env = "AWS_COST_CLI_END_DATE", default_value = "2025-07-04")]
     end_date: String,
-    #[arg(long, value_enum, default_value_t = GranularityOption::Monthly)]
+    #[arg(long, env = "AWS_COST_CLI_MONTH", conflicts_with_all = ["start_date", "end_date"], help = "Report on a single calendar month (YYYY-MM, or 'last' for the previous month); defaults --granularity to daily")]
+    month: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_MTD", default_value_t = false, conflicts_with_all = ["start_date", "end_date", "month", "ytd"], help = "Report month-to-date: the first of the current month through today")]
+    mtd: bool,
+    #[arg(long, env = "AWS_COST_CLI_YTD", default_value_t = false, conflicts_with_all = ["start_date", "end_date", "month"], help = "Report year-to-date: January 1st (or the fiscal year start) through today")]
+    ytd: bool,
+    #[arg(long, env = "AWS_COST_CLI_INCLUSIVE_END", default_value_t = false, conflicts_with_all = ["month", "mtd", "ytd"], help = "Treat --end-date as inclusive, like the Cost Explorer console (the API's end date is exclusive)")]
+    inclusive_end: bool,
+    /// Exclusive end date sent to Cost Explorer; differs from `end_date` with `--inclusive-end`.
+    #[arg(skip)]
+    query_end_date: String,
+    #[arg(long, env = "AWS_COST_CLI_GRANULARITY", value_enum, default_value_t = GranularityOption::Monthly)]
     granularity: GranularityOption,
-    #[arg(long)]
+    #[arg(long, env = "AWS_COST_CLI_WEEK_START", value_enum, default_value_t = WeekStart::Mon, help = "First day of the week for --granularity weekly")]
+    week_start: WeekStart,
+    #[arg(long, env = "AWS_COST_CLI_FISCAL_YEAR_START", value_parser = clap::value_parser!(u32).range(1..=12), help = "First month (1-12) of the fiscal year; quarterly periods become fiscal quarters (FY26-Q1) and --ytd becomes fiscal year-to-date")]
+    fiscal_year_start: Option<u32>,
+    #[arg(long, env = "AWS_COST_CLI_YES", default_value_t = false, help = "Proceed with long hourly ranges that need many Cost Explorer requests, or runs estimated to exceed --max-api-calls")]
+    yes: bool,
+    #[arg(long, env = "AWS_COST_CLI_CACHE_DIR", help = "Directory to cache per-account Cost Explorer results in; later runs only query periods that aren't cached, are estimated, or ended within --cache-recent-days (monthly and daily granularity)")]
+    cache_dir: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_CACHE_RECENT_DAYS", default_value_t = 3, help = "Always re-fetch cached periods that ended within this many days, since AWS restates recent costs")]
+    cache_recent_days: u32,
+    #[arg(long, env = "AWS_COST_CLI_CE_RPS", default_value_t = 4.0, help = "Maximum Cost Explorer requests per second, shared by all queries (0 = unlimited)")]
+    ce_rps: f64,
+    #[arg(long, env = "AWS_COST_CLI_ORG_RPS", default_value_t = 2.0, help = "Maximum Organizations requests per second during account discovery (0 = unlimited)")]
+    org_rps: f64,
+    #[arg(long, env = "AWS_COST_CLI_ACCOUNT_CONCURRENCY", default_value_t = 8, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), help = "Number of a profile's accounts to query Cost Explorer for at once, still paced by --ce-rps")]
+    account_concurrency: usize,
+    #[arg(long, env = "AWS_COST_CLI_REQUEST_TIMEOUT", value_parser = clap::value_parser!(u64).range(1..), help = "Timeout in seconds for each AWS request attempt during discovery and cost queries; timed-out attempts are retried, then the account is skipped")]
+    request_timeout: Option<u64>,
+    #[arg(long, env = "AWS_COST_CLI_RUN_TIMEOUT", value_parser = parse_interval, help = "Stop querying costs after this long (e.g. 90s, 10m) and report the accounts collected so far as incomplete")]
+    run_timeout: Option<std::time::Duration>,
+    #[arg(long, env = "AWS_COST_CLI_MAX_API_CALLS", help = "Refuse to start when the estimated number of Cost Explorer requests ($0.01 each) exceeds N, and stop with partial results when N requests have been made")]
+    max_api_calls: Option<usize>,
+    #[arg(long, env = "AWS_COST_CLI_FALLBACK_GRANULARITY", value_enum, help = "Granularity to use instead when hourly data isn't enabled in Cost Explorer")]
+    fallback_granularity: Option<GranularityOption>,
+    #[arg(long, env = "AWS_COST_CLI_INCLUDE_PARTIAL", default_value_t = false, help = "Count the in-progress final period in average costs")]
+    include_partial: bool,
+    #[arg(long, env = "AWS_COST_CLI_PER_DAY", default_value_t = false, help = "Show monthly costs per day (total / days in the month) to remove month-length effects")]
+    per_day: bool,
+    #[arg(long, env = "AWS_COST_CLI_TIMEZONE", value_parser = parse_timezone, help = "IANA time zone (e.g. America/New_York) for day boundaries and period labels; daily reports are built from hourly data")]
+    timezone: Option<chrono_tz::Tz>,
+    #[arg(long, env = "AWS_COST_CLI_WEEKDAY_ANALYSIS", default_value_t = false, help = "Compare average weekday and weekend daily costs per account (daily granularity)")]
+    weekday_analysis: bool,
+    #[arg(long, env = "AWS_COST_CLI_WEEKEND_TARGET_PERCENT", default_value_t = 50.0, help = "Weekend spend, as a percentage of weekday spend, used to estimate savings in --weekday-analysis")]
+    weekend_target_percent: f64,
+    #[arg(long = "preset", env = "AWS_COST_CLI_PRESET", help = "Report preset: built-in data-transfer (each account broken down by data transfer usage type instead of by service), or a [presets.<name>] section of the config file whose options apply unless given on the command line or in the environment")]
+    preset_name: Option<String>,
+    /// Built-in preset of `--preset`, directly or through a config preset.
+    #[arg(skip)]
+    preset: Option<Preset>,
+    #[arg(long, env = "AWS_COST_CLI_CONFIG", help = "TOML config file with [presets.<name>] sections (default: ~/.config/aws-cost-cli/config.toml when it exists)")]
+    config: Option<String>,
+    #[arg(long, default_value_t = false, help = "List the built-in and config file presets with their definitions, then exit")]
+    list_presets: bool,
+    #[arg(long, env = "AWS_COST_CLI_TOP_RESOURCES", help = "List each account's costliest resources of this service (e.g. EC2 or \"Amazon Elastic Compute Cloud - Compute\") over the last 14 days, the resource-level data Cost Explorer keeps")]
+    top_resources: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_TOP_RESOURCES_COUNT", default_value_t = 10, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), help = "Number of resources listed per account by --top-resources")]
+    top_resources_count: usize,
+    #[arg(long, env = "AWS_COST_CLI_COMPUTE_OPTIMIZER", default_value_t = false, help = "Add each account's Compute Optimizer findings for EC2 instances, EBS volumes and Lambda functions in the profile's region: over- and under-provisioned counts and estimated monthly savings")]
+    compute_optimizer: bool,
+    #[arg(long, env = "AWS_COST_CLI_FREE_TIER", default_value_t = false, help = "Add the Free Tier usage of each profile's own account (the management account's covers its organization): limit, month-to-date and forecast usage per offer, flagging those over --free-tier-threshold")]
+    free_tier: bool,
+    #[arg(long, env = "AWS_COST_CLI_FREE_TIER_THRESHOLD", default_value_t = 80.0, help = "Percentage of a Free Tier limit consumed above which --free-tier flags the offer")]
+    free_tier_threshold: f64,
+    #[arg(long, env = "AWS_COST_CLI_OPTIMIZATION_HUB", default_value_t = false, help = "Summarize each profile's Cost Optimization Hub recommendations: estimated monthly savings by action type and account, and the recommendations saving the most")]
+    optimization_hub: bool,
+    #[arg(long, env = "AWS_COST_CLI_OPTIMIZATION_HUB_TOP", default_value_t = 10, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), help = "Number of recommendations listed in detail by --optimization-hub")]
+    optimization_hub_top: usize,
+    #[arg(long, env = "AWS_COST_CLI_SP_RECOMMENDATIONS", default_value_t = false, help = "Add each profile's payer-level Compute Savings Plans purchase recommendation: hourly commitment, estimated savings, coverage, utilization and break-even, with each account's contribution")]
+    sp_recommendations: bool,
+    #[arg(long, env = "AWS_COST_CLI_TRUSTED_ADVISOR", default_value_t = false, help = "Summarize the Trusted Advisor cost optimization checks of each profile's own account (idle load balancers, low-utilization EC2 instances, unassociated Elastic IPs, underutilized EBS volumes, ...): flagged resources and estimated monthly savings. Needs a Business or Enterprise support plan")]
+    trusted_advisor: bool,
+    #[arg(long, env = "AWS_COST_CLI_SP_TERM", value_enum, default_value_t = SpTerm::OneYear, help = "Commitment term of --sp-recommendations")]
+    sp_term: SpTerm,
+    #[arg(long, env = "AWS_COST_CLI_SP_PAYMENT_OPTION", value_enum, default_value_t = SpPaymentOption::None, help = "Payment option of --sp-recommendations")]
+    sp_payment_option: SpPaymentOption,
+    #[arg(long, env = "AWS_COST_CLI_SP_LOOKBACK_DAYS", value_enum, default_value_t = SpLookback::Thirty, help = "Days of usage --sp-recommendations are based on")]
+    sp_lookback_days: SpLookback,
+    /// Set once resource-level data turns out not to be enabled, so remaining accounts skip the query.
+    #[arg(skip)]
+    resources_unavailable: Arc<AtomicBool>,
+    #[arg(long, env = "AWS_COST_CLI_TAX_HANDLING", value_enum, default_value_t = TaxHandling::Include, help = "Tax (VAT, sales tax) handling: include it as a service, exclude it from every total, or separate it into its own row with service percentages net of tax")]
+    tax_handling: TaxHandling,
+    #[arg(long, env = "AWS_COST_CLI_NO_COMPOSITION", default_value_t = false, help = "Skip the per-account record type query behind the trend table's Composition annotations")]
+    no_composition: bool,
+    #[arg(long, env = "AWS_COST_CLI_COMPOSITION_THRESHOLD", default_value_t = 20.0, help = "Annotate a trend period when a record type other than usage (a refund, credit, upfront fee, ...) makes up more than this percentage of it")]
+    composition_threshold: f64,
+    #[arg(long, env = "AWS_COST_CLI_SHOW_CREDITS", default_value_t = false, help = "Break each account's costs down by record type (Usage, Credit, Refund, Tax, support fees) per period, so credits and refunds show where and when they landed")]
+    show_credits: bool,
+    #[arg(long, env = "AWS_COST_CLI_COMPARE_METRICS", value_enum, value_delimiter = ',', help = "Two cost metrics to compare side by side (e.g. unblended,blended): paired trend columns with their delta and a per-account summary of the difference")]
+    compare_metrics: Option<Vec<CostMetric>>,
+    #[arg(long, env = "AWS_COST_CLI_COMPARE_THRESHOLD", default_value_t = 5.0, help = "Flag accounts whose --compare-metrics totals differ by more than this percentage")]
+    compare_threshold: f64,
+    #[arg(long, env = "AWS_COST_CLI_AMORTIZATION_DELTA", default_value_t = false, help = "Also fetch AmortizedCost and show, per account and period, how unblended cost differs from it, flagging periods with upfront RI/Savings Plans purchases")]
+    amortization_delta: bool,
+    #[arg(long, env = "AWS_COST_CLI_UNIT_RATES", value_delimiter = ',', help = "Usage types to show the effective rate (cost per unit of usage) of, per account and period, e.g. EBS:VolumeUsage.gp3,NatGateway-Bytes,DataTransfer-Regional-Bytes; region prefixes are ignored")]
+    unit_rates: Option<Vec<String>>,
+    #[arg(long, env = "AWS_COST_CLI_UNIT_RATE_THRESHOLD", default_value_t = 10.0, help = "Flag --unit-rates usage types whose rate changes by more than this percentage between periods")]
+    unit_rate_threshold: f64,
+    #[arg(long, env = "AWS_COST_CLI_PRICE_CONTEXT", default_value_t = false, requires = "unit_rates", help = "Compare --unit-rates rates with the On-Demand list price from the Pricing API and show the discount; covers EBS volumes, NAT gateway processing, S3 Standard storage and inter-region data transfer")]
+    price_context: bool,
+    #[arg(long, env = "AWS_COST_CLI_HOURLY_PROFILE", default_value_t = false, help = "Show average cost per hour of day for each account and flag accounts that never scale down overnight (hourly granularity)")]
+    hourly_profile: bool,
+    #[arg(long, env = "AWS_COST_CLI_PERCENT_DECIMALS", default_value_t = 1, help = "Decimal places shown for percentages (MoM change, percent of total) in tables and CSVs; JSON keeps full precision")]
+    percent_decimals: usize,
+    #[arg(long, env = "AWS_COST_CLI_CSV")]
     csv: Option<String>,
-    #[arg(long, value_delimiter = ',', help = "Comma-separated list of account IDs to filter (e.g., 1234567890,546796989090)")]
+    #[arg(long, env = "AWS_COST_CLI_ACCOUNT_ID", value_delimiter = ',', value_parser = parse_account_id, help = "Comma-separated list of account IDs to filter (e.g., 1234567890,546796989090)")]
     account_id: Option<Vec<String>>,
-    #[arg(long, value_delimiter = ',', help = "Comma-separated list of AWS profile names (e.g., prod-profile,dev-profile)")]
+    #[arg(long, env = "AWS_COST_CLI_EXCLUDE_ACCOUNTS", value_delimiter = ',', value_parser = parse_account_id, help = "Comma-separated list of account IDs to leave out of the report; their costs are listed separately in the summary")]
+    exclude_accounts: Option<Vec<String>>,
+    #[arg(long, env = "AWS_COST_CLI_PROFILES", value_delimiter = ',', help = "Comma-separated list of AWS profile names (e.g., prod-profile,dev-profile)")]
     profiles: Option<Vec<String>>,
-    #[arg(long, help = "Path to JSON file mapping profiles to account IDs (e.g., {\"prod-profile\": \"123456789012\"})")]
+    #[arg(long, env = "AWS_COST_CLI_INCLUDE_SUSPENDED", default_value_t = false, help = "Include suspended and closed Organizations accounts (skipped by default), e.g. to see trailing charges")]
+    include_suspended: bool,
+    #[arg(long, env = "AWS_COST_CLI_DEDUPE_STRATEGY", value_enum, default_value_t = DedupeStrategy::First, help = "Which profile reports an account found via several profiles: first, prefer-member (the account's own profile over an Organizations listing), or error")]
+    dedupe_strategy: DedupeStrategy,
+    #[arg(long, env = "AWS_COST_CLI_MFA_CODE", help = "MFA token code for profiles that require MFA (mfa_serial), instead of prompting on the terminal")]
+    mfa_code: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_NO_PROFILE", default_value_t = false, conflicts_with = "profiles", help = "Use the default credential chain (env vars, ECS/Lambda task role, IRSA, instance profile) instead of named profiles; reported as profile 'default-chain'")]
+    no_profile: bool,
+    #[arg(long, env = "AWS_COST_CLI_PROFILE_ACCOUNT_MAP", help = "Path to JSON file mapping profiles to account IDs (e.g., {\"prod-profile\": \"123456789012\"} or {\"prod-profile\": {\"id\": \"123456789012\", \"name\": \"Production\"}})")]
     profile_account_map: Option<String>,
-    #[arg(long)]
+    #[arg(long, env = "AWS_COST_CLI_ORG_PROFILE", help = "AWS profile with Organizations access used to look up names of mapped or STS-discovered accounts")]
+    org_profile: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_ACCOUNT_NAMES", help = "JSON or TOML file mapping account IDs to display names used in reports instead of discovered names")]
+    account_names: Option<String>,
+    /// Display names loaded from `--account-names`.
+    #[arg(skip)]
+    display_names: HashMap<String, String>,
+    #[arg(long, env = "AWS_COST_CLI_BUDGET_FILE", help = "JSON or TOML file mapping account IDs to monthly budgets in USD (e.g. {\"123456789012\": 5000}); shows how fast each account is burning through the current month's budget")]
+    budget_file: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_BURN_RATE_THRESHOLD", default_value_t = 10.0, help = "Alert (exit code 2) when an account's share of --budget-file budget used exceeds the share of the month elapsed by more than this many percentage points")]
+    burn_rate_threshold: f64,
+    /// Monthly budgets loaded from `--budget-file`.
+    #[arg(skip)]
+    budgets: HashMap<String, f64>,
+    #[arg(long, env = "AWS_COST_CLI_SERVICE_ALIASES", help = "JSON or TOML file mapping Cost Explorer service names or globs (e.g. \"AWS Glue\", \"Amazon Elastic*\") to display names; services sharing a name are merged into one row")]
+    service_aliases: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_LAYOUT", value_enum, default_value_t = TableLayout::Auto, help = "Unified view layout: wide (periods as columns, paginated), narrow (periods as rows, one block per account) or auto (narrow when the wide table is wider than the terminal)")]
+    layout: TableLayout,
+    #[arg(long, env = "AWS_COST_CLI_DATE_FORMAT", help = "strftime pattern for period labels in table headers and charts (default by granularity: \"%b %Y\" monthly, \"%b %d\" daily, \"%b %d %H:%M\" hourly; quarters show as \"Q1 25\"), or \"iso\" for ISO dates. CSV and JSON always use ISO dates")]
+    date_format: Option<String>,
+    /// Aliases loaded from `--service-aliases`.
+    #[arg(skip)]
+    aliases: ServiceAliases,
+    /// Phase durations, recorded with `--timings` or `--verbose`.
+    #[arg(skip)]
+    phase_timings: Timings,
+    /// Set by the first Ctrl-C; cost queries stop and the collected data is reported.
+    #[arg(skip)]
+    interrupted: Arc<AtomicBool>,
+    /// When `--run-timeout` stops further cost queries.
+    #[arg(skip)]
+    run_deadline: Option<tokio::time::Instant>,
+    /// Paces Cost Explorer requests to `--ce-rps`.
+    #[arg(skip)]
+    ce_limiter: RateLimiter,
+    /// Paces Organizations requests to `--org-rps`.
+    #[arg(skip)]
+    org_limiter: RateLimiter,
+    /// Cost Explorer requests made by every client from `cost_explorer_client`.
+    #[arg(skip)]
+    ce_requests: RequestCounter,
+    #[arg(long, env = "AWS_COST_CLI_NO_ALIAS_LOOKUP", default_value_t = false, help = "Don't name standalone accounts after their IAM account alias (for credentials without IAM read access)")]
+    no_alias_lookup: bool,
+    #[arg(long, env = "AWS_COST_CLI_OU_ID", help = "Only report on accounts in this Organizations OU subtree (ou-xxxx, or r-xxxx for the whole organization)")]
+    ou_id: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_GROUP_BY_OU", default_value_t = false, help = "Group the unified view by OU, with per-OU subtotals, and print per-OU totals in the global summary")]
+    group_by_ou: bool,
+    #[arg(long, env = "AWS_COST_CLI_ACCOUNT_TAG", value_parser = parse_account_tag, help = "Only report on accounts with this Organizations tag (Key=Value); repeat to require several")]
+    account_tag: Vec<(String, String)>,
+    #[arg(long, env = "AWS_COST_CLI_SHOW_ACCOUNT_TAGS", value_delimiter = ',', help = "Comma-separated Organizations tag keys to show as extra unified view columns (e.g. CostCenter,Owner)")]
+    show_account_tags: Option<Vec<String>>,
+    #[arg(long, env = "AWS_COST_CLI_SOURCE", value_enum, default_value_t = CostSource::Api, help = "Read costs from Cost Explorer (api), from Cost and Usage Report parquet files under --cur-path (cur), or from the CUR table --athena-table with an Athena query (athena)")]
+    source: CostSource,
+    #[arg(long, env = "AWS_COST_CLI_CUR_PATH", help = "Directory, parquet file or s3://bucket/prefix of the CUR parquet export read by --source cur")]
+    cur_path: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_ATHENA_DATABASE", help = "Glue database of the CUR table queried by --source athena")]
+    athena_database: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_ATHENA_TABLE", help = "CUR table queried by --source athena")]
+    athena_table: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_ATHENA_OUTPUT", help = "s3://bucket/prefix where Athena writes the --source athena query results")]
+    athena_output: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_ATHENA_TIMEOUT", value_parser = parse_interval, default_value = "5m", help = "Stop the --source athena query if it hasn't finished after this long (e.g. 90s, 10m)")]
+    athena_timeout: std::time::Duration,
+    #[arg(long, env = "AWS_COST_CLI_TAG_KEY")]
     tag_key: Option<String>,
-    #[arg(long)]
+    #[arg(long, env = "AWS_COST_CLI_TAG_VALUE")]
     tag_value: Option<String>,
-    #[arg(long, default_value_t = false)]
+    #[arg(long, env = "AWS_COST_CLI_JSON", default_value_t = false)]
     json: bool,
-    #[arg(long, default_value_t = false)]
+    #[arg(long, env = "AWS_COST_CLI_CHART", default_value_t = false)]
     chart: bool,
+    #[arg(long, env = "AWS_COST_CLI_COMPRESS", default_value_t = false, help = "Gzip-compress CSV exports (implied when --csv ends in .gz)")]
+    compress: bool,
+    #[arg(long, env = "AWS_COST_CLI_CSV_DELIMITER", default_value = ",", value_parser = parse_csv_delimiter, help = "Field delimiter for CSV exports (single character; use '\\t' or 'tab' for TSV)")]
+    csv_delimiter: u8,
+    #[arg(long, env = "AWS_COST_CLI_JSON_OUT", help = "Write the JSON report to this path or s3://bucket/key (in addition to any other output)")]
+    json_out: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_CHART_DIR", help = "Directory or s3://bucket/prefix/ for chart PNGs (implies --chart)")]
+    chart_dir: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_UPLOAD_PROFILE", help = "AWS profile used for S3 uploads (defaults to the first processed profile)")]
+    upload_profile: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_SSE_KMS_KEY_ID", help = "KMS key ID for SSE-KMS encryption of S3 uploads")]
+    sse_kms_key_id: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_SHORT_NAMES", value_enum, default_value_t = ShortNames::Tables, help = "Abbreviate service names (EC2, S3, RDS, ...): always, tables (terminal tables and TUI only; CSV and JSON keep full names) or never")]
+    short_names: ShortNames,
+    #[arg(long, env = "AWS_COST_CLI_REDACT", default_value_t = false, conflicts_with_all = ["dynamodb_table", "cloudwatch_namespace"], help = "Replace account IDs and names with pseudonyms (acct-01, acct-02, ... by descending cost) in tables, CSV, JSON, charts and notifications")]
+    redact: bool,
+    #[arg(long, env = "AWS_COST_CLI_REDACT_PROFILES", default_value_t = false, requires = "redact", help = "With --redact, also replace profile names with pseudonyms (profile-01, ...)")]
+    redact_profiles: bool,
+    #[arg(long, env = "AWS_COST_CLI_REDACT_MAP", requires = "redact", help = "Write the pseudonym to real account ID, name and profile mapping of --redact to this JSON file")]
+    redact_map: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_ALERT_THRESHOLD", help = "Alert (exit code 2, ALERT section, notifications) when the total cost of the latest complete period exceeds this amount in USD")]
+    alert_threshold: Option<f64>,
+    #[arg(long, env = "AWS_COST_CLI_ACCOUNT_ALERT_THRESHOLD", help = "Alert when any single account's cost in the latest complete period exceeds this amount in USD")]
+    account_alert_threshold: Option<f64>,
+    #[arg(long, env = "AWS_COST_CLI_SLACK_WEBHOOK", help = "Slack incoming webhook URL to post a report summary to")]
+    slack_webhook: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_SLACK_TOKEN", requires = "slack_channel", help = "Slack bot token used to upload the global trend chart")]
+    slack_token: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_SLACK_CHANNEL", requires = "slack_token", help = "Slack channel ID for the chart upload")]
+    slack_channel: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_FAIL_ON_NOTIFY_ERROR", default_value_t = false, help = "Fail the run if a notification cannot be delivered")]
+    fail_on_notify_error: bool,
+    #[arg(long, env = "AWS_COST_CLI_SNS_TOPIC_ARN", help = "SNS topic ARN to publish a JSON report summary to")]
+    sns_topic_arn: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_SNS_PROFILE", help = "AWS profile used to publish to SNS (defaults to the first processed profile)")]
+    sns_profile: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_PREVIEW_NOTIFICATIONS", default_value_t = false, help = "Run the report but print the Slack and SNS notifications, CloudWatch metrics and DynamoDB history items instead of sending them")]
+    preview_notifications: bool,
+    #[arg(long, env = "AWS_COST_CLI_CLOUDWATCH_NAMESPACE", help = "CloudWatch namespace to publish per-account and per-service cost metrics to")]
+    cloudwatch_namespace: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_DYNAMODB_TABLE", help = "DynamoDB table (keys account_id, period_start) to store run history in")]
+    dynamodb_table: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_HISTORY_PROFILE", help = "AWS profile used to write run history (defaults to the first processed profile)")]
+    history_profile: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_WATCH", value_parser = parse_interval, help = "Re-run and re-render the report at this interval (e.g. 30s, 15m, 1h) until Ctrl-C")]
+    watch: Option<std::time::Duration>,
+    /// Temporary cache `--watch` refreshes reuse settled periods from when `--cache-dir` isn't given.
+    #[arg(skip)]
+    watch_cache_dir: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_TUI", default_value_t = false, conflicts_with_all = ["watch", "json"], help = "Browse the report interactively in a terminal UI")]
+    tui: bool,
+    #[arg(long, env = "AWS_COST_CLI_INTERACTIVE", default_value_t = false, conflicts_with = "watch", help = "Pick profiles and accounts from multi-select lists before querying costs")]
+    interactive: bool,
+    #[arg(long, env = "AWS_COST_CLI_REGION", help = "AWS region for STS calls, overriding the profile and environment; it also selects the partition (Cost Explorer and Organizations always use their partition's endpoint region, e.g. us-east-1)")]
+    region: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_PARTITION", value_enum, help = "AWS partition of the profiles (aws, aws-us-gov, aws-cn); detected per profile from its region by default")]
+    partition: Option<Partition>,
+    #[arg(long, env = "AWS_COST_CLI_ENDPOINT_URL", help = "Endpoint URL for all AWS calls during discovery and cost queries, e.g. a LocalStack or moto server (http://localhost:5000)")]
+    endpoint_url: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_CE_ENDPOINT_URL", help = "Endpoint URL for Cost Explorer only, overriding --endpoint-url")]
+    ce_endpoint_url: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_ORGANIZATIONS_ENDPOINT_URL", help = "Endpoint URL for Organizations only, overriding --endpoint-url")]
+    organizations_endpoint_url: Option<String>,
+    #[arg(long, env = "AWS_COST_CLI_STATIC_CREDENTIALS", default_value_t = false, help = "Use static dummy credentials instead of the profile's, for testing against mock endpoints")]
+    static_credentials: bool,
+    #[arg(long, env = "AWS_COST_CLI_TIMINGS", default_value_t = false, help = "Time config loading, discovery, each account's cost queries, rendering and output per profile; prints a summary and adds the timings to JSON metadata (also enabled by --verbose)")]
+    timings: bool,
+    #[arg(long, short, env = "AWS_COST_CLI_VERBOSE", default_value_t = false, help = "Print debug details such as the effective AWS regions")]
+    verbose: bool,
+    #[arg(long, short, env = "AWS_COST_CLI_QUIET", action = clap::ArgAction::Count, conflicts_with = "verbose", help = "Hide progress and export messages on stderr, leaving warnings and errors; give twice to hide those too. Tables and JSON on stdout are unaffected")]
+    quiet: u8,
+    #[arg(long, visible_alias = "plan", env = "AWS_COST_CLI_DRY_RUN", default_value_t = false, conflicts_with_all = ["watch", "tui", "check", "preview_notifications"], help = "Discover accounts and print the Cost Explorer requests that would be made (intervals, group-by, filter expressions, request and cost estimate) without querying costs; JSON with --json. Profiles that fail the credential check or discovery are listed and exit with code 4")]
+    dry_run: bool,
+    #[arg(long, env = "AWS_COST_CLI_CHECK", default_value_t = false, help = "Only check each profile's credentials (sts:GetCallerIdentity) and print the results, then exit: code 4 if any profile fails, 6 if all do")]
+    check: bool,
+    #[arg(long, default_value_t = false, help = "Print each option's effective value and where it came from (command line, AWS_COST_CLI_* environment variable, --preset or default), then exit")]
+    print_config: bool,
+    #[arg(long, env = "AWS_COST_CLI_NO_PAGER", default_value_t = false, help = "Print tables directly instead of piping long output through $PAGER")]
+    no_pager: bool,
+    #[arg(long, env = "AWS_COST_CLI_TREND_WINDOW", default_value_t = 0, help = "Limit trends, totals and averages to the last N months of the range (0 = the full requested range)")]
+    trend_window: u32,
+}
+
+impl Cli {
+    /// Granularity requested from Cost Explorer; `--timezone` daily reports are re-bucketed from hourly data.
+    fn query_granularity(&self) -> GranularityOption {
+        match (&self.granularity, self.timezone) {
+            (GranularityOption::Daily, Some(_)) => GranularityOption::Hourly,
+            (granularity, _) => granularity.clone(),
+        }
+    }
+
+    /// Whether an account is left out of the report by `--exclude-accounts` (it is still queried for the summary).
+    fn is_excluded(&self, account_id: &str) -> bool {
+        self.exclude_accounts.iter().flatten().any(|id| id == account_id)
+    }
+
+    /// Metrics the cost query requests: `COST_METRIC` first, then those of `--amortization-delta` and
+    /// `--compare-metrics`.
+    fn cost_metrics(&self) -> Vec<String> {
+        let mut metrics = vec![COST_METRIC];
+        if self.amortization_delta {
+            metrics.push(AMORTIZED_METRIC);
+        }
+        for metric in self.compare_metrics.iter().flatten() {
+            if !metrics.contains(&metric.api_name()) {
+                metrics.push(metric.api_name());
+            }
+        }
+        metrics.into_iter().map(str::to_string).collect()
+    }
+
+    /// Whether trend periods get composition annotations. Not with `--preset`, whose rows leave out
+    /// part of what a record type query returns.
+    fn composition(&self) -> bool {
+        !self.no_composition && self.preset.is_none()
+    }
+
+    /// Whether composition needs its own record type query, rather than reusing the record types
+    /// `--show-credits` groups the cost query by.
+    fn composition_query(&self) -> bool {
+        self.composition() && !self.show_credits
+    }
+
+    /// Cost Explorer queries per account and interval: the cost query, plus the record type query of
+    /// composition annotations and the `--unit-rates` query.
+    fn queries_per_account(&self) -> usize {
+        1 + usize::from(self.composition_query()) + usize::from(self.unit_rates.is_some())
+    }
+
+    /// The two metrics of `--compare-metrics`.
+    fn compared_metrics(&self) -> Option<(CostMetric, CostMetric)> {
+        match self.compare_metrics.as_deref() {
+            Some([first, second]) => Some((*first, *second)),
+            _ => None,
+        }
+    }
+
+    /// Names of the per-account breakdown: services, or the rows of `--preset`.
+    fn breakdown(&self) -> Breakdown {
+        self.preset.as_ref().map_or(presets::SERVICES, Preset::breakdown)
+    }
+
+    /// A service name as shown in terminal tables under `--short-names`.
+    fn table_service_name<'a>(&self, service: &'a str) -> &'a str {
+        match self.short_names {
+            ShortNames::Never => service,
+            _ => service_names::short_name(service),
+        }
+    }
 }
 
 #[derive(ValueEnum, Clone, Debug, PartialEq)]
@@ -46,6 +536,151 @@ enum GranularityOption {
     Daily,
     Monthly,
     Hourly,
+    /// Aggregated client-side from daily data.
+    Weekly,
+    /// Aggregated client-side from monthly data.
+    Quarterly,
+}
+
+/// How to handle an account reachable through more than one profile.
+#[derive(ValueEnum, Clone, Debug, PartialEq)]
+enum DedupeStrategy {
+    /// Keep the account under the first profile that found it.
+    First,
+    /// Keep it under a profile for that account itself (mapping or STS) over an Organizations listing.
+    PreferMember,
+    /// Fail the run.
+    Error,
+}
+
+/// Layout of the unified view table, chosen with `--layout`.
+#[derive(ValueEnum, Clone, Debug, PartialEq)]
+enum TableLayout {
+    Wide,
+    Narrow,
+    Auto,
+}
+
+/// Cost Explorer cost metrics, for `--compare-metrics`.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum CostMetric {
+    Unblended,
+    Blended,
+    Amortized,
+    NetUnblended,
+    NetAmortized,
+}
+
+impl CostMetric {
+    /// Name of the metric in Cost Explorer requests and results.
+    const fn api_name(&self) -> &'static str {
+        match self {
+            CostMetric::Unblended => "UnblendedCost",
+            CostMetric::Blended => "BlendedCost",
+            CostMetric::Amortized => "AmortizedCost",
+            CostMetric::NetUnblended => "NetUnblendedCost",
+            CostMetric::NetAmortized => "NetAmortizedCost",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            CostMetric::Unblended => "Unblended",
+            CostMetric::Blended => "Blended",
+            CostMetric::Amortized => "Amortized",
+            CostMetric::NetUnblended => "Net Unblended",
+            CostMetric::NetAmortized => "Net Amortized",
+        }
+    }
+}
+
+/// How `--tax-handling` treats the Tax record type (VAT, sales tax) on invoices.
+#[derive(ValueEnum, Clone, Debug, PartialEq)]
+enum TaxHandling {
+    /// Tax counts as a service like any other.
+    Include,
+    /// Tax is filtered out of every query, so all totals are net of tax.
+    Exclude,
+    /// Totals include tax, but it is shown as its own row and service percentages are net of it.
+    Separate,
+}
+
+impl TaxHandling {
+    fn name(&self) -> &'static str {
+        match self {
+            TaxHandling::Include => "include",
+            TaxHandling::Exclude => "exclude",
+            TaxHandling::Separate => "separate",
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Debug, PartialEq)]
+enum WeekStart {
+    Mon,
+    Sun,
+}
+
+impl WeekStart {
+    fn weekday(&self) -> chrono::Weekday {
+        match self {
+            WeekStart::Mon => chrono::Weekday::Mon,
+            WeekStart::Sun => chrono::Weekday::Sun,
+        }
+    }
+}
+
+impl GranularityOption {
+    /// Name of a single period, used for table and CSV column headers.
+    fn period_label(&self) -> &'static str {
+        match self {
+            GranularityOption::Daily => "Day",
+            GranularityOption::Monthly => "Month",
+            GranularityOption::Hourly => "Hour",
+            GranularityOption::Weekly => "Week",
+            GranularityOption::Quarterly => "Quarter",
+        }
+    }
+
+    fn change_label(&self) -> &'static str {
+        match self {
+            GranularityOption::Daily => "DoD Change (%)",
+            GranularityOption::Monthly => "MoM Change (%)",
+            GranularityOption::Hourly => "HoH Change (%)",
+            GranularityOption::Weekly => "WoW Change (%)",
+            GranularityOption::Quarterly => "QoQ Change (%)",
+        }
+    }
+
+    fn average_label(&self) -> &'static str {
+        match self {
+            GranularityOption::Daily => "Average Daily Cost",
+            GranularityOption::Monthly => "Average Monthly Cost",
+            GranularityOption::Hourly => "Average Hourly Cost",
+            GranularityOption::Weekly => "Average Weekly Cost",
+            GranularityOption::Quarterly => "Average Quarterly Cost",
+        }
+    }
+
+    fn adjective(&self) -> &'static str {
+        match self {
+            GranularityOption::Daily => "Daily",
+            GranularityOption::Monthly => "Monthly",
+            GranularityOption::Hourly => "Hourly",
+            GranularityOption::Weekly => "Weekly",
+            GranularityOption::Quarterly => "Quarterly",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            GranularityOption::Daily => "daily",
+            GranularityOption::Monthly => "monthly",
+            GranularityOption::Hourly => "hourly",
+            GranularityOption::Weekly => "weekly",
+            GranularityOption::Quarterly => "quarterly",
+        }
+    }
 }
 
 impl From<GranularityOption> for Granularity {
@@ -54,6 +689,38 @@ impl From<GranularityOption> for Granularity {
             GranularityOption::Daily => Granularity::Daily,
             GranularityOption::Monthly => Granularity::Monthly,
             GranularityOption::Hourly => Granularity::Hourly,
+            GranularityOption::Weekly => Granularity::Daily,
+            GranularityOption::Quarterly => Granularity::Monthly,
+        }
+    }
+}
+
+/// Change of a period's cost against the previous period. Serialized as `{"percent": 12.5}`,
+/// `"new"` (the previous period cost nothing) or `"no_data"` (the first period).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum PeriodChange {
+    Percent(f64),
+    New,
+    NoData,
+}
+
+impl PeriodChange {
+    fn between(previous: Option<f64>, current: f64) -> Self {
+        match previous {
+            None => PeriodChange::NoData,
+            Some(0.0) if current == 0.0 => PeriodChange::Percent(0.0),
+            Some(0.0) => PeriodChange::New,
+            Some(prev) => PeriodChange::Percent((current - prev) / prev * 100.0),
+        }
+    }
+
+    /// Table and CSV cell text, with `decimals` places for percentages.
+    fn display(&self, decimals: usize) -> String {
+        match self {
+            PeriodChange::Percent(percent) => format!("{:.*}", decimals, percent),
+            PeriodChange::New => "new".to_string(),
+            PeriodChange::NoData => String::new(),
         }
     }
 }
@@ -62,15 +729,56 @@ impl From<GranularityOption> for Granularity {
 struct CostTrendData {
     month: String,
     total_cost: f64,
-    mom_change_percent: f64,
+    mom_change_percent: PeriodChange,
+    /// The period is still in progress, so its total will grow.
+    is_partial: bool,
+    /// Cost Explorer reported the period's costs as estimated.
+    estimated: bool,
+    /// Monthly total divided by the days it covers (elapsed days for the current month).
+    cost_per_day: Option<f64>,
+    /// Change of `cost_per_day` against the previous period, free of month-length effects.
+    per_day_change_percent: Option<f64>,
+    /// Record types other than usage above `--composition-threshold` of the period, which make its
+    /// change misleading.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    composition: Vec<RecordTypeShare>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct ServiceConsumptionData {
     service: String,
-    monthly_costs: HashMap<String, f64>,
+    /// Costs for the displayed periods only.
+    monthly_costs: BTreeMap<String, f64>,
+    /// Sum of `monthly_costs`, i.e. of the displayed periods.
+    total_cost: f64,
+    percent_of_total: f64,
+    /// Total over every period returned for the requested range, including those before `--trend-window`.
+    full_range_total_cost: f64,
+    full_range_percent_of_total: f64,
+    /// Cost Explorer services merged into this row by `--service-aliases`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source_services: Option<Vec<String>>,
+    /// Change over the last two displayed periods, for presets that show it (`--preset ec2`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    latest_change_percent: Option<PeriodChange>,
+}
+
+/// Tax kept out of the service rows by `--tax-handling separate`.
+#[derive(Serialize, Deserialize, Debug)]
+struct TaxCosts {
+    monthly_costs: BTreeMap<String, f64>,
+    total_cost: f64,
+}
+
+/// An instance family's share of EC2 compute, rolled up from the instance types of `--preset ec2`.
+#[derive(Serialize, Deserialize, Debug)]
+struct InstanceFamilyCost {
+    family: String,
+    monthly_costs: BTreeMap<String, f64>,
     total_cost: f64,
     percent_of_total: f64,
+    /// Change over the last two displayed periods.
+    latest_change_percent: PeriodChange,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -78,10 +786,47 @@ struct AccountCostData {
     profile: String,
     account_id: String,
     account_name: String,
+    /// OU path (e.g. `Workloads/Prod`) when accounts were discovered through `--ou-id`.
+    ou_path: Option<String>,
+    /// Organizations tag values for the `--show-account-tags` keys; empty when a tag is unset or unreadable.
+    account_tags: BTreeMap<String, String>,
+    /// Discovered (Organizations, alias or placeholder) name when `account_name` comes from `--account-names`.
+    original_account_name: Option<String>,
     cost_trend: Vec<CostTrendData>,
     service_consumption: Vec<ServiceConsumptionData>,
     total_cost: f64,
+    /// Granularity of each `cost_trend` entry; `average_monthly_cost` is the average per such period.
+    period_granularity: String,
     average_monthly_cost: f64,
+    /// Spread of the period totals averaged in `average_monthly_cost`.
+    stats: Option<CostStats>,
+    /// With `--top-resources`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    top_resources: Option<TopResources>,
+    /// With `--preset ec2`, `service_consumption` rolled up by instance family.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    instance_families: Option<Vec<InstanceFamilyCost>>,
+    /// With `--amortization-delta`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    amortization_delta: Option<AmortizationDelta>,
+    /// With `--compare-metrics`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    metric_comparison: Option<MetricComparison>,
+    /// With `--tax-handling separate`; `service_consumption` and its percentages leave it out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tax: Option<TaxCosts>,
+    /// With `--show-credits`, costs by record type and period; they sum to the `cost_trend` totals.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    record_type_breakdown: Option<RecordTypeBreakdown>,
+    /// With `--unit-rates`, effective rates of the selected usage types.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    unit_rates: Option<Vec<UnitRate>>,
+    /// With `--compute-optimizer`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    compute_optimizer: Option<ComputeOptimizerSummary>,
+    /// With `--free-tier`, for the account of the profile's credentials.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    free_tier: Option<FreeTierUsage>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -89,550 +834,4615 @@ struct UnifiedViewData {
     profile: String,
     account_id: String,
     account_name: String,
-    monthly_costs: HashMap<String, f64>,
+    /// OU path when known from `--ou-id` or `--group-by-ou`.
+    ou_path: Option<String>,
+    account_tags: BTreeMap<String, String>,
+    monthly_costs: BTreeMap<String, f64>,
+    /// With `--budget-file`, for accounts that have a budget.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    budget_burn: Option<BudgetBurn>,
 }
 
-fn get_aws_profile_names() -> Vec<String> {
-    use std::collections::HashSet;
-    use std::fs;
-    use std::path::PathBuf;
+#[derive(Serialize, Deserialize, Debug)]
+struct ReportFilters {
+    account_ids: Option<Vec<String>>,
+    exclude_accounts: Option<Vec<String>>,
+    ou_id: Option<String>,
+    /// `--account-tag` filters as `Key=Value`.
+    account_tags: Vec<String>,
+    tag_key: Option<String>,
+    tag_value: Option<String>,
+}
 
-    let mut profiles = HashSet::new();
-    let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-    for file in &[".aws/credentials", ".aws/config"] {
-        let path = PathBuf::from(&home).join(file);
-        if let Ok(content) = fs::read_to_string(path) {
-            for line in content.lines() {
-                if let Some(profile) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
-                    let profile = profile.trim().trim_start_matches("profile ").to_string();
-                    profiles.insert(profile);
-                }
-            }
+/// A profile or account that was skipped during collection.
+#[derive(Serialize, Deserialize, Debug)]
+struct ReportError {
+    profile: String,
+    account_id: Option<String>,
+    phase: String,
+    error: String,
+    retriable: bool,
+}
+
+/// Context a report was produced under, embedded in JSON output and written next to CSV exports.
+#[derive(Serialize, Deserialize, Debug)]
+struct ReportMetadata {
+    schema_version: u32,
+    tool_version: String,
+    generated_at: String,
+    start_date: String,
+    end_date: String,
+    /// Whether `end_date` is included in the range (`--inclusive-end`) or exclusive as in the API.
+    inclusive_end: bool,
+    /// First day covered by trends, totals and averages when `--trend-window` trims the range.
+    trend_window_start: Option<String>,
+    /// True when the range extends past today, so the final period is still accumulating costs.
+    partial_period: bool,
+    /// IANA time zone of day boundaries and hourly labels; UTC when absent.
+    timezone: Option<String>,
+    granularity: String,
+    metric: String,
+    /// `--tax-handling`: `include`, `exclude` or `separate`.
+    #[serde(default)]
+    tax_handling: String,
+    /// `--preset` the breakdown rows come from; services when absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    preset: Option<String>,
+    filters: ReportFilters,
+    profiles: Vec<String>,
+    /// Cost Explorer requests made so far in the run, including pages and retries.
+    api_usage: ApiUsage,
+    /// Phase durations up to building the report, with `--timings` or `--verbose`.
+    timings: Vec<Timing>,
+    /// With `--cache-dir`, whether each period came from the `cache`, the `api`, or both (`mixed`) across accounts.
+    period_sources: BTreeMap<String, String>,
+}
+
+/// Raw Cost Explorer results for one account, before trends and summaries are derived.
+struct AccountCosts {
+    profile: String,
+    account_id: String,
+    account_name: String,
+    /// OU path of the account when discovered through `--ou-id`.
+    ou_path: Option<String>,
+    /// `--show-account-tags` values.
+    account_tags: BTreeMap<String, String>,
+    /// Discovered name when `account_name` comes from `--account-names`.
+    original_account_name: Option<String>,
+    /// Left out of the report by `--exclude-accounts`; only its total is shown, for reconciliation.
+    excluded: bool,
+    period_totals: BTreeMap<String, f64>,
+    service_period_totals: ServiceCosts,
+    /// Periods Cost Explorer flagged as estimated, i.e. not yet final.
+    estimated_periods: BTreeSet<String>,
+    /// Periods taken from `--cache-dir` instead of queried.
+    cached_periods: BTreeSet<String>,
+    /// With `--top-resources`; always over the last 14 days, whatever the report range.
+    top_resources: Option<TopResources>,
+    /// Totals of the metrics requested besides `COST_METRIC`, by metric and period.
+    metric_totals: BTreeMap<String, BTreeMap<String, f64>>,
+    /// Costs per record type, with `--show-credits` or for composition annotations.
+    record_type_totals: Option<ServiceCosts>,
+    /// With `--unit-rates`, cost and usage of the selected usage types.
+    usage_samples: Option<BTreeMap<String, UsageSamples>>,
+    /// With `--compute-optimizer`; current findings, whatever the report range.
+    compute_optimizer: Option<ComputeOptimizerSummary>,
+    /// With `--free-tier`, only on the account of the profile's credentials; the current month's usage.
+    free_tier: Option<FreeTierUsage>,
+}
+
+/// The `--json` / `--json-out` document. Fields are in alphabetical order, the order the report has
+/// always been written in; nested objects get their keys sorted the same way by `sorted_keys`.
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    #[serde(serialize_with = "sorted_keys_each")]
+    accounts: &'a [AccountCostData],
+    #[serde(serialize_with = "sorted_keys")]
+    alerts: &'a [Alert],
+    complete: bool,
+    #[serde(serialize_with = "sorted_keys")]
+    errors: &'a [ReportError],
+    #[serde(serialize_with = "sorted_keys")]
+    excluded_accounts: &'a [ExcludedAccount],
+    global_summary: GlobalSummary,
+    /// With `--hourly-profile`.
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "sorted_keys")]
+    hourly_profile: Option<&'a [HourlyProfile]>,
+    #[serde(serialize_with = "sorted_keys")]
+    metadata: &'a ReportMetadata,
+    /// With `--optimization-hub`.
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "sorted_keys")]
+    optimization_hub: Option<&'a OptimizationHub>,
+    /// With `--sp-recommendations`.
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "sorted_keys")]
+    savings_plans_recommendations: Option<&'a [SpRecommendation]>,
+    /// With `--trusted-advisor`.
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "sorted_keys")]
+    trusted_advisor: Option<&'a TrustedAdvisor>,
+    #[serde(serialize_with = "sorted_keys_each")]
+    unified_view: &'a [UnifiedViewData],
+    /// With `--weekday-analysis`.
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "sorted_keys")]
+    weekday_analysis: Option<WeekdayAnalysisOutput<'a>>,
+}
+
+/// Serializes `value` with the keys of every object sorted, as when the report was built as one
+/// `serde_json::Value` before being written.
+fn sorted_keys<T: Serialize, S: serde::Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    serde_json::to_value(value).map_err(serde::ser::Error::custom)?.serialize(serializer)
+}
+
+/// `sorted_keys` for each item of a list, so only one account is held as a `serde_json::Value` at a time.
+fn sorted_keys_each<T: Serialize, S: serde::Serializer>(items: &&[T], serializer: S) -> Result<S::Ok, S::Error> {
+    struct Sorted<'a, T>(&'a T);
+    impl<T: Serialize> Serialize for Sorted<'_, T> {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            sorted_keys(self.0, serializer)
         }
     }
-    profiles.into_iter().collect()
+    serializer.collect_seq(items.iter().map(Sorted))
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-    let cli = Cli::parse();
+#[derive(Serialize)]
+struct GlobalSummary {
+    average_monthly_cost: f64,
+    period_granularity: &'static str,
+    /// With `--optimization-hub`, the estimated monthly savings of its recommendations.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    potential_monthly_savings: Option<f64>,
+    /// With `--tax-handling separate`, the tax included in `total_cost`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tax_cost: Option<f64>,
+    total_cost: f64,
+}
+
+#[derive(Serialize)]
+struct WeekdayAnalysisOutput<'a> {
+    accounts: &'a [WeekdaySplit],
+    weekend_target_percent: f64,
+}
 
-    let start_date = NaiveDate::parse_from_str(&cli.start_date, "%Y-%m-%d")
-        .map_err(|e| format!("Invalid start date: {}", e))?;
-    let end_date = NaiveDate::parse_from_str(&cli.end_date, "%Y-%m-%d")
-        .map_err(|e| format!("Invalid end date: {}", e))?;
+/// A fully derived report, ready to be rendered and exported.
+struct Report {
+    metadata: ReportMetadata,
+    accounts: Vec<AccountCostData>,
+    unified_view: Vec<UnifiedViewData>,
+    global_period_totals: BTreeMap<String, f64>,
+    /// Periods shown in the unified view, service tables and CSV columns.
+    periods: Vec<String>,
+    /// The last period when it is still in progress (the range runs through today).
+    partial_period: Option<String>,
+    /// Periods with estimated costs in any account.
+    estimated_periods: BTreeSet<String>,
+    /// Days covered by each monthly period, for `--per-day` normalization.
+    period_days: BTreeMap<String, i64>,
+    /// Per-account weekday/weekend split, with `--weekday-analysis` on daily reports.
+    weekday_analysis: Vec<WeekdaySplit>,
+    /// Per-account hour-of-day averages, with `--hourly-profile` on hourly reports.
+    hourly_profiles: Vec<HourlyProfile>,
+    total_cost: f64,
+    average_period_cost: f64,
+    /// Tax included in `total_cost`, with `--tax-handling separate`.
+    total_tax: Option<f64>,
+    /// Accounts left out by `--exclude-accounts`, with their cost over the displayed periods.
+    excluded_accounts: Vec<ExcludedAccount>,
+    /// Thresholds exceeded in the latest complete period.
+    alerts: Vec<Alert>,
+    errors: Vec<ReportError>,
+    /// With `--optimization-hub`, added once the report's accounts are known.
+    optimization_hub: Option<OptimizationHub>,
+    /// With `--sp-recommendations`, one per profile, added like `optimization_hub`.
+    sp_recommendations: Option<Vec<SpRecommendation>>,
+    /// With `--trusted-advisor`, added like `optimization_hub`.
+    trusted_advisor: Option<TrustedAdvisor>,
+}
 
-    let six_months_ago = end_date - Duration::days(180);
-    if start_date < six_months_ago {
-        eprintln!("Warning: Start date is before {}. Trend analysis will include data from {} onwards.", 
-            six_months_ago.format("%Y-%m-%d"), six_months_ago.format("%Y-%m-%d"));
+impl Report {
+    /// The `--json` document of the report.
+    fn json<'a>(&'a self, cli: &Cli) -> JsonReport<'a> {
+        JsonReport {
+            accounts: &self.accounts,
+            alerts: &self.alerts,
+            complete: self.errors.is_empty(),
+            errors: &self.errors,
+            excluded_accounts: &self.excluded_accounts,
+            global_summary: GlobalSummary {
+                average_monthly_cost: self.average_period_cost,
+                period_granularity: cli.granularity.name(),
+                potential_monthly_savings: self.optimization_hub.as_ref().map(|hub| hub.estimated_monthly_savings),
+                tax_cost: self.total_tax,
+                total_cost: self.total_cost,
+            },
+            hourly_profile: cli.hourly_profile.then_some(self.hourly_profiles.as_slice()),
+            metadata: &self.metadata,
+            optimization_hub: self.optimization_hub.as_ref(),
+            savings_plans_recommendations: self.sp_recommendations.as_deref(),
+            trusted_advisor: self.trusted_advisor.as_ref(),
+            unified_view: &self.unified_view,
+            weekday_analysis: cli.weekday_analysis.then(|| WeekdayAnalysisOutput {
+                accounts: &self.weekday_analysis,
+                weekend_target_percent: cli.weekend_target_percent,
+            }),
+        }
     }
 
-    if cli.granularity != GranularityOption::Monthly {
-        eprintln!("Warning: Cost trend analysis is best with --granularity monthly. Using {} instead.", 
-            cli.granularity.to_possible_value().unwrap().get_name());
+    /// Accounts burning through their `--budget-file` budget faster than `--burn-rate-threshold` allows.
+    fn burn_rate_alerts(&self) -> impl Iterator<Item = (&UnifiedViewData, &BudgetBurn)> {
+        self.unified_view
+            .iter()
+            .filter_map(|account| account.budget_burn.as_ref().map(|burn| (account, burn)))
+            .filter(|(_, burn)| burn.exceeds_threshold)
     }
+}
 
-    // Validate hourly granularity date range
-    if cli.granularity == GranularityOption::Hourly {
-        let days = (end_date - start_date).num_days();
-        if days > 7 {
-            eprintln!("Warning: Hourly granularity is limited to 7 days. Please adjust the date range.");
-            return Ok(());
+/// An account left out of the report by `--exclude-accounts`.
+#[derive(Serialize, Deserialize, Debug)]
+struct ExcludedAccount {
+    profile: String,
+    account_id: String,
+    account_name: String,
+    total_cost: f64,
+}
+
+/// Profiles declared in the shared credentials and config files, in the order they appear.
+fn get_aws_profile_names() -> Vec<String> {
+    let mut profiles = Vec::new();
+    for kind in [FileKind::Credentials, FileKind::Config] {
+        if let Ok(content) = std::fs::read_to_string(kind.path()) {
+            profiles::collect_profiles(&content, kind, &mut profiles);
         }
     }
+    profiles
+}
 
-    // Load AWS profiles
-    let profiles = cli.profiles.clone().unwrap_or_else(get_aws_profile_names);
-
-    if profiles.is_empty() {
-        eprintln!("No AWS profiles found in ~/.aws/credentials or ~/.aws/config.");
-        return Ok(());
+/// Loads a profile's configuration. `region` (from `--region`) takes precedence over the profile's own
+/// region and the environment, which fall back to us-east-1. Profiles that needed MFA use the session
+/// established for them.
+async fn load_profile_config(profile: &str, region: Option<&str>) -> SdkConfig {
+    // A region provider passed to the loader would ignore the profile name, so only `--region` is set up front
+    let mut loader = aws_config::defaults(aws_config::BehaviorVersion::latest());
+    if let Some(region) = region {
+        loader = loader.region(Region::new(region.to_string()));
     }
+    if profile != DEFAULT_CHAIN_PROFILE {
+        loader = loader.profile_name(profile);
+    }
+    if let Some(credentials) = mfa::cached(profile).await {
+        loader = loader.credentials_provider(credentials);
+    }
+    let config = loader.load().await;
+    if config.region().is_some() {
+        return config;
+    }
+    config.into_builder().region(Region::new("us-east-1")).build()
+}
 
-    // Load profile-to-account mapping if provided
-    let profile_account_map: HashMap<String, String> = if let Some(map_path) = cli.profile_account_map {
-        let map_str = std::fs::read_to_string(&map_path)?;
-        serde_json::from_str(&map_str)?
-    } else {
-        HashMap::new()
-    };
+/// Whether credentials are available without a named profile (env vars, ECS task role, IRSA).
+fn has_environment_credentials() -> bool {
+    [
+        "AWS_ACCESS_KEY_ID",
+        "AWS_CONTAINER_CREDENTIALS_RELATIVE_URI",
+        "AWS_CONTAINER_CREDENTIALS_FULL_URI",
+        "AWS_WEB_IDENTITY_TOKEN_FILE",
+    ]
+    .iter()
+    .any(|var| std::env::var_os(var).is_some())
+}
 
-    let mut account_cost_data: Vec<AccountCostData> = Vec::new();
-    let mut unified_view_data: Vec<UnifiedViewData> = Vec::new();
-    let mut global_monthly_totals: HashMap<String, f64> = HashMap::new();
-    let mut all_months: Vec<String> = Vec::new();
-    let account_id_set: Option<HashSet<String>> = cli.account_id.map(|ids| ids.into_iter().collect());
+/// Cost Explorer client for the endpoint region of the profile's partition, whichever region the profile is configured for.
+fn cost_explorer_client(cli: &Cli, config: &SdkConfig) -> CostExplorerClient {
+    let mut builder = aws_sdk_costexplorer::config::Builder::from(config)
+        .region(Region::new(Partition::of(config).endpoint_region()));
+    if let Some(url) = &cli.ce_endpoint_url {
+        builder = builder.endpoint_url(url);
+    }
+    CostExplorerClient::from_conf(builder.interceptor(cli.ce_requests.clone()).build())
+}
 
-    // Iterate through each profile
-    for profile in &profiles {
-        eprintln!("Processing profile: {}", profile);
+/// Organizations client for the endpoint region of the profile's partition.
+fn organizations_client(cli: &Cli, config: &SdkConfig) -> OrganizationsClient {
+    OrganizationsClient::from_conf(organizations_config(cli, config).build())
+}
 
-        // Load AWS configuration for the profile
-        let region_provider = RegionProviderChain::default_provider()
-            .or_else("us-east-1");
-        let config = aws_config::defaults(aws_config::BehaviorVersion::latest())
-            .profile_name(profile)
-            .region(region_provider)
-            .load()
-            .await;
+fn organizations_config(cli: &Cli, config: &SdkConfig) -> aws_sdk_organizations::config::Builder {
+    let mut builder = aws_sdk_organizations::config::Builder::from(config)
+        .region(Region::new(Partition::of(config).endpoint_region()));
+    if let Some(url) = &cli.organizations_endpoint_url {
+        builder = builder.endpoint_url(url);
+    }
+    builder
+}
 
-        let ce_client = CostExplorerClient::new(&config);
-        let org_client = OrganizationsClient::new(&config);
-        let sts_client = StsClient::new(&config);
+/// Loads a profile for account discovery and Cost Explorer, moved into `--partition` when given,
+/// pointed at `--endpoint-url` (e.g. a LocalStack or moto server) with `--static-credentials`, and
+/// with `--request-timeout` as the per-attempt timeout.
+async fn load_discovery_config(cli: &Cli, profile: &str) -> SdkConfig {
+    let mut config = load_profile_config(profile, cli.region.as_deref()).await;
+    if let Some(partition) = cli.partition {
+        config = partition::in_partition(config, partition);
+    }
+    if cli.endpoint_url.is_none() && !cli.static_credentials && cli.request_timeout.is_none() {
+        return config;
+    }
+    let mut builder = config.into_builder();
+    if let Some(seconds) = cli.request_timeout {
+        builder = builder.timeout_config(
+            aws_config::timeout::TimeoutConfig::builder()
+                .operation_attempt_timeout(std::time::Duration::from_secs(seconds))
+                .build(),
+        );
+    }
+    if let Some(url) = &cli.endpoint_url {
+        builder = builder.endpoint_url(url);
+    }
+    if cli.static_credentials {
+        let credentials = Credentials::new("testing", "testing", None, None, "static-credentials");
+        builder = builder.credentials_provider(SharedCredentialsProvider::new(credentials));
+    }
+    builder.build()
+}
 
-        // Fetch accounts for the profile
-        let mut accounts = Vec::new();
-        if let Some(account_id) = profile_account_map.get(profile) {
-            // Use mapping if provided
-            accounts.push(
-                aws_sdk_organizations::types::Account::builder()
-                    .id(account_id.clone())
-                    .name(format!("Account-{}", account_id))
-                    .build()
-            );
-        } else {
-            // Try AWS Organizations first
-            match org_client.list_accounts().send().await {
-                Ok(response) => {
-                    accounts.extend(response.accounts.unwrap_or_default());
-                }
-                Err(e) => {
-                    eprintln!("Error fetching accounts for profile {} via Organizations: {}. Attempting STS fallback.", profile, e);
-                    // Fallback to STS for standalone account
-                    match sts_client.get_caller_identity().send().await {
-                        Ok(response) => {
-                            if let Some(account_id) = response.account {
-                                accounts.push(
-                                    aws_sdk_organizations::types::Account::builder()
-                                        .id(account_id.clone())
-                                        .name(format!("Account-{}", account_id))
-                                        .build()
-                                );
-                            } else {
-                                eprintln!("No account ID returned by STS for profile {}. Skipping profile.", profile);
-                                continue;
-                            }
-                        }
-                        Err(e) => {
-                            eprintln!("Error fetching account ID via STS for profile {}: {}. Skipping profile.", profile, e);
-                            continue;
-                        }
-                    }
-                }
-            }
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(std::env::args_os().collect()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(failure) => {
+            failure.print();
+            failure.exit_code()
         }
+    }
+}
 
-        let filtered_accounts = if let Some(ref account_ids) = account_id_set {
-            accounts.into_iter()
-                .filter(|acc| acc.id.as_ref().map_or(false, |id| account_ids.contains(id)))
-                .collect::<Vec<_>>()
-        } else {
-            accounts
+/// With `--json`, reports a run that produced no report as a JSON document with `complete: false`,
+/// the report's `metadata` and an `errors` array, so consumers reading stdout always get parseable output.
+fn json_failure(cli: &Cli, failure: RunFailure, profiles: &[String], report_errors: &[ReportError]) -> RunFailure {
+    if cli.json {
+        let mut errors: Vec<serde_json::Value> = report_errors
+            .iter()
+            .filter_map(|e| serde_json::to_value(e).ok())
+            .collect();
+        errors.push(serde_json::json!({
+            "profile": null,
+            "account_id": null,
+            "phase": failure.phase(),
+            "error": failure.to_string(),
+            "retriable": false,
+        }));
+        let start = NaiveDate::parse_from_str(&cli.start_date, "%Y-%m-%d");
+        let end = NaiveDate::parse_from_str(&cli.query_end_date, "%Y-%m-%d");
+        let cutoff = match (start, end) {
+            (Ok(start), Ok(end)) => trend_cutoff(cli.trend_window, start, end),
+            _ => chrono::Utc::now().date_naive(),
         };
+        let metadata = report_metadata(cli, profiles, cutoff, BTreeMap::new());
+        let output = serde_json::json!({ "schema_version": SCHEMA_VERSION, "complete": false, "metadata": metadata, "errors": errors });
+        if let Ok(rendered) = serde_json::to_string_pretty(&output) {
+            println!("{}", rendered);
+        }
+    }
+    failure
+}
 
-        if filtered_accounts.is_empty() {
-            eprintln!("No accounts found for profile {}{}", profile, 
-                account_id_set.clone().map_or("".to_string(), |ids| format!(" for account IDs {:?}", ids)));
+/// Options whose values are replaced by `(set)` in `--print-config` output.
+const SECRET_OPTIONS: &[&str] = &["slack_webhook", "slack_token"];
+
+/// Prints every option with its effective value and source, for `--print-config`. Values from the
+/// command line take precedence over `AWS_COST_CLI_*` environment variables, which take precedence over defaults.
+fn print_config(matches: &clap::ArgMatches, preset: Option<&(String, Vec<String>)>) {
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_DEFAULT);
+    table.set_titles(Row::new(vec![Cell::new("Option"), Cell::new("Value"), Cell::new("Source")]));
+    for arg in Cli::command().get_arguments() {
+        let id = arg.get_id().as_str();
+        let Some(long) = arg.get_long() else {
+            continue;
+        };
+        if matches!(long, "help" | "version" | "print-config" | "list-presets") {
             continue;
         }
+        let value = match matches.get_raw(id) {
+            Some(_) if SECRET_OPTIONS.contains(&id) => "(set)".to_string(),
+            Some(values) => values.map(|v| v.to_string_lossy()).collect::<Vec<_>>().join(","),
+            None => String::new(),
+        };
+        let from_preset = preset.filter(|(_, options)| options.iter().any(|option| option == id));
+        let source = match (matches.value_source(id), arg.get_env()) {
+            (Some(ValueSource::CommandLine), _) if from_preset.is_some() => format!("preset {}", from_preset.map_or("", |(name, _)| name.as_str())),
+            (Some(ValueSource::CommandLine), _) => "command line".to_string(),
+            (Some(ValueSource::EnvVariable), Some(env)) => format!("env {}", env.to_string_lossy()),
+            (Some(ValueSource::DefaultValue), _) => "default".to_string(),
+            _ => "unset".to_string(),
+        };
+        table.add_row(Row::new(vec![Cell::new(&format!("--{}", long)), Cell::new(&value), Cell::new(&source)]));
+    }
+    table.printstd();
+}
 
-        for account in filtered_accounts {
-            let account_id = account.id.unwrap_or_default();
-            let account_name = account.name.unwrap_or("N/A".to_string());
-            let mut monthly_totals: HashMap<String, f64> = HashMap::new();
-            let mut service_monthly_totals: HashMap<String, HashMap<String, f64>> = HashMap::new();
+fn parse_args(args: &[std::ffi::OsString]) -> Result<clap::ArgMatches, RunFailure> {
+    match Cli::command().try_get_matches_from(args) {
+        Ok(matches) => Ok(matches),
+        // --help and --version
+        Err(e) if !e.use_stderr() => e.exit(),
+        Err(e) => Err(RunFailure::InvalidArguments(e)),
+    }
+}
 
-            let mut request_builder = ce_client
-                .get_cost_and_usage()
-                .time_period(
-                    DateInterval::builder()
-                        .start(cli.start_date.clone())
-                        .end(cli.end_date.clone())
-                        .build()?,
-                )
-                .granularity(cli.granularity.clone().into())
-                .metrics("UnblendedCost")
-                .group_by(
-                    GroupDefinition::builder()
-                        .r#type(GroupDefinitionType::Dimension)
-                        .key("SERVICE")
-                        .build(),
-                )
-                .filter(
-                    aws_sdk_costexplorer::types::Expression::builder()
-                        .dimensions(
-                            aws_sdk_costexplorer::types::DimensionValues::builder()
-                                .key(Dimension::LinkedAccount)
-                                .values(account_id.clone())
-                                .build(),
-                        )
-                        .build(),
-                );
+/// Prints the built-in presets and those of the config file for `--list-presets`.
+fn print_presets(config_presets: &BTreeMap<String, presets::PresetDefinition>) {
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_DEFAULT);
+    table.set_titles(Row::new(vec![Cell::new("Preset"), Cell::new("Source"), Cell::new("Definition")]));
+    for preset in Preset::value_variants() {
+        let help = preset.to_possible_value().and_then(|value| value.get_help().map(|help| help.to_string())).unwrap_or_default();
+        table.add_row(Row::new(vec![Cell::new(preset.name()), Cell::new("built-in"), Cell::new(&help)]));
+    }
+    for (name, definition) in config_presets {
+        let options: Vec<String> = definition.iter().map(|(key, value)| format!("{} = {}", key, value)).collect();
+        table.add_row(Row::new(vec![Cell::new(name), Cell::new("config file"), Cell::new(&options.join("\n"))]));
+    }
+    table.printstd();
+}
 
-            if let (Some(tag_key), Some(tag_value)) = (&cli.tag_key, &cli.tag_value) {
-                request_builder = request_builder.filter(
-                    aws_sdk_costexplorer::types::Expression::builder()
-                        .tags(
-                            aws_sdk_costexplorer::types::TagValues::builder()
-                                .key(tag_key)
-                                .values(tag_value)
-                                .build(),
-                        )
-                        .build(),
-                );
-            } else if let Some(tag_key) = &cli.tag_key {
-                request_builder = request_builder.group_by(
-                    GroupDefinition::builder()
-                        .r#type(GroupDefinitionType::Tag)
-                        .key(tag_key)
-                        .build(),
-                );
+async fn run(mut args: Vec<std::ffi::OsString>) -> Result<(), RunFailure> {
+    let invalid = |message: String| RunFailure::InvalidArguments(Cli::command().error(clap::error::ErrorKind::InvalidValue, message));
+    let mut matches = parse_args(&args)?;
+    let config_path = matches.get_one::<String>("config").cloned();
+    if matches.get_flag("list_presets") {
+        print_presets(&presets::load_config_presets(config_path.as_deref()).map_err(invalid)?);
+        return Ok(());
+    }
+    // A config preset becomes extra arguments, so clap parses and validates its options like any other
+    let mut builtin_preset = None;
+    let mut preset_options = None;
+    if let Some(name) = matches.get_one::<String>("preset_name").cloned() {
+        match Preset::from_str(&name, true) {
+            Ok(preset) => builtin_preset = Some(preset),
+            Err(_) => {
+                let config_presets = presets::load_config_presets(config_path.as_deref()).map_err(invalid)?;
+                let definition = config_presets.get(&name).ok_or_else(|| {
+                    invalid(format!("Unknown preset '{}'. Available presets: {}", name, presets::available_names(&config_presets)))
+                })?;
+                let preset_args = presets::preset_args(&name, definition, &Cli::command(), &matches).map_err(invalid)?;
+                args.extend(preset_args.args.into_iter().map(std::ffi::OsString::from));
+                matches = parse_args(&args)?;
+                builtin_preset = preset_args.builtin;
+                preset_options = Some((name, preset_args.options));
             }
-
-            let response = match request_builder.send().await {
-                Ok(response) => response,
-                Err(e) => {
-                    eprintln!("Error fetching cost data for account {} (profile {}): {}. Skipping account.", 
-                        account_id, profile, e);
-                    continue;
-                }
-            };
-
-            if let Some(results) = response.results_by_time {
+        }
+    }
+    let mut cli = Cli::from_arg_matches(&matches).map_err(RunFailure::InvalidArguments)?;
+    cli.preset = builtin_preset;
+    log::set_quiet(cli.quiet);
+    let today = chrono::Utc::now().date_naive();
+    if let Some(month) = &cli.month {
+        let (start, end) = dates::month_range(month, today).map_err(invalid)?;
+        cli.start_date = start.format("%Y-%m-%d").to_string();
+        cli.end_date = end.format("%Y-%m-%d").to_string();
+        if matches.value_source("granularity") == Some(ValueSource::DefaultValue) {
+            cli.granularity = GranularityOption::Daily;
+        }
+    } else if cli.mtd || cli.ytd {
+        let (start, end) = if cli.mtd { dates::month_to_date(today) } else { dates::year_to_date(today, cli.fiscal_year_start) };
+        cli.start_date = start.format("%Y-%m-%d").to_string();
+        cli.end_date = end.format("%Y-%m-%d").to_string();
+    }
+    validate::validate(&cli).map_err(RunFailure::InvalidArguments)?;
+    if cli.timings || cli.verbose {
+        cli.phase_timings = Timings::enabled();
+    }
+    cli.ce_limiter = RateLimiter::new(cli.ce_rps);
+    cli.org_limiter = RateLimiter::new(cli.org_rps);
+    cli.run_deadline = cli.run_timeout.map(|timeout| tokio::time::Instant::now() + timeout);
+    if cli.watch.is_none() {
+        // --watch exits on Ctrl-C between refreshes instead
+        let interrupted = cli.interrupted.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                interrupted.store(true, Ordering::SeqCst);
+                warn!("\nInterrupted: writing the data collected so far. Press Ctrl-C again to quit immediately.");
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    std::process::exit(130);
+                }
+            }
+        });
+    }
+    if cli.print_config {
+        print_config(&matches, preset_options.as_ref());
+        return Ok(());
+    }
+    if cli.interactive {
+        picker::ensure_terminal()?;
+    }
+
+    let start_date = dates::parse_date("start date", &cli.start_date).map_err(invalid)?;
+    let requested_end_date = dates::parse_date("end date", &cli.end_date).map_err(invalid)?;
+    let end_date = dates::query_end(requested_end_date, cli.inclusive_end);
+    dates::validate_range(start_date, end_date, today).map_err(invalid)?;
+    cli.query_end_date = end_date.format("%Y-%m-%d").to_string();
+
+    let trend_cutoff = trend_cutoff(cli.trend_window, start_date, end_date);
+    if start_date < trend_cutoff {
+        warn!("Warning: Start date is before {}. Trend analysis will include data from {} onwards (use --trend-window 0 for the full range).", 
+            trend_cutoff.format("%Y-%m-%d"), trend_cutoff.format("%Y-%m-%d"));
+    }
+
+    if !matches!(cli.granularity, GranularityOption::Monthly | GranularityOption::Quarterly) && cli.month.is_none() {
+        warn!("Warning: Cost trend analysis is best with --granularity monthly. Using {} instead.", 
+            cli.granularity.to_possible_value().unwrap().get_name());
+    }
+
+    if cli.weekday_analysis && cli.granularity != GranularityOption::Daily {
+        warn!("Warning: --weekday-analysis needs --granularity daily; skipping the weekday/weekend comparison.");
+    }
+
+    if cli.hourly_profile && cli.granularity != GranularityOption::Hourly {
+        warn!("Warning: --hourly-profile needs --granularity hourly; skipping the hour-of-day profile.");
+    }
+
+    if cli.top_resources.is_some() {
+        info!("--top-resources covers the last {} days regardless of the report range: Cost Explorer only keeps resource-level data that long.",
+            resources::RESOURCE_DAYS);
+    }
+
+    // Long hourly ranges need one Cost Explorer request per chunk and account
+    if cli.query_granularity() == GranularityOption::Hourly {
+        let chunks = dates::split_range(start_date, end_date, dates::MAX_HOURLY_DAYS_PER_REQUEST).len();
+        if chunks > 1 {
+            info!("Hourly range of {} days will be split into {} Cost Explorer requests per account.",
+                (end_date - start_date).num_days(), chunks);
+        }
+        if chunks > MAX_HOURLY_CHUNKS_UNCONFIRMED && !cli.yes {
+            let message = format!("This hourly range needs {} requests per account. Pass --yes to proceed, or shorten the range.", chunks);
+            return Err(json_failure(&cli, RunFailure::HourlyRangeUnconfirmed(message), &[], &[]));
+        }
+    }
+
+    // Load AWS profiles
+    let mut profiles = if cli.no_profile {
+        vec![DEFAULT_CHAIN_PROFILE.to_string()]
+    } else {
+        cli.profiles.clone().unwrap_or_else(get_aws_profile_names)
+    };
+    if profiles.is_empty() && cli.profiles.is_none() && has_environment_credentials() {
+        info!("No AWS profiles found; using the default credential chain (environment or container credentials).");
+        profiles.push(DEFAULT_CHAIN_PROFILE.to_string());
+    }
+
+    if profiles.is_empty() {
+        return Err(json_failure(&cli, RunFailure::NoProfiles, &[], &[]));
+    }
+
+    if let Some(Command::Profiles) = &cli.command {
+        let checks = if cli.check { Some(preflight::check_profiles(&cli, &profiles).await) } else { None };
+        let listed = profile_list::build(&profiles, checks.as_deref());
+        if cli.json {
+            let output = serde_json::json!({ "schema_version": SCHEMA_VERSION, "profiles": listed });
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        } else {
+            profile_list::print(&listed, cli.check);
+        }
+        let failed = listed.iter().filter(|profile| profile.error.is_some()).count();
+        if failed > 0 {
+            return Err(RunFailure::Partial(failed));
+        }
+        return Ok(());
+    }
+
+    if cli.interactive {
+        profiles = picker::pick_profiles(&profiles)?;
+    }
+
+    // Check credentials up front so broken profiles fail once, before any discovery or cost queries
+    let checks = preflight::check_profiles(&cli, &profiles).await;
+    if cli.check || log::info_enabled() {
+        preflight::print_checks(&checks);
+    }
+    let failed_checks: Vec<ReportError> = checks.iter().filter_map(|check| check.report_error()).collect();
+    if cli.check {
+        if failed_checks.is_empty() {
+            return Ok(());
+        }
+        if failed_checks.len() == checks.len() {
+            return Err(RunFailure::AuthFailed);
+        }
+        return Err(RunFailure::Partial(failed_checks.len()));
+    }
+    profiles.retain(|profile| !failed_checks.iter().any(|failed| &failed.profile == profile));
+    if profiles.is_empty() {
+        let checked: Vec<String> = checks.iter().map(|check| check.profile.clone()).collect();
+        return Err(json_failure(&cli, RunFailure::AuthFailed, &checked, &failed_checks));
+    }
+
+    if let Some(Command::AnomalyMonitors) = &cli.command {
+        let results = anomaly_monitors::list(&cli, &profiles).await;
+        for result in results.iter().filter(|result| result.error.is_none() && result.monitors.is_empty()) {
+            warn!("Warning: profile {} has no Cost Anomaly Detection monitors, so no anomalies are detected for it.", result.profile);
+        }
+        if cli.json {
+            let output = serde_json::json!({ "schema_version": SCHEMA_VERSION, "profiles": results });
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        } else {
+            anomaly_monitors::print(&results);
+        }
+        let failed = failed_checks.len() + results.iter().filter(|result| result.error.is_some()).count();
+        if failed > 0 {
+            return Err(RunFailure::Partial(failed));
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::Services { command: ServicesCommand::List { search } }) = &cli.command {
+        let results = service_list::list(&cli, &profiles, search.as_deref()).await;
+        if cli.json {
+            let output = serde_json::json!({
+                "schema_version": SCHEMA_VERSION,
+                "start_date": cli.start_date,
+                "end_date": cli.end_date,
+                "profiles": results,
+            });
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        } else {
+            service_list::print(&cli, &results);
+        }
+        info!("{}", cli.ce_requests.usage().summary());
+        let failed = failed_checks.len() + results.iter().filter(|result| result.error.is_some()).count();
+        if failed > 0 {
+            return Err(RunFailure::Partial(failed));
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::Tags { command }) = &cli.command {
+        let mut output = serde_json::json!({
+            "schema_version": SCHEMA_VERSION,
+            "start_date": cli.start_date,
+            "end_date": cli.end_date,
+        });
+        let failed_profiles = match command {
+            TagsCommand::List => {
+                let results = tag_list::list_keys(&cli, &profiles).await;
+                if cli.json {
+                    output["profiles"] = serde_json::to_value(&results)?;
+                } else {
+                    tag_list::print_keys(&cli, &results);
+                }
+                results.iter().filter(|result| result.error.is_some()).count()
+            }
+            TagsCommand::Values { key } => {
+                let results = tag_list::list_values(&cli, &profiles, key).await;
+                if cli.json {
+                    output["tag_key"] = serde_json::json!(key);
+                    output["profiles"] = serde_json::to_value(&results)?;
+                } else {
+                    tag_list::print_values(&cli, key, &results);
+                }
+                results.iter().filter(|result| result.error.is_some()).count()
+            }
+        };
+        if cli.json {
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        }
+        info!("{}", cli.ce_requests.usage().summary());
+        let failed = failed_checks.len() + failed_profiles;
+        if failed > 0 {
+            return Err(RunFailure::Partial(failed));
+        }
+        return Ok(());
+    }
+
+    // Load profile-to-account mapping if provided
+    let profile_account_map: HashMap<String, MappedAccount> = if let Some(map_path) = &cli.profile_account_map {
+        let map_str = std::fs::read_to_string(map_path)?;
+        let map: HashMap<String, MappedAccount> = serde_json::from_str(&map_str)?;
+        for (profile, mapped) in &map {
+            parse_account_id(mapped.id()).map_err(|e| format!("{} for profile {} in {}", e, profile, map_path))?;
+        }
+        map
+    } else {
+        HashMap::new()
+    };
+
+    if let Some(names_path) = &cli.account_names {
+        cli.display_names = load_display_names(names_path)?;
+    }
+    if let Some(budget_path) = &cli.budget_file {
+        cli.budgets = budgets::load(budget_path)?;
+    }
+    if let Some(aliases_path) = &cli.service_aliases {
+        cli.aliases = ServiceAliases::load(aliases_path)?;
+    }
+
+    if let Some(Command::Accounts) = &cli.command {
+        let mut report_errors = failed_checks;
+        let discovered = discover_accounts(&cli, &profiles, &profile_account_map, &mut report_errors).await?;
+        let accounts = account_list::build(&discovered);
+        if cli.json {
+            let output = serde_json::json!({ "schema_version": SCHEMA_VERSION, "accounts": accounts, "errors": report_errors });
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        } else {
+            account_list::print(&accounts);
+        }
+        if !report_errors.is_empty() {
+            return Err(RunFailure::Partial(report_errors.len()));
+        }
+        return Ok(());
+    }
+
+    if let Some(interval) = cli.watch {
+        let temporary_cache = cli.cache_dir.is_none().then(|| std::env::temp_dir().join(format!("aws-cost-cli-watch-{}", std::process::id())));
+        cli.watch_cache_dir = temporary_cache.as_ref().map(|dir| dir.to_string_lossy().into_owned());
+        let watched = run_watch(&cli, &profiles, &profile_account_map, interval, trend_cutoff).await;
+        if let Some(dir) = temporary_cache {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+        return Ok(watched?);
+    }
+
+    let mut report_errors = failed_checks;
+    if cli.dry_run {
+        let discovered = discover_accounts(&cli, &profiles, &profile_account_map, &mut report_errors).await?;
+        let plan = plan::build(&cli, &discovered, request_intervals(&cli, &cli.start_date));
+        // Profiles that failed the credential check or discovery have no accounts in the plan
+        if cli.json {
+            let mut output = serde_json::to_value(&plan)?;
+            output["errors"] = serde_json::to_value(&report_errors)?;
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        } else {
+            print!("{}", plan::render(&plan));
+            if !report_errors.is_empty() {
+                println!("\nNot planned:");
+                for error in &report_errors {
+                    let account = error.account_id.as_ref().map_or(String::new(), |id| format!(" account {}", id));
+                    println!("  profile {}{} ({}): {}", error.profile, account, error.phase, error.error);
+                }
+            }
+        }
+        if !report_errors.is_empty() {
+            return Err(RunFailure::Partial(report_errors.len()));
+        }
+        return Ok(());
+    }
+    let account_costs = if cli.interactive {
+        let discovered = discover_accounts(&cli, &profiles, &profile_account_map, &mut report_errors).await?;
+        if discovered.is_empty() {
+            return Err(json_failure(&cli, RunFailure::NoCostData, &profiles, &report_errors));
+        }
+        let discovered = picker::pick_accounts(discovered)?;
+        profiles = discovered.iter().map(|p| p.profile.clone()).collect();
+        let account_ids: Vec<String> = discovered
+            .iter()
+            .flat_map(|p| p.accounts.iter().filter_map(|a| a.id.clone()))
+            .collect();
+        info!("Equivalent flags: {}", picker::equivalent_flags(&profiles, &account_ids));
+        cli.account_id = Some(account_ids);
+        query_costs(&cli, &discovered, &cli.start_date, &mut report_errors).await?
+    } else {
+        match collect_costs(&cli, &profiles, &profile_account_map, &cli.start_date, &mut report_errors).await {
+            Err(e) if e.is::<HourlyNotEnabled>() && cli.timezone.is_some() && cli.granularity == GranularityOption::Daily => {
+                warn!("Warning: {} Falling back to UTC days instead of --timezone days.", e);
+                cli.timezone = None;
+                report_errors.retain(|e| e.phase == "credential_check");
+                collect_costs(&cli, &profiles, &profile_account_map, &cli.start_date, &mut report_errors).await?
+            }
+            Err(e) if e.is::<HourlyNotEnabled>() && cli.fallback_granularity.is_some() => {
+                let fallback = cli.fallback_granularity.clone().unwrap_or(GranularityOption::Daily);
+                warn!("Warning: {} Falling back to --granularity {}.", e, fallback.name());
+                cli.granularity = fallback;
+                report_errors.retain(|e| e.phase == "credential_check");
+                collect_costs(&cli, &profiles, &profile_account_map, &cli.start_date, &mut report_errors).await?
+            }
+            Err(e) if e.is::<HourlyNotEnabled>() => {
+                return Err(format!("{} Re-run with --fallback-granularity daily to use daily data instead.", e).into());
+            }
+            result => result?,
+        }
+    };
+
+    if account_costs.is_empty() {
+        let failure = if cli.interrupted.load(Ordering::SeqCst) { RunFailure::Interrupted } else { RunFailure::NoCostData };
+        return Err(json_failure(&cli, failure, &profiles, &report_errors));
+    }
+    let mut unknown_names: Vec<&String> = cli
+        .display_names
+        .keys()
+        .filter(|id| !account_costs.iter().any(|account| &account.account_id == *id))
+        .collect();
+    if !unknown_names.is_empty() {
+        unknown_names.sort();
+        warn!("Warning: --account-names lists accounts that weren't reported on: {:?}", unknown_names);
+    }
+    let mut unknown_budgets: Vec<&String> = cli
+        .budgets
+        .keys()
+        .filter(|id| !account_costs.iter().any(|account| &account.account_id == *id))
+        .collect();
+    if !unknown_budgets.is_empty() {
+        unknown_budgets.sort();
+        warn!("Warning: --budget-file lists accounts that weren't reported on: {:?}", unknown_budgets);
+    }
+
+    let mut report = build_report(&cli, &profiles, &account_costs, report_errors, trend_cutoff);
+    drop(account_costs);
+    if cli.optimization_hub {
+        report.optimization_hub = Some(optimization_hub::collect(&cli, &profiles, &report).await);
+    }
+    if cli.sp_recommendations {
+        report.sp_recommendations = Some(sp_recommendations::collect(&cli, &profiles, &report).await);
+    }
+    if cli.trusted_advisor {
+        report.trusted_advisor = Some(trusted_advisor::collect(&cli, &profiles, &report).await);
+    }
+    if cli.price_context {
+        pricing::apply(&cli, &profiles, &mut report).await;
+    }
+    if cli.redact {
+        let mut redactor = Redactor::new(cli.redact_profiles);
+        redactor.apply(&mut report);
+        if let Some(path) = &cli.redact_map {
+            redactor.write_map(path)?;
+        }
+    }
+    if cli.tui {
+        tui::run(&report, cli.short_names != ShortNames::Never)?;
+    } else if !cli.json {
+        let mut rendered = ReportBuffer::for_stdout();
+        {
+            let _span = cli.phase_timings.span("render", None, None);
+            render_tables(&cli, &report, None, &mut rendered)?;
+        }
+        pager::show(rendered, !cli.no_pager)?;
+    }
+    {
+        let _span = cli.phase_timings.span("write_outputs", None, None);
+        write_outputs(&cli, &profiles, &report).await?;
+    }
+    info!("{}", cli.ce_requests.usage().summary());
+    cli.phase_timings.print_summary();
+    if cli.interrupted.load(Ordering::SeqCst) {
+        return Err(RunFailure::Interrupted);
+    }
+    let alert_count = report.alerts.len() + report.burn_rate_alerts().count();
+    if alert_count > 0 {
+        return Err(RunFailure::AlertTriggered(alert_count));
+    }
+    if !report.errors.is_empty() {
+        return Err(RunFailure::Partial(report.errors.len()));
+    }
+    Ok(())
+}
+
+/// Re-collects and re-renders the report every `interval` until Ctrl-C.
+///
+/// Each refresh queries the whole range through the cost cache (`--cache-dir`, or a temporary one),
+/// so settled periods are read from disk and only those that can still change are queried again.
+async fn run_watch(
+    cli: &Cli,
+    profiles: &[String],
+    profile_account_map: &HashMap<String, MappedAccount>,
+    interval: std::time::Duration,
+    trend_cutoff: NaiveDate,
+) -> Result<(), Box<dyn Error>> {
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let wake = Arc::new(Notify::new());
+    {
+        let shutdown = shutdown.clone();
+        let wake = wake.clone();
+        tokio::spawn(async move {
+            while tokio::signal::ctrl_c().await.is_ok() {
+                shutdown.store(true, Ordering::SeqCst);
+                wake.notify_one();
+            }
+        });
+    }
+
+    let mut previous: Option<Report> = None;
+    let mut redactor = Redactor::new(cli.redact_profiles);
+    loop {
+        let mut report_errors: Vec<ReportError> = Vec::new();
+        let account_costs = collect_costs(cli, profiles, profile_account_map, &cli.start_date, &mut report_errors).await?;
+
+        // Clear the screen and move the cursor home before re-rendering.
+        print!("\x1B[2J\x1B[H");
+        if account_costs.is_empty() {
+            warn!("No cost data retrieved for any accounts across specified profiles.");
+        } else {
+            let mut report = build_report(cli, profiles, &account_costs, report_errors, trend_cutoff);
+            if cli.optimization_hub {
+                report.optimization_hub = Some(optimization_hub::collect(cli, profiles, &report).await);
+            }
+            if cli.sp_recommendations {
+                report.sp_recommendations = Some(sp_recommendations::collect(cli, profiles, &report).await);
+            }
+            if cli.trusted_advisor {
+                report.trusted_advisor = Some(trusted_advisor::collect(cli, profiles, &report).await);
+            }
+            if cli.price_context {
+                pricing::apply(cli, profiles, &mut report).await;
+            }
+            if cli.redact {
+                redactor.apply(&mut report);
+                if let Some(path) = &cli.redact_map {
+                    redactor.write_map(path)?;
+                }
+            }
+            if !cli.json {
+                let mut rendered = ReportBuffer::for_stdout();
+                render_tables(cli, &report, previous.as_ref(), &mut rendered)?;
+                pager::show(rendered, false)?;
+            }
+            write_outputs(cli, profiles, &report).await?;
+            info!("{}", cli.ce_requests.usage().summary());
+            previous = Some(report);
+        }
+
+        info!(
+            "\nLast updated: {} (refreshing every {}s, press Ctrl-C to exit)",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+            interval.as_secs()
+        );
+        if shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = wake.notified() => {}
+        }
+        if shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Discovers accounts for each profile and queries Cost Explorer for each of them from `query_start`
+/// to the requested end date. Skipped profiles and accounts are recorded in `report_errors`.
+async fn collect_costs(
+    cli: &Cli,
+    profiles: &[String],
+    profile_account_map: &HashMap<String, MappedAccount>,
+    query_start: &str,
+    report_errors: &mut Vec<ReportError>,
+) -> Result<Vec<AccountCosts>, Box<dyn Error>> {
+    let discovered = discover_accounts(cli, profiles, profile_account_map, report_errors).await?;
+    query_costs(cli, &discovered, query_start, report_errors).await
+}
+
+/// What a profile can see of the organization, for assigning `--account-id` IDs to profiles.
+struct AccountListing {
+    profile: String,
+    /// Accounts from `ListAccounts` by ID, or `None` when the profile isn't allowed to list them.
+    listed: Option<HashMap<String, aws_sdk_organizations::types::Account>>,
+    /// The profile's own account from STS.
+    own_account: Option<String>,
+}
+
+/// Picks the one profile that queries each `--account-id`: the first whose `ListAccounts` includes it,
+/// else the profile signed in to that account, else the first profile that couldn't list accounts.
+/// IDs no profile can see have no owner.
+fn account_id_owners(account_ids: &[String], listings: &[AccountListing]) -> HashMap<String, String> {
+    let mut owners = HashMap::new();
+    for account_id in account_ids {
+        let owner = listings
+            .iter()
+            .find(|listing| listing.listed.as_ref().is_some_and(|listed| listed.contains_key(account_id)))
+            .or_else(|| listings.iter().find(|listing| listing.own_account.as_ref() == Some(account_id)))
+            .or_else(|| listings.iter().find(|listing| listing.listed.is_none()));
+        if let Some(owner) = owner {
+            owners.insert(account_id.clone(), owner.profile.clone());
+        }
+    }
+    owners
+}
+
+/// A `--profile-account-map` entry: a bare account ID, or an ID with a display name.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum MappedAccount {
+    Id(String),
+    Named { id: String, name: Option<String> },
+}
+
+impl MappedAccount {
+    fn id(&self) -> &str {
+        match self {
+            MappedAccount::Id(id) | MappedAccount::Named { id, .. } => id,
+        }
+    }
+
+    fn name(&self) -> Option<&str> {
+        match self {
+            MappedAccount::Id(_) => None,
+            MappedAccount::Named { name, .. } => name.as_deref(),
+        }
+    }
+}
+
+/// Looks up an account's name with `describe_account`, falling back to `Account-<id>`. Names found
+/// are cached for the run so accounts seen through several profiles are only looked up once.
+async fn account_name(
+    org_client: &OrganizationsClient,
+    limiter: &RateLimiter,
+    account_id: &str,
+    cache: &mut HashMap<String, String>,
+) -> String {
+    if let Some(name) = cache.get(account_id) {
+        return name.clone();
+    }
+    limiter.acquire().await;
+    let described = org_client.describe_account().account_id(account_id).send().await;
+    match described.ok().and_then(|response| response.account).and_then(|account| account.name) {
+        Some(name) => {
+            cache.insert(account_id.to_string(), name.clone());
+            name
+        }
+        None => format!("Account-{}", account_id),
+    }
+}
+
+/// The IAM account alias of the profile's own account. Lookup failures only warn, so the account is still reported.
+async fn account_alias(config: &SdkConfig, profile: &str) -> Option<String> {
+    match IamClient::new(config).list_account_aliases().send().await {
+        Ok(response) => response.account_aliases.into_iter().next(),
+        Err(e) => {
+            warn!("Could not look up the IAM account alias for profile {}: {} (use --no-alias-lookup to skip).", profile, e);
+            None
+        }
+    }
+}
+
+/// Accounts discovered for a profile, with the profile's loaded configuration.
+struct ProfileAccounts {
+    profile: String,
+    config: SdkConfig,
+    accounts: Vec<aws_sdk_organizations::types::Account>,
+    /// The profile resolved to its own account (mapping or STS) rather than an Organizations listing.
+    own_account: bool,
+    /// OU path (e.g. `Workloads/Prod`) of each account found through `--ou-id`.
+    ou_paths: HashMap<String, String>,
+    /// Values of the `--show-account-tags` keys for each account.
+    account_tags: HashMap<String, BTreeMap<String, String>>,
+    /// Discovered names of accounts renamed by `--account-names`.
+    original_names: HashMap<String, String>,
+    /// Other profiles an account was also found through, left out by `--dedupe-strategy`.
+    also_via: HashMap<String, Vec<String>>,
+}
+
+/// Reads an account ID to display name map, as TOML when the path ends in `.toml` and JSON otherwise.
+fn load_display_names(path: &str) -> Result<HashMap<String, String>, Box<dyn Error>> {
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("Failed to read --account-names file {}: {}", path, e))?;
+    let names: HashMap<String, String> = if path.ends_with(".toml") {
+        toml::from_str(&contents).map_err(|e| format!("Invalid --account-names file {}: {}", path, e))?
+    } else {
+        serde_json::from_str(&contents).map_err(|e| format!("Invalid --account-names file {}: {}", path, e))?
+    };
+    for account_id in names.keys() {
+        parse_account_id(account_id).map_err(|e| format!("{} in {}", e, path))?;
+    }
+    Ok(names)
+}
+
+/// Every account `ListAccounts` returns for the profile, one paced request per page.
+async fn list_org_accounts(client: &OrganizationsClient, limiter: &RateLimiter) -> Result<Vec<aws_sdk_organizations::types::Account>, Box<dyn Error>> {
+    let mut accounts = Vec::new();
+    let mut pages = client.list_accounts().into_paginator().send();
+    while let Some(page) = {
+        limiter.acquire().await;
+        pages.next().await
+    } {
+        accounts.extend(page?.accounts.unwrap_or_default());
+    }
+    Ok(accounts)
+}
+
+/// All Organizations tags on an account.
+async fn account_tags(client: &OrganizationsClient, limiter: &RateLimiter, account_id: &str) -> Result<BTreeMap<String, String>, Box<dyn Error>> {
+    let mut tags = BTreeMap::new();
+    let mut pages = client.list_tags_for_resource().resource_id(account_id).into_paginator().send();
+    // Each page is a request, so pace it like any other Organizations call
+    while let Some(page) = {
+        limiter.acquire().await;
+        pages.next().await
+    } {
+        for tag in page?.tags.unwrap_or_default() {
+            tags.insert(tag.key, tag.value);
+        }
+    }
+    Ok(tags)
+}
+
+/// Organizations client for walking OUs. Organizations throttles these calls heavily, so they are retried more than usual.
+fn ou_client(cli: &Cli, config: &SdkConfig) -> OrganizationsClient {
+    OrganizationsClient::from_conf(
+        organizations_config(cli, config)
+            .retry_config(RetryConfig::standard().with_max_attempts(ORGANIZATIONS_MAX_ATTEMPTS))
+            .build(),
+    )
+}
+
+/// Path of an account's OU from the organization root (e.g. `Root/Workloads/Prod`). Paths of the OUs
+/// passed on the way are cached, so accounts sharing an OU cost one `ListParents` call each.
+async fn ou_path_of(
+    client: &OrganizationsClient,
+    limiter: &RateLimiter,
+    account_id: &str,
+    cache: &mut HashMap<String, String>,
+) -> Result<String, Box<dyn Error>> {
+    let mut chain: Vec<(String, String)> = Vec::new();
+    let mut child = account_id.to_string();
+    let base = loop {
+        limiter.acquire().await;
+        let response = client.list_parents().child_id(&child).send().await?;
+        let Some(parent) = response.parents.unwrap_or_default().into_iter().next() else {
+            break String::new();
+        };
+        let parent_id = parent.id.unwrap_or_default();
+        if let Some(path) = cache.get(&parent_id) {
+            break path.clone();
+        }
+        if parent.r#type == Some(ParentType::Root) {
+            cache.insert(parent_id, "Root".to_string());
+            break "Root".to_string();
+        }
+        limiter.acquire().await;
+        let described = client.describe_organizational_unit().organizational_unit_id(&parent_id).send().await?;
+        let name = described.organizational_unit.and_then(|ou| ou.name).unwrap_or_else(|| parent_id.clone());
+        chain.push((parent_id.clone(), name));
+        child = parent_id;
+    };
+
+    let mut path = base;
+    for (ou_id, name) in chain.into_iter().rev() {
+        path = if path.is_empty() { name } else { format!("{}/{}", path, name) };
+        cache.insert(ou_id, path.clone());
+    }
+    Ok(path)
+}
+
+/// Walks the OU subtree rooted at `ou_id` and returns its accounts with their OU paths, starting at
+/// the given OU's name.
+async fn accounts_in_ou(
+    client: &OrganizationsClient,
+    limiter: &RateLimiter,
+    ou_id: &str,
+) -> Result<Vec<(aws_sdk_organizations::types::Account, String)>, Box<dyn Error>> {
+    let top_name = if ou_id.starts_with("r-") {
+        "Root".to_string()
+    } else {
+        limiter.acquire().await;
+        let described = client.describe_organizational_unit().organizational_unit_id(ou_id).send().await?;
+        described.organizational_unit.and_then(|ou| ou.name).unwrap_or_else(|| ou_id.to_string())
+    };
+
+    let mut found = Vec::new();
+    let mut pending = vec![(ou_id.to_string(), top_name)];
+    while let Some((parent_id, path)) = pending.pop() {
+        let mut pages = client.list_accounts_for_parent().parent_id(&parent_id).into_paginator().send();
+        // Each page is a request, so pace it like any other Organizations call
+        while let Some(page) = {
+            limiter.acquire().await;
+            pages.next().await
+        } {
+            for account in page?.accounts.unwrap_or_default() {
+                found.push((account, path.clone()));
+            }
+        }
+        let mut pages = client.list_organizational_units_for_parent().parent_id(&parent_id).into_paginator().send();
+        while let Some(page) = {
+            limiter.acquire().await;
+            pages.next().await
+        } {
+            for child in page?.organizational_units.unwrap_or_default() {
+                if let Some(child_id) = child.id {
+                    let child_path = format!("{}/{}", path, child.name.as_deref().unwrap_or(&child_id));
+                    pending.push((child_id, child_path));
+                }
+            }
+        }
+    }
+    Ok(found)
+}
+
+/// Resolves the accounts to report on for each profile via the profile-account map, `--account-id`,
+/// Organizations or STS, narrowed to `--account-id` when given. Profiles without accounts are left out.
+async fn discover_accounts(
+    cli: &Cli,
+    profiles: &[String],
+    profile_account_map: &HashMap<String, MappedAccount>,
+    report_errors: &mut Vec<ReportError>,
+) -> Result<Vec<ProfileAccounts>, Box<dyn Error>> {
+    let mut discovered = Vec::new();
+    let account_id_set: Option<HashSet<String>> = cli.account_id.clone().map(|ids| ids.into_iter().collect());
+    let mut inactive_skipped = 0;
+    let mut account_names: HashMap<String, String> = HashMap::new();
+    let mut ou_path_cache: HashMap<String, String> = HashMap::new();
+    let mut tag_cache: HashMap<String, BTreeMap<String, String>> = HashMap::new();
+    let mut tag_failures: Vec<String> = Vec::new();
+    let org_profile_client = match &cli.org_profile {
+        Some(org_profile) => Some(organizations_client(cli, &load_discovery_config(cli, org_profile).await)),
+        None => None,
+    };
+
+    // Each --account-id is queried through one profile only, so it is neither reported twice nor
+    // queried by a profile whose Cost Explorer can't see it
+    let mut listings = Vec::new();
+    let mut account_owners = HashMap::new();
+    if let (Some(account_ids), None) = (&cli.account_id, &cli.ou_id) {
+        for profile in profiles.iter().filter(|profile| !profile_account_map.contains_key(*profile)) {
+            let config = load_discovery_config(cli, profile).await;
+            let listed = match list_org_accounts(&organizations_client(cli, &config), &cli.org_limiter).await {
+                Ok(listed) => Some(listed.into_iter().filter_map(|account| Some((account.id.clone()?, account))).collect()),
+                Err(e) => {
+                    info!("Could not list accounts for profile {} ({}); it only queries its own account and --account-id IDs no other profile lists.", profile, e);
+                    None
+                }
+            };
+            let identity = StsClient::new(&config).get_caller_identity().send().await;
+            listings.push(AccountListing { profile: profile.clone(), listed, own_account: identity.ok().and_then(|identity| identity.account) });
+        }
+        account_owners = account_id_owners(account_ids, &listings);
+        for account_id in account_ids.iter().filter(|id| !account_owners.contains_key(*id)) {
+            warn!("Warning: account {} is not in the organization of any profile; skipping it.", account_id);
+        }
+    }
+
+    // Iterate through each profile
+    for profile in profiles {
+        if cli.interrupted.load(Ordering::SeqCst) {
+            break;
+        }
+        info!("Processing profile: {}", profile);
+        let _span = cli.phase_timings.span("discovery", Some(profile), None);
+
+        // Load AWS configuration for the profile
+        let config = {
+            let _span = cli.phase_timings.span("config_load", Some(profile), None);
+            load_discovery_config(cli, profile).await
+        };
+        if cli.verbose {
+            eprintln!("Debug: profile {} uses region {} for STS, {} for Organizations and Cost Explorer",
+                profile, config.region().map_or("(none)", |r| r.as_ref()), Partition::of(&config).endpoint_region());
+        }
+
+        let org_client = organizations_client(cli, &config);
+        let sts_client = StsClient::new(&config);
+        let names_client = org_profile_client.as_ref().unwrap_or(&org_client);
+
+        // Fetch accounts for the profile
+        let mut accounts = Vec::new();
+        let mut own_account = false;
+        let mut ou_paths = HashMap::new();
+        if let Some(mapped) = profile_account_map.get(profile) {
+            // Use mapping if provided
+            own_account = true;
+            let name = match mapped.name() {
+                Some(name) => name.to_string(),
+                None => account_name(names_client, &cli.org_limiter, mapped.id(), &mut account_names).await,
+            };
+            accounts.push(
+                aws_sdk_organizations::types::Account::builder()
+                    .id(mapped.id())
+                    .name(name)
+                    .build()
+            );
+        } else if let Some(ou_id) = &cli.ou_id {
+            match accounts_in_ou(&ou_client(cli, &config), &cli.org_limiter, ou_id).await {
+                Ok(found) => {
+                    for (account, ou_path) in found {
+                        if let Some(id) = &account.id {
+                            ou_paths.insert(id.clone(), ou_path);
+                        }
+                        accounts.push(account);
+                    }
+                }
+                Err(e) => {
+                    warn!("Error listing accounts in OU {} for profile {}: {}. Skipping profile.", ou_id, profile, e);
+                    report_errors.push(ReportError {
+                        profile: profile.clone(),
+                        account_id: None,
+                        phase: "account_discovery".to_string(),
+                        error: e.to_string(),
+                        retriable: false,
+                    });
+                    continue;
+                }
+            }
+        } else if let Some(account_ids) = &cli.account_id {
+            // Explicit IDs are queried directly, which only needs ce:GetCostAndUsage, each by the one profile
+            // `account_id_owners` picked for it
+            let listing = listings.iter().find(|listing| &listing.profile == profile);
+            for account_id in account_ids.iter().filter(|id| account_owners.get(*id) == Some(profile)) {
+                if let Some(listed) = listing.and_then(|listing| listing.listed.as_ref()?.get(account_id)) {
+                    accounts.push(listed.clone());
+                    continue;
+                }
+                let name = account_name(names_client, &cli.org_limiter, account_id, &mut account_names).await;
+                accounts.push(
+                    aws_sdk_organizations::types::Account::builder()
+                        .id(account_id.clone())
+                        .name(name)
+                        .build()
+                );
+            }
+        } else {
+            // Try AWS Organizations first
+            match list_org_accounts(&org_client, &cli.org_limiter).await {
+                Ok(listed) => {
+                    accounts.extend(listed);
+                }
+                Err(e) => {
+                    warn!("Error fetching accounts for profile {} via Organizations: {}. Attempting STS fallback.", profile, e);
+                    // Fallback to STS for standalone account
+                    match sts_client.get_caller_identity().send().await {
+                        Ok(response) => {
+                            if let Some(account_id) = response.account {
+                                own_account = true;
+                                let alias = if cli.no_alias_lookup { None } else { account_alias(&config, profile).await };
+                                let name = match alias {
+                                    Some(alias) => alias,
+                                    None => account_name(names_client, &cli.org_limiter, &account_id, &mut account_names).await,
+                                };
+                                accounts.push(
+                                    aws_sdk_organizations::types::Account::builder()
+                                        .id(account_id)
+                                        .name(name)
+                                        .build()
+                                );
+                            } else {
+                                warn!("No account ID returned by STS for profile {}. Skipping profile.", profile);
+                                report_errors.push(ReportError {
+                                    profile: profile.clone(),
+                                    account_id: None,
+                                    phase: "account_discovery".to_string(),
+                                    error: "No account ID returned by STS".to_string(),
+                                    retriable: false,
+                                });
+                                continue;
+                            }
+                        }
+                        Err(e) => {
+                            warn!("Error fetching account ID via STS for profile {}: {}. Skipping profile.", profile, e);
+                            report_errors.push(ReportError {
+                                profile: profile.clone(),
+                                account_id: None,
+                                phase: "account_discovery".to_string(),
+                                error: e.to_string(),
+                                retriable: is_retriable(&e),
+                            });
+                            continue;
+                        }
+                    }
+                }
+            }
+        }
+
+        accounts.retain(|account| {
+            let status = account.status().cloned().unwrap_or(AccountStatus::Active);
+            if status == AccountStatus::Active || cli.include_suspended {
+                return true;
+            }
+            info!("Skipping {} account {} ({}) for profile {}.",
+                status.as_str().to_lowercase(), account.id().unwrap_or("unknown"), account.name().unwrap_or(""), profile);
+            inactive_skipped += 1;
+            false
+        });
+
+        let mut filtered_accounts = if let Some(ref account_ids) = account_id_set {
+            accounts.into_iter()
+                .filter(|acc| acc.id.as_ref().is_some_and(|id| account_ids.contains(id)))
+                .collect::<Vec<_>>()
+        } else {
+            accounts
+        };
+
+        let mut shown_tags = HashMap::new();
+        if !cli.account_tag.is_empty() || cli.show_account_tags.is_some() {
+            for account_id in filtered_accounts.iter().filter_map(|account| account.id.as_ref()) {
+                if tag_cache.contains_key(account_id) {
+                    continue;
+                }
+                let tags = match account_tags(names_client, &cli.org_limiter, account_id).await {
+                    Ok(tags) => tags,
+                    Err(e) => {
+                        tag_failures.push(format!("{} ({})", account_id, e));
+                        BTreeMap::new()
+                    }
+                };
+                tag_cache.insert(account_id.clone(), tags);
+            }
+            filtered_accounts.retain(|account| {
+                let tags = account.id.as_ref().and_then(|id| tag_cache.get(id));
+                cli.account_tag.iter().all(|(key, value)| tags.and_then(|t| t.get(key)) == Some(value))
+            });
+            for account_id in filtered_accounts.iter().filter_map(|account| account.id.as_ref()) {
+                let shown: BTreeMap<String, String> = cli
+                    .show_account_tags
+                    .iter()
+                    .flatten()
+                    .map(|key| (key.clone(), tag_cache[account_id].get(key).cloned().unwrap_or_default()))
+                    .collect();
+                shown_tags.insert(account_id.clone(), shown);
+            }
+        }
+
+        if cli.group_by_ou {
+            let client = ou_client(cli, &config);
+            for account_id in filtered_accounts.iter().filter_map(|account| account.id.as_ref()) {
+                if ou_paths.contains_key(account_id) {
+                    continue;
+                }
+                match ou_path_of(&client, &cli.org_limiter, account_id, &mut ou_path_cache).await {
+                    Ok(path) => {
+                        ou_paths.insert(account_id.clone(), path);
+                    }
+                    Err(e) => {
+                        warn!("Could not resolve OUs for profile {} ({}); its accounts are grouped under {}.", profile, e, UNKNOWN_OU);
+                        break;
+                    }
+                }
+            }
+        }
+
+        let mut original_names = HashMap::new();
+        for account in &mut filtered_accounts {
+            let Some(display_name) = account.id.as_ref().and_then(|id| cli.display_names.get(id)) else {
+                continue;
+            };
+            if let Some(original) = account.name.replace(display_name.clone()) {
+                original_names.insert(account.id.clone().unwrap_or_default(), original);
+            }
+        }
+
+        if filtered_accounts.is_empty() {
+            warn!("No accounts found for profile {}{}", profile, 
+                account_id_set.clone().map_or("".to_string(), |ids| format!(" for account IDs {:?}", ids)));
+            continue;
+        }
+
+        discovered.push(ProfileAccounts {
+            profile: profile.clone(),
+            config,
+            accounts: filtered_accounts,
+            own_account,
+            ou_paths,
+            account_tags: shown_tags,
+            original_names,
+            also_via: HashMap::new(),
+        });
+    }
+
+    if inactive_skipped > 0 {
+        info!("Skipped {} suspended or closed accounts (use --include-suspended to report on them).", inactive_skipped);
+    }
+    if let Some(first_failure) = tag_failures.first() {
+        warn!("Warning: Could not read Organizations tags for {} accounts (e.g. {}); their tag values are treated as empty.",
+            tag_failures.len(), first_failure);
+    }
+    dedupe_accounts(discovered, &cli.dedupe_strategy)
+}
+
+/// Leaves each account under a single profile so it is queried, and counted in global totals, once.
+fn dedupe_accounts(mut discovered: Vec<ProfileAccounts>, strategy: &DedupeStrategy) -> Result<Vec<ProfileAccounts>, Box<dyn Error>> {
+    let mut owners: HashMap<String, usize> = HashMap::new();
+    let mut reached_via: HashMap<String, Vec<String>> = HashMap::new();
+    for (index, entry) in discovered.iter().enumerate() {
+        for account_id in entry.accounts.iter().filter_map(|account| account.id.clone()) {
+            let Some(&first) = owners.get(&account_id) else {
+                owners.insert(account_id, index);
+                continue;
+            };
+            if first == index {
+                continue;
+            }
+            let keep = match strategy {
+                DedupeStrategy::First => first,
+                DedupeStrategy::PreferMember if entry.own_account && !discovered[first].own_account => index,
+                DedupeStrategy::PreferMember => first,
+                DedupeStrategy::Error => {
+                    return Err(format!(
+                        "Account {} is reachable via profiles {} and {}; use --dedupe-strategy first or prefer-member to pick one",
+                        account_id, discovered[first].profile, entry.profile
+                    )
+                    .into());
+                }
+            };
+            info!("Account {} is reachable via profiles {} and {}; reporting it under {} only.",
+                account_id, discovered[first].profile, entry.profile, discovered[keep].profile);
+            let via = reached_via.entry(account_id.clone()).or_insert_with(|| vec![discovered[first].profile.clone()]);
+            if !via.contains(&entry.profile) {
+                via.push(entry.profile.clone());
+            }
+            owners.insert(account_id, keep);
+        }
+    }
+
+    for (account_id, via) in reached_via {
+        let entry = &mut discovered[owners[&account_id]];
+        let others: Vec<String> = via.into_iter().filter(|profile| *profile != entry.profile).collect();
+        entry.also_via.insert(account_id, others);
+    }
+    for (index, entry) in discovered.iter_mut().enumerate() {
+        let mut seen = HashSet::new();
+        entry.accounts.retain(|account| match &account.id {
+            Some(id) => owners.get(id) == Some(&index) && seen.insert(id.clone()),
+            None => true,
+        });
+    }
+    discovered.retain(|entry| !entry.accounts.is_empty());
+    Ok(discovered)
+}
+
+/// Queries Cost Explorer for every discovered account from `query_start` to the requested end date.
+/// A profile's accounts are queried `--account-concurrency` at a time, paced by the shared Cost
+/// Explorer rate limiter, and reported in account ID order.
+async fn query_costs(
+    cli: &Cli,
+    discovered: &[ProfileAccounts],
+    query_start: &str,
+    report_errors: &mut Vec<ReportError>,
+) -> Result<Vec<AccountCosts>, Box<dyn Error>> {
+    let mut account_costs: Vec<AccountCosts> = Vec::new();
+    let query_granularity = cli.query_granularity();
+    let rebucket_timezone = cli.timezone.filter(|_| query_granularity != cli.granularity);
+    let intervals = request_intervals(cli, query_start);
+    // Cached periods are only reused for granularities Cost Explorer returns as-is
+    let cache = match cli.cache_dir.as_ref().or(cli.watch_cache_dir.as_ref()) {
+        Some(dir) if rebucket_timezone.is_none() && matches!(query_granularity, GranularityOption::Monthly | GranularityOption::Daily) => {
+            Some(CostCache::new(dir, cli.cache_recent_days).map_err(|e| format!("Cannot use --cache-dir {}: {}", dir, e))?)
+        }
+        _ => None,
+    };
+    let mut cache_tag = match (&cli.tag_key, &cli.tag_value) {
+        (Some(key), Some(value)) => format!("{}={}", key, value),
+        (Some(key), None) => key.clone(),
+        _ => String::new(),
+    };
+    if let Some(preset) = &cli.preset {
+        cache_tag = [format!("preset={}", preset.name()), cache_tag].join("-").trim_end_matches('-').to_string();
+    }
+    // Extra metrics go in separate cache files, so every period reused from them has their totals
+    let extra_metrics = cli.cost_metrics().split_off(1);
+    if !extra_metrics.is_empty() {
+        cache_tag = [format!("metrics={}", extra_metrics.join("+")), cache_tag].join("-").trim_end_matches('-').to_string();
+    }
+    if cli.show_credits {
+        cache_tag = ["record-types".to_string(), cache_tag].join("-").trim_end_matches('-').to_string();
+    }
+    if cli.tax_handling == TaxHandling::Exclude {
+        cache_tag = ["tax=exclude".to_string(), cache_tag].join("-").trim_end_matches('-').to_string();
+    }
+    let mut scope = QueryScope {
+        query_start,
+        query_granularity,
+        rebucket_timezone,
+        intervals,
+        cache,
+        cache_tag,
+        today: chrono::Utc::now().date_naive(),
+        cur: None,
+    };
+    if cli.source != CostSource::Api {
+        let (Some((start, _)), Some((_, end)), Some(first)) = (scope.intervals.first(), scope.intervals.last(), discovered.first()) else {
+            return Ok(account_costs);
+        };
+        let cur = if cli.source == CostSource::Athena {
+            let account_ids: Vec<String> = discovered.iter().flat_map(|p| p.accounts.iter().filter_map(|a| a.id().map(str::to_string))).collect();
+            let cur = athena::load(cli, &first.config, &account_ids, start, end).await?;
+            info!("Read {} rows from the Athena query", cur.line_items);
+            cur
+        } else {
+            let cur = cur::load(cli, &first.config, start, end).await?;
+            info!("Read {} line items from {} CUR files", cur.line_items, cur.files);
+            cur
+        };
+        scope.cur = Some(cur);
+    }
+
+    if let Some(max_calls) = cli.max_api_calls.filter(|_| scope.cur.is_none()) {
+        let account_count: usize = discovered.iter().map(|p| p.accounts.len()).sum();
+        let estimate = cli.ce_requests.count() + account_count * scope.intervals.len() * cli.queries_per_account();
+        if estimate > max_calls && !cli.yes {
+            return Err(format!(
+                "This run needs an estimated {} Cost Explorer requests (~${:.2}) for {} accounts, over --max-api-calls {}. Pass --yes to start anyway (it still stops at the budget), or narrow the accounts or range.",
+                estimate, estimate as f64 * api_usage::COST_PER_REQUEST, account_count, max_calls
+            ).into());
+        }
+    }
+
+    for profile_accounts in discovered {
+        let ce_client = cost_explorer_client(cli, &profile_accounts.config);
+        let mut queries = futures::stream::iter(profile_accounts.accounts.iter().cloned())
+            .map(|account| query_account(cli, &scope, &ce_client, profile_accounts, account))
+            .buffer_unordered(cli.account_concurrency);
+        let mut outcomes = Vec::new();
+        while let Some(outcome) = queries.next().await {
+            outcomes.push(outcome?);
+        }
+        outcomes.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        // Free Tier usage is only reported for the caller's account, so it is attached to that one
+        let mut free_tier = match cli.free_tier {
+            true => free_tier::query(&profile_accounts.config, cli.free_tier_threshold).await,
+            false => None,
+        };
+        let mut stopped = None;
+        for (account_id, outcome) in outcomes {
+            match outcome {
+                AccountOutcome::Costs(mut costs) => {
+                    if !costs.excluded && free_tier.as_ref().is_some_and(|(caller, _)| *caller == costs.account_id) {
+                        costs.free_tier = free_tier.take().map(|(_, usage)| usage);
+                    }
+                    account_costs.push(*costs)
+                }
+                AccountOutcome::Skipped(error) => report_errors.push(error),
+                AccountOutcome::Stopped(stop) => {
+                    stopped.get_or_insert((stop, account_id));
+                }
+            }
+        }
+        if let Some((stop, account_id)) = stopped {
+            stop.record(&profile_accounts.profile, &account_id, report_errors);
+            return Ok(account_costs);
+        }
+    }
+
+    Ok(account_costs)
+}
+
+/// Run-wide query settings shared by every account's queries.
+struct QueryScope<'a> {
+    query_start: &'a str,
+    query_granularity: GranularityOption,
+    /// Time zone hourly results are summed into local days in, when the report isn't hourly itself.
+    rebucket_timezone: Option<chrono_tz::Tz>,
+    intervals: Vec<(String, String)>,
+    cache: Option<CostCache>,
+    cache_tag: String,
+    today: NaiveDate,
+    /// With `--source cur`, the costs read from the CUR files, used in place of Cost Explorer's.
+    cur: Option<CurCosts>,
+}
+
+/// Result of querying one account.
+enum AccountOutcome {
+    Costs(Box<AccountCosts>),
+    /// The account's query failed and it is left out of the report.
+    Skipped(ReportError),
+    /// The run stopped before the account was fully queried.
+    Stopped(EarlyStop),
+}
+
+/// Queries one account's costs over every request interval, returning its ID with the outcome. Fails
+/// only when hourly data isn't enabled, which `--fallback-granularity` may retry.
+async fn query_account(
+    cli: &Cli,
+    scope: &QueryScope<'_>,
+    ce_client: &CostExplorerClient,
+    profile_accounts: &ProfileAccounts,
+    account: aws_sdk_organizations::types::Account,
+) -> Result<(String, AccountOutcome), Box<dyn Error>> {
+    let ProfileAccounts { profile, ou_paths, account_tags, original_names, .. } = profile_accounts;
+    let QueryScope { query_start, query_granularity, rebucket_timezone, intervals, cache, cache_tag, today, cur } = scope;
+    let account_id = account.id.unwrap_or_default();
+    let _span = cli.phase_timings.span("cost_query", Some(profile), Some(&account_id));
+    let account_name = account.name.unwrap_or("N/A".to_string());
+    let mut monthly_totals: BTreeMap<String, f64> = BTreeMap::new();
+    let extra_metrics = cli.cost_metrics().split_off(1);
+    let mut metric_totals: BTreeMap<String, BTreeMap<String, f64>> = BTreeMap::new();
+    let mut record_type_totals: BTreeMap<String, BTreeMap<String, f64>> = BTreeMap::new();
+    let mut service_monthly_totals: BTreeMap<String, BTreeMap<String, f64>> = BTreeMap::new();
+    let mut estimated_periods: BTreeSet<String> = BTreeSet::new();
+    let mut cached_periods: BTreeSet<String> = BTreeSet::new();
+    let mut period_ends: BTreeMap<String, String> = BTreeMap::new();
+
+    // Serve the settled leading periods from the cache and only query Cost Explorer for the rest
+    let mut account_intervals = intervals.clone();
+    let mut account_cache = None;
+    if let (Some(cache), [(start, end)]) = (cache, intervals.as_slice()) {
+        let stored = cache.load(&account_id, query_granularity, COST_METRIC, cache_tag);
+        if let (Ok(start), Ok(end)) = (NaiveDate::parse_from_str(start, "%Y-%m-%d"), NaiveDate::parse_from_str(end, "%Y-%m-%d")) {
+            let (reused, fetch_from) = cache.reusable_prefix(&stored, query_granularity, start, end, *today);
+            for period in reused {
+                let cached = &stored.periods[&period];
+                monthly_totals.insert(period.clone(), cached.total);
+                for (metric, cost) in &cached.metrics {
+                    metric_totals.entry(metric.clone()).or_default().insert(period.clone(), *cost);
+                }
+                for (record_type, cost) in cached.record_types.iter().flatten() {
+                    record_type_totals.entry(record_type.clone()).or_default().insert(period.clone(), *cost);
+                }
+                for (service, cost) in &cached.services {
+                    service_monthly_totals.entry(service.clone()).or_default().insert(period.clone(), *cost);
+                }
+                cached_periods.insert(period);
+            }
+            account_intervals = if fetch_from < end {
+                vec![(fetch_from.format("%Y-%m-%d").to_string(), end.format("%Y-%m-%d").to_string())]
+            } else {
+                Vec::new()
+            };
+        }
+        account_cache = Some(stored);
+    }
+    if let Some(cur) = cur {
+        cur.fill(&account_id, &mut monthly_totals, &mut service_monthly_totals, &mut record_type_totals);
+        account_intervals.clear();
+    }
+
+    for (interval_start, interval_end) in &account_intervals {
+        // Groups beyond a page (many usage types under `--preset`) come in further pages of the interval
+        let mut next_page_token = None;
+        loop {
+            if let Some(stop) = EarlyStop::check(cli) {
+                return Ok((account_id, AccountOutcome::Stopped(stop)));
+            }
+            let request_builder = ce_client
+                .get_cost_and_usage()
+                .time_period(
+                    DateInterval::builder()
+                        .start(interval_start)
+                        .end(interval_end)
+                        .build()?,
+                )
+                .granularity(query_granularity.clone().into())
+                .set_metrics(Some(cli.cost_metrics()))
+                .set_group_by(Some(cost_group_by(cli)))
+                .filter(cost_filter(cli, &account_id))
+                .set_next_page_token(next_page_token);
+
+            let request = async {
+                cli.ce_limiter.acquire().await;
+                match cli.run_deadline {
+                    Some(deadline) => tokio::time::timeout_at(deadline, request_builder.send())
+                        .await
+                        .map_err(|_| EarlyStop::RunTimeout),
+                    None => Ok(request_builder.send().await),
+                }
+            };
+            let sent = tokio::select! {
+                sent = request => sent,
+                _ = interrupt_grace(&cli.interrupted) => Err(EarlyStop::Interrupted),
+            };
+            let sent = match sent {
+                Ok(sent) => sent,
+                Err(stop) => return Ok((account_id, AccountOutcome::Stopped(stop))),
+            };
+            let response = match sent {
+                Ok(response) => response,
+                Err(e) if *query_granularity == GranularityOption::Hourly && is_hourly_not_enabled(&e) => {
+                    return Err(Box::new(HourlyNotEnabled { account_id: account_id.clone() }));
+                }
+                Err(e) => {
+                    warn!("Error fetching cost data for account {} (profile {}): {}. Skipping account.", 
+                        account_id, profile, e);
+                    let error = ReportError {
+                        profile: profile.clone(),
+                        account_id: Some(account_id.clone()),
+                        phase: "cost_query".to_string(),
+                        error: error_text(cli, &e),
+                        retriable: is_retriable(&e),
+                    };
+                    return Ok((account_id, AccountOutcome::Skipped(error)));
+                }
+            };
+
+            if let Some(results) = response.results_by_time {
                 for result in results {
                     let month = result.time_period.as_ref().map(|tp| tp.start.clone()).unwrap_or_default();
+                    if let Some(time_period) = &result.time_period {
+                        period_ends.insert(time_period.start.clone(), time_period.end.clone());
+                    }
                     let mut total_cost = 0.0;
+                    let mut extra_costs = vec![0.0; extra_metrics.len()];
+                    if result.estimated {
+                        estimated_periods.insert(month.clone());
+                    }
+
+                    if let Some(groups) = result.groups {
+                        for group in groups {
+                            let mut keys = group.keys.unwrap_or_default();
+                            let record_type = if cli.show_credits { keys.pop() } else { None };
+                            let service = match &cli.preset {
+                                Some(preset) => match preset.row_label(&keys) {
+                                    Some(label) => label,
+                                    None => continue,
+                                },
+                                None => keys.join(", "),
+                            };
+                            let metric = |name: &str| {
+                                group
+                                    .metrics
+                                    .as_ref()
+                                    .and_then(|m| m.get(name))
+                                    .map(|m| m.amount.as_ref().map(|a| a.parse::<f64>().unwrap_or(0.0)).unwrap_or(0.0))
+                                    .unwrap_or(0.0)
+                            };
+                            let cost = metric(COST_METRIC);
+                            total_cost += cost;
+                            for (total, name) in extra_costs.iter_mut().zip(&extra_metrics) {
+                                *total += metric(name);
+                            }
+                            if let Some(record_type) = record_type {
+                                *record_type_totals.entry(record_type).or_default().entry(month.clone()).or_insert(0.0) += cost;
+                            }
+
+                            let service_monthly = service_monthly_totals
+                                .entry(service.clone())
+                                .or_default();
+                            *service_monthly.entry(month.clone()).or_insert(0.0) += cost;
+                        }
+                    }
+
+                    for (name, cost) in extra_metrics.iter().zip(extra_costs) {
+                        *metric_totals.entry(name.clone()).or_default().entry(month.clone()).or_insert(0.0) += cost;
+                    }
+                    *monthly_totals.entry(month).or_insert(0.0) += total_cost;
+                }
+            }
+            next_page_token = response.next_page_token;
+            if next_page_token.is_none() {
+                break;
+            }
+        }
+    }
+
+    if let Some(tz) = *rebucket_timezone {
+        monthly_totals = periods::local_days(&monthly_totals, tz, query_start, &cli.query_end_date);
+        for totals in metric_totals.values_mut() {
+            *totals = periods::local_days(totals, tz, query_start, &cli.query_end_date);
+        }
+        for totals in record_type_totals.values_mut() {
+            *totals = periods::local_days(totals, tz, query_start, &cli.query_end_date);
+        }
+        for totals in service_monthly_totals.values_mut() {
+            *totals = periods::local_days(totals, tz, query_start, &cli.query_end_date);
+        }
+        estimated_periods = estimated_periods.iter().filter_map(|hour| periods::local_day(hour, tz)).collect();
+    }
+
+    if let (Some(cache), Some(mut stored)) = (cache, account_cache) {
+        for (period, end) in period_ends {
+            let services = service_monthly_totals
+                .iter()
+                .filter_map(|(service, totals)| totals.get(&period).map(|cost| (service.clone(), *cost)))
+                .collect();
+            let cached = CachedPeriod {
+                end,
+                total: monthly_totals.get(&period).copied().unwrap_or(0.0),
+                services,
+                metrics: metric_totals
+                    .iter()
+                    .filter_map(|(metric, totals)| totals.get(&period).map(|cost| (metric.clone(), *cost)))
+                    .collect(),
+                record_types: cli.show_credits.then(|| {
+                    record_type_totals
+                        .iter()
+                        .filter_map(|(record_type, totals)| totals.get(&period).map(|cost| (record_type.clone(), *cost)))
+                        .collect()
+                }),
+                estimated: estimated_periods.contains(&period),
+            };
+            stored.periods.insert(period, cached);
+        }
+        if let Err(e) = cache.save(&account_id, query_granularity, COST_METRIC, cache_tag, &stored) {
+            warn!("Warning: could not update the cost cache for account {}: {}", account_id, e);
+        }
+    }
+
+    let record_type_totals = if cli.show_credits {
+        Some(ServiceCosts::from_maps(record_type_totals))
+    } else if cli.composition_query() && !cli.is_excluded(&account_id) {
+        match cur {
+            // The CUR files have the record types already, as line item types
+            Some(_) => Some(ServiceCosts::from_maps(record_type_totals)),
+            None => query_record_types(cli, scope, ce_client, &account_id).await.map(ServiceCosts::from_maps),
+        }
+    } else {
+        None
+    };
+
+    let usage_samples = match &cli.unit_rates {
+        Some(usage_types) if !cli.is_excluded(&account_id) => query_unit_rates(cli, scope, ce_client, &account_id, usage_types).await,
+        _ => None,
+    };
+
+    let top_resources = match &cli.top_resources {
+        Some(service) if !cli.is_excluded(&account_id) => Some(query_top_resources(cli, ce_client, &account_id, service, *today).await),
+        _ => None,
+    };
+
+    let compute_optimizer = if cli.compute_optimizer && !cli.is_excluded(&account_id) {
+        Some(compute_optimizer::query(&profile_accounts.config, &account_id).await)
+    } else {
+        None
+    };
+
+    let costs = AccountCosts {
+        excluded: cli.is_excluded(&account_id),
+        profile: profile.clone(),
+        ou_path: ou_paths.get(&account_id).cloned(),
+        account_tags: account_tags.get(&account_id).cloned().unwrap_or_default(),
+        original_account_name: original_names.get(&account_id).cloned(),
+        account_id: account_id.clone(),
+        account_name,
+        period_totals: monthly_totals,
+        service_period_totals: ServiceCosts::from_maps(service_monthly_totals),
+        estimated_periods,
+        cached_periods,
+        top_resources,
+        metric_totals,
+        record_type_totals,
+        usage_samples,
+        compute_optimizer,
+        free_tier: None,
+    };
+    Ok((account_id, AccountOutcome::Costs(Box::new(costs))))
+}
+
+/// Costs by record type and period of one account, for the trend table's composition annotations.
+/// Goes through `--cache-dir` like the cost query; a failure only leaves the annotations out.
+async fn query_record_types(
+    cli: &Cli,
+    scope: &QueryScope<'_>,
+    ce_client: &CostExplorerClient,
+    account_id: &str,
+) -> Option<BTreeMap<String, BTreeMap<String, f64>>> {
+    let QueryScope { query_start, query_granularity, rebucket_timezone, intervals, cache, cache_tag, today, .. } = scope;
+    let cache_tag = ["composition".to_string(), cache_tag.clone()].join("-").trim_end_matches('-').to_string();
+    let mut totals: BTreeMap<String, BTreeMap<String, f64>> = BTreeMap::new();
+    let mut estimated_periods: BTreeSet<String> = BTreeSet::new();
+    let mut period_ends: BTreeMap<String, String> = BTreeMap::new();
+
+    let mut account_intervals = intervals.clone();
+    let mut account_cache = None;
+    if let (Some(cache), [(start, end)]) = (cache, intervals.as_slice()) {
+        let stored = cache.load(account_id, query_granularity, COST_METRIC, &cache_tag);
+        if let (Ok(start), Ok(end)) = (NaiveDate::parse_from_str(start, "%Y-%m-%d"), NaiveDate::parse_from_str(end, "%Y-%m-%d")) {
+            let (reused, fetch_from) = cache.reusable_prefix(&stored, query_granularity, start, end, *today);
+            for period in reused {
+                for (record_type, cost) in &stored.periods[&period].services {
+                    totals.entry(record_type.clone()).or_default().insert(period.clone(), *cost);
+                }
+            }
+            account_intervals = if fetch_from < end {
+                vec![(fetch_from.format("%Y-%m-%d").to_string(), end.format("%Y-%m-%d").to_string())]
+            } else {
+                Vec::new()
+            };
+        }
+        account_cache = Some(stored);
+    }
+
+    for (interval_start, interval_end) in &account_intervals {
+        let mut next_page_token = None;
+        loop {
+            if EarlyStop::check(cli).is_some() {
+                return None;
+            }
+            cli.ce_limiter.acquire().await;
+            let response = ce_client
+                .get_cost_and_usage()
+                .time_period(DateInterval::builder().start(interval_start).end(interval_end).build().expect("start and end are set"))
+                .granularity(query_granularity.clone().into())
+                .metrics(COST_METRIC)
+                .group_by(GroupDefinition::builder().r#type(GroupDefinitionType::Dimension).key("RECORD_TYPE").build())
+                .filter(cost_filter(cli, account_id))
+                .set_next_page_token(next_page_token)
+                .send()
+                .await;
+            let response = match response {
+                Ok(response) => response,
+                Err(e) => {
+                    warn!("Warning: no composition annotations for account {}: {}", account_id, error_text(cli, &e));
+                    return None;
+                }
+            };
+            for result in response.results_by_time.unwrap_or_default() {
+                let Some(time_period) = result.time_period else {
+                    continue;
+                };
+                if result.estimated {
+                    estimated_periods.insert(time_period.start.clone());
+                }
+                for group in result.groups.unwrap_or_default() {
+                    let record_type = group.keys.unwrap_or_default().join(", ");
+                    let cost = group
+                        .metrics
+                        .as_ref()
+                        .and_then(|m| m.get(COST_METRIC))
+                        .and_then(|m| m.amount.as_ref())
+                        .and_then(|a| a.parse::<f64>().ok())
+                        .unwrap_or(0.0);
+                    *totals.entry(record_type).or_default().entry(time_period.start.clone()).or_insert(0.0) += cost;
+                }
+                period_ends.insert(time_period.start, time_period.end);
+            }
+            next_page_token = response.next_page_token;
+            if next_page_token.is_none() {
+                break;
+            }
+        }
+    }
+
+    if let Some(tz) = *rebucket_timezone {
+        for record_totals in totals.values_mut() {
+            *record_totals = periods::local_days(record_totals, tz, query_start, &cli.query_end_date);
+        }
+    }
+
+    // Record types are stored as the cached period's services
+    if let (Some(cache), Some(mut stored)) = (cache, account_cache) {
+        for (period, end) in period_ends {
+            let services: BTreeMap<String, f64> = totals
+                .iter()
+                .filter_map(|(record_type, record_totals)| record_totals.get(&period).map(|cost| (record_type.clone(), *cost)))
+                .collect();
+            let cached = CachedPeriod {
+                end,
+                total: services.values().sum(),
+                services,
+                metrics: BTreeMap::new(),
+                record_types: None,
+                estimated: estimated_periods.contains(&period),
+            };
+            stored.periods.insert(period, cached);
+        }
+        if let Err(e) = cache.save(account_id, query_granularity, COST_METRIC, &cache_tag, &stored) {
+            warn!("Warning: could not update the record type cache for account {}: {}", account_id, e);
+        }
+    }
+    Some(totals)
+}
+
+/// Cost and usage quantity of the `--unit-rates` usage types of one account. A failure only leaves
+/// the rates out.
+async fn query_unit_rates(
+    cli: &Cli,
+    scope: &QueryScope<'_>,
+    ce_client: &CostExplorerClient,
+    account_id: &str,
+    usage_types: &[String],
+) -> Option<BTreeMap<String, UsageSamples>> {
+    let QueryScope { query_start, query_granularity, rebucket_timezone, intervals, .. } = scope;
+    let mut samples: BTreeMap<String, UsageSamples> = BTreeMap::new();
+    for (interval_start, interval_end) in intervals {
+        let mut next_page_token = None;
+        loop {
+            if EarlyStop::check(cli).is_some() {
+                return None;
+            }
+            cli.ce_limiter.acquire().await;
+            let response = ce_client
+                .get_cost_and_usage()
+                .time_period(DateInterval::builder().start(interval_start).end(interval_end).build().expect("start and end are set"))
+                .granularity(query_granularity.clone().into())
+                .metrics(COST_METRIC)
+                .metrics(unit_rates::USAGE_METRIC)
+                .group_by(GroupDefinition::builder().r#type(GroupDefinitionType::Dimension).key("USAGE_TYPE").build())
+                .filter(cost_filter(cli, account_id))
+                .set_next_page_token(next_page_token)
+                .send()
+                .await;
+            let response = match response {
+                Ok(response) => response,
+                Err(e) => {
+                    warn!("Warning: no unit rates for account {}: {}", account_id, error_text(cli, &e));
+                    return None;
+                }
+            };
+            for result in response.results_by_time.unwrap_or_default() {
+                let period = result.time_period.map(|period| period.start).unwrap_or_default();
+                for group in result.groups.unwrap_or_default() {
+                    let usage_type = group.keys.unwrap_or_default().join(", ");
+                    if !unit_rates::is_selected(&usage_type, usage_types) {
+                        continue;
+                    }
+                    let metrics = group.metrics.unwrap_or_default();
+                    let amount = |metric: &str| metrics.get(metric).and_then(|m| m.amount.as_ref()).and_then(|a| a.parse::<f64>().ok());
+                    let usage = samples.entry(usage_type).or_default();
+                    *usage.costs.entry(period.clone()).or_insert(0.0) += amount(COST_METRIC).unwrap_or(0.0);
+                    if let Some(quantity) = amount(unit_rates::USAGE_METRIC) {
+                        *usage.quantities.entry(period.clone()).or_insert(0.0) += quantity;
+                    }
+                    if let Some(unit) = metrics.get(unit_rates::USAGE_METRIC).and_then(|m| m.unit.clone()) {
+                        usage.units.insert(unit);
+                    }
+                }
+            }
+            next_page_token = response.next_page_token;
+            if next_page_token.is_none() {
+                break;
+            }
+        }
+    }
+    if let Some(tz) = *rebucket_timezone {
+        for usage in samples.values_mut() {
+            usage.costs = periods::local_days(&usage.costs, tz, query_start, &cli.query_end_date);
+            usage.quantities = periods::local_days(&usage.quantities, tz, query_start, &cli.query_end_date);
+        }
+    }
+    Some(samples)
+}
+
+/// The costliest resources of `service` in one account over the days Cost Explorer keeps
+/// resource-level data for. Failures are recorded on the result rather than skipping the account.
+async fn query_top_resources(cli: &Cli, ce_client: &CostExplorerClient, account_id: &str, service: &str, today: NaiveDate) -> TopResources {
+    let service = service_names::long_name(service);
+    let mut top = TopResources::new(service, today);
+    if cli.resources_unavailable.load(Ordering::SeqCst) {
+        top.error = Some(resources::NOT_ENABLED.to_string());
+        return top;
+    }
+    let filter = Expression::builder()
+        .and(cost_filter(cli, account_id))
+        .and(
+            Expression::builder()
+                .dimensions(DimensionValues::builder().key(Dimension::Service).values(service).build())
+                .build(),
+        )
+        .build();
+    let mut daily: BTreeMap<String, BTreeMap<String, f64>> = BTreeMap::new();
+    let mut next_page_token = None;
+    loop {
+        if EarlyStop::check(cli).is_some() {
+            top.error = Some("not queried: cost queries were stopped".to_string());
+            return top;
+        }
+        cli.ce_limiter.acquire().await;
+        let response = ce_client
+            .get_cost_and_usage_with_resources()
+            .time_period(DateInterval::builder().start(&top.start).end(&top.end).build().expect("start and end are set"))
+            .granularity(Granularity::Daily)
+            .metrics(COST_METRIC)
+            .group_by(GroupDefinition::builder().r#type(GroupDefinitionType::Dimension).key("RESOURCE_ID").build())
+            .filter(filter.clone())
+            .set_next_page_token(next_page_token)
+            .send()
+            .await;
+        let response = match response {
+            Ok(response) => response,
+            Err(e) if resources::is_not_enabled(&e) => {
+                if !cli.resources_unavailable.swap(true, Ordering::SeqCst) {
+                    warn!("Warning: --top-resources skipped: {}", resources::NOT_ENABLED);
+                }
+                top.error = Some(resources::NOT_ENABLED.to_string());
+                return top;
+            }
+            Err(e) => {
+                warn!("Error fetching top {} resources for account {}: {}", service, account_id, e);
+                top.error = Some(error_text(cli, &e));
+                return top;
+            }
+        };
+        for result in response.results_by_time.unwrap_or_default() {
+            let day = result.time_period.map(|period| period.start).unwrap_or_default();
+            for group in result.groups.unwrap_or_default() {
+                let resource = group.keys.unwrap_or_default().join(", ");
+                let cost = group
+                    .metrics
+                    .as_ref()
+                    .and_then(|m| m.get(COST_METRIC))
+                    .and_then(|m| m.amount.as_ref())
+                    .and_then(|a| a.parse::<f64>().ok())
+                    .unwrap_or(0.0);
+                *daily.entry(resource).or_default().entry(day.clone()).or_insert(0.0) += cost;
+            }
+        }
+        next_page_token = response.next_page_token;
+        if next_page_token.is_none() {
+            break;
+        }
+    }
+    top.set_resources(daily, cli.top_resources_count);
+    top
+}
+
+/// Why cost queries stop before every account is queried; the accounts queried so far are still reported.
+enum EarlyStop {
+    /// `--max-api-calls` requests have been made.
+    ApiBudget(usize),
+    /// `--run-timeout` has elapsed.
+    RunTimeout,
+    /// Ctrl-C was pressed.
+    Interrupted,
+}
+
+impl EarlyStop {
+    /// Checks whether another Cost Explorer request may start, claiming it against `--max-api-calls`.
+    fn check(cli: &Cli) -> Option<Self> {
+        if cli.run_deadline.is_some_and(|deadline| tokio::time::Instant::now() >= deadline) {
+            return Some(EarlyStop::RunTimeout);
+        }
+        if cli.interrupted.load(Ordering::SeqCst) {
+            return Some(EarlyStop::Interrupted);
+        }
+        if let Some(max_calls) = cli.max_api_calls.filter(|max| !cli.ce_requests.start_within(*max)) {
+            return Some(EarlyStop::ApiBudget(max_calls));
+        }
+        None
+    }
+
+    /// Reports the stop, with `account_id` as the first account left unqueried.
+    fn record(&self, profile: &str, account_id: &str, report_errors: &mut Vec<ReportError>) {
+        let (phase, reason) = match self {
+            EarlyStop::ApiBudget(max_calls) => ("api_budget", format!("--max-api-calls budget of {} requests reached", max_calls)),
+            EarlyStop::RunTimeout => ("run_timeout", "--run-timeout elapsed".to_string()),
+            EarlyStop::Interrupted => ("interrupted", "interrupted by Ctrl-C".to_string()),
+        };
+        warn!("Stopping: {}. The report is incomplete.", reason);
+        report_errors.push(ReportError {
+            profile: profile.to_string(),
+            account_id: Some(account_id.to_string()),
+            phase: phase.to_string(),
+            error: format!("{}; this and the remaining accounts were not queried", reason),
+            retriable: true,
+        });
+    }
+}
+
+/// `ReportError` phases of an `EarlyStop`, which make the report partial.
+const EARLY_STOP_PHASES: &[&str] = &["api_budget", "run_timeout", "interrupted"];
+
+/// Time an in-flight Cost Explorer request is given to finish after Ctrl-C.
+const INTERRUPT_GRACE: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Completes `INTERRUPT_GRACE` after Ctrl-C is pressed.
+async fn interrupt_grace(interrupted: &AtomicBool) {
+    while !interrupted.load(Ordering::SeqCst) {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    tokio::time::sleep(INTERRUPT_GRACE).await;
+}
+
+/// Error text for a failed AWS call, naming `--request-timeout` when the call timed out.
+fn error_text<E: Error + 'static, R: std::fmt::Debug>(cli: &Cli, err: &SdkError<E, R>) -> String {
+    match (err, cli.request_timeout) {
+        (SdkError::TimeoutError(_), Some(timeout)) => format!("timed out after {}s (--request-timeout)", timeout),
+        (SdkError::TimeoutError(_), None) => "request timed out".to_string(),
+        _ => err.to_string(),
+    }
+}
+
+/// Intervals requested from Cost Explorer for each account, from `query_start` to the query end date.
+fn request_intervals(cli: &Cli, query_start: &str) -> Vec<(String, String)> {
+    let query_granularity = cli.query_granularity();
+    // Local days straddle two UTC days, so fetch the hours either side of the range as well.
+    let (range_start, range_end) = match cli.timezone.filter(|_| query_granularity != cli.granularity) {
+        Some(_) => {
+            let tomorrow = chrono::Utc::now().date_naive() + chrono::Duration::days(1);
+            let shift = |date: &str, days: i64| {
+                NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .map(|d| (d + chrono::Duration::days(days)).min(tomorrow).format("%Y-%m-%d").to_string())
+                    .unwrap_or_else(|_| date.to_string())
+            };
+            (shift(query_start, -1), shift(&cli.query_end_date, 1))
+        }
+        None => (query_start.to_string(), cli.query_end_date.clone()),
+    };
+    query_intervals(&query_granularity, &range_start, &range_end)
+}
+
+/// Cost Explorer filter for one account: its linked account, the `--tag-key`/`--tag-value` pair when both
+/// are given, tax with `--tax-handling exclude`, and the services of `--preset`.
+fn cost_filter(cli: &Cli, account_id: &str) -> Expression {
+    let mut filters = vec![Expression::builder()
+        .dimensions(
+            DimensionValues::builder()
+                .key(Dimension::LinkedAccount)
+                .values(account_id)
+                .build(),
+        )
+        .build()];
+    if let (Some(tag_key), Some(tag_value)) = (&cli.tag_key, &cli.tag_value) {
+        filters.push(
+            Expression::builder()
+                .tags(TagValues::builder().key(tag_key).values(tag_value).build())
+                .build(),
+        );
+    }
+    if cli.tax_handling == TaxHandling::Exclude {
+        let tax = Expression::builder()
+            .dimensions(DimensionValues::builder().key(Dimension::RecordType).values(TAX).build())
+            .build();
+        filters.push(Expression::builder().not(tax).build());
+    }
+    if let Some(services) = cli.preset.as_ref().and_then(Preset::services) {
+        filters.push(
+            Expression::builder()
+                .dimensions(DimensionValues::builder().key(Dimension::Service).set_values(Some(services.iter().map(|s| s.to_string()).collect())).build())
+                .build(),
+        );
+    }
+    match filters.len() {
+        1 => filters.remove(0),
+        _ => Expression::builder().set_and(Some(filters)).build(),
+    }
+}
+
+/// Group-by for cost queries: by service (or the `--preset` dimension), and by the `--tag-key` tag when
+/// no `--tag-value` filters on it.
+fn cost_group_by(cli: &Cli) -> Vec<GroupDefinition> {
+    let mut group_by = vec![GroupDefinition::builder()
+        .r#type(GroupDefinitionType::Dimension)
+        .key(cli.preset.as_ref().map_or("SERVICE", Preset::group_by))
+        .build()];
+    if let (Some(tag_key), None) = (&cli.tag_key, &cli.tag_value) {
+        group_by.push(GroupDefinition::builder().r#type(GroupDefinitionType::Tag).key(tag_key).build());
+    }
+    // Last, so it is the last key of each group
+    if cli.show_credits {
+        group_by.push(GroupDefinition::builder().r#type(GroupDefinitionType::Dimension).key("RECORD_TYPE").build());
+    }
+    group_by
+}
+
+/// Date intervals to query; hourly ranges are split into chunks Cost Explorer accepts in one request.
+fn query_intervals(granularity: &GranularityOption, start: &str, end: &str) -> Vec<(String, String)> {
+    let parsed = (
+        NaiveDate::parse_from_str(start, "%Y-%m-%d"),
+        NaiveDate::parse_from_str(end, "%Y-%m-%d"),
+    );
+    match (granularity, parsed) {
+        (GranularityOption::Hourly, (Ok(start), Ok(end))) => dates::split_range(start, end, dates::MAX_HOURLY_DAYS_PER_REQUEST)
+            .into_iter()
+            .map(|(s, e)| (s.format("%Y-%m-%d").to_string(), e.format("%Y-%m-%d").to_string()))
+            .collect(),
+        _ => vec![(start.to_string(), end.to_string())],
+    }
+}
+
+/// Where each period's data came from across the reported accounts, for `--cache-dir` runs.
+fn period_sources(cli: &Cli, buckets: &PeriodBuckets, account_costs: &[AccountCosts]) -> BTreeMap<String, String> {
+    let mut sources: BTreeMap<String, (bool, bool)> = BTreeMap::new();
+    if cli.cache_dir.is_none() {
+        return BTreeMap::new();
+    }
+    for account in account_costs.iter().filter(|account| !account.excluded) {
+        for period in account.period_totals.keys() {
+            let (cached, fetched) = sources.entry(buckets.label(period)).or_default();
+            if account.cached_periods.contains(period) {
+                *cached = true;
+            } else {
+                *fetched = true;
+            }
+        }
+    }
+    sources
+        .into_iter()
+        .map(|(period, sources)| {
+            let source = match sources {
+                (true, false) => "cache",
+                (false, true) => "api",
+                _ => "mixed",
+            };
+            (period, source.to_string())
+        })
+        .collect()
+}
+
+/// Derives trends, service breakdowns, the unified view and global totals from raw account costs.
+fn build_report(
+    cli: &Cli,
+    profiles: &[String],
+    account_costs: &[AccountCosts],
+    report_errors: Vec<ReportError>,
+    trend_cutoff: NaiveDate,
+) -> Report {
+    let mut account_cost_data: Vec<AccountCostData> = Vec::new();
+    let mut unified_view_data: Vec<UnifiedViewData> = Vec::new();
+    let mut global_monthly_totals: BTreeMap<String, f64> = BTreeMap::new();
+
+    let range_start = NaiveDate::parse_from_str(&cli.start_date, "%Y-%m-%d").unwrap_or(trend_cutoff);
+    let range_end = NaiveDate::parse_from_str(&cli.query_end_date, "%Y-%m-%d").unwrap_or(trend_cutoff);
+    let buckets = PeriodBuckets::new(
+        cli.granularity.clone(),
+        cli.week_start.weekday(),
+        cli.fiscal_year_start,
+        cli.timezone,
+        range_start,
+        range_end,
+    );
+
+    let filtered_months: Vec<String> = account_costs
+        .iter()
+        .filter(|account| !account.excluded)
+        .flat_map(|account| account.period_totals.keys())
+        .filter(|m| {
+            m.get(..10)
+                .and_then(|day| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok())
+                .is_some_and(|d| d >= trend_cutoff)
+        })
+        .map(|m| buckets.label(m))
+        .collect::<BTreeSet<String>>()
+        .into_iter()
+        .collect();
+
+    let window_start = filtered_months.first().cloned();
+    let in_window = |period: &String| window_start.as_ref().is_some_and(|start| period >= start);
+    let mut excluded_accounts: Vec<ExcludedAccount> = Vec::new();
+
+    let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
+    let partial_period = filtered_months.last().filter(|_| cli.query_end_date > today).cloned();
+    let estimated_periods: BTreeSet<String> = account_costs
+        .iter()
+        .filter(|account| !account.excluded)
+        .flat_map(|account| account.estimated_periods.iter().map(|period| buckets.label(period)))
+        .collect();
+    // Days each monthly period covers within the range, for per-day normalization.
+    let period_days: BTreeMap<String, i64> = if cli.granularity == GranularityOption::Monthly {
+        filtered_months
+            .iter()
+            .filter_map(|period| {
+                let start = NaiveDate::parse_from_str(period.get(..10)?, "%Y-%m-%d").ok()?;
+                let end = (start + Months::new(1)).min(range_end);
+                let days = (end - start.max(range_start)).num_days();
+                (days > 0).then(|| (period.clone(), days))
+            })
+            .collect()
+    } else {
+        BTreeMap::new()
+    };
+
+    // The in-progress period would drag averages down, so it only counts with --include-partial.
+    let average_window: Vec<String> = filtered_months
+        .iter()
+        .filter(|period| cli.include_partial || partial_period.as_ref() != Some(*period))
+        .cloned()
+        .collect();
+
+    let no_costs = BTreeMap::new();
+    // Budget burn needs the whole current month so far, from its first day through today
+    let today_date = NaiveDate::parse_from_str(&today, "%Y-%m-%d").unwrap_or(range_end);
+    let burn_month = today_date.with_day(1).filter(|month_start| range_start <= *month_start && range_end > today_date);
+    // Accounts are bucketed one at a time, so only one rolled-up copy is held next to the raw costs
+    for account in account_costs {
+        let budget_burn = cli.budgets.get(&account.account_id).zip(burn_month).map(|(budget, month_start)| {
+            let month = month_start.format("%Y-%m").to_string();
+            let month_to_date: f64 = account.period_totals.iter().filter(|(period, _)| period.starts_with(&month)).map(|(_, cost)| cost).sum();
+            BudgetBurn::new(*budget, month_to_date, today_date, cli.burn_rate_threshold)
+        });
+        let account = buckets.apply(account);
+        if account.excluded {
+            excluded_accounts.push(ExcludedAccount {
+                profile: account.profile.clone(),
+                account_id: account.account_id.clone(),
+                account_name: account.account_name.clone(),
+                total_cost: account.period_totals.iter().filter(|(period, _)| in_window(period)).map(|(_, cost)| cost).sum(),
+            });
+            continue;
+        }
+        let period_totals: BTreeMap<String, f64> = account
+            .period_totals
+            .iter()
+            .filter(|(period, _)| in_window(period))
+            .map(|(period, cost)| (period.clone(), *cost))
+            .collect();
+        for (month, cost) in &period_totals {
+            *global_monthly_totals.entry(month.clone()).or_insert(0.0) += cost;
+        }
+
+        let mut cost_trend = build_cost_trend(
+            &filtered_months,
+            &period_totals,
+            partial_period.as_deref(),
+            &account.estimated_periods,
+            &period_days,
+        );
+        if let Some(record_types) = account.record_type_totals.as_ref().filter(|_| cli.composition()) {
+            for entry in &mut cost_trend {
+                if let Ok(index) = record_types.periods().binary_search(&entry.month) {
+                    let costs: Vec<(&String, f64)> = record_types.columns().map(|(record_type, costs)| (record_type, costs[index])).collect();
+                    entry.composition = analysis::unusual_record_types(&costs, cli.composition_threshold);
+                }
+            }
+        }
+
+        let total_cost: f64 = period_totals.values().sum();
+        let average_monthly_cost = window_average(&average_window, &period_totals);
+        let window_costs: Vec<f64> = average_window.iter().map(|period| *period_totals.get(period).unwrap_or(&0.0)).collect();
+        let stats = analysis::cost_stats(&window_costs);
+
+        // Table totals and percentages cover exactly the displayed periods; full-range figures are kept alongside.
+        let mut service_consumption = Vec::new();
+        // With `--tax-handling separate`, tax rows (`Tax`, or `Tax, <tag>` with a tag grouping) are merged
+        // into one and kept out of the percentages
+        let separate_tax = cli.tax_handling == TaxHandling::Separate && cli.preset.is_none();
+        let is_tax = |service: &str| separate_tax && (service == TAX || service.starts_with("Tax, "));
+        // Services sharing a `--service-aliases` name become one row, so percentages cover the merged costs
+        let (aliased, sources) = if cli.aliases.is_empty() && !separate_tax {
+            (None, BTreeMap::new())
+        } else {
+            let (aliased, sources) = account.service_period_totals.rename(|service| match is_tax(service) {
+                true => Some(TAX.to_string()),
+                false => cli.aliases.alias(service).map(str::to_string),
+            });
+            (Some(aliased), sources)
+        };
+        let sources: BTreeMap<String, Vec<String>> = sources.into_iter().filter(|(service, _)| !is_tax(service)).collect();
+        let services = aliased.as_ref().unwrap_or(&account.service_period_totals);
+        let window: Vec<usize> = (0..services.periods().len()).filter(|index| in_window(&services.periods()[*index])).collect();
+        let total_service_cost: f64 = services
+            .columns()
+            .filter(|(service, _)| !is_tax(service))
+            .flat_map(|(_, costs)| window.iter().map(|index| costs[*index]))
+            .sum();
+        let full_range_service_cost: f64 = services.columns().filter(|(service, _)| !is_tax(service)).flat_map(|(_, costs)| costs).sum();
+        let percent = |cost: f64, total: f64| if total > 0.0 { cost / total * 100.0 } else { 0.0 };
+        let latest_change = |costs: &[f64]| match window.as_slice() {
+            [.., previous, last] => PeriodChange::between(Some(costs[*previous]), costs[*last]),
+            _ => PeriodChange::NoData,
+        };
+        let mut tax = separate_tax.then(|| TaxCosts { monthly_costs: BTreeMap::new(), total_cost: 0.0 });
+        for (service, costs) in services.columns() {
+            let service_total_cost: f64 = window.iter().map(|index| costs[*index]).sum();
+            if let Some(tax) = tax.as_mut().filter(|_| is_tax(service)) {
+                tax.monthly_costs = window
+                    .iter()
+                    .filter(|index| costs[**index] != 0.0)
+                    .map(|index| (services.periods()[*index].clone(), costs[*index]))
+                    .collect();
+                tax.total_cost = service_total_cost;
+                continue;
+            }
+            if service_total_cost > 0.0 {
+                let full_range_total_cost: f64 = costs.iter().sum();
+                service_consumption.push(ServiceConsumptionData {
+                    service: match cli.short_names {
+                        ShortNames::Always => service_names::short_name(service).to_string(),
+                        _ => service.clone(),
+                    },
+                    monthly_costs: window
+                        .iter()
+                        .filter(|index| costs[**index] != 0.0)
+                        .map(|index| (services.periods()[*index].clone(), costs[*index]))
+                        .collect(),
+                    total_cost: service_total_cost,
+                    percent_of_total: percent(service_total_cost, total_service_cost),
+                    full_range_total_cost,
+                    full_range_percent_of_total: percent(full_range_total_cost, full_range_service_cost),
+                    source_services: sources.get(service).cloned(),
+                    latest_change_percent: cli.breakdown().row_change.then(|| latest_change(costs)),
+                });
+            }
+        }
+        service_consumption.sort_by(|a, b| {
+            b.total_cost
+                .partial_cmp(&a.total_cost)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.service.cmp(&b.service))
+        });
+
+        // Instance types of `--preset ec2` summed by family over the same periods
+        let instance_families = (cli.preset == Some(Preset::Ec2)).then(|| {
+            let mut families: BTreeMap<&str, Vec<f64>> = BTreeMap::new();
+            for (instance_type, costs) in services.columns() {
+                let family = families.entry(presets::instance_family(instance_type)).or_insert_with(|| vec![0.0; costs.len()]);
+                for (total, cost) in family.iter_mut().zip(costs) {
+                    *total += cost;
+                }
+            }
+            let mut families: Vec<InstanceFamilyCost> = families
+                .into_iter()
+                .map(|(family, costs)| {
+                    let family_total: f64 = window.iter().map(|index| costs[*index]).sum();
+                    InstanceFamilyCost {
+                        family: family.to_string(),
+                        monthly_costs: window
+                            .iter()
+                            .filter(|index| costs[**index] != 0.0)
+                            .map(|index| (services.periods()[*index].clone(), costs[*index]))
+                            .collect(),
+                        total_cost: family_total,
+                        percent_of_total: percent(family_total, total_service_cost),
+                        latest_change_percent: latest_change(&costs),
+                    }
+                })
+                .filter(|family| family.total_cost > 0.0)
+                .collect();
+            families.sort_by(|a, b| b.total_cost.total_cmp(&a.total_cost).then_with(|| a.family.cmp(&b.family)));
+            families
+        });
+
+        account_cost_data.push(AccountCostData {
+            profile: account.profile.clone(),
+            account_id: account.account_id.clone(),
+            account_name: account.account_name.clone(),
+            ou_path: account.ou_path.clone(),
+            account_tags: account.account_tags.clone(),
+            original_account_name: account.original_account_name.clone(),
+            cost_trend,
+            service_consumption,
+            total_cost,
+            period_granularity: cli.granularity.name().to_string(),
+            average_monthly_cost,
+            stats,
+            top_resources: account.top_resources.clone(),
+            compute_optimizer: account.compute_optimizer.clone(),
+            free_tier: account.free_tier.clone(),
+            instance_families,
+            tax,
+            unit_rates: account
+                .usage_samples
+                .as_ref()
+                .map(|samples| unit_rates::unit_rates(samples, &filtered_months, cli.unit_rate_threshold)),
+            amortization_delta: cli.amortization_delta.then(|| {
+                analysis::amortization_delta(&filtered_months, &period_totals, account.metric_totals.get(AMORTIZED_METRIC).unwrap_or(&no_costs))
+            }),
+            metric_comparison: cli.compared_metrics().map(|(first, second)| {
+                let totals = |metric: CostMetric| match metric.api_name() {
+                    COST_METRIC => &period_totals,
+                    name => account.metric_totals.get(name).unwrap_or(&no_costs),
+                };
+                analysis::metric_comparison(
+                    &filtered_months,
+                    (first.api_name(), totals(first)),
+                    (second.api_name(), totals(second)),
+                    cli.compare_threshold,
+                )
+            }),
+            record_type_breakdown: account.record_type_totals.as_ref().filter(|_| cli.show_credits).map(|totals| {
+                totals
+                    .to_maps()
+                    .into_iter()
+                    .map(|(record_type, costs)| (record_type, costs.into_iter().filter(|(period, _)| in_window(period)).collect::<BTreeMap<_, _>>()))
+                    .filter(|(_, costs)| !costs.is_empty())
+                    .collect()
+            }),
+        });
+
+        unified_view_data.push(UnifiedViewData {
+            profile: account.profile.clone(),
+            account_id: account.account_id.clone(),
+            account_name: account.account_name.clone(),
+            ou_path: account.ou_path.clone(),
+            account_tags: account.account_tags.clone(),
+            monthly_costs: period_totals,
+            budget_burn,
+        });
+    }
+
+    account_cost_data.sort_by(|a, b| (&a.profile, &a.account_id).cmp(&(&b.profile, &b.account_id)));
+    excluded_accounts.sort_by(|a, b| (&a.profile, &a.account_id).cmp(&(&b.profile, &b.account_id)));
+    unified_view_data.sort_by(|a, b| (&a.profile, &a.account_id).cmp(&(&b.profile, &b.account_id)));
+
+    let total_global_cost: f64 = global_monthly_totals.values().sum();
+    let average_global_monthly_cost = window_average(&average_window, &global_monthly_totals);
+    let total_tax = (cli.tax_handling == TaxHandling::Separate)
+        .then(|| account_cost_data.iter().filter_map(|account| account.tax.as_ref()).map(|tax| tax.total_cost).sum());
+    let weekday_analysis = if cli.weekday_analysis {
+        account_cost_data
+            .iter()
+            .filter_map(|account| analysis::weekday_split(account, cli.weekend_target_percent, cli.include_partial))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let hourly_profiles = if cli.hourly_profile {
+        account_cost_data.iter().filter_map(analysis::hourly_profile).collect()
+    } else {
+        Vec::new()
+    };
+
+    let metadata = report_metadata(cli, profiles, trend_cutoff, period_sources(cli, &buckets, account_costs));
+
+    let alerts = alerts::evaluate(
+        cli.alert_threshold,
+        cli.account_alert_threshold,
+        &filtered_months,
+        partial_period.as_deref(),
+        &global_monthly_totals,
+        &unified_view_data,
+    );
+
+    Report {
+        metadata,
+        accounts: account_cost_data,
+        unified_view: unified_view_data,
+        global_period_totals: global_monthly_totals,
+        periods: filtered_months,
+        partial_period,
+        estimated_periods,
+        period_days,
+        weekday_analysis,
+        hourly_profiles,
+        total_cost: total_global_cost,
+        average_period_cost: average_global_monthly_cost,
+        total_tax,
+        excluded_accounts,
+        alerts,
+        errors: report_errors,
+        optimization_hub: None,
+        sp_recommendations: None,
+        trusted_advisor: None,
+    }
+}
+
+/// Unified view rows grouped by OU path, with accounts of unknown OU last.
+fn ou_groups(unified_view: &[UnifiedViewData]) -> Vec<(String, Vec<&UnifiedViewData>)> {
+    let mut groups: BTreeMap<(bool, String), Vec<&UnifiedViewData>> = BTreeMap::new();
+    for account in unified_view {
+        let key = match &account.ou_path {
+            Some(path) => (false, path.clone()),
+            None => (true, UNKNOWN_OU.to_string()),
+        };
+        groups.entry(key).or_default().push(account);
+    }
+    groups.into_iter().map(|((_, path), accounts)| (path, accounts)).collect()
+}
+
+/// Average cost per period over the trend window, counting periods without data as zero.
+fn window_average(window: &[String], totals: &BTreeMap<String, f64>) -> f64 {
+    if window.is_empty() {
+        return 0.0;
+    }
+    let window_cost: f64 = window.iter().filter_map(|period| totals.get(period)).sum();
+    window_cost / window.len() as f64
+}
+
+/// Metadata of a report on `profiles` over the range of `cli`, as of now.
+fn report_metadata(cli: &Cli, profiles: &[String], trend_cutoff: NaiveDate, period_sources: BTreeMap<String, String>) -> ReportMetadata {
+    ReportMetadata {
+        schema_version: SCHEMA_VERSION,
+        tool_version: env!("CARGO_PKG_VERSION").to_string(),
+        generated_at: chrono::Utc::now().to_rfc3339(),
+        start_date: cli.start_date.clone(),
+        end_date: cli.end_date.clone(),
+        inclusive_end: cli.inclusive_end,
+        trend_window_start: if cli.trend_window > 0 {
+            Some(trend_cutoff.format("%Y-%m-%d").to_string())
+        } else {
+            None
+        },
+        partial_period: cli.query_end_date > chrono::Utc::now().format("%Y-%m-%d").to_string(),
+        timezone: cli.timezone.map(|tz| tz.name().to_string()),
+        granularity: cli.granularity.name().to_string(),
+        metric: COST_METRIC.to_string(),
+        tax_handling: cli.tax_handling.name().to_string(),
+        preset: cli.preset.as_ref().map(|preset| preset.name().to_string()),
+        filters: ReportFilters {
+            account_ids: cli.account_id.clone(),
+            exclude_accounts: cli.exclude_accounts.clone(),
+            ou_id: cli.ou_id.clone(),
+            account_tags: cli.account_tag.iter().map(|(key, value)| format!("{}={}", key, value)).collect(),
+            tag_key: cli.tag_key.clone(),
+            tag_value: cli.tag_value.clone(),
+        },
+        profiles: profiles.to_vec(),
+        api_usage: cli.ce_requests.usage(),
+        timings: cli.phase_timings.entries(),
+        period_sources,
+    }
+}
+
+/// First day covered by trends, totals and averages: the start of the range, or `--trend-window`
+/// months before its (exclusive) end.
+fn trend_cutoff(trend_window: u32, start: NaiveDate, end: NaiveDate) -> NaiveDate {
+    match trend_window {
+        0 => start,
+        months => end.checked_sub_months(Months::new(months)).unwrap_or(start),
+    }
+}
+
+/// Caption for tables limited to the trend window, e.g. "Past 6 Months".
+fn trend_window_caption(trend_window: u32) -> String {
+    match trend_window {
+        0 => "Full Range".to_string(),
+        1 => "Past Month".to_string(),
+        months => format!("Past {} Months", months),
+    }
+}
+
+/// Period label for table headers: `*` marks the in-progress period, `(est.)` estimated costs.
+fn period_caption(cli: &Cli, report: &Report, period: &str) -> String {
+    let mut caption = periods::display_label(period, &cli.granularity, cli.date_format.as_deref());
+    if report.partial_period.as_deref() == Some(period) {
+        caption.push('*');
+    }
+    if report.estimated_periods.contains(period) {
+        caption.push_str(" (est.)");
+    }
+    caption
+}
+
+/// Widest line of a table as rendered, without colour escapes.
+fn table_width(table: &Table) -> usize {
+    table.to_string().lines().map(|line| line.chars().count()).max().unwrap_or(0)
+}
+
+/// Style for a cost cell, highlighted when it differs from the previous `--watch` iteration.
+fn cost_style(current: f64, previous: Option<f64>) -> &'static str {
+    match previous {
+        Some(previous) if (previous - current).abs() >= 0.005 => "bFy",
+        _ => "Fr",
+    }
+}
+
+/// Renders the unified view, per-account and global summary tables into `out`.
+fn render_tables(cli: &Cli, report: &Report, previous: Option<&Report>, out: &mut ReportBuffer) -> io::Result<()> {
+    let filtered_months = &report.periods;
+    let range_start = report.metadata.trend_window_start.as_deref().unwrap_or(&cli.start_date);
+    let previous_unified = |account: &UnifiedViewData, month: &str| {
+        previous.map(|p| {
+            p.unified_view
+                .iter()
+                .find(|u| u.profile == account.profile && u.account_id == account.account_id)
+                .and_then(|u| u.monthly_costs.get(month).copied())
+                .unwrap_or(0.0)
+        })
+    };
+
+    if let Some(stop) = report.errors.iter().find(|e| EARLY_STOP_PHASES.contains(&e.phase.as_str())) {
+        writeln!(out, "PARTIAL REPORT: {}", stop.error)?;
+    }
+    let alert_count = report.alerts.len() + report.burn_rate_alerts().count();
+    if alert_count > 0 {
+        writeln!(out, "ALERT: {} cost thresholds exceeded", alert_count)?;
+        for alert in &report.alerts {
+            writeln!(out, "  - {}", alert.message())?;
+        }
+        for (account, burn) in report.burn_rate_alerts() {
+            writeln!(out, "  - Profile {} Account {} ({}) has used {:.1}% of its ${:.2} budget with {:.1}% of the month elapsed, over --burn-rate-threshold {} points",
+                account.profile, account.account_id, account.account_name, burn.budget_used_percent, burn.budget, burn.month_elapsed_percent, cli.burn_rate_threshold)?;
+        }
+    }
+
+    // Unified View Table, paginated by period, or transposed to one block per account when too wide
+    let tag_columns: Vec<String> = cli.show_account_tags.clone().unwrap_or_default();
+    let show_burn = report.unified_view.iter().any(|account| account.budget_burn.is_some());
+    let burn_columns: &[&str] = if show_burn { &["Budget Used (%)", "Burn Overage (pts)"] } else { &[] };
+    let burn_cells = |account: &UnifiedViewData| -> Vec<Cell> {
+        let Some(burn) = account.budget_burn.as_ref().filter(|_| show_burn) else {
+            return burn_columns.iter().map(|_| Cell::new("")).collect();
+        };
+        let style = if burn.exceeds_threshold { "bFr" } else if burn.overage_percent > 0.0 { "Fy" } else { "Fc" };
+        vec![
+            Cell::new(&format!("{:.*}", cli.percent_decimals, burn.budget_used_percent)).style_spec("Fc"),
+            Cell::new(&format!("{:+.*}", cli.percent_decimals, burn.overage_percent)).style_spec(style),
+        ]
+    };
+    let cost_cell = |cost: f64, month: &str, style: &str| match report.period_days.get(month).filter(|_| cli.per_day) {
+        Some(days) => Cell::new(&format!("{:.2}", cost / *days as f64)).style_spec(style),
+        None => Cell::new(&format!("{:.2}", cost)).style_spec(style),
+    };
+    let max_columns = 10; // Adjust this based on terminal width
+    let mut wide_pages = Vec::new();
+    for chunk in filtered_months.chunks(max_columns - 3) { // -3 for Profile, Account ID, Account Name
+        let mut unified_table = Table::new();
+        unified_table.set_format(*format::consts::FORMAT_DEFAULT); // Restore grid lines
+        let mut unified_titles = vec![
+            Cell::new("Profile").style_spec("bFc"),
+            Cell::new("Account ID").style_spec("bFc"),
+            Cell::new("Account Name").style_spec("bFc"),
+        ];
+        for key in &tag_columns {
+            unified_titles.push(Cell::new(key).style_spec("bFc"));
+        }
+        for column in burn_columns {
+            unified_titles.push(Cell::new(column).style_spec("bFc"));
+        }
+        for month in chunk {
+            unified_titles.push(Cell::new(&period_caption(cli, report, month)).style_spec("bFr"));
+        }
+        unified_table.set_titles(Row::new(unified_titles));
+
+        let account_row = |account: &UnifiedViewData| {
+            let mut row = vec![
+                Cell::new(&account.profile),
+                Cell::new(&account.account_id),
+                Cell::new(&account.account_name),
+            ];
+            for key in &tag_columns {
+                row.push(Cell::new(account.account_tags.get(key).map_or("", String::as_str)));
+            }
+            row.extend(burn_cells(account));
+            for month in chunk {
+                let cost = *account.monthly_costs.get(month).unwrap_or(&0.0);
+                row.push(cost_cell(cost, month, cost_style(cost, previous_unified(account, month))));
+            }
+            Row::new(row)
+        };
+
+        if cli.group_by_ou {
+            for (ou_path, accounts) in ou_groups(&report.unified_view) {
+                let mut heading = vec![Cell::new(&format!("OU: {}", ou_path)).style_spec("bFy"), Cell::new(""), Cell::new("")];
+                heading.extend(tag_columns.iter().map(String::as_str).chain(burn_columns.iter().copied()).chain(chunk.iter().map(String::as_str)).map(|_| Cell::new("")));
+                unified_table.add_row(Row::new(heading));
+                for account in &accounts {
+                    unified_table.add_row(account_row(account));
+                }
+                let mut subtotal = vec![Cell::new(""), Cell::new(""), Cell::new(&format!("Subtotal {}", ou_path)).style_spec("b")];
+                subtotal.extend(tag_columns.iter().map(|_| Cell::new("")));
+                subtotal.extend(burn_columns.iter().map(|_| Cell::new("")));
+                for month in chunk {
+                    let cost: f64 = accounts.iter().filter_map(|account| account.monthly_costs.get(month)).sum();
+                    subtotal.push(cost_cell(cost, month, "bFr"));
+                }
+                unified_table.add_row(Row::new(subtotal));
+            }
+        } else {
+            for account in &report.unified_view {
+                unified_table.add_row(account_row(account));
+            }
+        }
+        wide_pages.push(unified_table);
+    }
+
+    let narrow = match cli.layout {
+        TableLayout::Wide => false,
+        TableLayout::Narrow => true,
+        TableLayout::Auto => pager::terminal_width().is_some_and(|width| wide_pages.iter().any(|table| table_width(table) > width)),
+    };
+    let view_name = if cli.per_day && !report.period_days.is_empty() { "Unified Cost per Day View" } else { "Unified Cost View" };
+    if narrow {
+        let mut narrow_table = Table::new();
+        narrow_table.set_format(*format::consts::FORMAT_DEFAULT);
+        narrow_table.set_titles(Row::new(vec![
+            Cell::new(cli.granularity.period_label()).style_spec("bFc"),
+            Cell::new("Cost (USD)").style_spec("bFr"),
+        ]));
+        let heading = |text: &str, style: &str| Row::new(vec![Cell::new(text).style_spec(style).with_hspan(2)]);
+        let account_block = |table: &mut Table, account: &UnifiedViewData| {
+            let mut title = format!("{} / {} ({})", account.profile, account.account_id, account.account_name);
+            for key in &tag_columns {
+                title.push_str(&format!(", {}: {}", key, account.account_tags.get(key).map_or("", String::as_str)));
+            }
+            if let Some(burn) = &account.budget_burn {
+                title.push_str(&format!(", budget used: {:.*}% ({:+.*} pts)", cli.percent_decimals, burn.budget_used_percent, cli.percent_decimals, burn.overage_percent));
+            }
+            table.add_row(heading(&title, "b"));
+            for month in filtered_months {
+                let cost = *account.monthly_costs.get(month).unwrap_or(&0.0);
+                table.add_row(Row::new(vec![
+                    Cell::new(&period_caption(cli, report, month)),
+                    cost_cell(cost, month, cost_style(cost, previous_unified(account, month))),
+                ]));
+            }
+        };
+        if cli.group_by_ou {
+            for (ou_path, accounts) in ou_groups(&report.unified_view) {
+                narrow_table.add_row(heading(&format!("OU: {}", ou_path), "bFy"));
+                for account in &accounts {
+                    account_block(&mut narrow_table, account);
+                }
+                narrow_table.add_row(heading(&format!("Subtotal {}", ou_path), "b"));
+                for month in filtered_months {
+                    let cost: f64 = accounts.iter().filter_map(|account| account.monthly_costs.get(month)).sum();
+                    narrow_table.add_row(Row::new(vec![Cell::new(&period_caption(cli, report, month)), cost_cell(cost, month, "bFr")]));
+                }
+            }
+        } else {
+            for account in &report.unified_view {
+                account_block(&mut narrow_table, account);
+            }
+        }
+        writeln!(out, "\n{} ({}):", view_name, trend_window_caption(cli.trend_window))?;
+        out.table(&narrow_table)?;
+    } else {
+        for (page, unified_table) in wide_pages.iter().enumerate() {
+            writeln!(out, "\n{} ({}) - Page {}:", view_name, trend_window_caption(cli.trend_window), page + 1)?;
+            out.table(unified_table)?;
+        }
+    }
+
+    // Per-Account Tables
+    for account_data in &report.accounts {
+        let previous_account = previous.and_then(|p| {
+            p.accounts
+                .iter()
+                .find(|a| a.profile == account_data.profile && a.account_id == account_data.account_id)
+        });
+
+        let mut trend_table = Table::new();
+        trend_table.set_format(*format::consts::FORMAT_DEFAULT);
+        let show_per_day = cli.per_day && !report.period_days.is_empty();
+        let mut trend_titles = vec![
+            Cell::new(cli.granularity.period_label()).style_spec("bFc"),
+            Cell::new("Total Cost (USD)").style_spec("bFr"),
+            Cell::new(cli.granularity.change_label()).style_spec("bFc"),
+        ];
+        if show_per_day {
+            trend_titles.push(Cell::new("Cost per Day (USD)").style_spec("bFr"));
+            trend_titles.push(Cell::new("Per-Day MoM Change (%)").style_spec("bFc"));
+        }
+        // Paired columns of `--compare-metrics`
+        let comparison = cli.compared_metrics().zip(account_data.metric_comparison.as_ref());
+        if let Some(((first, second), _)) = comparison {
+            trend_titles.push(Cell::new(&format!("{} (USD)", first.label())).style_spec("bFr"));
+            trend_titles.push(Cell::new(&format!("{} (USD)", second.label())).style_spec("bFr"));
+            trend_titles.push(Cell::new("Delta (USD)").style_spec("bFr"));
+        }
+        let show_composition = account_data.cost_trend.iter().any(|data| !data.composition.is_empty());
+        if show_composition {
+            trend_titles.push(Cell::new("Composition").style_spec("bFl"));
+        }
+        trend_table.set_titles(Row::new(trend_titles));
+
+        for data in &account_data.cost_trend {
+            let previous_cost = previous.map(|_| {
+                previous_account
+                    .and_then(|a| a.cost_trend.iter().find(|t| t.month == data.month))
+                    .map_or(0.0, |t| t.total_cost)
+            });
+            let mut cells = vec![
+                Cell::new(&period_caption(cli, report, &data.month)),
+                Cell::new(&format!("{:.2}", data.total_cost)).style_spec(cost_style(data.total_cost, previous_cost)),
+                Cell::new(&data.mom_change_percent.display(cli.percent_decimals)).style_spec("Fc"),
+            ];
+            if show_per_day {
+                cells.push(Cell::new(&data.cost_per_day.map_or(String::new(), |c| format!("{:.2}", c))).style_spec("Fr"));
+                cells.push(Cell::new(&data.per_day_change_percent.map_or(String::new(), |c| format!("{:.*}", cli.percent_decimals, c))).style_spec("Fc"));
+            }
+            if let Some((_, comparison)) = comparison {
+                let period = comparison.periods.iter().find(|p| p.period == data.month);
+                for cost in [period.map(|p| p.first_cost), period.map(|p| p.second_cost), period.map(|p| p.delta)] {
+                    cells.push(Cell::new(&format!("{:.2}", cost.unwrap_or(0.0))).style_spec("Fr"));
+                }
+            }
+            if show_composition {
+                cells.push(Cell::new(&composition_label(&data.composition)).style_spec("Fy"));
+            }
+            trend_table.add_row(Row::new(cells));
+        }
+
+        writeln!(out, "\nCost Trend Analysis for Profile {} Account {} ({}){}:", 
+            account_data.profile, account_data.account_id, account_data.account_name,
+            account_data.ou_path.as_ref().map_or(String::new(), |path| format!(" in OU {}", path)))?;
+        out.table(&trend_table)?;
+        writeln!(out, "Total Cost ({} to {}): ${:.2}", range_start, cli.end_date, account_data.total_cost)?;
+        writeln!(out, "{}: ${:.2}", cli.granularity.average_label(), account_data.average_monthly_cost)?;
+        if let Some(stats) = &account_data.stats {
+            writeln!(out, "Min: ${:.2}  Max: ${:.2}  Median: ${:.2}  Std Dev: {}  Coeff. of Variation: {}",
+                stats.min, stats.max, stats.median,
+                stats.std_dev.map_or("n/a".to_string(), |sd| format!("${:.2}", sd)),
+                stats.coefficient_of_variation.map_or("n/a".to_string(), |cv| format!("{:.2}", cv)))?;
+        }
+        if let Some(((first, second), comparison)) = comparison {
+            writeln!(out, "{} vs {}: ${:.2} vs ${:.2}, difference ${:.2} ({}){}",
+                second.label(), first.label(), comparison.second_total, comparison.first_total, comparison.delta,
+                comparison.delta_percent.map_or("n/a".to_string(), |p| format!("{:.*}%", cli.percent_decimals, p)),
+                if comparison.exceeds_threshold { format!(" - over the {}% --compare-threshold", cli.compare_threshold) } else { String::new() })?;
+        }
+
+        // Service Consumption Table with pagination
+        for chunk in filtered_months.chunks(max_columns - 2) { // -2 for Service, Total Cost, Percent of Total
+            let mut service_table = Table::new();
+            service_table.set_format(*format::consts::FORMAT_DEFAULT);
+            let mut service_titles = vec![
+                Cell::new(cli.breakdown().row_header).style_spec("bFc"),
+            ];
+            for month in chunk {
+                service_titles.push(Cell::new(&period_caption(cli, report, month)).style_spec("bFr"));
+            }
+            service_titles.push(Cell::new("Total Cost (USD)").style_spec("bFr"));
+            service_titles.push(Cell::new("Percent of Total (%)").style_spec("bFc"));
+            if cli.breakdown().row_change {
+                service_titles.push(Cell::new(cli.granularity.change_label()).style_spec("bFc"));
+            }
+
+            service_table.set_titles(Row::new(service_titles));
+
+            for data in &account_data.service_consumption {
+                let previous_service = previous_account
+                    .and_then(|a| a.service_consumption.iter().find(|s| s.service == data.service));
+                let mut row = vec![Cell::new(cli.table_service_name(&data.service))];
+                for month in chunk {
+                    let cost = *data.monthly_costs.get(month).unwrap_or(&0.0);
+                    let previous_cost = previous.map(|_| {
+                        previous_service.and_then(|s| s.monthly_costs.get(month).copied()).unwrap_or(0.0)
+                    });
+                    row.push(Cell::new(&format!("{:.2}", cost)).style_spec(cost_style(cost, previous_cost)));
+                }
+                row.push(Cell::new(&format!("{:.2}", data.total_cost)).style_spec("Fr"));
+                row.push(Cell::new(&format!("{:.*}", cli.percent_decimals, data.percent_of_total)).style_spec("Fc"));
+                if let Some(change) = &data.latest_change_percent {
+                    row.push(Cell::new(&change.display(cli.percent_decimals)).style_spec("Fc"));
+                }
+                service_table.add_row(Row::new(row));
+            }
+            // Percentages above are net of tax, so the tax row has none
+            if let Some(tax) = account_data.tax.as_ref().filter(|tax| tax.total_cost != 0.0) {
+                let mut row = vec![Cell::new(TAX).style_spec("i")];
+                for month in chunk {
+                    row.push(Cell::new(&format!("{:.2}", tax.monthly_costs.get(month).copied().unwrap_or(0.0))).style_spec("Fr"));
+                }
+                row.push(Cell::new(&format!("{:.2}", tax.total_cost)).style_spec("Fr"));
+                row.push(Cell::new("-").style_spec("Fc"));
+                service_table.add_row(Row::new(row));
+            }
+
+            writeln!(
+                out,
+                "\n{} for Profile {} Account {} ({} to {}) - Page {}:",
+                cli.breakdown().title, account_data.profile, account_data.account_id, range_start, cli.end_date,
+                (filtered_months.iter().position(|m| m == chunk[0].as_str()).unwrap() / (max_columns - 2)) + 1
+            )?;
+            out.table(&service_table)?;
+        }
+
+        // Instance Family Table, paginated like the service table
+        for (page, chunk) in account_data.instance_families.iter().flat_map(|_| filtered_months.chunks(max_columns - 2)).enumerate() {
+            let mut family_table = Table::new();
+            family_table.set_format(*format::consts::FORMAT_DEFAULT);
+            let mut family_titles = vec![Cell::new("Instance Family").style_spec("bFc")];
+            for month in chunk {
+                family_titles.push(Cell::new(&period_caption(cli, report, month)).style_spec("bFr"));
+            }
+            family_titles.push(Cell::new("Total Cost (USD)").style_spec("bFr"));
+            family_titles.push(Cell::new("Percent of Total (%)").style_spec("bFc"));
+            family_titles.push(Cell::new(cli.granularity.change_label()).style_spec("bFc"));
+            family_table.set_titles(Row::new(family_titles));
+            for family in account_data.instance_families.iter().flatten() {
+                let mut row = vec![Cell::new(&family.family)];
+                for month in chunk {
+                    row.push(Cell::new(&format!("{:.2}", family.monthly_costs.get(month).copied().unwrap_or(0.0))).style_spec("Fr"));
+                }
+                row.push(Cell::new(&format!("{:.2}", family.total_cost)).style_spec("Fr"));
+                row.push(Cell::new(&format!("{:.*}", cli.percent_decimals, family.percent_of_total)).style_spec("Fc"));
+                row.push(Cell::new(&family.latest_change_percent.display(cli.percent_decimals)).style_spec("Fc"));
+                family_table.add_row(Row::new(row));
+            }
+            writeln!(
+                out,
+                "\nInstance Families for Profile {} Account {} ({} to {}) - Page {}:",
+                account_data.profile, account_data.account_id, range_start, cli.end_date, page + 1
+            )?;
+            out.table(&family_table)?;
+        }
+
+        // Record Type Table, paginated like the service table
+        if let Some(breakdown) = &account_data.record_type_breakdown {
+            for (page, chunk) in filtered_months.chunks(max_columns - 2).enumerate() {
+                let mut record_table = Table::new();
+                record_table.set_format(*format::consts::FORMAT_DEFAULT);
+                let mut record_titles = vec![Cell::new("Record Type").style_spec("bFc")];
+                for month in chunk {
+                    record_titles.push(Cell::new(&period_caption(cli, report, month)).style_spec("bFr"));
+                }
+                record_titles.push(Cell::new("Total Cost (USD)").style_spec("bFr"));
+                record_table.set_titles(Row::new(record_titles));
+                for record_type in analysis::ordered_record_types(breakdown) {
+                    let costs = &breakdown[record_type];
+                    let mut row = vec![Cell::new(record_type)];
+                    for month in chunk {
+                        row.push(Cell::new(&format!("{:.2}", costs.get(month).copied().unwrap_or(0.0))).style_spec("Fr"));
+                    }
+                    row.push(Cell::new(&format!("{:.2}", costs.values().sum::<f64>())).style_spec("Fr"));
+                    record_table.add_row(Row::new(row));
+                }
+                let mut net_row = vec![Cell::new("Net Total").style_spec("b")];
+                for month in chunk {
+                    let net: f64 = breakdown.values().filter_map(|costs| costs.get(month)).sum();
+                    net_row.push(Cell::new(&format!("{:.2}", net)).style_spec("bFr"));
+                }
+                net_row.push(Cell::new(&format!("{:.2}", breakdown.values().flat_map(|costs| costs.values()).sum::<f64>())).style_spec("bFr"));
+                record_table.add_row(Row::new(net_row));
+                writeln!(
+                    out,
+                    "\nCost by Record Type for Profile {} Account {} ({} to {}) - Page {}:",
+                    account_data.profile, account_data.account_id, range_start, cli.end_date, page + 1
+                )?;
+                out.table(&record_table)?;
+            }
+        }
+
+        // Amortized vs Unblended Table
+        if let Some(delta) = &account_data.amortization_delta {
+            let mut delta_table = Table::new();
+            delta_table.set_format(*format::consts::FORMAT_DEFAULT);
+            delta_table.set_titles(Row::new(vec![
+                Cell::new(cli.granularity.period_label()).style_spec("bFc"),
+                Cell::new("Unblended (USD)").style_spec("bFr"),
+                Cell::new("Amortized (USD)").style_spec("bFr"),
+                Cell::new("Delta (USD)").style_spec("bFr"),
+                Cell::new("Delta (%)").style_spec("bFc"),
+                Cell::new("Upfront Purchase").style_spec("bFc"),
+            ]));
+            for period in &delta.periods {
+                delta_table.add_row(Row::new(vec![
+                    Cell::new(&period_caption(cli, report, &period.period)),
+                    Cell::new(&format!("{:.2}", period.unblended_cost)).style_spec("Fr"),
+                    Cell::new(&format!("{:.2}", period.amortized_cost)).style_spec("Fr"),
+                    Cell::new(&format!("{:.2}", period.delta)).style_spec(if period.upfront_purchase { "bFy" } else { "Fr" }),
+                    Cell::new(&period.delta_percent.map_or(String::new(), |p| format!("{:.*}", cli.percent_decimals, p))).style_spec("Fc"),
+                    Cell::new(if period.upfront_purchase { "yes" } else { "" }).style_spec("Fc"),
+                ]));
+            }
+            writeln!(out, "\nAmortized vs Unblended Cost for Profile {} Account {} ({} to {}):",
+                account_data.profile, account_data.account_id, range_start, cli.end_date)?;
+            out.table(&delta_table)?;
+            writeln!(out, "Total Unblended: ${:.2}  Total Amortized: ${:.2}  Delta: ${:.2}",
+                delta.unblended_total, delta.amortized_total, delta.unblended_total - delta.amortized_total)?;
+            if let (Some(unblended), Some(amortized)) = (delta.unblended_std_dev, delta.amortized_std_dev) {
+                writeln!(out, "Std Dev: ${:.2} unblended, ${:.2} amortized", unblended, amortized)?;
+            }
+        }
+
+        // Unit Rate Table
+        if let Some(rates) = account_data.unit_rates.as_ref().filter(|rates| !rates.is_empty()) {
+            let mut rate_table = Table::new();
+            rate_table.set_format(*format::consts::FORMAT_DEFAULT);
+            rate_table.set_titles(Row::new(vec![
+                Cell::new("Usage Type").style_spec("bFc"),
+                Cell::new(cli.granularity.period_label()).style_spec("bFc"),
+                Cell::new("Cost (USD)").style_spec("bFr"),
+                Cell::new("Quantity").style_spec("bFr"),
+                Cell::new("Rate (USD per Unit)").style_spec("bFr"),
+                Cell::new("Change (%)").style_spec("bFc"),
+            ]));
+            for rate in rates {
+                for period in &rate.periods {
+                    rate_table.add_row(Row::new(vec![
+                        Cell::new(&rate.usage_type),
+                        Cell::new(&period_caption(cli, report, &period.period)),
+                        Cell::new(&format!("{:.2}", period.cost)).style_spec("Fr"),
+                        Cell::new(&format!("{:.2} {}", period.quantity, rate.unit.as_deref().unwrap_or(""))).style_spec("Fr"),
+                        Cell::new(&period.rate.map_or("n/a".to_string(), |r| format!("{:.4}", r))).style_spec("Fr"),
+                        Cell::new(&period.change_percent.map_or(String::new(), |c| format!("{:+.*}", cli.percent_decimals, c)))
+                            .style_spec(if period.exceeds_threshold { "bFy" } else { "Fc" }),
+                    ]));
+                }
+            }
+            writeln!(out, "\nUnit Rates for Profile {} Account {} ({} to {}):",
+                account_data.profile, account_data.account_id, range_start, cli.end_date)?;
+            out.table(&rate_table)?;
+            let flagged: Vec<&str> = rates.iter().filter(|rate| rate.exceeds_threshold).map(|rate| rate.usage_type.as_str()).collect();
+            if !flagged.is_empty() {
+                writeln!(out, "Rate changed by more than {}%: {}", cli.unit_rate_threshold, flagged.join(", "))?;
+            }
+
+            // Price Context Table: the latest rate against the On-Demand list price
+            let priced: Vec<&UnitRate> = rates.iter().filter(|rate| rate.list_price.is_some()).collect();
+            if !priced.is_empty() {
+                let mut price_table = Table::new();
+                price_table.set_format(*format::consts::FORMAT_DEFAULT);
+                price_table.set_titles(Row::new(vec![
+                    Cell::new("Usage Type").style_spec("bFc"),
+                    Cell::new("Unit").style_spec("bFc"),
+                    Cell::new("Latest Rate (USD)").style_spec("bFr"),
+                    Cell::new("List Price (USD)").style_spec("bFr"),
+                    Cell::new("Discount (%)").style_spec("bFc"),
+                ]));
+                for rate in priced {
+                    let latest = rate.periods.iter().rev().find_map(|period| period.rate);
+                    price_table.add_row(Row::new(vec![
+                        Cell::new(&rate.usage_type),
+                        Cell::new(rate.unit.as_deref().unwrap_or("")),
+                        Cell::new(&latest.map_or("n/a".to_string(), |r| format!("{:.4}", r))).style_spec("Fr"),
+                        Cell::new(&rate.list_price.map_or(String::new(), |p| format!("{:.4}", p))).style_spec("Fr"),
+                        Cell::new(&rate.discount_percent.map_or(String::new(), |d| format!("{:+.*}", cli.percent_decimals, d))).style_spec("Fc"),
+                    ]));
+                }
+                writeln!(out, "\nOn-Demand List Price Comparison for Profile {} Account {}:", account_data.profile, account_data.account_id)?;
+                out.table(&price_table)?;
+            }
+        }
+
+        // Top Resources Table, paginated by day like the service table
+        if let Some(top) = &account_data.top_resources {
+            let heading = format!(
+                "\nTop {} Resources for Profile {} Account {} ({} to {})",
+                cli.table_service_name(&top.service), account_data.profile, account_data.account_id, top.start, top.days.last().map_or("", String::as_str)
+            );
+            if let Some(error) = &top.error {
+                writeln!(out, "{}: unavailable - {}", heading, error)?;
+            } else if top.resources.is_empty() {
+                writeln!(out, "{}: no resource-level costs", heading)?;
+            }
+            for (page, days) in top.days.chunks(max_columns - 2).enumerate().filter(|_| !top.resources.is_empty()) { // -2 for Resource ID, Total Cost
+                let mut resource_table = Table::new();
+                resource_table.set_format(*format::consts::FORMAT_DEFAULT);
+                let mut resource_titles = vec![Cell::new("Resource ID").style_spec("bFc")];
+                for day in days {
+                    resource_titles.push(Cell::new(&periods::display_label(day, &GranularityOption::Daily, cli.date_format.as_deref())).style_spec("bFr"));
+                }
+                resource_titles.push(Cell::new("Total Cost (USD)").style_spec("bFr"));
+                resource_table.set_titles(Row::new(resource_titles));
+                for resource in &top.resources {
+                    let mut row = vec![Cell::new(&resource.resource_id)];
+                    for day in days {
+                        row.push(Cell::new(&format!("{:.2}", resource.daily_costs.get(day).copied().unwrap_or(0.0))).style_spec("Fr"));
+                    }
+                    row.push(Cell::new(&format!("{:.2}", resource.total_cost)).style_spec("Fr"));
+                    resource_table.add_row(Row::new(row));
+                }
+                writeln!(out, "{} - Page {}:", heading, page + 1)?;
+                out.table(&resource_table)?;
+            }
+        }
+
+        // Compute Optimizer Table
+        if let Some(optimizer) = &account_data.compute_optimizer {
+            let heading = format!("\nCompute Optimizer for Profile {} Account {}", account_data.profile, account_data.account_id);
+            if let Some(error) = &optimizer.error {
+                writeln!(out, "{}: {}", heading, error)?;
+            } else if optimizer.resources.is_empty() {
+                writeln!(out, "{}: no EC2, EBS or Lambda findings", heading)?;
+            } else {
+                let mut optimizer_table = Table::new();
+                optimizer_table.set_format(*format::consts::FORMAT_DEFAULT);
+                optimizer_table.set_titles(Row::new(vec![
+                    Cell::new("Resource Type").style_spec("bFc"),
+                    Cell::new("Over-provisioned").style_spec("bFr"),
+                    Cell::new("Under-provisioned").style_spec("bFr"),
+                    Cell::new("Not Optimized").style_spec("bFr"),
+                    Cell::new("Optimized").style_spec("bFr"),
+                    Cell::new("Est. Monthly Savings (USD)").style_spec("bFr"),
+                ]));
+                for resources in &optimizer.resources {
+                    optimizer_table.add_row(Row::new(vec![
+                        Cell::new(&resources.resource_type),
+                        Cell::new(&resources.over_provisioned.to_string()).style_spec("Fr"),
+                        Cell::new(&resources.under_provisioned.to_string()).style_spec("Fr"),
+                        Cell::new(&resources.not_optimized.to_string()).style_spec("Fr"),
+                        Cell::new(&resources.optimized.to_string()).style_spec("Fr"),
+                        Cell::new(&format!("{:.2}", resources.estimated_monthly_savings)).style_spec("Fr"),
+                    ]));
+                }
+                writeln!(out, "{} (est. savings ${:.2}/month):", heading, optimizer.estimated_monthly_savings)?;
+                out.table(&optimizer_table)?;
+            }
+        }
+
+        // Free Tier Table, most consumed first
+        if let Some(free_tier) = &account_data.free_tier {
+            let heading = format!("\nFree Tier Usage for Profile {} Account {}", account_data.profile, account_data.account_id);
+            if let Some(error) = &free_tier.error {
+                writeln!(out, "{}: unavailable - {}", heading, error)?;
+            } else if free_tier.items.is_empty() {
+                writeln!(out, "{}: no Free Tier offers in use this month", heading)?;
+            } else {
+                let mut free_tier_table = Table::new();
+                free_tier_table.set_format(*format::consts::FORMAT_DEFAULT);
+                free_tier_table.set_titles(Row::new(vec![
+                    Cell::new("Service").style_spec("bFc"),
+                    Cell::new("Usage Type").style_spec("bFc"),
+                    Cell::new("Unit").style_spec("bFc"),
+                    Cell::new("Limit").style_spec("bFr"),
+                    Cell::new("Actual Usage").style_spec("bFr"),
+                    Cell::new("Forecasted Usage").style_spec("bFr"),
+                    Cell::new("Used (%)").style_spec("bFr"),
+                ]));
+                for item in &free_tier.items {
+                    let percent = item.percent_used.map_or("N/A".to_string(), |p| format!("{:.*}%", cli.percent_decimals, p));
+                    free_tier_table.add_row(Row::new(vec![
+                        Cell::new(&item.service),
+                        Cell::new(&item.usage_type),
+                        Cell::new(item.unit.as_deref().unwrap_or("")),
+                        Cell::new(&format!("{:.2}", item.limit)).style_spec("Fr"),
+                        Cell::new(&format!("{:.2}", item.actual_usage)).style_spec("Fr"),
+                        Cell::new(&format!("{:.2}", item.forecasted_usage)).style_spec("Fr"),
+                        Cell::new(&percent).style_spec(if item.exceeds_threshold { "bFr" } else { "Fc" }),
+                    ]));
+                }
+                let flagged = free_tier.items.iter().filter(|item| item.exceeds_threshold).count();
+                writeln!(out, "{} ({} over {}% of the limit):", heading, flagged, cli.free_tier_threshold)?;
+                out.table(&free_tier_table)?;
+            }
+        }
+    }
+
+    // Budget Burn Table, fastest burn first
+    if cli.budget_file.is_some() {
+        let mut burns: Vec<(&UnifiedViewData, &BudgetBurn)> = report
+            .unified_view
+            .iter()
+            .filter_map(|account| account.budget_burn.as_ref().map(|burn| (account, burn)))
+            .collect();
+        burns.sort_by(|a, b| b.1.overage_percent.total_cmp(&a.1.overage_percent).then_with(|| a.0.account_id.cmp(&b.0.account_id)));
+        if burns.is_empty() {
+            writeln!(out, "\nBurn Rate: no data; it needs a range covering the current month from its first day through today, and accounts listed in --budget-file")?;
+        } else {
+            let mut burn_table = Table::new();
+            burn_table.set_format(*format::consts::FORMAT_DEFAULT);
+            burn_table.set_titles(Row::new(vec![
+                Cell::new("Profile").style_spec("bFc"),
+                Cell::new("Account ID").style_spec("bFc"),
+                Cell::new("Account Name").style_spec("bFc"),
+                Cell::new("Budget (USD)").style_spec("bFr"),
+                Cell::new("Month to Date (USD)").style_spec("bFr"),
+                Cell::new("Budget Used (%)").style_spec("bFc"),
+                Cell::new("Month Elapsed (%)").style_spec("bFc"),
+                Cell::new("Overage (pts)").style_spec("bFc"),
+                Cell::new("Projected (USD)").style_spec("bFr"),
+            ]));
+            for (account, burn) in &burns {
+                let style = if burn.exceeds_threshold { "bFr" } else if burn.overage_percent > 0.0 { "Fy" } else { "Fc" };
+                burn_table.add_row(Row::new(vec![
+                    Cell::new(&account.profile),
+                    Cell::new(&account.account_id),
+                    Cell::new(&account.account_name),
+                    Cell::new(&format!("{:.2}", burn.budget)).style_spec("Fr"),
+                    Cell::new(&format!("{:.2}", burn.month_to_date_cost)).style_spec("Fr"),
+                    Cell::new(&format!("{:.*}", cli.percent_decimals, burn.budget_used_percent)).style_spec("Fc"),
+                    Cell::new(&format!("{:.*}", cli.percent_decimals, burn.month_elapsed_percent)).style_spec("Fc"),
+                    Cell::new(&format!("{:+.*}", cli.percent_decimals, burn.overage_percent)).style_spec(style),
+                    Cell::new(&format!("{:.2}", burn.projected_cost)).style_spec("Fr"),
+                ]));
+            }
+            writeln!(out, "\nBurn Rate (budget used vs month elapsed, alert over {} points):", cli.burn_rate_threshold)?;
+            out.table(&burn_table)?;
+        }
+    }
+
+    // Cost Optimization Hub Tables: savings by action type and account, then the largest recommendations
+    if let Some(hub) = &report.optimization_hub {
+        if hub.recommendations == 0 {
+            writeln!(out, "\nCost Optimization Hub: no recommendations for the reported accounts")?;
+        } else {
+            let mut action_table = Table::new();
+            action_table.set_format(*format::consts::FORMAT_DEFAULT);
+            action_table.set_titles(Row::new(vec![
+                Cell::new("Action").style_spec("bFc"),
+                Cell::new("Recommendations").style_spec("bFr"),
+                Cell::new("Est. Monthly Savings (USD)").style_spec("bFr"),
+            ]));
+            for action in &hub.by_action_type {
+                action_table.add_row(Row::new(vec![
+                    Cell::new(&action.action_type),
+                    Cell::new(&action.recommendations.to_string()).style_spec("Fr"),
+                    Cell::new(&format!("{:.2}", action.estimated_monthly_savings)).style_spec("Fr"),
+                ]));
+            }
+            writeln!(out, "\nCost Optimization Hub Savings by Action:")?;
+            out.table(&action_table)?;
+
+            let mut account_table = Table::new();
+            account_table.set_format(*format::consts::FORMAT_DEFAULT);
+            account_table.set_titles(Row::new(vec![
+                Cell::new("Profile").style_spec("bFc"),
+                Cell::new("Account ID").style_spec("bFc"),
+                Cell::new("Account Name").style_spec("bFc"),
+                Cell::new("Recommendations").style_spec("bFr"),
+                Cell::new("Est. Monthly Savings (USD)").style_spec("bFr"),
+            ]));
+            for account in &hub.by_account {
+                account_table.add_row(Row::new(vec![
+                    Cell::new(&account.profile),
+                    Cell::new(&account.account_id),
+                    Cell::new(&account.account_name),
+                    Cell::new(&account.recommendations.to_string()).style_spec("Fr"),
+                    Cell::new(&format!("{:.2}", account.estimated_monthly_savings)).style_spec("Fr"),
+                ]));
+            }
+            writeln!(out, "\nCost Optimization Hub Savings by Account:")?;
+            out.table(&account_table)?;
+
+            let mut top_table = Table::new();
+            top_table.set_format(*format::consts::FORMAT_DEFAULT);
+            top_table.set_titles(Row::new(vec![
+                Cell::new("Account ID").style_spec("bFc"),
+                Cell::new("Resource").style_spec("bFc"),
+                Cell::new("Action").style_spec("bFc"),
+                Cell::new("Current").style_spec("bFc"),
+                Cell::new("Recommended").style_spec("bFc"),
+                Cell::new("Est. Monthly Savings (USD)").style_spec("bFr"),
+            ]));
+            for recommendation in &hub.top_recommendations {
+                top_table.add_row(Row::new(vec![
+                    Cell::new(&recommendation.account_id),
+                    Cell::new(&recommendation.resource),
+                    Cell::new(&recommendation.action_type),
+                    Cell::new(recommendation.current.as_deref().unwrap_or("")),
+                    Cell::new(recommendation.recommended.as_deref().unwrap_or("")),
+                    Cell::new(&format!("{:.2}", recommendation.estimated_monthly_savings)).style_spec("Fr"),
+                ]));
+            }
+            writeln!(out, "\nTop {} Cost Optimization Hub Recommendations:", hub.top_recommendations.len())?;
+            out.table(&top_table)?;
+        }
+        for note in &hub.notes {
+            writeln!(out, "Cost Optimization Hub, profile {}: {}", note.profile, note.note)?;
+        }
+    }
+
+    // Savings Plans Purchase Recommendations, one summary per profile and its accounts' contributions
+    for recommendation in report.sp_recommendations.iter().flatten() {
+        let heading = format!(
+            "\nCompute Savings Plans Recommendation for Profile {} ({}, {}, last {} days)",
+            recommendation.profile, recommendation.term, recommendation.payment_option, recommendation.lookback_days
+        );
+        if let Some(note) = &recommendation.note {
+            writeln!(out, "{}: {}", heading, note)?;
+            continue;
+        }
+        writeln!(out, "{}:", heading)?;
+        writeln!(out, "Hourly Commitment: ${:.3}/hour  Upfront Cost: ${:.2}", recommendation.hourly_commitment, recommendation.upfront_cost)?;
+        writeln!(out, "Estimated Monthly Savings: ${:.2} ({:.*}%)", recommendation.estimated_monthly_savings, cli.percent_decimals, recommendation.estimated_savings_percent)?;
+        let percent = |value: Option<f64>| value.map_or("n/a".to_string(), |value| format!("{:.*}%", cli.percent_decimals, value));
+        writeln!(out, "Estimated Coverage: {}  Estimated Utilization: {}", percent(recommendation.estimated_coverage_percent), percent(recommendation.estimated_utilization_percent))?;
+        match recommendation.break_even_months {
+            Some(months) if months > 0.0 => writeln!(out, "Break-even: {:.1} months", months)?,
+            Some(_) => writeln!(out, "Break-even: immediate (no upfront payment)")?,
+            None => {}
+        }
+        let mut contribution_table = Table::new();
+        contribution_table.set_format(*format::consts::FORMAT_DEFAULT);
+        contribution_table.set_titles(Row::new(vec![
+            Cell::new("Account ID").style_spec("bFc"),
+            Cell::new("Account Name").style_spec("bFc"),
+            Cell::new("Hourly Commitment (USD)").style_spec("bFr"),
+            Cell::new("Upfront (USD)").style_spec("bFr"),
+            Cell::new("Est. Monthly Savings (USD)").style_spec("bFr"),
+            Cell::new("Savings (%)").style_spec("bFc"),
+            Cell::new("Utilization (%)").style_spec("bFc"),
+        ]));
+        for account in &recommendation.accounts {
+            contribution_table.add_row(Row::new(vec![
+                Cell::new(&account.account_id),
+                Cell::new(account.account_name.as_deref().unwrap_or("")),
+                Cell::new(&format!("{:.3}", account.hourly_commitment)).style_spec("Fr"),
+                Cell::new(&format!("{:.2}", account.upfront_cost)).style_spec("Fr"),
+                Cell::new(&format!("{:.2}", account.estimated_monthly_savings)).style_spec("Fr"),
+                Cell::new(&format!("{:.*}", cli.percent_decimals, account.estimated_savings_percent)).style_spec("Fc"),
+                Cell::new(&format!("{:.*}", cli.percent_decimals, account.estimated_utilization_percent)).style_spec("Fc"),
+            ]));
+        }
+        out.table(&contribution_table)?;
+    }
+
+    // Trusted Advisor Table: each account's flagged cost checks, highest savings first
+    if let Some(advisor) = &report.trusted_advisor {
+        if advisor.flagged_resources == 0 {
+            if !advisor.accounts.is_empty() || advisor.notes.is_empty() {
+                writeln!(out, "\nTrusted Advisor: no cost optimization checks flagged resources in the reported accounts")?;
+            }
+        } else {
+            let mut advisor_table = Table::new();
+            advisor_table.set_format(*format::consts::FORMAT_DEFAULT);
+            advisor_table.set_titles(Row::new(vec![
+                Cell::new("Profile").style_spec("bFc"),
+                Cell::new("Account ID").style_spec("bFc"),
+                Cell::new("Account Name").style_spec("bFc"),
+                Cell::new("Check").style_spec("bFc"),
+                Cell::new("Status").style_spec("bFc"),
+                Cell::new("Flagged Resources").style_spec("bFr"),
+                Cell::new("Est. Monthly Savings (USD)").style_spec("bFr"),
+            ]));
+            for account in &advisor.accounts {
+                for check in &account.checks {
+                    advisor_table.add_row(Row::new(vec![
+                        Cell::new(&account.profile),
+                        Cell::new(&account.account_id),
+                        Cell::new(&account.account_name),
+                        Cell::new(&check.name),
+                        Cell::new(&check.status),
+                        Cell::new(&check.flagged_resources.to_string()).style_spec("Fr"),
+                        Cell::new(&format!("{:.2}", check.estimated_monthly_savings)).style_spec("Fr"),
+                    ]));
+                }
+            }
+            writeln!(
+                out,
+                "\nTrusted Advisor Cost Optimization ({} flagged resources, est. savings ${:.2}/month):",
+                advisor.flagged_resources, advisor.estimated_monthly_savings
+            )?;
+            out.table(&advisor_table)?;
+        }
+        for note in &advisor.notes {
+            writeln!(out, "Trusted Advisor, profile {}: {}", note.profile, note.note)?;
+        }
+    }
+
+    // Weekday vs Weekend Table
+    if !report.weekday_analysis.is_empty() {
+        let mut weekday_table = Table::new();
+        weekday_table.set_format(*format::consts::FORMAT_DEFAULT);
+        weekday_table.set_titles(Row::new(vec![
+            Cell::new("Profile").style_spec("bFc"),
+            Cell::new("Account ID").style_spec("bFc"),
+            Cell::new("Account Name").style_spec("bFc"),
+            Cell::new("Weekday Avg/Day (USD)").style_spec("bFr"),
+            Cell::new("Weekend Avg/Day (USD)").style_spec("bFr"),
+            Cell::new("Weekend/Weekday").style_spec("bFc"),
+            Cell::new("Est. Monthly Savings (USD)").style_spec("bFr"),
+        ]));
+        for split in &report.weekday_analysis {
+            weekday_table.add_row(Row::new(vec![
+                Cell::new(&split.profile),
+                Cell::new(&split.account_id),
+                Cell::new(&split.account_name),
+                Cell::new(&format!("{:.2}", split.weekday_average)).style_spec("Fr"),
+                Cell::new(&format!("{:.2}", split.weekend_average)).style_spec("Fr"),
+                Cell::new(&split.weekend_ratio.map_or("n/a".to_string(), |r| format!("{:.2}", r))).style_spec("Fc"),
+                Cell::new(&format!("{:.2}", split.estimated_monthly_savings)).style_spec("Fr"),
+            ]));
+        }
+        writeln!(out, "\nWeekday vs Weekend Spend (savings if weekend days cost {}% of a weekday):", cli.weekend_target_percent)?;
+        out.table(&weekday_table)?;
+    }
+
+    // Hour-of-Day Profiles
+    for hourly in &report.hourly_profiles {
+        let peak = hourly.hourly_averages.iter().cloned().fold(0.0, f64::max);
+        let mut hourly_table = Table::new();
+        hourly_table.set_format(*format::consts::FORMAT_DEFAULT);
+        hourly_table.set_titles(Row::new(vec![
+            Cell::new("Hour").style_spec("bFc"),
+            Cell::new("Avg Cost (USD)").style_spec("bFr"),
+            Cell::new("").style_spec("bFc"),
+        ]));
+        for (hour, average) in hourly.hourly_averages.iter().enumerate() {
+            let bar_len = if peak > 0.0 { (average / peak * 30.0).round() as usize } else { 0 };
+            hourly_table.add_row(Row::new(vec![
+                Cell::new(&format!("{:02}:00", hour)),
+                Cell::new(&format!("{:.4}", average)).style_spec("Fr"),
+                Cell::new(&"#".repeat(bar_len)).style_spec("Fb"),
+            ]));
+        }
+        writeln!(out, "\nHour-of-Day Profile for Profile {} Account {} ({}):",
+            hourly.profile, hourly.account_id, hourly.account_name)?;
+        out.table(&hourly_table)?;
+        writeln!(out, "Overnight (00:00-06:00) average: ${:.4}/h; business hours (09:00-18:00) average: ${:.4}/h{}",
+            hourly.overnight_average, hourly.business_hours_average,
+            if hourly.always_on { " - never scales down overnight" } else { "" })?;
+    }
+
+    // Metric Comparison Table
+    if let Some((first, second)) = cli.compared_metrics() {
+        let mut comparison_table = Table::new();
+        comparison_table.set_format(*format::consts::FORMAT_DEFAULT);
+        comparison_table.set_titles(Row::new(vec![
+            Cell::new("Profile").style_spec("bFc"),
+            Cell::new("Account ID").style_spec("bFc"),
+            Cell::new("Account Name").style_spec("bFc"),
+            Cell::new(&format!("{} (USD)", first.label())).style_spec("bFr"),
+            Cell::new(&format!("{} (USD)", second.label())).style_spec("bFr"),
+            Cell::new("Delta (USD)").style_spec("bFr"),
+            Cell::new("Delta (%)").style_spec("bFc"),
+        ]));
+        for account in &report.accounts {
+            let Some(comparison) = &account.metric_comparison else {
+                continue;
+            };
+            let delta_style = if comparison.exceeds_threshold { "bFy" } else { "Fr" };
+            comparison_table.add_row(Row::new(vec![
+                Cell::new(&account.profile),
+                Cell::new(&account.account_id),
+                Cell::new(&account.account_name),
+                Cell::new(&format!("{:.2}", comparison.first_total)).style_spec("Fr"),
+                Cell::new(&format!("{:.2}", comparison.second_total)).style_spec("Fr"),
+                Cell::new(&format!("{:.2}", comparison.delta)).style_spec(delta_style),
+                Cell::new(&comparison.delta_percent.map_or(String::new(), |p| format!("{:.*}", cli.percent_decimals, p))).style_spec(delta_style),
+            ]));
+        }
+        writeln!(out, "\n{} vs {} Cost by Account (highlighted: over {}%):", second.label(), first.label(), cli.compare_threshold)?;
+        out.table(&comparison_table)?;
+    }
+
+    // Global Summary
+    writeln!(out, "\nGlobal Summary (All Accounts):")?;
+    writeln!(out, "Total Cost ({} to {}): ${:.2}", range_start, cli.end_date, report.total_cost)?;
+    writeln!(out, "{}: ${:.2}", cli.granularity.average_label(), report.average_period_cost)?;
+    if let Some(tax) = report.total_tax {
+        writeln!(out, "Tax: ${:.2}  Net of Tax: ${:.2}", tax, report.total_cost - tax)?;
+    } else if cli.tax_handling == TaxHandling::Exclude {
+        writeln!(out, "Tax excluded by --tax-handling exclude")?;
+    }
+    if let Some(hub) = &report.optimization_hub {
+        writeln!(out, "Potential Monthly Savings (Cost Optimization Hub): ${:.2}", hub.estimated_monthly_savings)?;
+    }
+    if cli.group_by_ou {
+        for (ou_path, accounts) in ou_groups(&report.unified_view) {
+            let ou_total: f64 = accounts.iter().flat_map(|account| account.monthly_costs.values()).sum();
+            writeln!(out, "  {}: ${:.2}", ou_path, ou_total)?;
+        }
+    }
+
+    if !report.excluded_accounts.is_empty() {
+        let excluded_total: f64 = report.excluded_accounts.iter().map(|account| account.total_cost).sum();
+        writeln!(out, "Excluded by --exclude-accounts (not in the totals above): ${:.2}", excluded_total)?;
+        for account in &report.excluded_accounts {
+            writeln!(out, "  - Profile {} Account {} ({}): ${:.2}", account.profile, account.account_id, account.account_name, account.total_cost)?;
+        }
+    }
+
+    if let Some(partial) = &report.partial_period {
+        if cli.include_partial {
+            writeln!(out, "\n* {} is still in progress; it is counted in averages (--include-partial).", partial)?;
+        } else {
+            writeln!(out, "\n* {} is still in progress and is excluded from averages (use --include-partial to count it).", partial)?;
+        }
+    }
+    if !report.estimated_periods.is_empty() {
+        writeln!(out, "(est.) Cost Explorer marks these costs as estimated; they may change until the period is finalized.")?;
+    }
+
+    if !report.errors.is_empty() {
+        writeln!(out, "\n{} skipped:", skipped_caption(&report.errors))?;
+        for err in &report.errors {
+            match &err.account_id {
+                Some(account_id) => writeln!(out, "  - Profile {} Account {} ({}): {}", err.profile, account_id, err.phase, err.error)?,
+                None => writeln!(out, "  - Profile {} ({}): {}", err.profile, err.phase, err.error)?,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// What the errors left out of the report, such as `2 accounts and 1 profiles`. Errors without an
+/// account stand for a whole profile, whose accounts are unknown.
+fn skipped_caption(errors: &[ReportError]) -> String {
+    let accounts = errors.iter().filter(|err| err.account_id.is_some()).count();
+    let profiles = errors.len() - accounts;
+    match (accounts, profiles) {
+        (accounts, 0) => format!("{} accounts", accounts),
+        (0, profiles) => format!("{} profiles", profiles),
+        (accounts, profiles) => format!("{} accounts and {} profiles", accounts, profiles),
+    }
+}
+
+/// Writes JSON, chart and CSV outputs, then sends notifications, metrics and run history.
+async fn write_outputs(cli: &Cli, profiles: &[String], report: &Report) -> Result<(), Box<dyn Error>> {
+    let metadata = &report.metadata;
+    let account_cost_data = &report.accounts;
+    let unified_view_data = &report.unified_view;
+    let global_monthly_totals = &report.global_period_totals;
+    let filtered_months = &report.periods;
+    let total_global_cost = report.total_cost;
+    let average_global_monthly_cost = report.average_period_cost;
+
+    let wants_s3 = [cli.csv.as_deref(), cli.json_out.as_deref(), cli.chart_dir.as_deref()]
+        .into_iter()
+        .flatten()
+        .any(is_s3_uri);
+    let uploader = if wants_s3 {
+        let upload_profile = cli.upload_profile.as_ref().unwrap_or(&profiles[0]);
+        let config = load_profile_config(upload_profile, None).await;
+        Some(S3Uploader::new(&config, cli.sse_kms_key_id.clone())?)
+    } else {
+        None
+    };
+
+    // JSON Output
+    if cli.json || cli.json_out.is_some() {
+        let output = report.json(cli);
+        // Serialized straight into each destination rather than built up as one document in memory
+        if let Some(json_target) = &cli.json_out {
+            let compress = cli.compress || json_target.ends_with(".gz");
+            let local_path = local_output_path(json_target, uploader.as_ref());
+            let mut sink = BufWriter::new(OutputSink::create(&local_path, compress)?);
+            serde_json::to_writer_pretty(&mut sink, &output)?;
+            sink.into_inner().map_err(|e| e.into_error())?.finish()?;
+            let note = publish_output(&local_path, json_target, compress, uploader.as_ref()).await?;
+            info!("Exported JSON report to {}{}", json_target, note);
+        }
+        if cli.json {
+            let mut stdout = BufWriter::new(io::stdout().lock());
+            serde_json::to_writer_pretty(&mut stdout, &output)?;
+            writeln!(stdout)?;
+            stdout.flush()?;
+        }
+    }
+
+    // Chart Output
+    if cli.chart || cli.chart_dir.is_some() {
+        let chart_dir = cli.chart_dir.as_deref().map(|dir| dir.trim_end_matches('/'));
+        if let Some(dir) = chart_dir.filter(|dir| !is_s3_uri(dir)) {
+            std::fs::create_dir_all(dir)?;
+        }
+        for account_data in account_cost_data {
+            if account_data.cost_trend.is_empty() {
+                warn!("Warning: No cost trend data available for profile {} account {}. Skipping chart generation.", 
+                    account_data.profile, account_data.account_id);
+                continue;
+            }
+            let chart_file = format!("{}_profile_{}_account_{}.png",
+                cli.breakdown().chart_stem, account_data.profile, account_data.account_id);
+            let chart_path = match chart_dir {
+                Some(dir) => format!("{}/{}", dir, chart_file),
+                None => chart_file,
+            };
+            let local_chart_path = local_output_path(&chart_path, uploader.as_ref());
+            match generate_cost_trend_chart(&account_data.cost_trend, account_data.amortization_delta.as_ref(), cli, &local_chart_path) {
+                Ok(()) => {
+                    publish_output(&local_chart_path, &chart_path, false, uploader.as_ref()).await?;
+                    info!("Cost trend chart saved to {}", chart_path);
+                }
+                Err(e) => warn!("Failed to generate chart for profile {} account {}: {}", 
+                    account_data.profile, account_data.account_id, e),
+            }
+        }
+    }
+
+    // CSV Output
+    if let Some(csv_path) = &cli.csv {
+        let compress = cli.compress || csv_path.ends_with(".gz");
+        let csv_base = csv_path.trim_end_matches(".gz").trim_end_matches(".csv").trim_end_matches(".tsv");
+        let csv_base = if csv_base.ends_with('/') { format!("{}report", csv_base) } else { csv_base.to_string() };
+        let exports = CsvExports {
+            extension: match (cli.csv_delimiter == b'\t', compress) {
+                (true, true) => "tsv.gz",
+                (true, false) => "tsv",
+                (false, true) => "csv.gz",
+                (false, false) => "csv",
+            },
+            base: csv_base.clone(),
+            compress,
+            delimiter: cli.csv_delimiter,
+            uploader: uploader.as_ref(),
+        };
+
+        for account_data in account_cost_data {
+            let comparison = cli.compared_metrics().zip(account_data.metric_comparison.as_ref());
+            let mut headers: Vec<String> = [cli.granularity.period_label(), "Total Cost (USD)", cli.granularity.change_label(), "Partial", "Estimated"]
+                .map(str::to_string)
+                .to_vec();
+            if let Some(((first, second), _)) = comparison {
+                headers.extend([format!("{} (USD)", first.label()), format!("{} (USD)", second.label()), "Delta (USD)".to_string()]);
+            }
+            if cli.composition() {
+                headers.push("Composition".to_string());
+            }
+            exports
+                .write(
+                    &format!("trend_profile_{}_account_{}", account_data.profile, account_data.account_id),
+                    &format!("trend report for profile {} account {}", account_data.profile, account_data.account_id),
+                    &headers,
+                    |writer| {
+                        for data in &account_data.cost_trend {
+                            let mut record = vec![
+                                data.month.clone(),
+                                format!("{:.2}", data.total_cost),
+                                data.mom_change_percent.display(cli.percent_decimals),
+                                data.is_partial.to_string(),
+                                data.estimated.to_string(),
+                            ];
+                            if let Some((_, comparison)) = comparison {
+                                let period = comparison.periods.iter().find(|p| p.period == data.month);
+                                for cost in [period.map(|p| p.first_cost), period.map(|p| p.second_cost), period.map(|p| p.delta)] {
+                                    record.push(format!("{:.2}", cost.unwrap_or(0.0)));
+                                }
+                            }
+                            if cli.composition() {
+                                record.push(composition_label(&data.composition));
+                            }
+                            writer.write_record(&record)?;
+                        }
+                        Ok(())
+                    },
+                )
+                .await?;
+
+            let mut headers = vec![cli.breakdown().row_header.to_string()];
+            headers.extend(filtered_months.iter().cloned());
+            headers.push("Total Cost (USD)".to_string());
+            headers.push("Percent of Total (%)".to_string());
+            if cli.breakdown().row_change {
+                headers.push(cli.granularity.change_label().to_string());
+            }
+            exports
+                .write(
+                    &format!("{}_profile_{}_account_{}", cli.breakdown().csv_stem, account_data.profile, account_data.account_id),
+                    &format!("service summary for profile {} account {}", account_data.profile, account_data.account_id),
+                    &headers,
+                    |writer| {
+                        for data in &account_data.service_consumption {
+                            let mut row = vec![data.service.clone()];
+                            for month in filtered_months {
+                                let cost = data.monthly_costs.get(month).unwrap_or(&0.0);
+                                row.push(format!("{:.2}", cost));
+                            }
+                            row.push(format!("{:.2}", data.total_cost));
+                            row.push(format!("{:.*}", cli.percent_decimals, data.percent_of_total));
+                            if let Some(change) = &data.latest_change_percent {
+                                row.push(change.display(cli.percent_decimals));
+                            }
+                            writer.write_record(&row)?;
+                        }
+                        if let Some(tax) = account_data.tax.as_ref().filter(|tax| tax.total_cost != 0.0) {
+                            let mut row = vec![TAX.to_string()];
+                            row.extend(filtered_months.iter().map(|month| format!("{:.2}", tax.monthly_costs.get(month).copied().unwrap_or(0.0))));
+                            row.push(format!("{:.2}", tax.total_cost));
+                            row.push(String::new());
+                            writer.write_record(&row)?;
+                        }
+                        Ok(())
+                    },
+                )
+                .await?;
+
+            if let Some(families) = &account_data.instance_families {
+                let mut headers = vec!["Instance Family".to_string()];
+                headers.extend(filtered_months.iter().cloned());
+                headers.push("Total Cost (USD)".to_string());
+                headers.push("Percent of Total (%)".to_string());
+                headers.push(cli.granularity.change_label().to_string());
+                exports
+                    .write(
+                        &format!("instance_families_profile_{}_account_{}", account_data.profile, account_data.account_id),
+                        &format!("instance families for profile {} account {}", account_data.profile, account_data.account_id),
+                        &headers,
+                        |writer| {
+                            for family in families {
+                                let mut row = vec![family.family.clone()];
+                                for month in filtered_months {
+                                    row.push(format!("{:.2}", family.monthly_costs.get(month).copied().unwrap_or(0.0)));
+                                }
+                                row.push(format!("{:.2}", family.total_cost));
+                                row.push(format!("{:.*}", cli.percent_decimals, family.percent_of_total));
+                                row.push(family.latest_change_percent.display(cli.percent_decimals));
+                                writer.write_record(&row)?;
+                            }
+                            Ok(())
+                        },
+                    )
+                    .await?;
+            }
+        }
+
+        exports
+            .write("global_summary", "global summary", ["Metric", "Value"], |writer| {
+                writer.write_record(["Total Cost (USD)", format!("{:.2}", total_global_cost).as_ref()])?;
+                writer.write_record([format!("{} (USD)", cli.granularity.average_label()), format!("{:.2}", average_global_monthly_cost)])?;
+                if let Some(tax) = report.total_tax {
+                    writer.write_record(["Tax (USD)", format!("{:.2}", tax).as_ref()])?;
+                    writer.write_record(["Net of Tax (USD)", format!("{:.2}", total_global_cost - tax).as_ref()])?;
+                }
+                Ok(())
+            })
+            .await?;
+
+        let mut headers = vec!["Profile".to_string(), "Account ID".to_string(), "Account Name".to_string()];
+        if cli.group_by_ou {
+            headers.push("OU Path".to_string());
+        }
+        headers.extend(cli.show_account_tags.iter().flatten().cloned());
+        if cli.budget_file.is_some() {
+            headers.extend(["Budget Used (%)".to_string(), "Burn Overage (pts)".to_string()]);
+        }
+        headers.extend(filtered_months.iter().cloned());
+        headers.push("Total".to_string());
+        let unified_rows: Vec<&UnifiedViewData> = if cli.group_by_ou {
+            ou_groups(unified_view_data).into_iter().flat_map(|(_, accounts)| accounts).collect()
+        } else {
+            unified_view_data.iter().collect()
+        };
+        exports
+            .write("unified_view", "unified view", &headers, |writer| {
+                for account in unified_rows {
+                    let mut row = vec![account.profile.clone(), account.account_id.clone(), account.account_name.clone()];
+                    if cli.group_by_ou {
+                        row.push(account.ou_path.clone().unwrap_or_else(|| UNKNOWN_OU.to_string()));
+                    }
+                    for key in cli.show_account_tags.iter().flatten() {
+                        row.push(account.account_tags.get(key).cloned().unwrap_or_default());
+                    }
+                    if cli.budget_file.is_some() {
+                        let burn = account.budget_burn.as_ref();
+                        row.push(burn.map_or(String::new(), |burn| format!("{:.*}", cli.percent_decimals, burn.budget_used_percent)));
+                        row.push(burn.map_or(String::new(), |burn| format!("{:.*}", cli.percent_decimals, burn.overage_percent)));
+                    }
+                    let mut account_total = 0.0;
+                    for month in filtered_months {
+                        let cost = account.monthly_costs.get(month).unwrap_or(&0.0);
+                        account_total += cost;
+                        row.push(format!("{:.2}", cost));
+                    }
+                    row.push(format!("{:.2}", account_total));
+                    writer.write_record(&row)?;
+                }
+                let mut totals_row = vec!["TOTAL".to_string(), String::new(), String::new()];
+                if cli.group_by_ou {
+                    totals_row.push(String::new());
+                }
+                totals_row.extend(cli.show_account_tags.iter().flatten().map(|_| String::new()));
+                if cli.budget_file.is_some() {
+                    totals_row.extend([String::new(), String::new()]);
+                }
+                let mut exported_total = 0.0;
+                for month in filtered_months {
+                    let cost = global_monthly_totals.get(month).unwrap_or(&0.0);
+                    exported_total += cost;
+                    totals_row.push(format!("{:.2}", cost));
+                }
+                totals_row.push(format!("{:.2}", exported_total));
+                writer.write_record(&totals_row)
+            })
+            .await?;
+
+        if !report.weekday_analysis.is_empty() {
+            let headers = [
+                "Profile",
+                "Account ID",
+                "Account Name",
+                "Weekday Avg/Day (USD)",
+                "Weekend Avg/Day (USD)",
+                "Weekend/Weekday",
+                "Est. Monthly Savings (USD)",
+            ];
+            exports
+                .write("weekday_analysis", "weekday analysis", headers, |writer| {
+                    for split in &report.weekday_analysis {
+                        writer.write_record(&[
+                            split.profile.clone(),
+                            split.account_id.clone(),
+                            split.account_name.clone(),
+                            format!("{:.2}", split.weekday_average),
+                            format!("{:.2}", split.weekend_average),
+                            split.weekend_ratio.map_or(String::new(), |r| format!("{:.2}", r)),
+                            format!("{:.2}", split.estimated_monthly_savings),
+                        ])?;
+                    }
+                    Ok(())
+                })
+                .await?;
+        }
+
+        let top_resources: Vec<(&AccountCostData, &TopResources)> = report
+            .accounts
+            .iter()
+            .filter_map(|account| account.top_resources.as_ref().map(|top| (account, top)))
+            .collect();
+        if let Some((_, first)) = top_resources.first() {
+            let mut header = vec!["Profile".to_string(), "Account ID".to_string(), "Account Name".to_string(), "Service".to_string(), "Resource ID".to_string()];
+            header.extend(first.days.iter().cloned());
+            header.push("Total Cost (USD)".to_string());
+            exports
+                .write("top_resources", "top resources", &header, |writer| {
+                    for (account, top) in &top_resources {
+                        for resource in &top.resources {
+                            let mut record = vec![
+                                account.profile.clone(),
+                                account.account_id.clone(),
+                                account.account_name.clone(),
+                                top.service.clone(),
+                                resource.resource_id.clone(),
+                            ];
+                            record.extend(first.days.iter().map(|day| format!("{:.2}", resource.daily_costs.get(day).copied().unwrap_or(0.0))));
+                            record.push(format!("{:.2}", resource.total_cost));
+                            writer.write_record(&record)?;
+                        }
+                    }
+                    Ok(())
+                })
+                .await?;
+        }
+
+        let free_tier: Vec<(&AccountCostData, &FreeTierItem)> = report
+            .accounts
+            .iter()
+            .flat_map(|account| account.free_tier.iter().flat_map(|usage| &usage.items).map(move |item| (account, item)))
+            .collect();
+        if !free_tier.is_empty() {
+            let headers = [
+                "Profile",
+                "Account ID",
+                "Account Name",
+                "Service",
+                "Usage Type",
+                "Region",
+                "Description",
+                "Unit",
+                "Limit",
+                "Actual Usage",
+                "Forecasted Usage",
+                "Used (%)",
+                "Exceeds Threshold",
+            ];
+            exports
+                .write("free_tier", "Free Tier usage", headers, |writer| {
+                    for (account, item) in &free_tier {
+                        writer.write_record([
+                            account.profile.clone(),
+                            account.account_id.clone(),
+                            account.account_name.clone(),
+                            item.service.clone(),
+                            item.usage_type.clone(),
+                            item.region.clone().unwrap_or_default(),
+                            item.description.clone().unwrap_or_default(),
+                            item.unit.clone().unwrap_or_default(),
+                            item.limit.to_string(),
+                            item.actual_usage.to_string(),
+                            item.forecasted_usage.to_string(),
+                            item.percent_used.map_or(String::new(), |p| format!("{:.*}", cli.percent_decimals, p)),
+                            item.exceeds_threshold.to_string(),
+                        ])?;
+                    }
+                    Ok(())
+                })
+                .await?;
+        }
+
+        if let Some(hub) = report.optimization_hub.as_ref().filter(|hub| !hub.top_recommendations.is_empty()) {
+            let headers = ["Profile", "Account ID", "Account Name", "Resource", "Action", "Current", "Recommended", "Estimated Monthly Savings (USD)"];
+            exports
+                .write("optimization_hub", "Cost Optimization Hub recommendations", headers, |writer| {
+                    for recommendation in &hub.top_recommendations {
+                        writer.write_record([
+                            recommendation.profile.as_str(),
+                            &recommendation.account_id,
+                            &recommendation.account_name,
+                            &recommendation.resource,
+                            &recommendation.action_type,
+                            recommendation.current.as_deref().unwrap_or(""),
+                            recommendation.recommended.as_deref().unwrap_or(""),
+                            &format!("{:.2}", recommendation.estimated_monthly_savings),
+                        ])?;
+                    }
+                    Ok(())
+                })
+                .await?;
+        }
+
+        let recommendations: Vec<&SpRecommendation> = report.sp_recommendations.iter().flatten().filter(|r| r.note.is_none()).collect();
+        if !recommendations.is_empty() {
+            let headers = [
+                "Profile", "Term", "Payment Option", "Account ID", "Account Name", "Hourly Commitment (USD)", "Upfront Cost (USD)",
+                "Estimated Monthly Savings (USD)", "Estimated Savings (%)", "Estimated Utilization (%)", "Estimated Coverage (%)", "Break-even (Months)",
+            ];
+            exports
+                .write("sp_recommendations", "Savings Plans recommendations", headers, |writer| {
+                    for recommendation in recommendations {
+                        let optional = |value: Option<f64>| value.map_or(String::new(), |value| format!("{:.2}", value));
+                        for account in &recommendation.accounts {
+                            writer.write_record([
+                                recommendation.profile.as_str(),
+                                &recommendation.term,
+                                &recommendation.payment_option,
+                                &account.account_id,
+                                account.account_name.as_deref().unwrap_or(""),
+                                &format!("{:.3}", account.hourly_commitment),
+                                &format!("{:.2}", account.upfront_cost),
+                                &format!("{:.2}", account.estimated_monthly_savings),
+                                &format!("{:.2}", account.estimated_savings_percent),
+                                &format!("{:.2}", account.estimated_utilization_percent),
+                                "",
+                                "",
+                            ])?;
+                        }
+                        writer.write_record([
+                            recommendation.profile.as_str(),
+                            &recommendation.term,
+                            &recommendation.payment_option,
+                            "TOTAL",
+                            "",
+                            &format!("{:.3}", recommendation.hourly_commitment),
+                            &format!("{:.2}", recommendation.upfront_cost),
+                            &format!("{:.2}", recommendation.estimated_monthly_savings),
+                            &format!("{:.2}", recommendation.estimated_savings_percent),
+                            &optional(recommendation.estimated_utilization_percent),
+                            &optional(recommendation.estimated_coverage_percent),
+                            &optional(recommendation.break_even_months),
+                        ])?;
+                    }
+                    Ok(())
+                })
+                .await?;
+        }
+
+        if let Some(advisor) = report.trusted_advisor.as_ref().filter(|advisor| !advisor.accounts.is_empty()) {
+            let headers = ["Profile", "Account ID", "Account Name", "Check", "Status", "Flagged Resources", "Estimated Monthly Savings (USD)"];
+            exports
+                .write("trusted_advisor", "Trusted Advisor checks", headers, |writer| {
+                    for account in &advisor.accounts {
+                        for check in &account.checks {
+                            writer.write_record([
+                                account.profile.as_str(),
+                                &account.account_id,
+                                &account.account_name,
+                                &check.name,
+                                &check.status,
+                                &check.flagged_resources.to_string(),
+                                &format!("{:.2}", check.estimated_monthly_savings),
+                            ])?;
+                        }
+                    }
+                    Ok(())
+                })
+                .await?;
+        }
+
+        let record_types: Vec<(&AccountCostData, &RecordTypeBreakdown)> = report
+            .accounts
+            .iter()
+            .filter_map(|account| account.record_type_breakdown.as_ref().map(|breakdown| (account, breakdown)))
+            .collect();
+        if !record_types.is_empty() {
+            let mut header = vec!["Profile".to_string(), "Account ID".to_string(), "Account Name".to_string(), "Record Type".to_string()];
+            header.extend(filtered_months.iter().cloned());
+            header.push("Total Cost (USD)".to_string());
+            exports
+                .write("record_types", "record types", &header, |writer| {
+                    for (account, breakdown) in &record_types {
+                        for record_type in analysis::ordered_record_types(breakdown) {
+                            let costs = &breakdown[record_type];
+                            let mut record = vec![account.profile.clone(), account.account_id.clone(), account.account_name.clone(), record_type.clone()];
+                            record.extend(filtered_months.iter().map(|month| format!("{:.2}", costs.get(month).copied().unwrap_or(0.0))));
+                            record.push(format!("{:.2}", costs.values().sum::<f64>()));
+                            writer.write_record(&record)?;
+                        }
+                    }
+                    Ok(())
+                })
+                .await?;
+        }
 
-                    if let Some(groups) = result.groups {
-                        for group in groups {
-                            let service = group.keys.unwrap_or_default().join(", ");
-                            let cost = group
-                                .metrics
-                                .as_ref()
-                                .and_then(|m| m.get("UnblendedCost"))
-                                .map(|m| m.amount.as_ref().map(|a| a.parse::<f64>().unwrap_or(0.0)).unwrap_or(0.0))
-                                .unwrap_or(0.0);
-                            total_cost += cost;
+        let unit_rates: Vec<(&AccountCostData, &UnitRate)> = report
+            .accounts
+            .iter()
+            .flat_map(|account| account.unit_rates.iter().flatten().map(move |rate| (account, rate)))
+            .collect();
+        if cli.unit_rates.is_some() {
+            let mut header = vec![
+                "Profile",
+                "Account ID",
+                "Account Name",
+                "Usage Type",
+                "Unit",
+                cli.granularity.period_label(),
+                "Cost (USD)",
+                "Quantity",
+                "Rate (USD per Unit)",
+                "Change (%)",
+                "Exceeds Threshold",
+            ];
+            if cli.price_context {
+                header.extend(["List Price (USD per Unit)", "Discount (%)"]);
+            }
+            exports
+                .write("unit_rates", "unit rates", &header, |writer| {
+                    for (account, rate) in &unit_rates {
+                        for period in &rate.periods {
+                            let mut record = vec![
+                                account.profile.clone(),
+                                account.account_id.clone(),
+                                account.account_name.clone(),
+                                rate.usage_type.clone(),
+                                rate.unit.clone().unwrap_or_default(),
+                                period.period.clone(),
+                                format!("{:.2}", period.cost),
+                                period.quantity.to_string(),
+                                period.rate.map_or(String::new(), |r| r.to_string()),
+                                period.change_percent.map_or(String::new(), |c| format!("{:.*}", cli.percent_decimals, c)),
+                                period.exceeds_threshold.to_string(),
+                            ];
+                            if cli.price_context {
+                                let discount = rate.list_price.filter(|price| *price > 0.0).zip(period.rate).map(|(price, r)| (price - r) / price * 100.0);
+                                record.push(rate.list_price.map_or(String::new(), |p| p.to_string()));
+                                record.push(discount.map_or(String::new(), |d| format!("{:.*}", cli.percent_decimals, d)));
+                            }
+                            writer.write_record(&record)?;
+                        }
+                    }
+                    Ok(())
+                })
+                .await?;
+        }
 
-                            let service_monthly = service_monthly_totals
-                                .entry(service.clone())
-                                .or_insert_with(HashMap::new);
-                            *service_monthly.entry(month.clone()).or_insert(0.0) += cost;
+        let amortization: Vec<(&AccountCostData, &AmortizationDelta)> = report
+            .accounts
+            .iter()
+            .filter_map(|account| account.amortization_delta.as_ref().map(|delta| (account, delta)))
+            .collect();
+        if !amortization.is_empty() {
+            let headers = [
+                "Profile",
+                "Account ID",
+                "Account Name",
+                cli.granularity.period_label(),
+                "Unblended (USD)",
+                "Amortized (USD)",
+                "Delta (USD)",
+                "Delta (%)",
+                "Upfront Purchase",
+            ];
+            exports
+                .write("amortization_delta", "amortization delta", headers, |writer| {
+                    for (account, delta) in &amortization {
+                        for period in &delta.periods {
+                            writer.write_record([
+                                account.profile.clone(),
+                                account.account_id.clone(),
+                                account.account_name.clone(),
+                                period.period.clone(),
+                                format!("{:.2}", period.unblended_cost),
+                                format!("{:.2}", period.amortized_cost),
+                                format!("{:.2}", period.delta),
+                                period.delta_percent.map_or(String::new(), |p| format!("{:.*}", cli.percent_decimals, p)),
+                                period.upfront_purchase.to_string(),
+                            ])?;
                         }
                     }
+                    Ok(())
+                })
+                .await?;
+        }
 
-                    *monthly_totals.entry(month.clone()).or_insert(0.0) += total_cost;
-                    *global_monthly_totals.entry(month.clone()).or_insert(0.0) += total_cost;
-                    if !all_months.contains(&month) {
-                        all_months.push(month);
+        let headers = [
+            "Profile",
+            "Account ID",
+            "Account Name",
+            "Total Cost (USD)",
+            "Average (USD)",
+            "Min (USD)",
+            "Max (USD)",
+            "Median (USD)",
+            "Std Dev (USD)",
+            "Coefficient of Variation",
+        ];
+        exports
+            .write("stats", "account statistics", headers, |writer| {
+                for account_data in account_cost_data {
+                    let mut row = vec![
+                        account_data.profile.clone(),
+                        account_data.account_id.clone(),
+                        account_data.account_name.clone(),
+                        format!("{:.2}", account_data.total_cost),
+                        format!("{:.2}", account_data.average_monthly_cost),
+                    ];
+                    match &account_data.stats {
+                        Some(stats) => row.extend([
+                            format!("{:.2}", stats.min),
+                            format!("{:.2}", stats.max),
+                            format!("{:.2}", stats.median),
+                            stats.std_dev.map_or(String::new(), |sd| format!("{:.2}", sd)),
+                            stats.coefficient_of_variation.map_or(String::new(), |cv| format!("{:.3}", cv)),
+                        ]),
+                        None => row.extend(std::iter::repeat_n(String::new(), 5)),
                     }
+                    writer.write_record(&row)?;
                 }
-            }
+                Ok(())
+            })
+            .await?;
+        let meta_path = format!("{}.meta.json", csv_base);
+        let local_path = local_output_path(&meta_path, uploader.as_ref());
+        let mut sink = OutputSink::create(&local_path, false)?;
+        sink.write_all(serde_json::to_string_pretty(metadata)?.as_bytes())?;
+        sink.finish()?;
+        publish_output(&local_path, &meta_path, false, uploader.as_ref()).await?;
+        info!("Exported report metadata to {}", meta_path);
+    }
 
-            let mut cost_trend = Vec::new();
-            let mut previous_cost: Option<f64> = None;
-            for month in &all_months {
-                let cost = monthly_totals.get(month).unwrap_or(&0.0);
-                let mom_change = previous_cost.map(|prev| {
-                    if prev == 0.0 { 0.0 } else { ((cost - prev) / prev * 100.0).round() }
-                }).unwrap_or(0.0);
-                cost_trend.push(CostTrendData {
-                    month: month.clone(),
-                    total_cost: *cost,
-                    mom_change_percent: mom_change,
-                });
-                previous_cost = Some(*cost);
+    // Notifications
+    if cli.preview_notifications {
+        if cli.slack_webhook.is_some() {
+            let message = slack::build_message(metadata, account_cost_data, global_monthly_totals, report.partial_period.as_deref(), total_global_cost, &report.alerts);
+            eprintln!("Preview: would post to Slack:\n{}", serde_json::to_string_pretty(&message)?);
+        }
+    } else if cli.slack_webhook.is_some() || cli.slack_token.is_some() {
+        let http = reqwest::Client::new();
+        if let Some(webhook) = &cli.slack_webhook {
+            let message = slack::build_message(metadata, account_cost_data, global_monthly_totals, report.partial_period.as_deref(), total_global_cost, &report.alerts);
+            match slack::post_webhook(&http, webhook, &message).await {
+                Ok(()) => info!("Posted report summary to Slack"),
+                Err(e) => notify_failure(&format!("Failed to post report summary to Slack: {}", e), cli.fail_on_notify_error)?,
             }
-
-            let total_cost: f64 = monthly_totals.values().sum();
-            let average_monthly_cost = if !all_months.is_empty() {
-                total_cost / all_months.len() as f64
-            } else {
-                0.0
+        }
+        if let (Some(token), Some(channel)) = (&cli.slack_token, &cli.slack_channel) {
+            let global_trend = build_cost_trend(
+                filtered_months,
+                global_monthly_totals,
+                report.partial_period.as_deref(),
+                &report.estimated_periods,
+                &report.period_days,
+            );
+            let chart_path = std::env::temp_dir()
+                .join(format!("aws-cost-cli-{}-global-trend.png", std::process::id()))
+                .to_string_lossy()
+                .into_owned();
+            let result = match generate_cost_trend_chart(&global_trend, None, cli, &chart_path) {
+                Ok(()) => slack::upload_file(&http, token, channel, &chart_path, "Global cost trend").await,
+                Err(e) => Err(e),
             };
-
-            let mut service_consumption = Vec::new();
-            let total_service_cost: f64 = service_monthly_totals
-                .iter()
-                .flat_map(|(_, months)| months.values())
-                .sum();
-            for (service, monthly_costs) in service_monthly_totals {
-                let service_total_cost: f64 = monthly_costs.values().sum();
-                if service_total_cost > 0.0 {
-                    service_consumption.push(ServiceConsumptionData {
-                        service,
-                        monthly_costs,
-                        total_cost: service_total_cost,
-                        percent_of_total: if total_service_cost > 0.0 {
-                            (service_total_cost / total_service_cost * 100.0).round()
-                        } else {
-                            0.0
-                        },
-                    });
-                }
+            let _ = std::fs::remove_file(&chart_path);
+            match result {
+                Ok(()) => info!("Uploaded global trend chart to Slack channel {}", channel),
+                Err(e) => notify_failure(&format!("Failed to upload chart to Slack: {}", e), cli.fail_on_notify_error)?,
             }
-            service_consumption.sort_by(|a, b| b.total_cost.partial_cmp(&a.total_cost).unwrap_or(std::cmp::Ordering::Equal));
-
-            account_cost_data.push(AccountCostData {
-                profile: profile.clone(),
-                account_id: account_id.clone(),
-                account_name: account_name.clone(),
-                cost_trend,
-                service_consumption,
-                total_cost,
-                average_monthly_cost,
-            });
+        }
+    }
 
-            unified_view_data.push(UnifiedViewData {
-                profile: profile.clone(),
-                account_id,
-                account_name,
-                monthly_costs: monthly_totals,
-            });
+    if let Some(topic_arn) = &cli.sns_topic_arn {
+        let alerts: Vec<String> = report.alerts.iter().map(Alert::message).collect();
+        let message = sns::build_message(metadata, account_cost_data, total_global_cost, average_global_monthly_cost, &alerts);
+        let attributes = sns::message_attributes(total_global_cost, alerts.len())?;
+        if cli.preview_notifications {
+            eprintln!("Preview: would publish to {} with attributes total_cost={:.2}, alert_count={}:\n{}",
+                topic_arn, total_global_cost, alerts.len(), serde_json::to_string_pretty(&message)?);
+        } else {
+            let sns_profile = cli.sns_profile.as_ref().unwrap_or(&profiles[0]);
+            let config = load_profile_config(sns_profile, None).await;
+            match sns::publish(&config, topic_arn, &message, attributes).await {
+                Ok(message_id) => info!("Published report summary to {} (message ID {})", topic_arn, message_id),
+                Err(e) => notify_failure(&format!("Failed to publish report summary to {}: {}", topic_arn, e), cli.fail_on_notify_error)?,
+            }
         }
     }
 
-    if account_cost_data.is_empty() {
-        eprintln!("No cost data retrieved for any accounts across specified profiles.");
-        return Ok(());
+    // CloudWatch Metrics
+    if let Some(namespace) = &cli.cloudwatch_namespace {
+        let datums = cloudwatch::build_datums(account_cost_data);
+        if cli.preview_notifications {
+            eprintln!("Preview: would publish {} datapoints to CloudWatch namespace {}", datums.len(), namespace);
+        } else {
+            let config = load_profile_config(&profiles[0], None).await;
+            let published = cloudwatch::publish(&config, namespace, datums).await?;
+            info!("Published {} datapoints to CloudWatch namespace {}", published, namespace);
+        }
     }
 
-    all_months.sort();
-    let filtered_months: Vec<String> = all_months
-        .into_iter()
-        .filter(|m| {
-            NaiveDate::parse_from_str(m, "%Y-%m-%d").map_or(false, |d| d >= six_months_ago)
-        })
-        .collect();
+    // Run History
+    if let Some(table) = &cli.dynamodb_table {
+        let items = history::build_items(account_cost_data, &metadata.metric, &metadata.generated_at);
+        if cli.preview_notifications {
+            eprintln!("Preview: would write {} history items to DynamoDB table {}", items.len(), table);
+        } else {
+            let history_profile = cli.history_profile.as_ref().unwrap_or(&profiles[0]);
+            let config = load_profile_config(history_profile, None).await;
+            let summary = history::write_history(&config, table, items).await?;
+            info!("Wrote {} history items to DynamoDB table {}{}", summary.written, table,
+                if summary.skipped_newer > 0 { format!(" ({} skipped, newer data already stored)", summary.skipped_newer) } else { String::new() });
+        }
+    }
 
-    let total_global_cost: f64 = global_monthly_totals.values().sum();
-    let average_global_monthly_cost = if !filtered_months.is_empty() {
-        total_global_cost / filtered_months.len() as f64
-    } else {
-        0.0
-    };
+    Ok(())
+}
 
-    // JSON Output
-    if cli.json {
-        let output = serde_json::json!({
-            "accounts": account_cost_data,
-            "unified_view": unified_view_data,
-            "global_summary": {
-                "total_cost": total_global_cost,
-                "average_monthly_cost": average_global_monthly_cost
-            }
+/// Period-over-period trend for the given periods, treating missing periods as zero cost.
+fn build_cost_trend(
+    periods: &[String],
+    totals: &BTreeMap<String, f64>,
+    partial_period: Option<&str>,
+    estimated_periods: &BTreeSet<String>,
+    period_days: &BTreeMap<String, i64>,
+) -> Vec<CostTrendData> {
+    let mut cost_trend = Vec::new();
+    let mut previous_cost: Option<f64> = None;
+    let mut previous_per_day: Option<f64> = None;
+    for month in periods {
+        let cost = totals.get(month).unwrap_or(&0.0);
+        let mom_change = PeriodChange::between(previous_cost, *cost);
+        let cost_per_day = period_days.get(month).map(|days| cost / *days as f64);
+        let per_day_change = match (cost_per_day, previous_per_day) {
+            (Some(current), Some(prev)) if prev != 0.0 => Some((current - prev) / prev * 100.0),
+            _ => None,
+        };
+        cost_trend.push(CostTrendData {
+            month: month.clone(),
+            total_cost: *cost,
+            mom_change_percent: mom_change,
+            is_partial: partial_period == Some(month.as_str()),
+            estimated: estimated_periods.contains(month),
+            cost_per_day,
+            per_day_change_percent: per_day_change,
+            composition: Vec::new(),
         });
-        println!("{}", serde_json::to_string_pretty(&output)?);
-    } else {
-        // Unified View Table with pagination for large datasets
-        let max_columns = 10; // Adjust this based on terminal width
-        for chunk in filtered_months.chunks(max_columns - 3) { // -3 for Profile, Account ID, Account Name
-            let mut unified_table = Table::new();
-            unified_table.set_format(*format::consts::FORMAT_DEFAULT); // Restore grid lines
-            let mut unified_titles = vec![
-                Cell::new("Profile").style_spec("bFc"),
-                Cell::new("Account ID").style_spec("bFc"),
-                Cell::new("Account Name").style_spec("bFc"),
-            ];
-            for month in chunk {
-                unified_titles.push(Cell::new(month).style_spec("bFr"));
-            }
-            unified_table.set_titles(Row::new(unified_titles));
-
-            for account in &unified_view_data {
-                let mut row = vec![
-                    Cell::new(&account.profile),
-                    Cell::new(&account.account_id),
-                    Cell::new(&account.account_name),
-                ];
-                for month in chunk {
-                    let cost = account.monthly_costs.get(month).unwrap_or(&0.0);
-                    row.push(Cell::new(&format!("{:.2}", cost)).style_spec("Fr"));
-                }
-                unified_table.add_row(Row::new(row));
-            }
-
-            println!("\nUnified Cost View (Past 6 Months) - Page {}:", (filtered_months.iter().position(|m| m == chunk[0].as_str()).unwrap() / (max_columns - 3)) + 1);
-            unified_table.printstd();
-        }
-
-        // Per-Account Tables
-        for account_data in &account_cost_data {
-            let mut trend_table = Table::new();
-            trend_table.set_format(*format::consts::FORMAT_DEFAULT);
-            trend_table.set_titles(Row::new(vec![
-                Cell::new("Month").style_spec("bFc"),
-                Cell::new("Total Cost (USD)").style_spec("bFr"),
-                Cell::new("MoM Change (%)").style_spec("bFc"),
-            ]));
+        previous_cost = Some(*cost);
+        previous_per_day = cost_per_day;
+    }
+    cost_trend
+}
 
-            for data in &account_data.cost_trend {
-                trend_table.add_row(Row::new(vec![
-                    Cell::new(&data.month),
-                    Cell::new(&format!("{:.2}", data.total_cost)).style_spec("Fr"),
-                    Cell::new(&format!("{:.1}", data.mom_change_percent)).style_spec("Fc"),
-                ]));
-            }
+/// Composition annotation of a trend period, such as `refund 34%, credit 21%`.
+fn composition_label(composition: &[RecordTypeShare]) -> String {
+    composition.iter().map(RecordTypeShare::label).collect::<Vec<_>>().join(", ")
+}
 
-            println!("\nCost Trend Analysis for Profile {} Account {} ({}):", 
-                account_data.profile, account_data.account_id, account_data.account_name);
-            trend_table.printstd();
-            println!("Total Cost ({} to {}): ${:.2}", cli.start_date, cli.end_date, account_data.total_cost);
-            println!("Average Monthly Cost: ${:.2}", account_data.average_monthly_cost);
+/// Notification failures only warn unless the user asked for them to fail the run.
+fn notify_failure(message: &str, fail_on_notify_error: bool) -> Result<(), Box<dyn Error>> {
+    if fail_on_notify_error {
+        Err(message.into())
+    } else {
+        warn!("Warning: {}", message);
+        Ok(())
+    }
+}
 
-            // Service Consumption Table with pagination
-            for chunk in filtered_months.chunks(max_columns - 2) { // -2 for Service, Total Cost, Percent of Total
-                let mut service_table = Table::new();
-                service_table.set_format(*format::consts::FORMAT_DEFAULT);
-                let mut service_titles = vec![
-                    Cell::new("Service").style_spec("bFc"),
-                ];
-                for month in chunk {
-                    service_titles.push(Cell::new(month).style_spec("bFr"));
-                }
-                service_titles.push(Cell::new("Total Cost (USD)").style_spec("bFr"));
-                service_titles.push(Cell::new("Percent of Total (%)").style_spec("bFc"));
+fn parse_interval(value: &str) -> Result<std::time::Duration, String> {
+    let value = value.trim();
+    let (number, unit) = value.split_at(value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len()));
+    let amount: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid interval '{}', expected e.g. 30s, 15m or 1h", value))?;
+    let seconds = match unit {
+        "" | "s" => amount,
+        "m" => amount * 60,
+        "h" => amount * 3600,
+        _ => return Err(format!("invalid interval unit '{}', expected s, m or h", unit)),
+    };
+    if seconds == 0 {
+        return Err("interval must be greater than zero".to_string());
+    }
+    Ok(std::time::Duration::from_secs(seconds))
+}
 
-                service_table.set_titles(Row::new(service_titles));
+/// Accepts a 12-digit AWS account ID, ignoring whitespace around comma-separated entries.
+fn parse_account_id(value: &str) -> Result<String, String> {
+    let id = value.trim();
+    if id.len() == 12 && id.bytes().all(|b| b.is_ascii_digit()) {
+        Ok(id.to_string())
+    } else {
+        Err(format!("Invalid account ID '{}': expected exactly 12 digits (e.g. 123456789012)", id))
+    }
+}
 
-                for data in &account_data.service_consumption {
-                    let mut row = vec![Cell::new(&data.service)];
-                    for month in chunk {
-                        let cost = data.monthly_costs.get(month).unwrap_or(&0.0);
-                        row.push(Cell::new(&format!("{:.2}", cost)).style_spec("Fr"));
-                    }
-                    row.push(Cell::new(&format!("{:.2}", data.total_cost)).style_spec("Fr"));
-                    row.push(Cell::new(&format!("{:.1}", data.percent_of_total)).style_spec("Fc"));
-                    service_table.add_row(Row::new(row));
-                }
+fn parse_account_tag(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, tag_value)) if !key.is_empty() => Ok((key.to_string(), tag_value.to_string())),
+        _ => Err(format!("Invalid account tag '{}': expected Key=Value (e.g. CostCenter=1234)", value)),
+    }
+}
 
-                println!(
-                    "\nService Consumption Summary for Profile {} Account {} ({} to {}) - Page {}:",
-                    account_data.profile, account_data.account_id, cli.start_date, cli.end_date,
-                    (filtered_months.iter().position(|m| m == chunk[0].as_str()).unwrap() / (max_columns - 2)) + 1
-                );
-                service_table.printstd();
-            }
-        }
+fn parse_timezone(value: &str) -> Result<chrono_tz::Tz, String> {
+    value
+        .parse()
+        .map_err(|_| format!("unknown time zone '{}'; use an IANA name such as America/New_York or Europe/Berlin", value))
+}
 
-        // Global Summary
-        println!("\nGlobal Summary (All Accounts):");
-        println!("Total Cost ({} to {}): ${:.2}", cli.start_date, cli.end_date, total_global_cost);
-        println!("Average Monthly Cost: ${:.2}", average_global_monthly_cost);
+fn parse_csv_delimiter(value: &str) -> Result<u8, String> {
+    match value {
+        "\\t" | "tab" | "\t" => Ok(b'\t'),
+        _ if value.len() == 1 && value.is_ascii() => Ok(value.as_bytes()[0]),
+        _ => Err(format!("delimiter must be a single ASCII character, got '{}'", value)),
     }
+}
 
-    // Chart Output
-    if cli.chart {
-        for account_data in &account_cost_data {
-            if account_data.cost_trend.is_empty() {
-                eprintln!("Warning: No cost trend data available for profile {} account {}. Skipping chart generation.", 
-                    account_data.profile, account_data.account_id);
-                continue;
-            }
-            let chart_path = format!("cost_trend_profile_{}_account_{}.png", 
-                account_data.profile, account_data.account_id);
-            match generate_cost_trend_chart(&account_data.cost_trend, &chart_path) {
-                Ok(()) => println!("Cost trend chart saved to {}", chart_path),
-                Err(e) => eprintln!("Failed to generate chart for profile {} account {}: {}", 
-                    account_data.profile, account_data.account_id, e),
-            }
-        }
+/// Whether a failed AWS call is worth retrying later (throttling, timeouts, transient service errors).
+fn is_retriable<E: ProvideErrorMetadata, R>(err: &SdkError<E, R>) -> bool {
+    match err {
+        SdkError::TimeoutError(_) | SdkError::DispatchFailure(_) => true,
+        SdkError::ServiceError(_) => matches!(
+            err.code(),
+            Some("ThrottlingException" | "Throttling" | "LimitExceededException" | "RequestLimitExceeded" | "ServiceUnavailable" | "TooManyRequestsException")
+        ),
+        _ => false,
     }
+}
 
-    // CSV Output
-    if let Some(csv_path) = cli.csv {
-        for account_data in &account_cost_data {
-            let trend_csv_path = format!(
-                "{}_trend_profile_{}_account_{}.csv", 
-                csv_path.trim_end_matches(".csv"), 
-                account_data.profile, 
-                account_data.account_id
-            );
-            let mut trend_writer = Writer::from_path(&trend_csv_path)?;
-            trend_writer.write_record(&["Month", "Total Cost (USD)", "MoM Change (%)"])?;
-            for data in &account_data.cost_trend {
-                trend_writer.write_record(&[
-                    data.month.clone(),
-                    format!("{:.2}", data.total_cost),
-                    format!("{:.1}", data.mom_change_percent),
-                ])?;
-            }
-            trend_writer.flush()?;
-            println!("Exported trend report for profile {} account {} to {}", 
-                account_data.profile, account_data.account_id, trend_csv_path);
-
-            let service_csv_path = format!(
-                "{}_service_summary_profile_{}_account_{}.csv",
-                csv_path.trim_end_matches(".csv"),
-                account_data.profile,
-                account_data.account_id
-            );
-            let mut service_writer = Writer::from_path(&service_csv_path)?;
-            let mut headers = vec!["Service".to_string()];
-            headers.extend(filtered_months.iter().map(|m| m.clone()));
-            headers.push("Total Cost (USD)".to_string());
-            headers.push("Percent of Total (%)".to_string());
-            service_writer.write_record(&headers)?;
-            for data in &account_data.service_consumption {
-                let mut row = vec![data.service.clone()];
-                for month in &filtered_months {
-                    let cost = data.monthly_costs.get(month).unwrap_or(&0.0);
-                    row.push(format!("{:.2}", cost));
-                }
-                row.push(format!("{:.2}", data.total_cost));
-                row.push(format!("{:.1}", data.percent_of_total));
-                service_writer.write_record(&row)?;
-            }
-            service_writer.flush()?;
-            println!(
-                "Exported service summary for profile {} account {} to {}",
-                account_data.profile, account_data.account_id, service_csv_path
-            );
-        }
+/// Cost Explorer rejects hourly queries unless hourly data has been enabled in its preferences.
+fn is_hourly_not_enabled<E: ProvideErrorMetadata, R>(err: &SdkError<E, R>) -> bool {
+    let message = err.message().unwrap_or_default().to_lowercase();
+    err.code() == Some("ValidationException") && message.contains("hourly") && (message.contains("enable") || message.contains("opt"))
+}
 
-        let global_csv_path = format!("{}_global_summary.csv", csv_path.trim_end_matches(".csv"));
-        let mut global_writer = Writer::from_path(&global_csv_path)?;
-        global_writer.write_record(&["Metric", "Value"])?;
-        global_writer.write_record(&["Total Cost (USD)", format!("{:.2}", total_global_cost).as_ref()])?;
-        global_writer.write_record(&["Average Monthly Cost (USD)", format!("{:.2}", average_global_monthly_cost).as_ref()])?;
-        global_writer.flush()?;
-        println!("Exported global summary to {}", global_csv_path);
+/// Hourly data was requested but isn't enabled; the whole run is retried or aborted rather than
+/// skipping every account one by one.
+#[derive(Debug)]
+struct HourlyNotEnabled {
+    account_id: String,
+}
 
-        let unified_csv_path = format!("{}_unified_view.csv", csv_path.trim_end_matches(".csv"));
-        let mut unified_writer = Writer::from_path(&unified_csv_path)?;
-        let mut headers = vec!["Profile".to_string(), "Account ID".to_string(), "Account Name".to_string()];
-        headers.extend(filtered_months.iter().map(|m| m.clone()));
-        unified_writer.write_record(&headers)?;
-        for account in &unified_view_data {
-            let mut row = vec![account.profile.clone(), account.account_id.clone(), account.account_name.clone()];
-            for month in &filtered_months {
-                let cost = account.monthly_costs.get(month).unwrap_or(&0.0);
-                row.push(format!("{:.2}", cost));
-            }
-            unified_writer.write_record(&row)?;
-        }
-        unified_writer.flush()?;
-        println!("Exported unified view to {}", unified_csv_path);
+impl std::fmt::Display for HourlyNotEnabled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Hourly granularity is not enabled in Cost Explorer (account {}). The management account can turn on \
+             \"Hourly and resource-level data\" in Cost Explorer preferences; hourly data is only collected from then on.",
+            self.account_id
+        )
     }
-
-    Ok(())
 }
 
-fn generate_cost_trend_chart(cost_trend: &[CostTrendData], output_path: &str) -> Result<(), Box<dyn Error>> {
+impl Error for HourlyNotEnabled {}
+
+/// Bar chart of period totals, with the amortized series of `--amortization-delta` drawn over it as a line.
+fn generate_cost_trend_chart(cost_trend: &[CostTrendData], amortization: Option<&AmortizationDelta>, cli: &Cli, output_path: &str) -> Result<(), Box<dyn Error>> {
     if cost_trend.is_empty() {
         return Err("No data available to generate chart".into());
     }
 
     let root = BitMapBackend::new(output_path, (800, 600)).into_drawing_area();
     root.fill(&WHITE)?;
-    let months: Vec<String> = cost_trend.iter().map(|data| data.month.clone()).collect();
-    let costs: Vec<f64> = cost_trend.iter().map(|data| data.total_cost).collect();
+    let months: Vec<String> = cost_trend
+        .iter()
+        .map(|data| {
+            let label = periods::display_label(&data.month, &cli.granularity, cli.date_format.as_deref());
+            if data.is_partial { format!("{}*", label) } else { label }
+        })
+        .collect();
+    let amortized: Vec<(usize, f64)> = amortization.map_or_else(Vec::new, |delta| {
+        cost_trend
+            .iter()
+            .enumerate()
+            .filter_map(|(i, data)| delta.periods.iter().find(|p| p.period == data.month).map(|p| (i, p.amortized_cost)))
+            .collect()
+    });
+    let costs: Vec<f64> = cost_trend.iter().map(|data| data.total_cost).chain(amortized.iter().map(|(_, cost)| *cost)).collect();
     let max_cost = costs.iter().cloned().fold(0.0, f64::max).max(1.0);
     let num_months = months.len();
 
     let mut chart = ChartBuilder::on(&root)
-        .caption("Cost Trend Analysis", ("sans-serif", 40))
+        .caption(format!("{} {}", cli.granularity.adjective(), cli.breakdown().chart_title), ("sans-serif", 40))
         .x_label_area_size(40)
         .y_label_area_size(40)
         .margin(10)
@@ -654,10 +5464,649 @@ fn generate_cost_trend_chart(cost_trend: &[CostTrendData], output_path: &str) ->
     chart.draw_series(
         Histogram::vertical(&chart)
             .style(BLUE.filled())
-            .data(costs.iter().enumerate().map(|(i, cost)| (i, *cost))),
+            .data(cost_trend.iter().enumerate().filter(|(_, data)| !data.is_partial).map(|(i, data)| (i, data.total_cost))),
+    )?;
+    // The in-progress period is drawn faded so its lower total doesn't read as a drop.
+    chart.draw_series(
+        Histogram::vertical(&chart)
+            .style(BLUE.mix(0.35).filled())
+            .data(cost_trend.iter().enumerate().filter(|(_, data)| data.is_partial).map(|(i, data)| (i, data.total_cost))),
     )?;
+    if !amortized.is_empty() {
+        // A step across each bar, since bars span a period rather than sit on a point
+        chart
+            .draw_series(LineSeries::new(amortized.iter().flat_map(|(i, cost)| [(*i, *cost), (i + 1, *cost)]), RED.stroke_width(2)))?
+            .label("Amortized")
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], RED.stroke_width(2)));
+        chart.draw_series(Histogram::vertical(&chart).style(BLUE.filled()).data(std::iter::empty::<(usize, f64)>()))?
+            .label("Unblended")
+            .legend(|(x, y)| Rectangle::new([(x, y - 5), (x + 20, y + 5)], BLUE.filled()));
+        chart.configure_series_labels().background_style(WHITE.mix(0.8)).border_style(BLACK).draw()?;
+    }
 
     root.present()?;
     Ok(())
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory under the system temp directory.
+    pub fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("aws-cost-cli-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Held by tests that change environment variables, which every test in the process shares, and
+    /// while parsing, since `AWS_COST_CLI_*` variables supply options.
+    pub static ENV_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    /// Parses a command line, filling in the exclusive end date `run` derives from it.
+    pub fn cli(args: &[&str]) -> Cli {
+        let parsed = {
+            let _env = ENV_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            Cli::parse_from(std::iter::once("aws-cost-cli").chain(args.iter().copied()))
+        };
+        let mut cli = parsed;
+        let end = dates::query_end(dates::parse_date("end date", &cli.end_date).unwrap(), cli.inclusive_end);
+        cli.query_end_date = end.format("%Y-%m-%d").to_string();
+        cli
+    }
+
+    /// Costs of consecutive days from `first`.
+    pub fn daily(first: &str, costs: &[f64]) -> Vec<(String, f64)> {
+        let first = NaiveDate::parse_from_str(first, "%Y-%m-%d").unwrap();
+        costs
+            .iter()
+            .enumerate()
+            .map(|(index, cost)| ((first + chrono::Duration::days(index as i64)).format("%Y-%m-%d").to_string(), *cost))
+            .collect()
+    }
+
+    /// Costs of consecutive months from January 2024, keyed by period start as Cost Explorer returns them.
+    pub fn monthly(costs: &[f64]) -> Vec<(String, f64)> {
+        let january = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        costs
+            .iter()
+            .enumerate()
+            .map(|(index, cost)| ((january + Months::new(index as u32)).format("%Y-%m-%d").to_string(), *cost))
+            .collect()
+    }
+
+    /// Raw costs of one account from each service's cost per period.
+    pub fn account_costs(profile: &str, account_id: &str, services: &[(&str, Vec<(String, f64)>)]) -> AccountCosts {
+        let services: BTreeMap<String, BTreeMap<String, f64>> =
+            services.iter().map(|(service, costs)| (service.to_string(), costs.iter().cloned().collect())).collect();
+        let mut period_totals: BTreeMap<String, f64> = BTreeMap::new();
+        for (period, cost) in services.values().flatten() {
+            *period_totals.entry(period.clone()).or_insert(0.0) += cost;
+        }
+        AccountCosts {
+            profile: profile.to_string(),
+            account_id: account_id.to_string(),
+            account_name: format!("Account-{}", account_id),
+            ou_path: None,
+            account_tags: BTreeMap::new(),
+            original_account_name: None,
+            excluded: false,
+            period_totals,
+            service_period_totals: ServiceCosts::from_maps(services),
+            estimated_periods: BTreeSet::new(),
+            cached_periods: BTreeSet::new(),
+            top_resources: None,
+            metric_totals: BTreeMap::new(),
+            record_type_totals: None,
+            usage_samples: None,
+            compute_optimizer: None,
+            free_tier: None,
+        }
+    }
+
+    /// The report of `accounts` over the range of `cli`, trimmed to `--trend-window` as `run` does.
+    pub fn report(cli: &Cli, accounts: &[AccountCosts]) -> Report {
+        let start = NaiveDate::parse_from_str(&cli.start_date, "%Y-%m-%d").unwrap();
+        let end = NaiveDate::parse_from_str(&cli.query_end_date, "%Y-%m-%d").unwrap();
+        let cutoff = trend_cutoff(cli.trend_window, start, end);
+        let mut report = build_report(cli, &["prod".to_string(), "dev".to_string()], accounts, Vec::new(), cutoff);
+        report.metadata.generated_at = "2025-01-01T00:00:00+00:00".to_string();
+        report
+    }
+
+    /// Two accounts whose services tie on cost, so only the name orders them.
+    pub fn synthetic_accounts() -> Vec<AccountCosts> {
+        vec![
+            account_costs(
+                "prod",
+                "111111111111",
+                &[("Amazon Simple Storage Service", monthly(&[10.0, 20.0, 30.0])), ("AWS Lambda", monthly(&[30.0, 20.0, 10.0])), ("Amazon EC2", monthly(&[5.0, 5.0, 5.0]))],
+            ),
+            account_costs("dev", "222222222222", &[("AWS Glue", monthly(&[1.0, 2.0, 3.0])), ("Amazon EC2", monthly(&[3.0, 2.0, 1.0]))]),
+        ]
+    }
+
+    #[test]
+    fn report_json_is_byte_identical_across_runs() {
+        let cli = cli(&["--start-date", "2024-01-01", "--end-date", "2024-04-01"]);
+        let first = report(&cli, &synthetic_accounts());
+        // Accounts finish in a different order from one run to the next
+        let second = report(&cli, &synthetic_accounts().into_iter().rev().collect::<Vec<_>>());
+        let first = serde_json::to_string_pretty(&first.json(&cli)).unwrap();
+        assert_eq!(first, serde_json::to_string_pretty(&second.json(&cli)).unwrap());
+
+        let json: serde_json::Value = serde_json::from_str(&first).unwrap();
+        let profiles: Vec<&str> = json["accounts"].as_array().unwrap().iter().map(|account| account["profile"].as_str().unwrap()).collect();
+        assert_eq!(profiles, ["dev", "prod"]);
+        let services: Vec<&str> =
+            json["accounts"][1]["service_consumption"].as_array().unwrap().iter().map(|service| service["service"].as_str().unwrap()).collect();
+        assert_eq!(services, ["AWS Lambda", "Amazon Simple Storage Service", "Amazon EC2"]);
+        let months: Vec<&String> = json["unified_view"][0]["monthly_costs"].as_object().unwrap().keys().collect();
+        assert_eq!(months, ["2024-01-01", "2024-02-01", "2024-03-01"]);
+    }
+
+    #[tokio::test]
+    async fn unified_view_csv_totals_match_its_rows() {
+        let dir = temp_dir("unified-view");
+        let base = dir.join("report");
+        let cli = cli(&["--start-date", "2024-01-01", "--end-date", "2024-04-01", "--exclude-accounts", "333333333333", "--csv", base.to_str().unwrap()]);
+        let mut accounts = synthetic_accounts();
+        let mut excluded = account_costs("dev", "333333333333", &[("Amazon EC2", monthly(&[100.0, 100.0, 100.0]))]);
+        excluded.excluded = true;
+        accounts.push(excluded);
+        write_outputs(&cli, &["prod".to_string()], &report(&cli, &accounts)).await.unwrap();
+
+        let mut reader = csv::Reader::from_path(dir.join("report_unified_view.csv")).unwrap();
+        assert_eq!(reader.headers().unwrap(), vec!["Profile", "Account ID", "Account Name", "2024-01-01", "2024-02-01", "2024-03-01", "Total"]);
+        let rows: Vec<Vec<String>> = reader.records().map(|row| row.unwrap().iter().map(str::to_string).collect()).collect();
+        let (totals, accounts) = rows.split_last().unwrap();
+        assert_eq!(totals[0], "TOTAL");
+        // The excluded account is neither listed nor counted
+        assert_eq!(accounts.iter().map(|row| row[1].as_str()).collect::<Vec<_>>(), ["222222222222", "111111111111"]);
+        let cost = |cell: &String| cell.parse::<f64>().unwrap();
+        for row in accounts {
+            assert_eq!(cost(&row[6]), row[3..6].iter().map(cost).sum::<f64>());
+        }
+        for column in 3..=6 {
+            assert_eq!(cost(&totals[column]), accounts.iter().map(|row| cost(&row[column])).sum::<f64>());
+        }
+        assert_eq!(totals[6], "147.00");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn full_requested_range_is_reported_by_default() {
+        let cli = cli(&["--start-date", "2024-01-01", "--end-date", "2025-01-01"]);
+        let costs: Vec<f64> = (1..=12).map(|month| month as f64 * 10.0).collect();
+        let report = report(&cli, &[account_costs("prod", "111111111111", &[("Amazon EC2", monthly(&costs))])]);
+
+        assert_eq!(report.periods, monthly(&costs).into_iter().map(|(period, _)| period).collect::<Vec<_>>());
+        assert_eq!(report.unified_view[0].monthly_costs.len(), 12);
+        assert_eq!(report.accounts[0].service_consumption[0].monthly_costs.len(), 12);
+        assert_eq!(report.accounts[0].cost_trend.len(), 12);
+        assert_eq!(report.total_cost, 780.0);
+        assert_eq!(report.average_period_cost, report.total_cost / 12.0);
+        assert_eq!(report.accounts[0].average_monthly_cost, report.accounts[0].total_cost / 12.0);
+    }
+
+    #[test]
+    fn trend_window_trims_every_output_alike() {
+        let cli = cli(&["--start-date", "2024-01-01", "--end-date", "2025-01-01", "--trend-window", "6"]);
+        let costs: Vec<f64> = (1..=12).map(|month| month as f64 * 10.0).collect();
+        let report = report(&cli, &[account_costs("prod", "111111111111", &[("Amazon EC2", monthly(&costs))])]);
+
+        assert_eq!(report.periods.first().map(String::as_str), Some("2024-07-01"));
+        assert_eq!(report.periods.len(), 6);
+        let window_total: f64 = costs[6..].iter().sum();
+        assert_eq!(report.total_cost, window_total);
+        assert_eq!(report.average_period_cost, window_total / 6.0);
+        let service = &report.accounts[0].service_consumption[0];
+        assert_eq!(service.total_cost, window_total);
+        assert_eq!(service.monthly_costs.len(), 6);
+        // The whole range is still reported, separately
+        assert_eq!(service.full_range_total_cost, 780.0);
+        assert_eq!(report.metadata.trend_window_start.as_deref(), Some("2024-07-01"));
+    }
+
+    #[test]
+    fn inclusive_end_reports_the_last_day_under_the_requested_date() {
+        let cli = cli(&["--start-date", "2024-02-27", "--end-date", "2024-02-29", "--inclusive-end", "--granularity", "daily"]);
+        assert_eq!(cli.query_end_date, "2024-03-01");
+        let report = report(&cli, &[account_costs("prod", "111111111111", &[("Amazon EC2", daily("2024-02-27", &[1.0, 2.0, 4.0]))])]);
+
+        assert_eq!(report.periods, ["2024-02-27", "2024-02-28", "2024-02-29"]);
+        assert_eq!(report.total_cost, 7.0);
+        assert_eq!(report.metadata.end_date, "2024-02-29");
+        assert!(report.metadata.inclusive_end);
+    }
+
+    #[test]
+    fn weekly_trend_compares_weeks_across_the_year_boundary() {
+        let cli = cli(&["--start-date", "2024-12-23", "--end-date", "2025-01-13", "--granularity", "weekly"]);
+        let days: Vec<f64> = [1.0, 2.0, 3.0].iter().flat_map(|cost| [*cost; 7]).collect();
+        let report = report(&cli, &[account_costs("prod", "111111111111", &[("Amazon EC2", daily("2024-12-23", &days))])]);
+
+        assert_eq!(report.periods, ["2024-W52", "2025-W01", "2025-W02"]);
+        let trend: Vec<(f64, PeriodChange)> = report.accounts[0].cost_trend.iter().map(|week| (week.total_cost, week.mom_change_percent)).collect();
+        assert_eq!(trend, [(7.0, PeriodChange::NoData), (14.0, PeriodChange::Percent(100.0)), (21.0, PeriodChange::Percent(50.0))]);
+    }
+
+    async fn run_args(args: &[&str]) -> Result<(), RunFailure> {
+        run(std::iter::once("aws-cost-cli").chain(args.iter().copied()).map(std::ffi::OsString::from).collect()).await
+    }
+
+    #[tokio::test]
+    async fn invalid_arguments_exit_with_code_5() {
+        for args in [
+            &["--granularity", "fortnightly"][..],
+            &["--start-date", "2025-1-1"],
+            &["--start-date", "2025-06-02", "--end-date", "2025-06-01"],
+            &["--start-date", "2025-01-01", "--end-date", "2999-01-01"],
+            &["--month", "2025-13"],
+        ] {
+            let failure = run_args(args).await.unwrap_err();
+            assert!(matches!(failure, RunFailure::InvalidArguments(_)), "{:?}: {}", args, failure);
+            assert_eq!(failure.exit_code(), ExitCode::from(5));
+        }
+    }
+
+    #[tokio::test]
+    async fn unconfirmed_long_hourly_range_exits_with_code_5() {
+        let today = chrono::Utc::now().date_naive();
+        let start = (today - chrono::Duration::days(40)).format("%Y-%m-%d").to_string();
+        let end = today.format("%Y-%m-%d").to_string();
+        let failure = run_args(&["--granularity", "hourly", "--start-date", &start, "--end-date", &end, "--quiet"]).await.unwrap_err();
+        assert!(matches!(failure, RunFailure::HourlyRangeUnconfirmed(_)), "{}", failure);
+        assert_eq!(failure.exit_code(), ExitCode::from(5));
+    }
+
+    #[test]
+    fn no_profiles_exits_with_code_6() {
+        let _env = ENV_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let dir = temp_dir("no-profiles");
+        std::env::set_var("AWS_CONFIG_FILE", dir.join("config"));
+        std::env::set_var("AWS_SHARED_CREDENTIALS_FILE", dir.join("credentials"));
+        let credentials = ["AWS_ACCESS_KEY_ID", "AWS_CONTAINER_CREDENTIALS_RELATIVE_URI", "AWS_CONTAINER_CREDENTIALS_FULL_URI", "AWS_WEB_IDENTITY_TOKEN_FILE"];
+        let saved: Vec<_> = credentials.iter().map(|var| (var, std::env::var_os(var))).collect();
+        for var in credentials {
+            std::env::remove_var(var);
+        }
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(run_args(&["--start-date", "2025-01-01", "--end-date", "2025-02-01", "--quiet"]));
+
+        std::env::remove_var("AWS_CONFIG_FILE");
+        std::env::remove_var("AWS_SHARED_CREDENTIALS_FILE");
+        for (var, value) in saved {
+            if let Some(value) = value {
+                std::env::set_var(var, value);
+            }
+        }
+        let failure = result.unwrap_err();
+        assert!(matches!(failure, RunFailure::NoProfiles), "{}", failure);
+        assert_eq!(failure.exit_code(), ExitCode::from(6));
+    }
+
+    #[test]
+    fn single_month_reports_have_no_cost_spread() {
+        let cli = cli(&["--start-date", "2024-01-01", "--end-date", "2024-02-01"]);
+        let report = report(&cli, &[account_costs("prod", "111111111111", &[("Amazon EC2", monthly(&[12.0]))]), account_costs("dev", "222222222222", &[("Amazon EC2", monthly(&[0.0]))])]);
+        for account in &report.accounts {
+            let stats = account.stats.as_ref().unwrap();
+            assert_eq!(stats.std_dev, None, "{}", account.account_id);
+            assert_eq!(stats.coefficient_of_variation, None);
+        }
+    }
+
+    #[test]
+    fn period_changes_keep_their_sign_and_small_values() {
+        assert_eq!(PeriodChange::between(None, 5.0), PeriodChange::NoData);
+        assert_eq!(PeriodChange::between(Some(0.0), 5.0), PeriodChange::New);
+        assert_eq!(PeriodChange::between(Some(0.0), 0.0), PeriodChange::Percent(0.0));
+        assert_eq!(PeriodChange::between(Some(300.0), 200.0).display(3), "-33.333");
+        // Used to be rounded to 0 before it was displayed
+        let small = PeriodChange::between(Some(10_000.0), 10_004.0);
+        assert_eq!(small.display(1), "0.0");
+        assert_eq!(small.display(2), "0.04");
+        assert_eq!(PeriodChange::New.display(2), "new");
+        assert_eq!(PeriodChange::NoData.display(2), "");
+    }
+
+    #[tokio::test]
+    async fn percent_decimals_apply_to_csv_percentages() {
+        let dir = temp_dir("percent-decimals");
+        let base = dir.join("report");
+        let cli = cli(&["--start-date", "2024-01-01", "--end-date", "2024-04-01", "--percent-decimals", "3", "--csv", base.to_str().unwrap()]);
+        let accounts = [account_costs("prod", "111111111111", &[("Amazon EC2", monthly(&[300.0, 200.0, 200.2])), ("AWS Lambda", monthly(&[1.0, 0.0, 0.0]))])];
+        let report = report(&cli, &accounts);
+        write_outputs(&cli, &["prod".to_string()], &report).await.unwrap();
+
+        let rows = |name: &str| -> Vec<Vec<String>> {
+            let mut reader = csv::Reader::from_path(dir.join(format!("report_{}_profile_prod_account_111111111111.csv", name))).unwrap();
+            reader.records().map(|row| row.unwrap().iter().map(str::to_string).collect()).collect()
+        };
+        let trend: Vec<String> = rows("trend").into_iter().map(|row| row[2].clone()).collect();
+        assert_eq!(trend, ["", "-33.555", "0.100"]);
+        let percents: Vec<String> = rows("service_summary").into_iter().map(|row| row.last().unwrap().clone()).collect();
+        assert_eq!(percents, ["99.857", "0.143"]);
+        // JSON keeps full precision whatever the table shows
+        let PeriodChange::Percent(percent) = report.accounts[0].cost_trend[2].mom_change_percent else { panic!() };
+        assert!((percent - 0.1).abs() < 1e-9 && percent != 0.1, "{}", percent);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn service_totals_are_the_sum_of_their_displayed_months() {
+        let dir = temp_dir("service-totals");
+        let base = dir.join("report");
+        let cli = cli(&["--start-date", "2024-01-01", "--end-date", "2024-07-01", "--trend-window", "3", "--csv", base.to_str().unwrap()]);
+        let accounts = [account_costs(
+            "prod",
+            "111111111111",
+            &[("Amazon EC2", monthly(&[100.0, 100.0, 100.0, 1.25, 2.5, 3.75])), ("AWS Lambda", monthly(&[50.0, 0.0, 0.0, 0.5, 0.25, 0.0]))],
+        )];
+        let report = report(&cli, &accounts);
+        write_outputs(&cli, &["prod".to_string()], &report).await.unwrap();
+
+        for service in &report.accounts[0].service_consumption {
+            assert_eq!(service.total_cost, service.monthly_costs.values().sum::<f64>(), "{}", service.service);
+            assert!(service.monthly_costs.keys().all(|period| report.periods.contains(period)), "{:?}", service.monthly_costs);
+        }
+        let mut reader = csv::Reader::from_path(dir.join("report_service_summary_profile_prod_account_111111111111.csv")).unwrap();
+        let rows: Vec<Vec<String>> = reader.records().map(|row| row.unwrap().iter().map(str::to_string).collect()).collect();
+        let cost = |cell: &String| cell.parse::<f64>().unwrap();
+        for row in &rows {
+            let (months, totals) = row[1..].split_at(3);
+            assert_eq!(cost(&totals[0]), months.iter().map(cost).sum::<f64>(), "{:?}", row);
+        }
+        assert_eq!(rows.iter().map(|row| cost(&row[5])).sum::<f64>(), 100.0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn listing(profile: &str, listed: Option<&[&str]>, own_account: Option<&str>) -> AccountListing {
+        AccountListing {
+            profile: profile.to_string(),
+            listed: listed.map(|ids| {
+                ids.iter().map(|id| (id.to_string(), aws_sdk_organizations::types::Account::builder().id(*id).build())).collect()
+            }),
+            own_account: own_account.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn each_account_id_is_queried_by_one_profile() {
+        let ids = ["111111111111".to_string(), "222222222222".to_string()];
+        // Both profiles list the shared account; only the second sees 222222222222
+        let listings = [listing("a", Some(&["111111111111"]), None), listing("b", Some(&["111111111111", "222222222222"]), None)];
+        let owners = account_id_owners(&ids, &listings);
+        assert_eq!(owners["111111111111"], "a");
+        assert_eq!(owners["222222222222"], "b");
+    }
+
+    #[test]
+    fn account_ids_no_profile_lists_go_to_the_profile_signed_in_to_them() {
+        let ids = ["111111111111".to_string(), "222222222222".to_string(), "333333333333".to_string()];
+        let listings = [
+            listing("org", Some(&["111111111111"]), None),
+            listing("member", None, Some("444444444444")),
+            listing("standalone", None, Some("222222222222")),
+        ];
+        let owners = account_id_owners(&ids, &listings);
+        assert_eq!(owners["111111111111"], "org");
+        assert_eq!(owners["222222222222"], "standalone");
+        // Unknown to everyone that listed: the first profile that couldn't list accounts
+        assert_eq!(owners["333333333333"], "member");
+    }
+
+    #[test]
+    fn account_ids_outside_every_listed_organization_are_skipped() {
+        let ids = ["999999999999".to_string()];
+        let listings = [listing("a", Some(&["111111111111"]), None), listing("b", Some(&[]), None)];
+        assert!(account_id_owners(&ids, &listings).is_empty());
+    }
+
+    /// A management profile and a member profile that both reach 222222222222, and a second
+    /// management profile that also lists 111111111111.
+    fn overlapping_profiles() -> Vec<ProfileAccounts> {
+        let entry = |profile: &str, own_account: bool, ids: &[&str]| ProfileAccounts {
+            profile: profile.to_string(),
+            config: SdkConfig::builder().build(),
+            accounts: ids.iter().map(|id| aws_sdk_organizations::types::Account::builder().id(*id).name(format!("Account-{}", id)).build()).collect(),
+            own_account,
+            ou_paths: HashMap::new(),
+            account_tags: HashMap::new(),
+            original_names: HashMap::new(),
+            also_via: HashMap::new(),
+        };
+        vec![
+            entry("management", false, &["111111111111", "222222222222"]),
+            entry("member", true, &["222222222222"]),
+            entry("other-management", false, &["111111111111", "333333333333"]),
+        ]
+    }
+
+    fn owners(discovered: &[ProfileAccounts]) -> Vec<(String, String)> {
+        let mut owners: Vec<(String, String)> = discovered
+            .iter()
+            .flat_map(|entry| entry.accounts.iter().map(|account| (account.id.clone().unwrap(), entry.profile.clone())))
+            .collect();
+        owners.sort();
+        owners
+    }
+
+    /// Queries each deduplicated account, every account costing 100 a month.
+    fn global_total(discovered: &[ProfileAccounts]) -> f64 {
+        let cli = cli(&["--start-date", "2024-01-01", "--end-date", "2024-03-01"]);
+        let accounts: Vec<AccountCosts> = discovered
+            .iter()
+            .flat_map(|entry| entry.accounts.iter().map(|account| account_costs(&entry.profile, account.id().unwrap(), &[("Amazon EC2", monthly(&[100.0, 100.0]))])))
+            .collect();
+        report(&cli, &accounts).total_cost
+    }
+
+    #[test]
+    fn first_strategy_keeps_the_first_profile() {
+        // Without deduplication two accounts would be counted twice
+        assert_eq!(global_total(&overlapping_profiles()), 1000.0);
+        let deduped = dedupe_accounts(overlapping_profiles(), &DedupeStrategy::First).unwrap();
+        let expected = [("111111111111", "management"), ("222222222222", "management"), ("333333333333", "other-management")];
+        assert_eq!(owners(&deduped), expected.map(|(id, profile)| (id.to_string(), profile.to_string())));
+        // The member profile reached nothing else, so it is dropped
+        assert!(deduped.iter().all(|entry| entry.profile != "member"));
+        assert_eq!(deduped[0].also_via["222222222222"], ["member"]);
+        assert_eq!(deduped[0].also_via["111111111111"], ["other-management"]);
+        assert_eq!(global_total(&deduped), 600.0);
+    }
+
+    #[test]
+    fn prefer_member_strategy_keeps_the_member_profile() {
+        let deduped = dedupe_accounts(overlapping_profiles(), &DedupeStrategy::PreferMember).unwrap();
+        let expected = [("111111111111", "management"), ("222222222222", "member"), ("333333333333", "other-management")];
+        assert_eq!(owners(&deduped), expected.map(|(id, profile)| (id.to_string(), profile.to_string())));
+        let member = deduped.iter().find(|entry| entry.profile == "member").unwrap();
+        assert_eq!(member.also_via["222222222222"], ["management"]);
+        assert_eq!(global_total(&deduped), 600.0);
+    }
+
+    #[test]
+    fn error_strategy_names_both_profiles() {
+        let Err(error) = dedupe_accounts(overlapping_profiles(), &DedupeStrategy::Error) else {
+            panic!("overlapping accounts were accepted");
+        };
+        assert!(error.to_string().starts_with("Account 222222222222 is reachable via profiles management and member"), "{}", error);
+    }
+
+    #[test]
+    fn distinct_profiles_are_left_alone() {
+        let mut discovered = overlapping_profiles();
+        discovered.truncate(1);
+        let deduped = dedupe_accounts(discovered, &DedupeStrategy::Error).unwrap();
+        assert_eq!(deduped[0].accounts.len(), 2);
+        assert!(deduped[0].also_via.is_empty());
+    }
+
+    /// Parses `args` with the `AWS_COST_CLI_*` variables in `vars` set.
+    fn parse_with_env(vars: &[(&str, &str)], args: &[&str]) -> Result<(Cli, clap::ArgMatches), clap::Error> {
+        let _env = ENV_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        for (var, value) in vars {
+            std::env::set_var(var, value);
+        }
+        let matches = Cli::command().try_get_matches_from(std::iter::once("aws-cost-cli").chain(args.iter().copied()));
+        for (var, _) in vars {
+            std::env::remove_var(var);
+        }
+        let matches = matches?;
+        Ok((Cli::from_arg_matches(&matches)?, matches))
+    }
+
+    #[test]
+    fn list_options_from_the_environment_are_split_on_commas() {
+        let (cli, matches) = parse_with_env(
+            &[
+                ("AWS_COST_CLI_PROFILES", "prod,dev"),
+                ("AWS_COST_CLI_ACCOUNT_ID", "111111111111, 222222222222"),
+                ("AWS_COST_CLI_EXCLUDE_ACCOUNTS", "333333333333"),
+                ("AWS_COST_CLI_UNIT_RATES", "NatGateway-Bytes,EBS:VolumeUsage.gp3"),
+                ("AWS_COST_CLI_SHOW_ACCOUNT_TAGS", "team,env"),
+                ("AWS_COST_CLI_COMPARE_METRICS", "unblended,amortized"),
+            ],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(cli.profiles.unwrap(), ["prod", "dev"]);
+        assert_eq!(cli.account_id.unwrap(), ["111111111111", "222222222222"]);
+        assert_eq!(cli.exclude_accounts.unwrap(), ["333333333333"]);
+        assert_eq!(cli.unit_rates.unwrap(), ["NatGateway-Bytes", "EBS:VolumeUsage.gp3"]);
+        assert_eq!(cli.show_account_tags.unwrap(), ["team", "env"]);
+        assert_eq!(cli.compare_metrics.unwrap(), [CostMetric::Unblended, CostMetric::Amortized]);
+        assert_eq!(matches.value_source("profiles"), Some(ValueSource::EnvVariable));
+    }
+
+    #[test]
+    fn command_line_lists_replace_those_from_the_environment() {
+        let (cli, matches) = parse_with_env(&[("AWS_COST_CLI_PROFILES", "prod,dev")], &["--profiles", "ops"]).unwrap();
+        assert_eq!(cli.profiles.unwrap(), ["ops"]);
+        assert_eq!(matches.value_source("profiles"), Some(ValueSource::CommandLine));
+    }
+
+    #[test]
+    fn every_option_can_be_set_from_the_environment() {
+        for arg in Cli::command().get_arguments() {
+            let Some(long) = arg.get_long() else {
+                continue;
+            };
+            if matches!(long, "help" | "version" | "print-config" | "list-presets") {
+                continue;
+            }
+            let expected = format!("AWS_COST_CLI_{}", long.to_uppercase().replace('-', "_"));
+            assert_eq!(arg.get_env().and_then(|env| env.to_str()), Some(expected.as_str()), "--{}", long);
+        }
+        let (cli, matches) = parse_with_env(&[("AWS_COST_CLI_INCLUDE_SUSPENDED", "true")], &[]).unwrap();
+        assert!(cli.include_suspended);
+        assert_eq!(matches.value_source("include_suspended"), Some(ValueSource::EnvVariable));
+    }
+
+    #[test]
+    fn each_environment_list_item_is_validated() {
+        let error = parse_with_env(&[("AWS_COST_CLI_ACCOUNT_ID", "111111111111,12345")], &[]).unwrap_err();
+        assert_eq!(error.kind(), clap::error::ErrorKind::ValueValidation);
+        assert!(error.to_string().contains("12345"), "{}", error);
+    }
+
+    #[test]
+    fn large_daily_report_builds_quickly() {
+        let cli = cli(&["--start-date", "2024-01-01", "--end-date", "2025-01-01", "--granularity", "daily"]);
+        let services: Vec<String> = (0..50).map(|index| format!("Service {:02}", index)).collect();
+        let days = daily("2024-01-01", &[1.0; 366]);
+        let accounts: Vec<AccountCosts> = (0..100)
+            .map(|index| {
+                let columns: Vec<(&str, Vec<(String, f64)>)> = services.iter().map(|service| (service.as_str(), days.clone())).collect();
+                account_costs("prod", &format!("{:012}", index), &columns)
+            })
+            .collect();
+        // Each account keeps one period index shared by its 50 service columns
+        assert!(accounts.iter().all(|account| account.service_period_totals.periods().len() == 366));
+
+        let started = std::time::Instant::now();
+        let report = report(&cli, &accounts);
+        let elapsed = started.elapsed();
+
+        assert_eq!(report.periods.len(), 366);
+        assert_eq!(report.unified_view.len(), 100);
+        assert_eq!(report.total_cost, 100.0 * 366.0 * 50.0);
+        assert!(report.global_period_totals.values().all(|total| *total == 5000.0));
+        assert!(elapsed < std::time::Duration::from_secs(30), "building the report took {:?}", elapsed);
+    }
+
+    /// The unified view section of the tables rendered with `--layout layout`.
+    fn unified_view(layout: &str) -> String {
+        let cli = cli(&["--start-date", "2024-01-01", "--end-date", "2024-04-01", "--layout", layout]);
+        let mut out = pager::ReportBuffer::plain();
+        render_tables(&cli, &report(&cli, &synthetic_accounts()), None, &mut out).unwrap();
+        let text = out.text();
+        text[..text.find("\nCost Trend Analysis").unwrap()].to_string()
+    }
+
+    #[test]
+    fn wide_and_narrow_layouts_match_their_snapshots() {
+        assert_eq!(unified_view("wide"), include_str!("snapshots/unified_view_wide.txt"));
+        assert_eq!(unified_view("narrow"), include_str!("snapshots/unified_view_narrow.txt"));
+    }
+
+    #[test]
+    fn ec2_preset_rolls_instance_types_up_by_family() {
+        let mut cli = cli(&["--start-date", "2024-01-01", "--end-date", "2024-04-01"]);
+        cli.preset = Some(Preset::Ec2);
+        let account = account_costs(
+            "prod",
+            "111111111111",
+            &[
+                ("m5.large", monthly(&[10.0, 10.0, 10.0])),
+                ("m5.xlarge", monthly(&[20.0, 20.0, 35.0])),
+                ("c7g.xlarge, team$web", monthly(&[0.0, 40.0, 20.0])),
+                ("NoInstanceType", monthly(&[5.0, 0.0, 0.0])),
+                ("t3.micro", monthly(&[0.0, 0.0, 0.0])),
+            ],
+        );
+        let report = report(&cli, &[account]);
+
+        let families = report.accounts[0].instance_families.as_ref().unwrap();
+        let rolled_up: Vec<(&str, f64)> = families.iter().map(|family| (family.family.as_str(), family.total_cost)).collect();
+        // Largest first; families without cost are left out
+        assert_eq!(rolled_up, [("m5", 105.0), ("c7g", 60.0), ("NoInstanceType", 5.0)]);
+        assert_eq!(families[0].monthly_costs.values().copied().collect::<Vec<_>>(), [30.0, 30.0, 45.0]);
+        assert_eq!(families[1].monthly_costs.keys().map(String::as_str).collect::<Vec<_>>(), ["2024-02-01", "2024-03-01"]);
+        assert_eq!(families[0].latest_change_percent, PeriodChange::Percent(50.0));
+        assert_eq!(families.iter().map(|family| family.percent_of_total).sum::<f64>().round(), 100.0);
+
+        cli.preset = None;
+        assert!(crate::tests::report(&cli, &synthetic_accounts()).accounts[0].instance_families.is_none());
+    }
+
+    #[test]
+    fn skipped_summary_counts_accounts_and_profiles_apart() {
+        let error = |profile: &str, account_id: Option<&str>| ReportError {
+            profile: profile.to_string(),
+            account_id: account_id.map(str::to_string),
+            phase: if account_id.is_some() { "cost_query" } else { "discovery" }.to_string(),
+            error: "AccessDenied".to_string(),
+            retriable: false,
+        };
+        let cli = cli(&["--start-date", "2024-01-01", "--end-date", "2024-04-01"]);
+        let mut report = report(&cli, &synthetic_accounts());
+        report.errors = vec![error("prod", Some("333333333333")), error("prod", Some("444444444444")), error("audit", None)];
+        let mut out = pager::ReportBuffer::plain();
+        render_tables(&cli, &report, None, &mut out).unwrap();
+
+        let text = out.text();
+        let summary = &text[text.find("\n2 accounts").unwrap()..];
+        assert_eq!(
+            summary,
+            "\n2 accounts and 1 profiles skipped:\n  - Profile prod Account 333333333333 (cost_query): AccessDenied\n  - Profile prod Account 444444444444 (cost_query): AccessDenied\n  - Profile audit (discovery): AccessDenied\n"
+        );
+        assert_eq!(skipped_caption(&report.errors[..2]), "2 accounts");
+        assert_eq!(skipped_caption(&report.errors[2..]), "1 profiles");
+    }
+}
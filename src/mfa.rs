@@ -0,0 +1,139 @@
+use crate::{load_profile_config, profiles, Cli};
+use aws_sdk_sts::config::Credentials;
+use aws_sdk_sts::Client as StsClient;
+use dialoguer::Input;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::IsTerminal;
+use std::sync::LazyLock;
+use std::time::SystemTime;
+use tokio::sync::Mutex;
+
+/// Session name for roles assumed after MFA.
+const ROLE_SESSION_NAME: &str = "aws-cost-cli";
+
+/// MFA-backed credentials established during this run: per MFA device (the `GetSessionToken` session,
+/// so each device is prompted for once) and per profile. Held across the prompt, so concurrent
+/// pre-flight checks wait for one another instead of prompting at the same time.
+#[derive(Default)]
+struct Sessions {
+    by_serial: HashMap<String, Credentials>,
+    by_profile: HashMap<String, Credentials>,
+}
+
+static SESSIONS: LazyLock<Mutex<Sessions>> = LazyLock::new(Default::default);
+
+/// Credentials established for `profile` with MFA earlier in the run.
+pub async fn cached(profile: &str) -> Option<Credentials> {
+    SESSIONS.lock().await.by_profile.get(profile).cloned()
+}
+
+/// Whether an STS error says the call needs MFA, e.g. a policy conditioned on `aws:MultiFactorAuthPresent`.
+pub fn is_mfa_error(message: &str) -> bool {
+    let message = message.to_lowercase();
+    message.contains("multifactorauth") || message.contains("mfa")
+}
+
+/// MFA device configured for a profile through `mfa_serial`.
+pub fn configured_serial(profile: &str) -> Option<String> {
+    profiles::profile_setting(profile, "mfa_serial")
+}
+
+/// Gets MFA session credentials for `profile` from the device `serial`, prompting for a token code
+/// (or using `--mfa-code`). A profile with `role_arn` assumes the role from its `source_profile` with
+/// the MFA session; otherwise the session is for the profile's own credentials.
+pub async fn establish(cli: &Cli, profile: &str, serial: &str) -> Result<(), Box<dyn Error>> {
+    let role_arn = profiles::profile_setting(profile, "role_arn");
+    let base_profile = match &role_arn {
+        Some(_) => profiles::profile_setting(profile, "source_profile")
+            .ok_or_else(|| format!("profile {} sets role_arn and mfa_serial but no source_profile", profile))?,
+        None => profile.to_string(),
+    };
+    // Loaded before taking the lock, since loading a profile consults the cache
+    let base_config = load_profile_config(&base_profile, cli.region.as_deref()).await;
+    let mut sessions = SESSIONS.lock().await;
+    if sessions.by_profile.contains_key(profile) {
+        return Ok(());
+    }
+
+    let session = match sessions.by_serial.get(serial) {
+        Some(session) => session.clone(),
+        None => {
+            let code = token_code(cli, profile, serial)?;
+            let response = StsClient::new(&base_config)
+                .get_session_token()
+                .serial_number(serial)
+                .token_code(code)
+                .send()
+                .await
+                .map_err(|e| format!("MFA GetSessionToken failed for profile {}: {}", profile, e))?;
+            let session = response
+                .credentials
+                .map(|c| to_credentials(c, "mfa-session"))
+                .ok_or_else(|| format!("No credentials returned by GetSessionToken for profile {}", profile))?;
+            sessions.by_serial.insert(serial.to_string(), session.clone());
+            session
+        }
+    };
+
+    let credentials = match role_arn {
+        Some(role_arn) => {
+            let config = base_config
+                .into_builder()
+                .credentials_provider(aws_sdk_sts::config::SharedCredentialsProvider::new(session))
+                .build();
+            let response = StsClient::new(&config)
+                .assume_role()
+                .role_arn(&role_arn)
+                .role_session_name(ROLE_SESSION_NAME)
+                .send()
+                .await
+                .map_err(|e| format!("AssumeRole {} with MFA failed for profile {}: {}", role_arn, profile, e))?;
+            response
+                .credentials
+                .map(|c| to_credentials(c, "mfa-assume-role"))
+                .ok_or_else(|| format!("No credentials returned by AssumeRole for profile {}", profile))?
+        }
+        None => session,
+    };
+    sessions.by_profile.insert(profile.to_string(), credentials);
+    Ok(())
+}
+
+/// `--mfa-code`, or a code read from the terminal.
+fn token_code(cli: &Cli, profile: &str, serial: &str) -> Result<String, Box<dyn Error>> {
+    if let Some(code) = &cli.mfa_code {
+        return Ok(code.clone());
+    }
+    if !(std::io::stdin().is_terminal() && std::io::stderr().is_terminal()) {
+        return Err(format!("profile {} requires MFA; pass --mfa-code when not running in a terminal", profile).into());
+    }
+    let code: String = Input::new()
+        .with_prompt(format!("MFA code for {} ({})", profile, serial))
+        .validate_with(|input: &String| {
+            if input.len() == 6 && input.chars().all(|c| c.is_ascii_digit()) {
+                Ok(())
+            } else {
+                Err("expected a 6-digit code")
+            }
+        })
+        .interact_text()
+        .map_err(|e| format!("MFA prompt aborted: {}", e))?;
+    Ok(code)
+}
+
+fn to_credentials(credentials: aws_sdk_sts::types::Credentials, provider: &'static str) -> Credentials {
+    Credentials::new(
+        credentials.access_key_id,
+        credentials.secret_access_key,
+        Some(credentials.session_token),
+        SystemTime::try_from(credentials.expiration).ok(),
+        provider,
+    )
+}
+
+/// The first MFA device of the IAM user behind `config`'s credentials, for profiles without `mfa_serial`.
+pub async fn user_device(config: &aws_config::SdkConfig) -> Option<String> {
+    let response = aws_sdk_iam::Client::new(config).list_mfa_devices().send().await.ok()?;
+    response.mfa_devices.into_iter().next().map(|device| device.serial_number)
+}
@@ -0,0 +1,207 @@
+use crate::partition::Partition;
+use crate::{load_discovery_config, Cli, Report};
+use aws_config::{Region, SdkConfig};
+use aws_sdk_costoptimizationhub::error::ProvideErrorMetadata;
+use aws_sdk_costoptimizationhub::types::Recommendation;
+use aws_sdk_costoptimizationhub::Client as OptimizationHubClient;
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Shown for profiles whose account hasn't enrolled in Cost Optimization Hub.
+pub const NOT_ENABLED: &str = "not enabled; the management account can enroll itself and its member accounts in the \
+     Billing and Cost Management console, and recommendations appear about a day later";
+
+/// One recommended change and what it would save.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HubRecommendation {
+    pub profile: String,
+    pub account_id: String,
+    pub account_name: String,
+    /// Resource ARN, or its ID when the recommendation has no ARN (e.g. Savings Plans purchases).
+    pub resource: String,
+    /// `stop`, `rightsize`, `upgrade`, `purchase commitment`, ...
+    pub action_type: String,
+    /// Summary of the current configuration, such as `m5.2xlarge`.
+    pub current: Option<String>,
+    pub recommended: Option<String>,
+    pub estimated_monthly_savings: f64,
+}
+
+/// Recommendations of one action type across the reported accounts.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ActionSavings {
+    pub action_type: String,
+    pub recommendations: usize,
+    pub estimated_monthly_savings: f64,
+}
+
+/// Recommendations for one reported account.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccountSavings {
+    pub profile: String,
+    pub account_id: String,
+    pub account_name: String,
+    pub recommendations: usize,
+    pub estimated_monthly_savings: f64,
+}
+
+/// Why a profile contributed no recommendations.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProfileNote {
+    pub profile: String,
+    pub note: String,
+}
+
+/// `--optimization-hub` savings for the reported accounts.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OptimizationHub {
+    pub estimated_monthly_savings: f64,
+    pub recommendations: usize,
+    /// Highest savings first.
+    pub by_action_type: Vec<ActionSavings>,
+    /// Highest savings first.
+    pub by_account: Vec<AccountSavings>,
+    /// The `--optimization-hub-top` recommendations with the highest savings.
+    pub top_recommendations: Vec<HubRecommendation>,
+    pub notes: Vec<ProfileNote>,
+}
+
+/// Cost Optimization Hub client for the endpoint region of the profile's partition.
+fn hub_client(config: &SdkConfig) -> OptimizationHubClient {
+    let builder = aws_sdk_costoptimizationhub::config::Builder::from(config).region(Region::new(Partition::of(config).endpoint_region()));
+    OptimizationHubClient::from_conf(builder.build())
+}
+
+/// Fetches every profile's recommendations concurrently and summarizes those for the report's
+/// accounts. Profiles reaching the same organization only count each recommendation once.
+pub async fn collect(cli: &Cli, profiles: &[String], report: &Report) -> OptimizationHub {
+    let fetched = join_all(profiles.iter().map(|profile| fetch_profile(cli, profile))).await;
+    let accounts: HashMap<&str, (&str, &str)> = report
+        .accounts
+        .iter()
+        .map(|account| (account.account_id.as_str(), (account.profile.as_str(), account.account_name.as_str())))
+        .collect();
+
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut recommendations: Vec<HubRecommendation> = Vec::new();
+    let mut notes = Vec::new();
+    for (profile, result) in profiles.iter().zip(fetched) {
+        let fetched = match result {
+            Ok(fetched) => fetched,
+            Err(note) => {
+                notes.push(ProfileNote { profile: profile.clone(), note });
+                continue;
+            }
+        };
+        for recommendation in fetched {
+            let Some(&(account_profile, account_name)) = recommendation.account_id().and_then(|id| accounts.get(id)) else {
+                continue;
+            };
+            if let Some(id) = recommendation.recommendation_id() {
+                if !seen.insert(id.to_string()) {
+                    continue;
+                }
+            }
+            recommendations.push(HubRecommendation {
+                profile: account_profile.to_string(),
+                account_id: recommendation.account_id().unwrap_or_default().to_string(),
+                account_name: account_name.to_string(),
+                resource: [recommendation.resource_arn(), recommendation.resource_id()].into_iter().flatten().find(|id| !id.is_empty()).unwrap_or("N/A").to_string(),
+                action_type: action_label(recommendation.action_type().unwrap_or_default()),
+                current: recommendation.current_resource_summary().or(recommendation.current_resource_type()).map(str::to_string),
+                recommended: recommendation.recommended_resource_summary().or(recommendation.recommended_resource_type()).map(str::to_string),
+                estimated_monthly_savings: recommendation.estimated_monthly_savings().unwrap_or(0.0),
+            });
+        }
+    }
+    summarize(recommendations, notes, cli.optimization_hub_top)
+}
+
+fn summarize(mut recommendations: Vec<HubRecommendation>, notes: Vec<ProfileNote>, top: usize) -> OptimizationHub {
+    let mut by_action_type: BTreeMap<&str, ActionSavings> = BTreeMap::new();
+    let mut by_account: BTreeMap<(&str, &str), AccountSavings> = BTreeMap::new();
+    for recommendation in &recommendations {
+        let action = by_action_type.entry(&recommendation.action_type).or_insert_with(|| ActionSavings {
+            action_type: recommendation.action_type.clone(),
+            recommendations: 0,
+            estimated_monthly_savings: 0.0,
+        });
+        action.recommendations += 1;
+        action.estimated_monthly_savings += recommendation.estimated_monthly_savings;
+        let account = by_account.entry((&recommendation.profile, &recommendation.account_id)).or_insert_with(|| AccountSavings {
+            profile: recommendation.profile.clone(),
+            account_id: recommendation.account_id.clone(),
+            account_name: recommendation.account_name.clone(),
+            recommendations: 0,
+            estimated_monthly_savings: 0.0,
+        });
+        account.recommendations += 1;
+        account.estimated_monthly_savings += recommendation.estimated_monthly_savings;
+    }
+    let mut by_action_type: Vec<ActionSavings> = by_action_type.into_values().collect();
+    by_action_type.sort_by(|a, b| b.estimated_monthly_savings.total_cmp(&a.estimated_monthly_savings));
+    let mut by_account: Vec<AccountSavings> = by_account.into_values().collect();
+    by_account.sort_by(|a, b| b.estimated_monthly_savings.total_cmp(&a.estimated_monthly_savings));
+
+    let estimated_monthly_savings = recommendations.iter().fold(0.0, |total, recommendation| total + recommendation.estimated_monthly_savings);
+    let count = recommendations.len();
+    recommendations.sort_by(|a, b| b.estimated_monthly_savings.total_cmp(&a.estimated_monthly_savings).then_with(|| a.resource.cmp(&b.resource)));
+    recommendations.truncate(top);
+    OptimizationHub {
+        estimated_monthly_savings,
+        recommendations: count,
+        by_action_type,
+        by_account,
+        top_recommendations: recommendations,
+        notes,
+    }
+}
+
+/// All of a profile's current recommendations; with the default `includeAllRecommendations`, only the
+/// one with the highest savings per resource, so savings add up. Errors come back as a one-line note.
+async fn fetch_profile(cli: &Cli, profile: &str) -> Result<Vec<Recommendation>, String> {
+    let config = load_discovery_config(cli, profile).await;
+    let client = hub_client(&config);
+    let mut recommendations = Vec::new();
+    let mut next_token = None;
+    loop {
+        let response = client.list_recommendations().set_next_token(next_token).send().await.map_err(|e| {
+            let (code, message) = (e.code().unwrap_or_default(), e.message().unwrap_or_default());
+            if is_not_enabled(code, message) {
+                NOT_ENABLED.to_string()
+            } else if code.is_empty() {
+                format!("unavailable - {}", e)
+            } else {
+                format!("unavailable - {}: {}", code, message)
+            }
+        })?;
+        recommendations.extend(response.items.unwrap_or_default());
+        next_token = response.next_token;
+        if next_token.is_none() {
+            return Ok(recommendations);
+        }
+    }
+}
+
+/// Accounts that haven't enrolled get an access denied or validation error that mentions enrollment.
+fn is_not_enabled(code: &str, message: &str) -> bool {
+    let message = message.to_lowercase();
+    matches!(code, "AccessDeniedException" | "ValidationException") && (message.contains("enroll") || message.contains("opt"))
+}
+
+/// Cost Optimization Hub action types in plain words; Savings Plans and Reserved Instance purchases
+/// are both a purchase commitment.
+fn action_label(action_type: &str) -> String {
+    match action_type {
+        "Stop" => "stop".to_string(),
+        "Rightsize" => "rightsize".to_string(),
+        "Upgrade" => "upgrade".to_string(),
+        "PurchaseSavingsPlans" | "PurchaseReservedInstances" => "purchase commitment".to_string(),
+        "MigrateToGraviton" => "migrate to Graviton".to_string(),
+        "Delete" => "delete".to_string(),
+        "ScaleIn" => "scale in".to_string(),
+        "" => "unknown".to_string(),
+        other => other.to_string(),
+    }
+}
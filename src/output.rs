@@ -0,0 +1,216 @@
+use crate::upload::{is_s3_uri, S3Uploader};
+use csv::{Writer, WriterBuilder};
+use flate2::{write::GzEncoder, Compression};
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+/// File destination for exported reports, optionally gzip-compressed as it is written. Data goes to
+/// a temporary file next to `path` that replaces it on `finish`, so an interrupted export never
+/// leaves a truncated file behind.
+pub struct OutputSink {
+    writer: SinkWriter,
+    temp_path: String,
+    path: String,
+}
+
+enum SinkWriter {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+}
+
+impl OutputSink {
+    pub fn create(path: &str, compress: bool) -> io::Result<Self> {
+        let temp_path = format!("{}.tmp-{}", path, std::process::id());
+        let file = BufWriter::new(File::create(&temp_path)?);
+        let writer = if compress {
+            SinkWriter::Gzip(GzEncoder::new(file, Compression::default()))
+        } else {
+            SinkWriter::Plain(file)
+        };
+        Ok(OutputSink { writer, temp_path, path: path.to_string() })
+    }
+
+    /// Flushes buffered data, writes the gzip trailer if any, and moves the file into place.
+    pub fn finish(self) -> io::Result<()> {
+        let file = match self.writer {
+            SinkWriter::Plain(w) => w.into_inner().map_err(|e| e.into_error())?,
+            SinkWriter::Gzip(w) => w.finish()?.into_inner().map_err(|e| e.into_error())?,
+        };
+        file.sync_all()?;
+        std::fs::rename(&self.temp_path, &self.path)
+    }
+}
+
+impl Write for OutputSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.writer {
+            SinkWriter::Plain(w) => w.write(buf),
+            SinkWriter::Gzip(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.writer {
+            SinkWriter::Plain(w) => w.flush(),
+            SinkWriter::Gzip(w) => w.flush(),
+        }
+    }
+}
+
+/// Where the `--csv` exports go and how they are encoded.
+pub struct CsvExports<'a> {
+    /// `--csv` without its extension; each export is named `<base>_<name>.<extension>`.
+    pub base: String,
+    pub extension: &'static str,
+    pub compress: bool,
+    pub delimiter: u8,
+    pub uploader: Option<&'a S3Uploader>,
+}
+
+impl CsvExports<'_> {
+    /// Writes one export: the header, then the rows `write_rows` streams into the writer. The file
+    /// is then moved into place (or uploaded) and logged as `Exported <description> to <path>`.
+    pub async fn write<H, T>(
+        &self,
+        name: &str,
+        description: &str,
+        headers: H,
+        write_rows: impl FnOnce(&mut Writer<OutputSink>) -> csv::Result<()>,
+    ) -> Result<(), Box<dyn Error>>
+    where
+        H: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        let path = format!("{}_{}.{}", self.base, name, self.extension);
+        let local_path = local_output_path(&path, self.uploader);
+        let mut writer = csv_writer(&local_path, self.compress, self.delimiter)?;
+        writer.write_record(headers)?;
+        write_rows(&mut writer)?;
+        finish_csv(writer)?;
+        let note = publish_output(&local_path, &path, self.compress, self.uploader).await?;
+        info!("Exported {} to {}{}", description, path, note);
+        Ok(())
+    }
+}
+
+fn csv_writer(path: &str, compress: bool, delimiter: u8) -> io::Result<Writer<OutputSink>> {
+    Ok(WriterBuilder::new().delimiter(delimiter).from_writer(OutputSink::create(path, compress)?))
+}
+
+fn finish_csv(writer: Writer<OutputSink>) -> Result<(), Box<dyn Error>> {
+    writer.into_inner().map_err(|e| e.into_error())?.finish()?;
+    Ok(())
+}
+
+/// Where to write `target` locally: the path itself, or a staging file when it is an S3 URI.
+pub fn local_output_path(target: &str, uploader: Option<&S3Uploader>) -> String {
+    match uploader {
+        Some(uploader) if is_s3_uri(target) => uploader.staging_path(target),
+        _ => target.to_string(),
+    }
+}
+
+/// Uploads a finished output file when its target is an S3 URI, returning the size note for the success message.
+pub async fn publish_output(local_path: &str, target: &str, compress: bool, uploader: Option<&S3Uploader>) -> Result<String, Box<dyn Error>> {
+    let note = compressed_size_note(local_path, compress);
+    if let Some(uploader) = uploader.filter(|_| is_s3_uri(target)) {
+        uploader.upload(local_path, target).await?;
+    }
+    Ok(note)
+}
+
+fn compressed_size_note(path: &str, compress: bool) -> String {
+    if !compress {
+        return String::new();
+    }
+    match std::fs::metadata(path) {
+        Ok(meta) => format!(" ({} bytes compressed)", meta.len()),
+        Err(_) => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::temp_dir;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    fn exports(dir: &std::path::Path, compress: bool) -> CsvExports<'static> {
+        CsvExports {
+            base: dir.join("report").to_string_lossy().into_owned(),
+            extension: if compress { "csv.gz" } else { "csv" },
+            compress,
+            delimiter: b',',
+            uploader: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn compressed_export_decompresses_to_the_rows_written() {
+        let dir = temp_dir("gzip-export");
+        let exports = exports(&dir, true);
+        exports
+            .write("costs", "costs", ["Month", "Cost"], |writer| {
+                for month in 1..=12 {
+                    writer.write_record([format!("2024-{:02}", month), format!("{:.2}", month as f64 * 1.5)])?;
+                }
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+        let path = dir.join("report_costs.csv.gz");
+        let mut csv = String::new();
+        GzDecoder::new(File::open(&path).unwrap()).read_to_string(&mut csv).unwrap();
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        assert_eq!(reader.headers().unwrap(), vec!["Month", "Cost"]);
+        let rows: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(rows.len(), 12);
+        assert_eq!(&rows[11][0], "2024-12");
+        assert_eq!(&rows[11][1], "18.00");
+        // Nothing is left behind but the finished file
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Streams the JSON report into a sink as `write_outputs` does, returning the file's text.
+    fn stream_json(path: &std::path::Path, compress: bool, value: &impl serde::Serialize) -> String {
+        let mut sink = BufWriter::new(OutputSink::create(&path.to_string_lossy(), compress).unwrap());
+        serde_json::to_writer_pretty(&mut sink, value).unwrap();
+        sink.into_inner().map_err(|e| e.into_error()).unwrap().finish().unwrap();
+        let mut text = String::new();
+        match compress {
+            true => GzDecoder::new(File::open(path).unwrap()).read_to_string(&mut text).unwrap(),
+            false => File::open(path).unwrap().read_to_string(&mut text).unwrap(),
+        };
+        text
+    }
+
+    #[test]
+    fn streamed_json_report_matches_the_buffered_document() {
+        let dir = temp_dir("json-report");
+        let cli = crate::tests::cli(&["--start-date", "2024-01-01", "--end-date", "2024-04-01"]);
+        let report = crate::tests::report(&cli, &crate::tests::synthetic_accounts());
+        // Reports used to be built as one `serde_json::Value` document, then written out
+        let buffered = serde_json::to_string_pretty(&serde_json::to_value(report.json(&cli)).unwrap()).unwrap();
+        assert_eq!(stream_json(&dir.join("report.json"), false, &report.json(&cli)), buffered);
+        assert_eq!(stream_json(&dir.join("report.json.gz"), true, &report.json(&cli)), buffered);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn uncompressed_export_uses_the_delimiter() {
+        let dir = temp_dir("tsv-export");
+        let exports = CsvExports { delimiter: b'\t', extension: "tsv", ..exports(&dir, false) };
+        exports
+            .write("summary", "summary", ["Metric", "Value"], |writer| writer.write_record(["Total Cost (USD)", "10.00"]))
+            .await
+            .unwrap();
+
+        let tsv = std::fs::read_to_string(dir.join("report_summary.tsv")).unwrap();
+        assert_eq!(tsv, "Metric\tValue\nTotal Cost (USD)\t10.00\n");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,98 @@
+use prettytable::Table;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+use term::terminfo::TerminfoTerminal;
+
+const DEFAULT_PAGER: &str = "less -R";
+
+/// Rendered report text. Table styles are kept as ANSI escapes when `colorize` is set.
+pub struct ReportBuffer {
+    buf: Vec<u8>,
+    colorize: bool,
+}
+
+/// Columns of the terminal stdout is attached to, or `None` when it isn't a terminal.
+pub fn terminal_width() -> Option<usize> {
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+    ratatui::crossterm::terminal::size().ok().map(|(columns, _)| columns as usize)
+}
+
+impl ReportBuffer {
+    /// A buffer that colorizes tables the same way `printstd()` would for the current stdout.
+    pub fn for_stdout() -> Self {
+        ReportBuffer { buf: Vec::new(), colorize: io::stdout().is_terminal() }
+    }
+
+    pub fn table(&mut self, table: &Table) -> io::Result<()> {
+        if self.colorize {
+            if let Some(mut terminal) = TerminfoTerminal::new(&mut self.buf) {
+                table.print_term(&mut terminal)?;
+                return Ok(());
+            }
+        }
+        table.print(&mut self.buf)?;
+        Ok(())
+    }
+
+    /// A buffer that never colorizes, for comparing rendered output in tests.
+    #[cfg(test)]
+    pub fn plain() -> Self {
+        ReportBuffer { buf: Vec::new(), colorize: false }
+    }
+
+    #[cfg(test)]
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.buf).into_owned()
+    }
+
+    fn line_count(&self) -> usize {
+        self.buf.iter().filter(|b| **b == b'\n').count()
+    }
+}
+
+impl Write for ReportBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes the rendered report to stdout, through `$PAGER` when stdout is a terminal the report doesn't fit on.
+pub fn show(report: ReportBuffer, use_pager: bool) -> io::Result<()> {
+    if use_pager && io::stdout().is_terminal() {
+        let fits = ratatui::crossterm::terminal::size().is_ok_and(|(_, rows)| report.line_count() < rows as usize);
+        if !fits {
+            match page(&report.buf) {
+                Ok(()) => return Ok(()),
+                Err(e) => warn!("Warning: Could not start pager ({}). Printing directly.", e),
+            }
+        }
+    }
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(&report.buf)?;
+    stdout.flush()
+}
+
+fn page(content: &[u8]) -> io::Result<()> {
+    let pager = std::env::var("PAGER")
+        .ok()
+        .filter(|p| !p.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_PAGER.to_string());
+    let mut parts = pager.split_whitespace();
+    let program = parts.next().unwrap_or("less");
+    let mut child = Command::new(program).args(parts).stdin(Stdio::piped()).spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // Quitting the pager before the end closes its stdin; that is not an error.
+        match stdin.write_all(content) {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return Err(e),
+            _ => {}
+        }
+    }
+    child.wait()?;
+    Ok(())
+}
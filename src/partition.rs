@@ -0,0 +1,46 @@
+use aws_config::{Region, SdkConfig};
+use clap::ValueEnum;
+
+/// AWS partition a profile's credentials belong to. Each has its own endpoints, and Cost Explorer and
+/// Organizations are only served from one region per partition.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Partition {
+    Aws,
+    AwsUsGov,
+    AwsCn,
+}
+
+impl Partition {
+    pub fn from_region(region: &str) -> Self {
+        if region.starts_with("us-gov-") {
+            Partition::AwsUsGov
+        } else if region.starts_with("cn-") {
+            Partition::AwsCn
+        } else {
+            Partition::Aws
+        }
+    }
+
+    /// Region of the partition's Cost Explorer and Organizations endpoints, also used for STS when a
+    /// profile has no region of its own in the partition.
+    pub fn endpoint_region(&self) -> &'static str {
+        match self {
+            Partition::Aws => "us-east-1",
+            Partition::AwsUsGov => "us-gov-west-1",
+            Partition::AwsCn => "cn-northwest-1",
+        }
+    }
+
+    /// The partition of a loaded profile configuration.
+    pub fn of(config: &SdkConfig) -> Self {
+        config.region().map_or(Partition::Aws, |region| Partition::from_region(region.as_ref()))
+    }
+}
+
+/// Moves `config` into `partition` when its region is elsewhere (e.g. the us-east-1 fallback for a GovCloud profile).
+pub fn in_partition(config: SdkConfig, partition: Partition) -> SdkConfig {
+    if Partition::of(&config) == partition {
+        return config;
+    }
+    config.into_builder().region(Region::new(partition.endpoint_region())).build()
+}
@@ -0,0 +1,317 @@
+use crate::unit_rates::UsageSamples;
+use crate::{AccountCosts, GranularityOption};
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, Weekday};
+use chrono_tz::Tz;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Maps Cost Explorer period starts onto the report's periods, for granularities that are
+/// aggregated client-side from a finer one (weekly from daily, quarterly from monthly).
+/// Native granularities pass through.
+pub struct PeriodBuckets {
+    granularity: GranularityOption,
+    week_start: Weekday,
+    /// First month (1-12) of the fiscal year; quarters are calendar quarters when unset.
+    fiscal_year_start: Option<u32>,
+    /// Hourly period starts are shown in this zone instead of UTC.
+    timezone: Option<Tz>,
+    range_start: NaiveDate,
+    range_end: NaiveDate,
+}
+
+impl PeriodBuckets {
+    /// `range_end` is exclusive, as sent to Cost Explorer.
+    pub fn new(
+        granularity: GranularityOption,
+        week_start: Weekday,
+        fiscal_year_start: Option<u32>,
+        timezone: Option<Tz>,
+        range_start: NaiveDate,
+        range_end: NaiveDate,
+    ) -> Self {
+        PeriodBuckets { granularity, week_start, fiscal_year_start, timezone, range_start, range_end }
+    }
+
+    pub fn label(&self, period: &str) -> String {
+        let day = period.get(..10).and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok());
+        match (&self.granularity, day) {
+            (GranularityOption::Hourly, _) => match (self.timezone, DateTime::parse_from_rfc3339(period)) {
+                (Some(tz), Ok(start)) => start.with_timezone(&tz).format("%Y-%m-%dT%H:%M:%S%:z").to_string(),
+                _ => period.to_string(),
+            },
+            (GranularityOption::Weekly, Some(day)) => self.week_label(day),
+            (GranularityOption::Quarterly, Some(day)) => self.quarter_label(day),
+            _ => period.to_string(),
+        }
+    }
+
+    /// `2025-W23`, numbered by ISO week. Sunday-start weeks take the number of the Monday that follows.
+    /// Weeks cut off by either end of the range are labelled `(partial)`.
+    fn week_label(&self, day: NaiveDate) -> String {
+        let first_day = day.week(self.week_start).first_day();
+        let numbered_by = if self.week_start == Weekday::Sun { first_day + Duration::days(1) } else { first_day };
+        let iso = numbered_by.iso_week();
+        let partial = first_day < self.range_start || first_day + Duration::days(7) > self.range_end;
+        format!("{}-W{:02}{}", iso.year(), iso.week(), if partial { " (partial)" } else { "" })
+    }
+
+    /// `2025-Q2` for calendar quarters, or `FY26-Q1` for fiscal quarters, where a fiscal year is named
+    /// after the calendar year it ends in. `(partial)` when the range doesn't cover the whole quarter.
+    fn quarter_label(&self, day: NaiveDate) -> String {
+        let year_start = fiscal_year_start_date(day, self.fiscal_year_start.unwrap_or(1));
+        let quarter = (day.month0() + 12 - year_start.month0()) % 12 / 3;
+        let first_day = year_start + Months::new(quarter * 3);
+        let partial = first_day < self.range_start || first_day + Months::new(3) > self.range_end;
+        let suffix = if partial { " (partial)" } else { "" };
+        match self.fiscal_year_start {
+            Some(_) => {
+                let ending_year = (year_start + Months::new(11)).year();
+                format!("FY{:02}-Q{}{}", ending_year % 100, quarter + 1, suffix)
+            }
+            None => format!("{}-Q{}{}", day.year(), quarter + 1, suffix),
+        }
+    }
+
+    fn rollup(&self, totals: &BTreeMap<String, f64>) -> BTreeMap<String, f64> {
+        let mut rolled: BTreeMap<String, f64> = BTreeMap::new();
+        for (period, cost) in totals {
+            *rolled.entry(self.label(period)).or_insert(0.0) += cost;
+        }
+        rolled
+    }
+
+    pub fn apply(&self, costs: &AccountCosts) -> AccountCosts {
+        AccountCosts {
+            profile: costs.profile.clone(),
+            account_id: costs.account_id.clone(),
+            account_name: costs.account_name.clone(),
+            ou_path: costs.ou_path.clone(),
+            account_tags: costs.account_tags.clone(),
+            original_account_name: costs.original_account_name.clone(),
+            excluded: costs.excluded,
+            period_totals: self.rollup(&costs.period_totals),
+            service_period_totals: costs.service_period_totals.relabel(|period| Some(self.label(period))),
+            estimated_periods: costs.estimated_periods.iter().map(|period| self.label(period)).collect(),
+            cached_periods: costs.cached_periods.iter().map(|period| self.label(period)).collect(),
+            top_resources: costs.top_resources.clone(),
+            compute_optimizer: costs.compute_optimizer.clone(),
+            free_tier: costs.free_tier.clone(),
+            metric_totals: costs.metric_totals.iter().map(|(metric, totals)| (metric.clone(), self.rollup(totals))).collect(),
+            record_type_totals: costs.record_type_totals.as_ref().map(|totals| totals.relabel(|period| Some(self.label(period)))),
+            usage_samples: costs.usage_samples.as_ref().map(|samples| {
+                samples
+                    .iter()
+                    .map(|(usage_type, usage)| {
+                        let rolled = UsageSamples {
+                            costs: self.rollup(&usage.costs),
+                            quantities: self.rollup(&usage.quantities),
+                            units: usage.units.clone(),
+                        };
+                        (usage_type.clone(), rolled)
+                    })
+                    .collect()
+            }),
+        }
+    }
+}
+
+/// First day of the fiscal year containing `day`, for a fiscal year starting in month `start_month` (1-12).
+pub fn fiscal_year_start_date(day: NaiveDate, start_month: u32) -> NaiveDate {
+    let year = if day.month() >= start_month { day.year() } else { day.year() - 1 };
+    NaiveDate::from_ymd_opt(year, start_month, 1).expect("fiscal year start month is validated to 1-12")
+}
+
+/// Re-buckets hourly totals (keyed by UTC period start) into calendar days in `tz`, keeping only
+/// days in `[start, end)`. Each hour lands on exactly one local day, so 23- and 25-hour DST days sum correctly.
+pub fn local_days(totals: &BTreeMap<String, f64>, tz: Tz, start: &str, end: &str) -> BTreeMap<String, f64> {
+    let mut days: BTreeMap<String, f64> = BTreeMap::new();
+    for (period, cost) in totals {
+        let Some(day) = local_day(period, tz) else {
+            continue;
+        };
+        if day.as_str() >= start && day.as_str() < end {
+            *days.entry(day).or_insert(0.0) += cost;
+        }
+    }
+    days
+}
+
+/// The calendar day in `tz` of an hourly period start such as `2025-06-01T04:00:00Z`.
+pub fn local_day(period: &str, tz: Tz) -> Option<String> {
+    let hour = DateTime::parse_from_rfc3339(period).ok()?;
+    Some(hour.with_timezone(&tz).format("%Y-%m-%d").to_string())
+}
+
+/// Label of a report period in table headers and chart axes. Daily, monthly and hourly periods are
+/// formatted with `format` (a strftime pattern), defaulting to `Mar 03`, `Mar 2025` and `Mar 03 14:00`;
+/// quarters become `Q1 25` (or `Q1 FY26`) and weeks keep their label. `iso` keeps every label as-is.
+/// chrono's month names are English regardless of locale, so output is stable across machines.
+pub fn display_label(period: &str, granularity: &GranularityOption, format: Option<&str>) -> String {
+    if format == Some("iso") {
+        return period.to_string();
+    }
+    let mut label = String::new();
+    let formatted = match granularity {
+        GranularityOption::Hourly => DateTime::parse_from_rfc3339(period)
+            .map(|start| write!(label, "{}", start.format(format.unwrap_or("%b %d %H:%M")))),
+        GranularityOption::Daily => NaiveDate::parse_from_str(period, "%Y-%m-%d").map(|day| write!(label, "{}", day.format(format.unwrap_or("%b %d")))),
+        GranularityOption::Monthly => NaiveDate::parse_from_str(period, "%Y-%m-%d").map(|day| write!(label, "{}", day.format(format.unwrap_or("%b %Y")))),
+        GranularityOption::Quarterly => return quarter_display(period),
+        GranularityOption::Weekly => return period.to_string(),
+    };
+    // A pattern asking for fields the period doesn't have (e.g. `%H` on a day) falls back to the period
+    match formatted {
+        Ok(Ok(())) => label,
+        _ => period.to_string(),
+    }
+}
+
+/// `2025-Q2 (partial)` → `Q2 25 (partial)`, `FY26-Q1` → `Q1 FY26`.
+fn quarter_display(period: &str) -> String {
+    let Some((year, rest)) = period.split_once("-Q") else {
+        return period.to_string();
+    };
+    let (quarter, suffix) = rest.split_at(rest.find(' ').unwrap_or(rest.len()));
+    if year.starts_with("FY") {
+        format!("Q{} {}{}", quarter, year, suffix)
+    } else {
+        format!("Q{} {}{}", quarter, year.get(2..).unwrap_or(year), suffix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{account_costs, daily, monthly};
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn quarterly(fiscal_year_start: Option<u32>, start: &str, end: &str) -> PeriodBuckets {
+        PeriodBuckets::new(GranularityOption::Quarterly, Weekday::Mon, fiscal_year_start, None, date(start), date(end))
+    }
+
+    fn weekly(week_start: Weekday, start: &str, end: &str) -> PeriodBuckets {
+        PeriodBuckets::new(GranularityOption::Weekly, week_start, None, None, date(start), date(end))
+    }
+
+    #[test]
+    fn weeks_are_numbered_by_iso_week_across_the_year_boundary() {
+        let buckets = weekly(Weekday::Mon, "2024-12-23", "2025-01-13");
+        assert_eq!(buckets.label("2024-12-23"), "2024-W52");
+        assert_eq!(buckets.label("2024-12-29"), "2024-W52");
+        // 2024-12-30 starts ISO week 1 of 2025
+        assert_eq!(buckets.label("2024-12-31"), "2025-W01");
+        assert_eq!(buckets.label("2025-01-05"), "2025-W01");
+        assert_eq!(buckets.label("2025-01-06"), "2025-W02");
+    }
+
+    #[test]
+    fn sunday_weeks_take_the_number_of_the_following_monday() {
+        let buckets = weekly(Weekday::Sun, "2024-12-22", "2025-01-12");
+        assert_eq!(buckets.label("2024-12-28"), "2024-W52");
+        assert_eq!(buckets.label("2024-12-29"), "2025-W01");
+        assert_eq!(buckets.label("2025-01-04"), "2025-W01");
+        assert_eq!(buckets.label("2025-01-05"), "2025-W02");
+    }
+
+    #[test]
+    fn weeks_cut_off_by_the_range_are_partial() {
+        let buckets = weekly(Weekday::Mon, "2025-01-01", "2025-01-10");
+        assert_eq!(buckets.label("2025-01-01"), "2025-W01 (partial)");
+        assert_eq!(buckets.label("2025-01-06"), "2025-W02 (partial)");
+    }
+
+    #[test]
+    fn apply_sums_days_into_their_weeks() {
+        let buckets = weekly(Weekday::Mon, "2024-12-23", "2025-01-06");
+        let costs = account_costs("prod", "111111111111", &[("Amazon EC2", daily("2024-12-23", &[1.0; 14])), ("AWS Lambda", daily("2024-12-30", &[0.5; 7]))]);
+        let weekly = buckets.apply(&costs);
+
+        assert_eq!(weekly.period_totals, BTreeMap::from([("2024-W52".to_string(), 7.0), ("2025-W01".to_string(), 10.5)]));
+        let services = weekly.service_period_totals.to_maps();
+        assert_eq!(services["Amazon EC2"]["2025-W01"], 7.0);
+        assert_eq!(services["AWS Lambda"]["2025-W01"], 3.5);
+    }
+
+    #[test]
+    fn months_roll_up_into_calendar_quarters() {
+        let buckets = quarterly(None, "2024-01-01", "2024-07-01");
+        let costs = account_costs("prod", "111111111111", &[("Amazon EC2", monthly(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]))]);
+        let quarters = buckets.apply(&costs);
+
+        assert_eq!(quarters.period_totals, BTreeMap::from([("2024-Q1".to_string(), 6.0), ("2024-Q2".to_string(), 15.0)]));
+        assert_eq!(quarters.service_period_totals.to_maps()["Amazon EC2"]["2024-Q2"], 15.0);
+    }
+
+    #[test]
+    fn quarters_the_range_doesnt_cover_are_partial() {
+        let buckets = quarterly(None, "2024-02-01", "2024-11-01");
+        assert_eq!(buckets.label("2024-02-01"), "2024-Q1 (partial)");
+        assert_eq!(buckets.label("2024-06-01"), "2024-Q2");
+        assert_eq!(buckets.label("2024-09-01"), "2024-Q3");
+        assert_eq!(buckets.label("2024-10-01"), "2024-Q4 (partial)");
+    }
+
+    #[test]
+    fn quarters_are_displayed_quarter_first() {
+        assert_eq!(display_label("2025-Q2", &GranularityOption::Quarterly, None), "Q2 25");
+        assert_eq!(display_label("2025-Q2 (partial)", &GranularityOption::Quarterly, None), "Q2 25 (partial)");
+        assert_eq!(display_label("FY26-Q1", &GranularityOption::Quarterly, None), "Q1 FY26");
+        assert_eq!(display_label("2025-Q2", &GranularityOption::Quarterly, Some("iso")), "2025-Q2");
+    }
+
+    #[test]
+    fn fiscal_years_roll_over_in_their_start_month() {
+        assert_eq!(fiscal_year_start_date(date("2025-09-30"), 10), date("2024-10-01"));
+        assert_eq!(fiscal_year_start_date(date("2025-10-01"), 10), date("2025-10-01"));
+        assert_eq!(fiscal_year_start_date(date("2025-01-15"), 4), date("2024-04-01"));
+        assert_eq!(fiscal_year_start_date(date("2025-12-31"), 1), date("2025-01-01"));
+    }
+
+    #[test]
+    fn fiscal_quarters_are_named_after_the_year_they_end_in() {
+        let buckets = quarterly(Some(10), "2024-10-01", "2025-12-01");
+        assert_eq!(buckets.label("2024-10-01"), "FY25-Q1");
+        assert_eq!(buckets.label("2025-01-01"), "FY25-Q2");
+        assert_eq!(buckets.label("2025-09-01"), "FY25-Q4");
+        assert_eq!(buckets.label("2025-11-01"), "FY26-Q1 (partial)");
+
+        // A fiscal year starting in January ends in the calendar year it starts in
+        assert_eq!(quarterly(Some(1), "2025-01-01", "2025-04-01").label("2025-02-01"), "FY25-Q1");
+    }
+
+    /// One dollar for every UTC hour in `[start, end)`.
+    fn hourly(start: &str, end: &str) -> BTreeMap<String, f64> {
+        let mut hour = date(start).and_hms_opt(0, 0, 0).unwrap();
+        let end = date(end).and_hms_opt(0, 0, 0).unwrap();
+        let mut totals = BTreeMap::new();
+        while hour < end {
+            totals.insert(hour.format("%Y-%m-%dT%H:%M:%SZ").to_string(), 1.0);
+            hour += Duration::hours(1);
+        }
+        totals
+    }
+
+    #[test]
+    fn dst_days_sum_their_23_and_25_hours() {
+        let tz: Tz = "Europe/Berlin".parse().unwrap();
+        let spring = local_days(&hourly("2025-03-28", "2025-04-02"), tz, "2025-03-29", "2025-04-01");
+        assert_eq!(spring, BTreeMap::from([("2025-03-29".to_string(), 24.0), ("2025-03-30".to_string(), 23.0), ("2025-03-31".to_string(), 24.0)]));
+
+        let autumn = local_days(&hourly("2025-10-24", "2025-10-29"), tz, "2025-10-25", "2025-10-28");
+        assert_eq!(autumn, BTreeMap::from([("2025-10-25".to_string(), 24.0), ("2025-10-26".to_string(), 25.0), ("2025-10-27".to_string(), 24.0)]));
+    }
+
+    #[test]
+    fn hours_land_on_their_local_day() {
+        let tz: Tz = "America/New_York".parse().unwrap();
+        assert_eq!(local_day("2025-06-01T03:00:00Z", tz).as_deref(), Some("2025-05-31"));
+        assert_eq!(local_day("2025-06-01T04:00:00Z", tz).as_deref(), Some("2025-06-01"));
+        assert_eq!(local_day("not an hour", tz), None);
+
+        let buckets = PeriodBuckets::new(GranularityOption::Hourly, Weekday::Mon, None, Some(tz), date("2025-06-01"), date("2025-06-02"));
+        assert_eq!(buckets.label("2025-06-01T04:00:00Z"), "2025-06-01T00:00:00-04:00");
+    }
+}
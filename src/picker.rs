@@ -0,0 +1,72 @@
+use crate::ProfileAccounts;
+use dialoguer::theme::ColorfulTheme;
+use dialoguer::MultiSelect;
+use std::error::Error;
+use std::io::IsTerminal;
+
+/// `--interactive` prompts on the terminal, so refuse to run without one rather than hang or read garbage.
+pub fn ensure_terminal() -> Result<(), Box<dyn Error>> {
+    if std::io::stdin().is_terminal() && std::io::stderr().is_terminal() {
+        Ok(())
+    } else {
+        Err("--interactive requires an interactive terminal; pass --profiles and --account-id instead".into())
+    }
+}
+
+fn multi_select(prompt: &str, items: &[String]) -> Result<Vec<usize>, Box<dyn Error>> {
+    let selection = MultiSelect::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .items(items)
+        .defaults(&vec![true; items.len()])
+        .interact()
+        .map_err(|e| format!("Selection aborted: {}", e))?;
+    if selection.is_empty() {
+        return Err("Nothing selected".into());
+    }
+    Ok(selection)
+}
+
+/// Lets the user choose which of the discovered profiles to report on.
+pub fn pick_profiles(available: &[String]) -> Result<Vec<String>, Box<dyn Error>> {
+    let selection = multi_select("Profiles (space to toggle, enter to confirm)", available)?;
+    Ok(selection.into_iter().map(|i| available[i].clone()).collect())
+}
+
+/// Lets the user choose accounts across all profiles; profiles left without accounts are dropped.
+pub fn pick_accounts(discovered: Vec<ProfileAccounts>) -> Result<Vec<ProfileAccounts>, Box<dyn Error>> {
+    let items: Vec<String> = discovered
+        .iter()
+        .flat_map(|p| {
+            p.accounts.iter().map(move |a| {
+                format!(
+                    "{} {} ({})",
+                    a.id.as_deref().unwrap_or_default(),
+                    a.name.as_deref().unwrap_or("N/A"),
+                    p.profile
+                )
+            })
+        })
+        .collect();
+    let selected = multi_select("Accounts (space to toggle, enter to confirm)", &items)?;
+
+    let mut index = 0;
+    let mut picked = Vec::new();
+    for mut profile_accounts in discovered {
+        let accounts = std::mem::take(&mut profile_accounts.accounts);
+        for account in accounts {
+            if selected.contains(&index) {
+                profile_accounts.accounts.push(account);
+            }
+            index += 1;
+        }
+        if !profile_accounts.accounts.is_empty() {
+            picked.push(profile_accounts);
+        }
+    }
+    Ok(picked)
+}
+
+/// The `--profiles`/`--account-id` flags that reproduce the interactive selection non-interactively.
+pub fn equivalent_flags(profiles: &[String], account_ids: &[String]) -> String {
+    format!("--profiles {} --account-id {}", profiles.join(","), account_ids.join(","))
+}
@@ -0,0 +1,125 @@
+use crate::api_usage::COST_PER_REQUEST;
+use crate::{cost_filter, cost_group_by, Cli, ProfileAccounts};
+use aws_sdk_costexplorer::types::{Expression, GroupDefinition};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+/// The Cost Explorer requests a run would make, printed by `--plan` instead of querying costs.
+#[derive(Serialize, Debug)]
+pub struct QueryPlan {
+    /// Intervals requested for every account (hourly ranges are split into several).
+    pub intervals: Vec<(String, String)>,
+    pub granularity: String,
+    pub metric: String,
+    /// `GroupBy` of every request, in API form (`{"Type": "DIMENSION", "Key": "SERVICE"}`).
+    pub group_by: Vec<Value>,
+    pub profiles: Vec<ProfilePlan>,
+    pub estimated_requests: usize,
+    pub estimated_cost_usd: f64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ProfilePlan {
+    pub profile: String,
+    pub accounts: Vec<PlannedAccount>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PlannedAccount {
+    pub account_id: String,
+    pub account_name: String,
+    /// Queried only for the excluded-accounts summary (`--exclude-accounts`).
+    pub excluded: bool,
+    /// `Filter` expression of the account's requests, as the API receives it.
+    pub filter: Value,
+}
+
+/// Plans the queries for the discovered accounts without calling Cost Explorer.
+pub fn build(cli: &Cli, discovered: &[ProfileAccounts], intervals: Vec<(String, String)>) -> QueryPlan {
+    let profiles: Vec<ProfilePlan> = discovered
+        .iter()
+        .map(|p| ProfilePlan {
+            profile: p.profile.clone(),
+            accounts: p
+                .accounts
+                .iter()
+                .map(|account| {
+                    let account_id = account.id.clone().unwrap_or_default();
+                    PlannedAccount {
+                        excluded: cli.is_excluded(&account_id),
+                        filter: expression_json(&cost_filter(cli, &account_id)),
+                        account_name: account.name.clone().unwrap_or("N/A".to_string()),
+                        account_id,
+                    }
+                })
+                .collect(),
+        })
+        .collect();
+    let account_count: usize = profiles.iter().map(|p| p.accounts.len()).sum();
+    let estimated_requests = account_count * intervals.len() * cli.queries_per_account();
+    QueryPlan {
+        granularity: cli.query_granularity().name().to_string(),
+        metric: cli.cost_metrics().join(", "),
+        group_by: cost_group_by(cli).iter().map(group_json).collect(),
+        profiles,
+        estimated_requests,
+        estimated_cost_usd: estimated_requests as f64 * COST_PER_REQUEST,
+        intervals,
+    }
+}
+
+/// Renders the plan for the terminal.
+pub fn render(plan: &QueryPlan) -> String {
+    let mut out = String::new();
+    let intervals: Vec<String> = plan.intervals.iter().map(|(start, end)| format!("{} to {}", start, end)).collect();
+    out.push_str(&format!("Interval(s): {} (end exclusive)\n", intervals.join(", ")));
+    out.push_str(&format!("Granularity: {}\nMetric: {}\n", plan.granularity, plan.metric));
+    let group_by: Vec<String> = plan.group_by.iter().map(Value::to_string).collect();
+    out.push_str(&format!("Group by: {}\n", group_by.join(", ")));
+    for profile in &plan.profiles {
+        out.push_str(&format!("\nProfile {} ({} accounts)\n", profile.profile, profile.accounts.len()));
+        for account in &profile.accounts {
+            let excluded = if account.excluded { " [excluded]" } else { "" };
+            out.push_str(&format!("  {} ({}){}\n    Filter: {}\n", account.account_id, account.account_name, excluded, account.filter));
+        }
+    }
+    out.push_str(&format!(
+        "\nEstimated {} Cost Explorer requests (~${:.2})\n",
+        plan.estimated_requests, plan.estimated_cost_usd
+    ));
+    out
+}
+
+fn group_json(group: &GroupDefinition) -> Value {
+    json!({
+        "Type": group.r#type().map(|t| t.as_str()),
+        "Key": group.key(),
+    })
+}
+
+/// An expression in the JSON form of the Cost Explorer API (`{"And": [{"Dimensions": {...}}, ...]}`).
+fn expression_json(expression: &Expression) -> Value {
+    let mut object = Map::new();
+    if let Some(and) = &expression.and {
+        object.insert("And".to_string(), and.iter().map(expression_json).collect());
+    }
+    if let Some(or) = &expression.or {
+        object.insert("Or".to_string(), or.iter().map(expression_json).collect());
+    }
+    if let Some(not) = &expression.not {
+        object.insert("Not".to_string(), expression_json(not));
+    }
+    if let Some(dimensions) = &expression.dimensions {
+        object.insert(
+            "Dimensions".to_string(),
+            json!({ "Key": dimensions.key().map(|k| k.as_str()), "Values": dimensions.values() }),
+        );
+    }
+    if let Some(tags) = &expression.tags {
+        object.insert("Tags".to_string(), json!({ "Key": tags.key(), "Values": tags.values() }));
+    }
+    if let Some(categories) = &expression.cost_categories {
+        object.insert("CostCategories".to_string(), json!({ "Key": categories.key(), "Values": categories.values() }));
+    }
+    Value::Object(object)
+}
@@ -0,0 +1,103 @@
+use crate::{is_retriable, load_discovery_config, mfa, Cli, ReportError};
+use aws_sdk_sts::error::ProvideErrorMetadata;
+use aws_sdk_sts::Client as StsClient;
+use futures::future::join_all;
+use prettytable::{format, Cell, Row, Table};
+
+/// Outcome of the `sts:GetCallerIdentity` check for one profile.
+pub struct ProfileCheck {
+    pub profile: String,
+    /// Caller identity ARN, or why the credentials couldn't be used.
+    pub identity: Result<String, String>,
+    retriable: bool,
+}
+
+impl ProfileCheck {
+    /// Entry for the report's `errors` array; `None` when the check passed.
+    pub fn report_error(&self) -> Option<ReportError> {
+        let error = self.identity.as_ref().err()?;
+        Some(ReportError {
+            profile: self.profile.clone(),
+            account_id: None,
+            phase: "credential_check".to_string(),
+            error: error.clone(),
+            retriable: self.retriable,
+        })
+    }
+}
+
+/// Checks every profile's credentials concurrently, before any Organizations or Cost Explorer work,
+/// so expired or missing credentials are reported once per profile up front.
+pub async fn check_profiles(cli: &Cli, profiles: &[String]) -> Vec<ProfileCheck> {
+    join_all(profiles.iter().map(|profile| check_profile(cli, profile))).await
+}
+
+async fn check_profile(cli: &Cli, profile: &str) -> ProfileCheck {
+    let _span = cli.phase_timings.span("credential_check", Some(profile), None);
+    // Profiles with mfa_serial fail without a session, so establish one first
+    if let (false, Some(serial)) = (cli.static_credentials, mfa::configured_serial(profile)) {
+        if let Err(e) = mfa::establish(cli, profile, &serial).await {
+            return ProfileCheck { profile: profile.to_string(), identity: Err(e.to_string()), retriable: false };
+        }
+    }
+    let mut check = caller_identity(cli, profile).await;
+    // A policy can demand MFA without the profile declaring a device; use the IAM user's device
+    if let Err(error) = &check.identity {
+        if !cli.static_credentials && mfa::is_mfa_error(error) && mfa::configured_serial(profile).is_none() {
+            let config = load_discovery_config(cli, profile).await;
+            match mfa::user_device(&config).await {
+                Some(serial) => match mfa::establish(cli, profile, &serial).await {
+                    Ok(()) => check = caller_identity(cli, profile).await,
+                    Err(e) => check.identity = Err(e.to_string()),
+                },
+                None => check.identity = Err(format!("{} (set mfa_serial for this profile to authenticate with MFA)", error)),
+            }
+        }
+    }
+    check
+}
+
+async fn caller_identity(cli: &Cli, profile: &str) -> ProfileCheck {
+    let config = load_discovery_config(cli, profile).await;
+    let (identity, retriable) = match StsClient::new(&config).get_caller_identity().send().await {
+        Ok(response) => (Ok(response.arn.unwrap_or_else(|| "(no ARN returned)".to_string())), false),
+        Err(e) => {
+            let message = match (e.code(), e.message()) {
+                (Some(code), Some(message)) => format!("{}: {}", code, message),
+                _ => error_chain(&e),
+            };
+            (Err(message), is_retriable(&e))
+        }
+    };
+    ProfileCheck { profile: profile.to_string(), identity, retriable }
+}
+
+/// An error followed by its sources, e.g. `dispatch failure: io error: ... Connection refused`, since
+/// credential provider failures only say "dispatch failure" at the top level.
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(e) = source {
+        let text = e.to_string();
+        if !message.contains(&text) {
+            message = format!("{}: {}", message, text);
+        }
+        source = e.source();
+    }
+    message
+}
+
+/// Prints the profile → identity table to stderr, keeping stdout free for the report.
+pub fn print_checks(checks: &[ProfileCheck]) {
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_DEFAULT);
+    table.set_titles(Row::new(vec![Cell::new("Profile"), Cell::new("Status"), Cell::new("Identity / Error")]));
+    for check in checks {
+        let (status, detail) = match &check.identity {
+            Ok(arn) => ("OK", arn.as_str()),
+            Err(error) => ("FAILED", error.as_str()),
+        };
+        table.add_row(Row::new(vec![Cell::new(&check.profile), Cell::new(status), Cell::new(detail)]));
+    }
+    let _ = table.print(&mut std::io::stderr());
+}
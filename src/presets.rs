@@ -0,0 +1,310 @@
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Command, ValueEnum};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Built-in reports chosen with `--preset`. Each regroups the per-account breakdown by another
+/// Cost Explorer dimension and keeps only the groups it is about; dates, accounts and outputs work as usual.
+#[derive(ValueEnum, Clone, Debug, PartialEq)]
+pub enum Preset {
+    /// Data transfer (inter-AZ, inter-region, internet egress, NAT processing) by usage type.
+    DataTransfer,
+    /// EC2 compute by instance type, with a roll-up by instance family.
+    Ec2,
+}
+
+/// Names of the per-account breakdown in tables, CSV files and charts.
+pub struct Breakdown {
+    /// Heading of the per-account breakdown table.
+    pub title: &'static str,
+    /// Header of the breakdown's row column.
+    pub row_header: &'static str,
+    /// Middle part of the per-account breakdown CSV file names.
+    pub csv_stem: &'static str,
+    /// Chart caption, after the granularity adjective.
+    pub chart_title: &'static str,
+    /// Prefix of chart file names.
+    pub chart_stem: &'static str,
+    /// Whether rows show their change over the last two periods.
+    pub row_change: bool,
+}
+
+/// The default breakdown, by service.
+pub const SERVICES: Breakdown = Breakdown {
+    title: "Service Consumption Summary",
+    row_header: "Service",
+    csv_stem: "service_summary",
+    chart_title: "Cost Trend Analysis",
+    chart_stem: "cost_trend",
+    row_change: false,
+};
+
+/// Cost Explorer service whose costs `--preset ec2` breaks down; EBS, snapshots and the like are
+/// billed under "EC2 - Other" and have no instance type.
+const EC2_COMPUTE_SERVICE: &str = "Amazon Elastic Compute Cloud - Compute";
+
+/// Data transfer categories by usage type suffix. Usage types are prefixed with a region code
+/// (`USE1-`, `EU-`, ...) except in us-east-1, and inter-region types with both regions
+/// (`USE1-USW2-AWS-Out-Bytes`), so a suffix matches the whole usage type or the part after a `-`.
+const DATA_TRANSFER_USAGE_TYPES: &[(&str, &str)] = &[
+    ("DataTransfer-Regional-Bytes", "Inter-AZ"),
+    ("DataTransfer-xAZ-In-Bytes", "Inter-AZ"),
+    ("DataTransfer-xAZ-Out-Bytes", "Inter-AZ"),
+    ("AWS-Out-Bytes", "Inter-region"),
+    ("AWS-In-Bytes", "Inter-region"),
+    ("DataTransfer-Out-Bytes", "Internet egress"),
+    ("DataTransfer-Out-ABytes", "Internet egress"),
+    ("NatGateway-Bytes", "NAT processing"),
+];
+
+/// The data transfer category of a usage type, or `None` when it isn't data transfer.
+pub fn data_transfer_category(usage_type: &str) -> Option<&'static str> {
+    DATA_TRANSFER_USAGE_TYPES
+        .iter()
+        .find(|(suffix, _)| usage_type_matches(usage_type, suffix))
+        .map(|(_, category)| *category)
+}
+
+/// Whether a usage type is `suffix` once its region prefix (`USE1-`, `USE1-USW2-`, ...) is ignored.
+pub fn usage_type_matches(usage_type: &str, suffix: &str) -> bool {
+    usage_type
+        .strip_suffix(suffix)
+        .is_some_and(|prefix| prefix.is_empty() || prefix.ends_with('-'))
+}
+
+/// Instance family of an `--preset ec2` row such as `m5.large` (`m5`) or `c7g.xlarge, team$web`
+/// (`c7g`); rows without an instance type, such as `NoInstanceType`, are their own family.
+pub fn instance_family(row: &str) -> &str {
+    let instance_type = row.split(", ").next().unwrap_or(row);
+    instance_type.split('.').next().unwrap_or(instance_type)
+}
+
+impl Preset {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::DataTransfer => "data-transfer",
+            Preset::Ec2 => "ec2",
+        }
+    }
+
+    /// Cost Explorer dimension the cost query groups by instead of `SERVICE`.
+    pub fn group_by(&self) -> &'static str {
+        match self {
+            Preset::DataTransfer => "USAGE_TYPE",
+            Preset::Ec2 => "INSTANCE_TYPE",
+        }
+    }
+
+    /// Services the cost query is limited to, when the preset is about specific ones.
+    pub fn services(&self) -> Option<&'static [&'static str]> {
+        match self {
+            Preset::DataTransfer => None,
+            Preset::Ec2 => Some(&[EC2_COMPUTE_SERVICE]),
+        }
+    }
+
+    /// Row label for a Cost Explorer group (the grouped dimension first, then any tag), or `None` to
+    /// leave the group's cost out of the report.
+    pub fn row_label(&self, keys: &[String]) -> Option<String> {
+        match self {
+            Preset::DataTransfer => {
+                let category = data_transfer_category(keys.first()?)?;
+                Some(format!("{}: {}", category, keys.join(", ")))
+            }
+            Preset::Ec2 => Some(keys.join(", ")),
+        }
+    }
+
+    pub fn breakdown(&self) -> Breakdown {
+        match self {
+            Preset::DataTransfer => Breakdown {
+                title: "Data Transfer by Usage Type",
+                row_header: "Usage Type",
+                csv_stem: "data_transfer",
+                chart_title: "Data Transfer Cost Trend",
+                chart_stem: "data_transfer_trend",
+                row_change: false,
+            },
+            Preset::Ec2 => Breakdown {
+                title: "EC2 Compute by Instance Type",
+                row_header: "Instance Type",
+                csv_stem: "ec2_instance_types",
+                chart_title: "EC2 Compute Cost Trend",
+                chart_stem: "ec2_trend",
+                row_change: true,
+            },
+        }
+    }
+}
+
+/// A preset from the config file's `[presets.<name>]` section: long option names (without `--`) and
+/// their values, plus optionally `preset = "<built-in>"` to build on a built-in preset.
+pub type PresetDefinition = BTreeMap<String, toml::Value>;
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    presets: BTreeMap<String, PresetDefinition>,
+}
+
+/// Options that select or inspect presets and so can't be set by one.
+const NOT_PRESETTABLE: &[&str] = &["config", "preset", "list-presets", "print-config", "help", "version"];
+
+/// `$XDG_CONFIG_HOME/aws-cost-cli/config.toml`, or `~/.config/aws-cost-cli/config.toml`.
+fn default_config_path() -> PathBuf {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map_or_else(|| crate::profiles::home_dir().join(".config"), PathBuf::from)
+        .join("aws-cost-cli")
+        .join("config.toml")
+}
+
+/// Presets defined in `--config`, or in the default config file when it exists.
+pub fn load_config_presets(path: Option<&str>) -> Result<BTreeMap<String, PresetDefinition>, String> {
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => match default_config_path() {
+            path if path.exists() => path,
+            _ => return Ok(BTreeMap::new()),
+        },
+    };
+    let contents = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read config file {}: {}", path.display(), e))?;
+    let config: ConfigFile = toml::from_str(&contents).map_err(|e| format!("Invalid config file {}: {}", path.display(), e))?;
+    if let Some(name) = config.presets.keys().find(|name| Preset::from_str(name, true).is_ok()) {
+        return Err(format!("Config file {} defines preset '{}', which is the name of a built-in preset", path.display(), name));
+    }
+    Ok(config.presets)
+}
+
+/// Names of the built-in and config presets, for error messages.
+pub fn available_names(config_presets: &BTreeMap<String, PresetDefinition>) -> String {
+    Preset::value_variants()
+        .iter()
+        .map(|preset| preset.name().to_string())
+        .chain(config_presets.keys().cloned())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// A config preset turned into command-line arguments.
+pub struct PresetArgs {
+    /// `--option=value` arguments for the options not set on the command line or in the environment.
+    pub args: Vec<String>,
+    /// IDs of the options those arguments set, for `--print-config`.
+    pub options: Vec<String>,
+    /// Built-in preset the definition builds on.
+    pub builtin: Option<Preset>,
+}
+
+/// Arguments applying a config preset under the options `matches` already has from the command line
+/// or environment, which take precedence.
+pub fn preset_args(name: &str, definition: &PresetDefinition, command: &Command, matches: &ArgMatches) -> Result<PresetArgs, String> {
+    let mut preset_args = PresetArgs { args: Vec::new(), options: Vec::new(), builtin: None };
+    for (key, value) in definition {
+        if key == "preset" {
+            let builtin = value.as_str().and_then(|value| Preset::from_str(value, true).ok()).ok_or_else(|| {
+                format!("Preset '{}': 'preset' must name a built-in preset ({})", name, available_names(&BTreeMap::new()))
+            })?;
+            preset_args.builtin = Some(builtin);
+            continue;
+        }
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(key.as_str()) && !NOT_PRESETTABLE.contains(&key.as_str()))
+            .ok_or_else(|| format!("Preset '{}' sets '{}', which is not an option that presets can set", name, key))?;
+        let id = arg.get_id().as_str();
+        if matches!(matches.value_source(id), Some(ValueSource::CommandLine | ValueSource::EnvVariable)) {
+            continue;
+        }
+        if arg.get_action().takes_values() {
+            let value = match value {
+                toml::Value::Array(items) => items.iter().map(|item| scalar(name, key, item)).collect::<Result<Vec<_>, _>>()?.join(","),
+                value => scalar(name, key, value)?,
+            };
+            preset_args.args.push(format!("--{}={}", key, value));
+        } else {
+            // Flags: `true`/`false`, or a count for repeatable ones such as `quiet = 2`
+            let times = match value {
+                toml::Value::Boolean(set) => usize::from(*set),
+                toml::Value::Integer(count) if *count >= 0 => *count as usize,
+                _ => return Err(format!("Preset '{}': '{}' is a flag and must be true or false", name, key)),
+            };
+            preset_args.args.extend(std::iter::repeat_n(format!("--{}", key), times));
+        }
+        preset_args.options.push(id.to_string());
+    }
+    Ok(preset_args)
+}
+
+fn scalar(name: &str, key: &str, value: &toml::Value) -> Result<String, String> {
+    match value {
+        toml::Value::String(value) => Ok(value.clone()),
+        toml::Value::Integer(value) => Ok(value.to_string()),
+        toml::Value::Float(value) => Ok(value.to_string()),
+        toml::Value::Boolean(value) => Ok(value.to_string()),
+        toml::Value::Datetime(value) => Ok(value.to_string()),
+        _ => Err(format!("Preset '{}': '{}' must be a string, number, boolean or list of those", name, key)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_transfer_usage_types_are_categorized_under_any_region_prefix() {
+        for (usage_type, category) in [
+            ("DataTransfer-Regional-Bytes", "Inter-AZ"),
+            ("USE2-DataTransfer-Regional-Bytes", "Inter-AZ"),
+            ("EU-DataTransfer-xAZ-In-Bytes", "Inter-AZ"),
+            ("APN1-DataTransfer-xAZ-Out-Bytes", "Inter-AZ"),
+            ("USE1-USW2-AWS-Out-Bytes", "Inter-region"),
+            ("EUC1-USE1-AWS-In-Bytes", "Inter-region"),
+            ("DataTransfer-Out-Bytes", "Internet egress"),
+            ("EU-DataTransfer-Out-Bytes", "Internet egress"),
+            ("USW2-DataTransfer-Out-ABytes", "Internet egress"),
+            ("NatGateway-Bytes", "NAT processing"),
+            ("USE2-NatGateway-Bytes", "NAT processing"),
+        ] {
+            assert_eq!(data_transfer_category(usage_type), Some(category), "{}", usage_type);
+        }
+    }
+
+    #[test]
+    fn other_usage_types_are_not_data_transfer() {
+        for usage_type in [
+            "BoxUsage:m5.large",
+            "USE2-NatGateway-Hours",
+            "DataTransfer-In-Bytes",
+            "EU-DataTransfer-In-Bytes",
+            "USE1-CloudFront-Out-Bytes",
+            // A suffix only matches after a `-`
+            "USE1-XNatGateway-Bytes",
+            "DataTransfer-Out-Bytes-Extra",
+        ] {
+            assert_eq!(data_transfer_category(usage_type), None, "{}", usage_type);
+        }
+    }
+
+    #[test]
+    fn data_transfer_rows_are_labelled_by_category_and_others_dropped() {
+        let keys = |keys: &[&str]| keys.iter().map(|key| key.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            Preset::DataTransfer.row_label(&keys(&["USE1-USW2-AWS-Out-Bytes", "team$web"])).as_deref(),
+            Some("Inter-region: USE1-USW2-AWS-Out-Bytes, team$web")
+        );
+        assert_eq!(Preset::DataTransfer.row_label(&keys(&["BoxUsage:m5.large"])), None);
+        assert_eq!(Preset::DataTransfer.row_label(&[]), None);
+    }
+
+    #[test]
+    fn instance_types_roll_up_to_their_family() {
+        assert_eq!(instance_family("m5.large"), "m5");
+        assert_eq!(instance_family("m5.24xlarge"), "m5");
+        assert_eq!(instance_family("c7g.xlarge, team$web"), "c7g");
+        assert_eq!(instance_family("u-6tb1.metal"), "u-6tb1");
+        assert_eq!(instance_family("NoInstanceType"), "NoInstanceType");
+        assert_eq!(instance_family("NoInstanceType, team$"), "NoInstanceType");
+    }
+}
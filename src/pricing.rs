@@ -0,0 +1,154 @@
+use crate::{load_discovery_config, Cli, Report};
+use aws_config::Region;
+use aws_sdk_pricing::error::ProvideErrorMetadata;
+use aws_sdk_pricing::types::{Filter, FilterType};
+use aws_sdk_pricing::Client as PricingClient;
+use futures::future::join_all;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+
+/// Region of the Pricing API endpoint; it serves every region's prices.
+const PRICING_REGION: &str = "us-east-1";
+
+/// Usage types `--price-context` looks up, by name once the region prefix is ignored, with the
+/// Pricing API service code that lists them. Both APIs use the same usage type names, region
+/// prefix included, so only the service has to be known.
+const PRICED_USAGE_TYPES: &[(&str, &str)] = &[
+    // EBS volumes
+    ("EBS:VolumeUsage", "AmazonEC2"),
+    ("EBS:VolumeUsage.gp2", "AmazonEC2"),
+    ("EBS:VolumeUsage.gp3", "AmazonEC2"),
+    ("EBS:VolumeUsage.piops", "AmazonEC2"),
+    ("EBS:VolumeUsage.io2", "AmazonEC2"),
+    ("EBS:VolumeUsage.st1", "AmazonEC2"),
+    ("EBS:VolumeUsage.sc1", "AmazonEC2"),
+    // NAT gateway data processing
+    ("NatGateway-Bytes", "AmazonEC2"),
+    // S3 Standard storage
+    ("TimedStorage-ByteHrs", "AmazonS3"),
+    // Inter-region data transfer out, such as USE1-USW2-AWS-Out-Bytes
+    ("AWS-Out-Bytes", "AWSDataTransfer"),
+];
+
+/// Pricing API service code of a usage type `--price-context` can price.
+pub fn service_code(usage_type: &str) -> Option<&'static str> {
+    PRICED_USAGE_TYPES
+        .iter()
+        .find(|(name, _)| crate::presets::usage_type_matches(usage_type, name))
+        .map(|(_, code)| *code)
+}
+
+/// Looks up the On-Demand list price of the report's priceable unit-rate usage types and sets it,
+/// with the discount of the latest rate, on every account's unit rates. Lookups that fail or
+/// return a different unit only leave the price out.
+pub async fn apply(cli: &Cli, profiles: &[String], report: &mut Report) {
+    let usage_types: BTreeSet<(String, String)> = report
+        .accounts
+        .iter()
+        .flat_map(|account| account.unit_rates.iter().flatten())
+        .filter(|rate| service_code(&rate.usage_type).is_some())
+        .filter_map(|rate| rate.unit.clone().map(|unit| (rate.usage_type.clone(), unit)))
+        .collect();
+    let Some(profile) = profiles.first().filter(|_| !usage_types.is_empty()) else {
+        return;
+    };
+    let config = load_discovery_config(cli, profile).await;
+    let client = PricingClient::from_conf(aws_sdk_pricing::config::Builder::from(&config).region(Region::new(PRICING_REGION)).build());
+    let looked_up = join_all(usage_types.iter().map(|(usage_type, _)| list_price(&client, usage_type))).await;
+
+    let mut prices: HashMap<&str, f64> = HashMap::new();
+    for ((usage_type, unit), result) in usage_types.iter().zip(looked_up) {
+        match result {
+            Ok((price, price_unit)) if price_unit.eq_ignore_ascii_case(unit) => {
+                prices.insert(usage_type, price);
+            }
+            Ok((_, price_unit)) => warn!("Warning: --price-context list price of {} is per {}, not per {}; left out", usage_type, price_unit, unit),
+            Err(e) => warn!("Warning: --price-context found no list price for {}: {}", usage_type, e),
+        }
+    }
+    for rate in report.accounts.iter_mut().flat_map(|account| account.unit_rates.iter_mut().flatten()) {
+        let Some(&price) = prices.get(rate.usage_type.as_str()) else {
+            continue;
+        };
+        rate.list_price = Some(price);
+        let latest = rate.periods.iter().rev().find_map(|period| period.rate);
+        rate.discount_percent = latest.filter(|_| price > 0.0).map(|latest| (price - latest) / price * 100.0);
+    }
+}
+
+/// First-tier On-Demand price of a usage type and its unit.
+async fn list_price(client: &PricingClient, usage_type: &str) -> Result<(f64, String), String> {
+    let filter = |field: &str, value: &str| Filter::builder().r#type(FilterType::TermMatch).field(field).value(value).build().map_err(|e| e.to_string());
+    let response = client
+        .get_products()
+        .service_code(service_code(usage_type).unwrap_or_default())
+        .filters(filter("usagetype", usage_type)?)
+        .format_version("aws_v1")
+        .max_results(10)
+        .send()
+        .await
+        .map_err(|e| match (e.code(), e.message()) {
+            (Some(code), Some(message)) => format!("{}: {}", code, message),
+            _ => e.to_string(),
+        })?;
+    response
+        .price_list()
+        .iter()
+        .filter_map(|product| serde_json::from_str::<Value>(product).ok())
+        .find_map(|product| first_tier(&product))
+        .ok_or_else(|| "no On-Demand price listed".to_string())
+}
+
+/// The price dimension starting at zero usage (the only one for flat prices) of a price list product.
+fn first_tier(product: &Value) -> Option<(f64, String)> {
+    product["terms"]["OnDemand"]
+        .as_object()?
+        .values()
+        .filter_map(|term| term["priceDimensions"].as_object())
+        .flat_map(|dimensions| dimensions.values())
+        .filter(|dimension| dimension["beginRange"].as_str().is_none_or(|begin| begin.parse::<f64>() == Ok(0.0)))
+        .find_map(|dimension| {
+            let price = dimension["pricePerUnit"]["USD"].as_str()?.parse::<f64>().ok()?;
+            Some((price, dimension["unit"].as_str()?.to_string()))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn priced_usage_types_resolve_to_their_service_under_any_region_prefix() {
+        for (usage_type, code) in PRICED_USAGE_TYPES {
+            assert_eq!(service_code(usage_type), Some(*code), "{}", usage_type);
+            assert_eq!(service_code(&format!("USE2-{}", usage_type)), Some(*code), "{}", usage_type);
+        }
+        assert_eq!(service_code("EU-EBS:VolumeUsage.gp3"), Some("AmazonEC2"));
+        assert_eq!(service_code("USE1-USW2-AWS-Out-Bytes"), Some("AWSDataTransfer"));
+    }
+
+    #[test]
+    fn other_usage_types_are_not_priced() {
+        for usage_type in ["BoxUsage:m5.large", "EBS:SnapshotUsage", "EBS:VolumeUsage.gp3-Extra", "NatGateway-Hours", "TimedStorage-INT-FA-ByteHrs", "USE1-XAWS-Out-Bytes"] {
+            assert_eq!(service_code(usage_type), None, "{}", usage_type);
+        }
+    }
+
+    #[test]
+    fn first_tier_is_the_dimension_starting_at_zero() {
+        let product = json!({
+            "terms": {"OnDemand": {"SKU.TERM": {"priceDimensions": {
+                "SKU.TERM.2": {"beginRange": "51200", "endRange": "Inf", "unit": "GB-Mo", "pricePerUnit": {"USD": "0.0210000000"}},
+                "SKU.TERM.1": {"beginRange": "0", "endRange": "51200", "unit": "GB-Mo", "pricePerUnit": {"USD": "0.0230000000"}}
+            }}}}
+        });
+        assert_eq!(first_tier(&product), Some((0.023, "GB-Mo".to_string())));
+
+        // Flat prices have no range
+        let flat = json!({"terms": {"OnDemand": {"SKU.TERM": {"priceDimensions": {"SKU.TERM.1": {"unit": "GB", "pricePerUnit": {"USD": "0.045"}}}}}}});
+        assert_eq!(first_tier(&flat), Some((0.045, "GB".to_string())));
+
+        assert_eq!(first_tier(&json!({"terms": {"Reserved": {}}})), None);
+    }
+}
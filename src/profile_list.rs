@@ -0,0 +1,126 @@
+use crate::preflight::ProfileCheck;
+use crate::profiles::{self, FileKind};
+use prettytable::{format, Cell, Row, Table};
+use serde::Serialize;
+
+/// A profile the report would run with, as the `profiles` subcommand lists it.
+#[derive(Serialize, Debug)]
+pub struct ListedProfile {
+    pub profile: String,
+    /// Config and credentials files declaring the profile; empty when neither does.
+    pub sources: Vec<String>,
+    pub region: Option<String>,
+    /// `sso`, `assume-role`, `web-identity`, `credential-process`, `static`, `default-chain` or `none`.
+    pub credential_type: String,
+    /// Profile an `assume-role` profile takes its credentials from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_profile: Option<String>,
+    /// Has `mfa_serial`, so runs ask for a code.
+    pub mfa: bool,
+    /// Caller identity ARN with `--check`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identity: Option<String>,
+    /// Why the `--check` failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Reads each profile's settings from the shared config and credentials files, the config file
+/// taking precedence as for the SDK, and adds the `--check` results when given.
+pub fn build(profile_names: &[String], checks: Option<&[ProfileCheck]>) -> Vec<ListedProfile> {
+    let files: Vec<(FileKind, String, String)> = [FileKind::Config, FileKind::Credentials]
+        .into_iter()
+        .filter_map(|kind| {
+            let path = kind.path();
+            let content = std::fs::read_to_string(&path).ok()?;
+            Some((kind, path.display().to_string(), content))
+        })
+        .collect();
+
+    profile_names
+        .iter()
+        .map(|profile| {
+            let setting = |key: &str| files.iter().find_map(|(kind, _, content)| profiles::setting(content, *kind, profile, key));
+            let sources: Vec<String> = files
+                .iter()
+                .filter(|(kind, _, content)| {
+                    let mut declared = Vec::new();
+                    profiles::collect_profiles(content, *kind, &mut declared);
+                    declared.contains(profile)
+                })
+                .map(|(_, path, _)| path.clone())
+                .collect();
+            let credential_type = if *profile == crate::DEFAULT_CHAIN_PROFILE {
+                "default-chain"
+            } else if setting("sso_session").is_some() || setting("sso_start_url").is_some() {
+                "sso"
+            } else if setting("role_arn").is_some() && setting("web_identity_token_file").is_some() {
+                "web-identity"
+            } else if setting("role_arn").is_some() {
+                "assume-role"
+            } else if setting("credential_process").is_some() {
+                "credential-process"
+            } else if setting("aws_access_key_id").is_some() {
+                "static"
+            } else {
+                "none"
+            };
+            let check = checks.and_then(|checks| checks.iter().find(|check| check.profile == *profile));
+            ListedProfile {
+                profile: profile.clone(),
+                sources,
+                region: setting("region"),
+                credential_type: credential_type.to_string(),
+                source_profile: setting("source_profile").filter(|_| credential_type == "assume-role"),
+                mfa: setting("mfa_serial").is_some(),
+                identity: check.and_then(|check| check.identity.clone().ok()),
+                error: check.and_then(|check| check.identity.clone().err()),
+            }
+        })
+        .collect()
+}
+
+/// Prints one table of profiles, with the `--check` results when they were run.
+pub fn print(profiles: &[ListedProfile], checked: bool) {
+    let mut titles = vec!["Profile", "Source", "Region", "Credentials"];
+    if checked {
+        titles.extend(["Status", "Identity / Error"]);
+    }
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_DEFAULT);
+    table.set_titles(Row::new(titles.into_iter().map(|title| Cell::new(title).style_spec("bFc")).collect()));
+    for profile in profiles {
+        let mut credentials = profile.credential_type.clone();
+        if let Some(source_profile) = &profile.source_profile {
+            credentials.push_str(&format!(" via {}", source_profile));
+        }
+        if profile.mfa {
+            credentials.push_str(", MFA");
+        }
+        let mut cells = vec![
+            Cell::new(&profile.profile),
+            if profile.sources.is_empty() && profile.credential_type != "default-chain" {
+                Cell::new("not found").style_spec("bFr")
+            } else {
+                Cell::new(&profile.sources.join("\n"))
+            },
+            Cell::new(profile.region.as_deref().unwrap_or("N/A")),
+            if profile.credential_type == "none" { Cell::new(&credentials).style_spec("Fy") } else { Cell::new(&credentials) },
+        ];
+        if checked {
+            match (&profile.identity, &profile.error) {
+                (Some(identity), _) => cells.extend([Cell::new("OK"), Cell::new(identity)]),
+                (None, error) => cells.extend([Cell::new("FAILED").style_spec("bFr"), Cell::new(error.as_deref().unwrap_or(""))]),
+            }
+        }
+        table.add_row(Row::new(cells));
+    }
+    println!("\nAWS Profiles:");
+    table.printstd();
+    let failed = profiles.iter().filter(|profile| profile.error.is_some()).count();
+    if checked {
+        println!("{} profiles, {} failed the credential check", profiles.len(), failed);
+    } else {
+        println!("{} profiles (add --check to test their credentials)", profiles.len());
+    }
+}
@@ -0,0 +1,246 @@
+use std::path::PathBuf;
+
+/// Kind of file a section header was read from; the two name profiles differently.
+#[derive(Clone, Copy, PartialEq)]
+pub enum FileKind {
+    /// `~/.aws/config`: `[default]` and `[profile name]`, alongside `[sso-session ...]` and `[services ...]`.
+    Config,
+    /// `~/.aws/credentials`: every section is a bare profile name.
+    Credentials,
+}
+
+impl FileKind {
+    /// Location of the file: `AWS_CONFIG_FILE` or `AWS_SHARED_CREDENTIALS_FILE` when set, as for the SDK,
+    /// otherwise under `.aws` in the home directory.
+    pub fn path(&self) -> PathBuf {
+        let (variable, default) = match self {
+            FileKind::Config => ("AWS_CONFIG_FILE", "config"),
+            FileKind::Credentials => ("AWS_SHARED_CREDENTIALS_FILE", "credentials"),
+        };
+        match std::env::var_os(variable).filter(|path| !path.is_empty()) {
+            Some(path) => expand_home(PathBuf::from(path)),
+            None => home_dir().join(".aws").join(default),
+        }
+    }
+}
+
+/// `HOME`, or `USERPROFILE` on Windows where `HOME` usually isn't set; the current directory as a last resort.
+pub fn home_dir() -> PathBuf {
+    ["HOME", "USERPROFILE"]
+        .iter()
+        .filter_map(std::env::var_os)
+        .find(|home| !home.is_empty())
+        .map_or_else(|| PathBuf::from("."), PathBuf::from)
+}
+
+/// Resolves a leading `~` in a path taken from the environment.
+fn expand_home(path: PathBuf) -> PathBuf {
+    match path.strip_prefix("~") {
+        Ok(rest) => home_dir().join(rest),
+        Err(_) => path,
+    }
+}
+
+/// Profile named by an INI section header line, or `None` for other lines and non-profile sections.
+fn section_profile(line: &str, kind: FileKind) -> Option<String> {
+    let line = line.trim();
+    if line.starts_with(['#', ';']) {
+        return None;
+    }
+    let header = line.strip_prefix('[')?;
+    let header = header[..header.find(']')?].trim();
+    match kind {
+        FileKind::Credentials => (!header.is_empty()).then(|| header.to_string()),
+        FileKind::Config => {
+            let mut words = header.split_whitespace();
+            match (words.next(), words.next(), words.next()) {
+                (Some("default"), None, _) => Some("default".to_string()),
+                (Some("profile"), Some(name), None) => Some(name.to_string()),
+                // [sso-session name], [services name] and anything unrecognized
+                _ => None,
+            }
+        }
+    }
+}
+
+/// Appends the profiles declared in `content` to `profiles`, in file order and without duplicates.
+pub fn collect_profiles(content: &str, kind: FileKind, profiles: &mut Vec<String>) {
+    for name in content.lines().filter_map(|line| section_profile(line, kind)) {
+        if !profiles.contains(&name) {
+            profiles.push(name);
+        }
+    }
+}
+
+/// Value of `key` in `profile`'s section of `content` (e.g. `mfa_serial`), if set.
+pub fn setting(content: &str, kind: FileKind, profile: &str, key: &str) -> Option<String> {
+    let mut in_profile = false;
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            in_profile = section_profile(trimmed, kind).as_deref() == Some(profile);
+        } else if in_profile && !trimmed.starts_with(['#', ';']) {
+            if let Some((name, value)) = trimmed.split_once('=') {
+                if name.trim() == key {
+                    return Some(value.trim().to_string());
+                }
+            }
+        }
+    }
+    None
+}
+
+/// `key` for `profile` from the config file, or the credentials file when the config doesn't set it.
+pub fn profile_setting(profile: &str, key: &str) -> Option<String> {
+    [FileKind::Config, FileKind::Credentials].into_iter().find_map(|kind| {
+        let content = std::fs::read_to_string(kind.path()).ok()?;
+        setting(&content, kind, profile, key)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{temp_dir, ENV_LOCK};
+    use std::path::Path;
+
+    const CONFIG: &str = "\
+# Managed by the platform team
+[default]
+region = us-east-1
+
+  [ profile prod ]   # production
+sso_session = corp
+sso_account_id = 111111111111
+; [profile commented-out]
+[sso-session corp]
+sso_start_url = https://corp.awsapps.com/start
+sso_region = us-east-1
+
+[services local-endpoints]
+s3 =
+  endpoint_url = http://localhost:4566
+
+[profile dev]
+source_profile = prod
+mfa_serial = arn:aws:iam::111111111111:mfa/ci
+[default]
+output = json
+";
+
+    const CREDENTIALS: &str = "\
+[default]
+aws_access_key_id = AKIAEXAMPLE
+#[not-a-profile]
+[ ci ]
+aws_access_key_id = AKIAEXAMPLE2
+[dev]
+";
+
+    #[test]
+    fn config_profiles_skip_sso_sessions_services_and_comments() {
+        let mut profiles = Vec::new();
+        collect_profiles(CONFIG, FileKind::Config, &mut profiles);
+        assert_eq!(profiles, ["default", "prod", "dev"]);
+    }
+
+    #[test]
+    fn credentials_sections_are_bare_profile_names() {
+        let mut profiles = Vec::new();
+        collect_profiles(CREDENTIALS, FileKind::Credentials, &mut profiles);
+        assert_eq!(profiles, ["default", "ci", "dev"]);
+    }
+
+    #[test]
+    fn profiles_from_both_files_keep_discovery_order_without_duplicates() {
+        let mut profiles = Vec::new();
+        collect_profiles(CREDENTIALS, FileKind::Credentials, &mut profiles);
+        collect_profiles(CONFIG, FileKind::Config, &mut profiles);
+        assert_eq!(profiles, ["default", "ci", "dev", "prod"]);
+    }
+
+    #[test]
+    fn config_headers_name_profiles_only_with_the_profile_prefix() {
+        for header in ["[prod]", "[profile]", "[profile a b]", "[sso-session prod]", "[services prod]", "[]", "[profile prod"] {
+            assert_eq!(section_profile(header, FileKind::Config), None, "{}", header);
+        }
+        assert_eq!(section_profile("\t[profile  prod]\t", FileKind::Config).as_deref(), Some("prod"));
+        assert_eq!(section_profile("[sso-session corp]", FileKind::Credentials).as_deref(), Some("sso-session corp"));
+    }
+
+    #[test]
+    fn settings_are_read_from_the_profile_section_only() {
+        assert_eq!(setting(CONFIG, FileKind::Config, "dev", "mfa_serial").as_deref(), Some("arn:aws:iam::111111111111:mfa/ci"));
+        assert_eq!(setting(CONFIG, FileKind::Config, "prod", "sso_session").as_deref(), Some("corp"));
+        // sso_region belongs to the sso-session section, not to the profile referencing it
+        assert_eq!(setting(CONFIG, FileKind::Config, "prod", "sso_region"), None);
+        assert_eq!(setting(CONFIG, FileKind::Config, "default", "output").as_deref(), Some("json"));
+        assert_eq!(setting(CREDENTIALS, FileKind::Credentials, "ci", "aws_access_key_id").as_deref(), Some("AKIAEXAMPLE2"));
+    }
+
+    /// Runs `f` with each variable set to its value, or unset for `None`, then restores them.
+    fn with_env<T>(vars: &[(&str, Option<&Path>)], f: impl FnOnce() -> T) -> T {
+        let _env = ENV_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let saved: Vec<_> = vars.iter().map(|(var, _)| (*var, std::env::var_os(var))).collect();
+        for (var, value) in vars {
+            match value {
+                Some(value) => std::env::set_var(var, value),
+                None => std::env::remove_var(var),
+            }
+        }
+        let result = f();
+        for (var, value) in saved {
+            match value {
+                Some(value) => std::env::set_var(var, value),
+                None => std::env::remove_var(var),
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn files_named_by_the_environment_replace_the_defaults() {
+        let dir = temp_dir("profile-files");
+        let (config, credentials) = (dir.join("generated-config"), dir.join("generated-credentials"));
+        std::fs::write(&config, CONFIG).unwrap();
+        std::fs::write(&credentials, CREDENTIALS).unwrap();
+
+        let vars = [("AWS_CONFIG_FILE", Some(config.as_path())), ("AWS_SHARED_CREDENTIALS_FILE", Some(credentials.as_path())), ("HOME", Some(Path::new("/nonexistent")))];
+        let (paths, profiles, mfa_serial) = with_env(&vars, || {
+            ([FileKind::Config.path(), FileKind::Credentials.path()], crate::get_aws_profile_names(), profile_setting("dev", "mfa_serial"))
+        });
+        assert_eq!(paths, [config, credentials]);
+        assert_eq!(profiles, ["default", "ci", "dev", "prod"]);
+        assert_eq!(mfa_serial.as_deref(), Some("arn:aws:iam::111111111111:mfa/ci"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn unset_or_empty_variables_fall_back_to_the_home_directory() {
+        let home = Path::new("/home/ci");
+        let empty = Path::new("");
+        let paths = with_env(&[("AWS_CONFIG_FILE", Some(empty)), ("AWS_SHARED_CREDENTIALS_FILE", None), ("HOME", Some(home))], || {
+            [FileKind::Config.path(), FileKind::Credentials.path()]
+        });
+        assert_eq!(paths, [home.join(".aws").join("config"), home.join(".aws").join("credentials")]);
+    }
+
+    #[test]
+    fn a_leading_tilde_is_the_home_directory() {
+        let home = Path::new("/home/ci");
+        let path = with_env(&[("AWS_CONFIG_FILE", Some(Path::new("~/generated/config"))), ("HOME", Some(home))], || FileKind::Config.path());
+        assert_eq!(path, home.join("generated").join("config"));
+    }
+
+    #[test]
+    fn userprofile_is_the_home_directory_without_home() {
+        let windows_home = Path::new("C:/Users/ci");
+        for home in [None, Some(Path::new(""))] {
+            let paths = with_env(&[("HOME", home), ("USERPROFILE", Some(windows_home)), ("AWS_CONFIG_FILE", None)], || (home_dir(), FileKind::Config.path()));
+            assert_eq!(paths, (windows_home.to_path_buf(), windows_home.join(".aws").join("config")));
+        }
+        // HOME wins when both are set
+        let home = with_env(&[("HOME", Some(Path::new("/home/ci"))), ("USERPROFILE", Some(windows_home))], home_dir);
+        assert_eq!(home, Path::new("/home/ci"));
+    }
+}
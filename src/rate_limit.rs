@@ -0,0 +1,104 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Token bucket shared by every task making requests to one service, so concurrent queries stay
+/// under its request rate instead of spending the run in throttling retries. Clones share the bucket.
+#[derive(Debug, Default, Clone)]
+pub struct RateLimiter {
+    /// `None` when unlimited.
+    bucket: Option<Arc<Mutex<Bucket>>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Requests per second, which is also the burst size.
+    rate: f64,
+    /// Negative while requests are queued for future tokens.
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    /// A limiter for `requests_per_second`; zero or less disables limiting.
+    pub fn new(requests_per_second: f64) -> Self {
+        let bucket = (requests_per_second > 0.0).then(|| {
+            Arc::new(Mutex::new(Bucket { rate: requests_per_second, tokens: requests_per_second.max(1.0), refilled_at: Instant::now() }))
+        });
+        RateLimiter { bucket }
+    }
+
+    /// Waits until a request may be sent. Each caller reserves a token up front, so waiting callers
+    /// are paced one `1 / rate` apart in the order they arrived.
+    pub async fn acquire(&self) {
+        let Some(bucket) = &self.bucket else {
+            return;
+        };
+        let wait = {
+            let mut bucket = bucket.lock().unwrap();
+            let now = Instant::now();
+            let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * bucket.rate).min(bucket.rate.max(1.0));
+            bucket.refilled_at = now;
+            bucket.tokens -= 1.0;
+            (bucket.tokens < 0.0).then(|| Duration::from_secs_f64(-bucket.tokens / bucket.rate))
+        };
+        if let Some(wait) = wait {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Completion times, relative to the start, of `count` concurrent acquisitions.
+    async fn completions(limiter: &RateLimiter, count: usize) -> Vec<Duration> {
+        let start = Instant::now();
+        let tasks: Vec<_> = (0..count)
+            .map(|_| {
+                let limiter = limiter.clone();
+                tokio::spawn(async move {
+                    limiter.acquire().await;
+                    start.elapsed()
+                })
+            })
+            .collect();
+        let mut times = Vec::new();
+        for task in tasks {
+            times.push(task.await.unwrap());
+        }
+        times.sort();
+        times
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn paces_requests_after_the_burst() {
+        let times = completions(&RateLimiter::new(2.0), 6).await;
+        let millis: Vec<u128> = times.iter().map(Duration::as_millis).collect();
+        // Two tokens up front, then one every 500 ms.
+        assert_eq!(millis, [0, 0, 500, 1000, 1500, 2000]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn refills_while_idle_up_to_the_burst_size() {
+        let limiter = RateLimiter::new(2.0);
+        completions(&limiter, 2).await;
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        let millis: Vec<u128> = completions(&limiter, 3).await.iter().map(Duration::as_millis).collect();
+        assert_eq!(millis, [0, 0, 500]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn fractional_rates_allow_one_request_then_wait() {
+        let millis: Vec<u128> = completions(&RateLimiter::new(0.5), 3).await.iter().map(Duration::as_millis).collect();
+        assert_eq!(millis, [0, 2000, 4000]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn zero_rate_is_unlimited() {
+        let times = completions(&RateLimiter::new(0.0), 50).await;
+        assert!(times.iter().all(|t| t.is_zero()));
+    }
+}
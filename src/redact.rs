@@ -0,0 +1,192 @@
+use crate::Report;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+
+/// Replacement for IDs that appear in a report without having been assigned a pseudonym (e.g. `--account-id` filters).
+const REDACTED: &str = "(redacted)";
+
+/// Real identity behind a pseudonym, written by `--redact-map`.
+#[derive(Serialize, Debug)]
+struct RedactedAccount {
+    account_id: String,
+    account_name: String,
+}
+
+/// Replaces account IDs and names (and with `--redact-profiles`, profile names) with pseudonyms such
+/// as `acct-01`, numbered by descending cost. Pseudonyms are kept for the whole run, so `--watch`
+/// refreshes only number accounts they haven't seen yet.
+#[derive(Default)]
+pub struct Redactor {
+    redact_profiles: bool,
+    /// Digits in account pseudonyms, fixed by the first report so they stay aligned across refreshes.
+    width: usize,
+    accounts: HashMap<String, String>,
+    names: BTreeMap<String, RedactedAccount>,
+    profiles: HashMap<String, String>,
+}
+
+impl Redactor {
+    pub fn new(redact_profiles: bool) -> Self {
+        Redactor { redact_profiles, ..Default::default() }
+    }
+
+    /// Assigns pseudonyms to the report's new accounts and profiles, then rewrites the report with them.
+    pub fn apply(&mut self, report: &mut Report) {
+        let mut ranked: Vec<(f64, &str, &str)> = report
+            .accounts
+            .iter()
+            .map(|account| (account.total_cost, account.account_id.as_str(), account.account_name.as_str()))
+            .chain(report.excluded_accounts.iter().map(|account| (account.total_cost, account.account_id.as_str(), account.account_name.as_str())))
+            .collect();
+        ranked.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+        let unranked = report.errors.iter().filter_map(|error| error.account_id.as_deref()).map(|id| (id, ""));
+        let mut new_accounts: Vec<(String, String)> = Vec::new();
+        for (id, name) in ranked.into_iter().map(|(_, id, name)| (id, name)).chain(unranked) {
+            if !self.accounts.contains_key(id) && !new_accounts.iter().any(|(new, _)| new == id) {
+                new_accounts.push((id.to_string(), name.to_string()));
+            }
+        }
+        if self.width == 0 {
+            self.width = new_accounts.len().to_string().len().max(2);
+        }
+        for (id, name) in new_accounts {
+            let pseudonym = format!("acct-{:0width$}", self.accounts.len() + 1, width = self.width);
+            self.names.insert(pseudonym.clone(), RedactedAccount { account_id: id.clone(), account_name: name });
+            self.accounts.insert(id, pseudonym);
+        }
+        if self.redact_profiles {
+            let mut profiles = report.metadata.profiles.clone();
+            profiles.extend(report.accounts.iter().map(|account| account.profile.clone()));
+            for profile in profiles {
+                if !self.profiles.contains_key(&profile) {
+                    let pseudonym = format!("profile-{:02}", self.profiles.len() + 1);
+                    self.profiles.insert(profile, pseudonym);
+                }
+            }
+        }
+        self.rewrite(report);
+    }
+
+    fn account(&self, id: &str) -> String {
+        self.accounts.get(id).cloned().unwrap_or_else(|| REDACTED.to_string())
+    }
+
+    fn profile(&self, profile: &str) -> String {
+        match self.profiles.get(profile) {
+            Some(pseudonym) => pseudonym.clone(),
+            None => profile.to_string(),
+        }
+    }
+
+    /// Replaces every known account ID inside free text such as error messages.
+    fn text(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for (id, pseudonym) in &self.accounts {
+            text = text.replace(id.as_str(), pseudonym);
+        }
+        text
+    }
+
+    fn rewrite(&self, report: &mut Report) {
+        for account in &mut report.accounts {
+            account.account_id = self.account(&account.account_id);
+            account.account_name = account.account_id.clone();
+            account.original_account_name = None;
+            account.profile = self.profile(&account.profile);
+            // Resource IDs such as ARNs embed the account ID
+            for resource in account.top_resources.iter_mut().flat_map(|top| top.resources.iter_mut()) {
+                resource.resource_id = self.text(&resource.resource_id);
+            }
+        }
+        for account in &mut report.unified_view {
+            account.account_id = self.account(&account.account_id);
+            account.account_name = account.account_id.clone();
+            account.profile = self.profile(&account.profile);
+        }
+        for account in &mut report.excluded_accounts {
+            account.account_id = self.account(&account.account_id);
+            account.account_name = account.account_id.clone();
+            account.profile = self.profile(&account.profile);
+        }
+        for split in &mut report.weekday_analysis {
+            split.account_id = self.account(&split.account_id);
+            split.account_name = split.account_id.clone();
+            split.profile = self.profile(&split.profile);
+        }
+        for hourly in &mut report.hourly_profiles {
+            hourly.account_id = self.account(&hourly.account_id);
+            hourly.account_name = hourly.account_id.clone();
+            hourly.profile = self.profile(&hourly.profile);
+        }
+        for alert in &mut report.alerts {
+            if let Some(id) = &alert.account_id {
+                let pseudonym = self.account(id);
+                alert.account_name = Some(pseudonym.clone());
+                alert.account_id = Some(pseudonym);
+            }
+            alert.profile = alert.profile.as_deref().map(|profile| self.profile(profile));
+        }
+        if let Some(hub) = &mut report.optimization_hub {
+            for account in &mut hub.by_account {
+                account.account_id = self.account(&account.account_id);
+                account.account_name = account.account_id.clone();
+                account.profile = self.profile(&account.profile);
+            }
+            for recommendation in &mut hub.top_recommendations {
+                recommendation.account_id = self.account(&recommendation.account_id);
+                recommendation.account_name = recommendation.account_id.clone();
+                recommendation.profile = self.profile(&recommendation.profile);
+                recommendation.resource = self.text(&recommendation.resource);
+            }
+            for note in &mut hub.notes {
+                note.profile = self.profile(&note.profile);
+            }
+        }
+        for recommendation in report.sp_recommendations.iter_mut().flatten() {
+            recommendation.profile = self.profile(&recommendation.profile);
+            for account in &mut recommendation.accounts {
+                account.account_id = self.account(&account.account_id);
+                account.account_name = account.account_name.as_ref().map(|_| account.account_id.clone());
+            }
+        }
+        if let Some(advisor) = &mut report.trusted_advisor {
+            for account in &mut advisor.accounts {
+                account.account_id = self.account(&account.account_id);
+                account.account_name = account.account_id.clone();
+                account.profile = self.profile(&account.profile);
+            }
+            for note in &mut advisor.notes {
+                note.profile = self.profile(&note.profile);
+            }
+        }
+        for error in &mut report.errors {
+            error.error = self.text(&error.error);
+            error.account_id = error.account_id.as_deref().map(|id| self.account(id));
+            error.profile = self.profile(&error.profile);
+        }
+
+        let metadata = &mut report.metadata;
+        metadata.profiles = metadata.profiles.iter().map(|profile| self.profile(profile)).collect();
+        for ids in [&mut metadata.filters.account_ids, &mut metadata.filters.exclude_accounts].into_iter().flatten() {
+            *ids = ids.iter().map(|id| self.account(id)).collect();
+        }
+        for timing in &mut metadata.timings {
+            timing.account_id = timing.account_id.as_deref().map(|id| self.account(id));
+            timing.profile = timing.profile.as_deref().map(|profile| self.profile(profile));
+        }
+
+        report.accounts.sort_by(|a, b| (&a.profile, &a.account_id).cmp(&(&b.profile, &b.account_id)));
+        report.unified_view.sort_by(|a, b| (&a.profile, &a.account_id).cmp(&(&b.profile, &b.account_id)));
+        report.excluded_accounts.sort_by(|a, b| (&a.profile, &a.account_id).cmp(&(&b.profile, &b.account_id)));
+    }
+
+    /// Writes the pseudonym → real account (and profile) mapping for `--redact-map`.
+    pub fn write_map(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let profiles: BTreeMap<&String, &String> = self.profiles.iter().map(|(profile, pseudonym)| (pseudonym, profile)).collect();
+        let map = serde_json::json!({ "accounts": self.names, "profiles": profiles });
+        std::fs::write(path, serde_json::to_string_pretty(&map)?)
+            .map_err(|e| format!("Failed to write --redact-map {}: {}", path, e))?;
+        Ok(())
+    }
+}
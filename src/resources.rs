@@ -0,0 +1,70 @@
+use aws_sdk_costexplorer::error::{ProvideErrorMetadata, SdkError};
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Days of resource-level data Cost Explorer keeps, counting today.
+pub const RESOURCE_DAYS: i64 = 14;
+
+/// Shown when the payer hasn't opted in to resource-level data.
+pub const NOT_ENABLED: &str = "Resource-level data is not enabled in Cost Explorer. The management account can turn on \
+     \"Hourly and resource-level data\" (with resource-level data for the service) in Cost Explorer preferences; \
+     data is only collected from then on.";
+
+/// One resource's cost over the resource-level window.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResourceCost {
+    pub resource_id: String,
+    pub total_cost: f64,
+    pub daily_costs: BTreeMap<String, f64>,
+}
+
+/// The costliest resources of `--top-resources` for one account.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TopResources {
+    pub service: String,
+    /// First day covered.
+    pub start: String,
+    /// Day after the last day covered.
+    pub end: String,
+    /// Days in the window, in order, for table and CSV columns.
+    pub days: Vec<String>,
+    pub resources: Vec<ResourceCost>,
+    /// Why resources couldn't be listed for this account.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl TopResources {
+    /// An empty result over the resource-level window ending today (inclusive).
+    pub fn new(service: &str, today: NaiveDate) -> Self {
+        let start = today - Duration::days(RESOURCE_DAYS - 1);
+        TopResources {
+            service: service.to_string(),
+            start: start.format("%Y-%m-%d").to_string(),
+            end: (today + Duration::days(1)).format("%Y-%m-%d").to_string(),
+            days: (0..RESOURCE_DAYS).map(|offset| (start + Duration::days(offset)).format("%Y-%m-%d").to_string()).collect(),
+            resources: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Keeps the `count` costliest resources of per-resource daily costs.
+    pub fn set_resources(&mut self, daily: BTreeMap<String, BTreeMap<String, f64>>, count: usize) {
+        let mut resources: Vec<ResourceCost> = daily
+            .into_iter()
+            .map(|(resource_id, daily_costs)| ResourceCost { resource_id, total_cost: daily_costs.values().sum(), daily_costs })
+            .filter(|resource| resource.total_cost > 0.0)
+            .collect();
+        resources.sort_by(|a, b| b.total_cost.total_cmp(&a.total_cost).then_with(|| a.resource_id.cmp(&b.resource_id)));
+        resources.truncate(count);
+        self.resources = resources;
+    }
+}
+
+/// Cost Explorer rejects resource-level queries unless resource-level data is enabled on the payer.
+pub fn is_not_enabled<E: ProvideErrorMetadata, R>(err: &SdkError<E, R>) -> bool {
+    let message = err.message().unwrap_or_default().to_lowercase();
+    err.code() == Some("DataUnavailableException")
+        || (err.code() == Some("ValidationException") && message.contains("resource") && (message.contains("enable") || message.contains("opt")))
+}
@@ -0,0 +1,108 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Per-service costs of one account, held as one column per service over a shared, sorted period
+/// index. Each period label is stored once per account instead of once per service, which keeps
+/// long daily and hourly ranges with many services compact.
+#[derive(Debug, Default, Clone)]
+pub struct ServiceCosts {
+    periods: Vec<String>,
+    /// Costs of each service, aligned with `periods`; zero where the service had no cost.
+    columns: BTreeMap<String, Vec<f64>>,
+}
+
+impl ServiceCosts {
+    pub fn from_maps(maps: BTreeMap<String, BTreeMap<String, f64>>) -> Self {
+        let periods: Vec<String> = maps
+            .values()
+            .flat_map(|totals| totals.keys())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .cloned()
+            .collect();
+        let columns = maps
+            .into_iter()
+            .map(|(service, totals)| {
+                let mut column = vec![0.0; periods.len()];
+                for (period, cost) in totals {
+                    if let Ok(index) = periods.binary_search(&period) {
+                        column[index] += cost;
+                    }
+                }
+                (service, column)
+            })
+            .collect();
+        ServiceCosts { periods, columns }
+    }
+
+    pub fn periods(&self) -> &[String] {
+        &self.periods
+    }
+
+    /// Each service with its costs, aligned with `periods()`.
+    pub fn columns(&self) -> impl Iterator<Item = (&String, &[f64])> {
+        self.columns.iter().map(|(service, column)| (service, column.as_slice()))
+    }
+
+    /// Re-keys every period through `relabel`, summing periods that share a new label and dropping
+    /// those mapped to `None`.
+    pub fn relabel(&self, relabel: impl Fn(&str) -> Option<String>) -> Self {
+        let targets: Vec<Option<String>> = self.periods.iter().map(|period| relabel(period)).collect();
+        let periods: Vec<String> = targets.iter().flatten().collect::<BTreeSet<_>>().into_iter().cloned().collect();
+        let slots: Vec<Option<usize>> = targets
+            .iter()
+            .map(|target| target.as_ref().and_then(|label| periods.binary_search(label).ok()))
+            .collect();
+        let columns = self
+            .columns
+            .iter()
+            .map(|(service, costs)| {
+                let mut column = vec![0.0; periods.len()];
+                for (cost, slot) in costs.iter().zip(&slots) {
+                    if let Some(index) = slot {
+                        column[*index] += cost;
+                    }
+                }
+                (service.clone(), column)
+            })
+            .collect();
+        ServiceCosts { periods, columns }
+    }
+
+    /// Re-keys every service through `rename`, summing the columns of services that share a new
+    /// name. Also returns the original services behind each renamed column.
+    pub fn rename(&self, rename: impl Fn(&str) -> Option<String>) -> (Self, BTreeMap<String, Vec<String>>) {
+        let mut columns: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+        let mut sources: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (service, costs) in &self.columns {
+            let name = match rename(service) {
+                Some(name) => {
+                    sources.entry(name.clone()).or_default().push(service.clone());
+                    name
+                }
+                None => service.clone(),
+            };
+            let column = columns.entry(name).or_insert_with(|| vec![0.0; self.periods.len()]);
+            for (total, cost) in column.iter_mut().zip(costs) {
+                *total += cost;
+            }
+        }
+        (ServiceCosts { periods: self.periods.clone(), columns }, sources)
+    }
+
+    /// Per-service period maps, leaving out periods without cost.
+    pub fn to_maps(&self) -> BTreeMap<String, BTreeMap<String, f64>> {
+        self.columns
+            .iter()
+            .map(|(service, column)| {
+                let totals = self
+                    .periods
+                    .iter()
+                    .zip(column)
+                    .filter(|(_, cost)| **cost != 0.0)
+                    .map(|(period, cost)| (period.clone(), *cost))
+                    .collect();
+                (service.clone(), totals)
+            })
+            .collect()
+    }
+}
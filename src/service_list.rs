@@ -0,0 +1,140 @@
+use crate::{cost_explorer_client, load_discovery_config, Cli, COST_METRIC};
+use aws_sdk_costexplorer::error::ProvideErrorMetadata;
+use aws_sdk_costexplorer::types::{Context, DateInterval, Dimension, Granularity, GroupDefinition, GroupDefinitionType};
+use aws_sdk_costexplorer::Client as CostExplorerClient;
+use futures::future::join_all;
+use prettytable::{format, Cell, Row, Table};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// A Cost Explorer service name with its cost over the range.
+#[derive(Serialize, Debug)]
+pub struct ServiceEntry {
+    pub service: String,
+    pub cost: f64,
+}
+
+/// The services one profile has usage of, or why they couldn't be listed.
+#[derive(Serialize, Debug)]
+pub struct ProfileServices {
+    pub profile: String,
+    /// Highest cost first.
+    pub services: Vec<ServiceEntry>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Lists every profile's services concurrently, keeping those whose name contains `search`
+/// (ignoring case). A failing profile gets an error entry instead of stopping the others.
+pub async fn list(cli: &Cli, profiles: &[String], search: Option<&str>) -> Vec<ProfileServices> {
+    join_all(profiles.iter().map(|profile| list_profile(cli, profile, search))).await
+}
+
+async fn list_profile(cli: &Cli, profile: &str, search: Option<&str>) -> ProfileServices {
+    let config = load_discovery_config(cli, profile).await;
+    let client = cost_explorer_client(cli, &config);
+    let fetched = match fetch_names(cli, &client).await {
+        Ok(names) => fetch_costs(cli, &client).await.map(|costs| (names, costs)),
+        Err(e) => Err(e),
+    };
+    let (names, costs) = match fetched {
+        Ok(fetched) => fetched,
+        Err(error) => return ProfileServices { profile: profile.to_string(), services: Vec::new(), error: Some(error) },
+    };
+    let search = search.map(str::to_lowercase);
+    let mut services: Vec<ServiceEntry> = names
+        .into_iter()
+        .filter(|name| search.as_ref().is_none_or(|search| name.to_lowercase().contains(search)))
+        .map(|name| ServiceEntry { cost: costs.get(&name).copied().unwrap_or(0.0), service: name })
+        .collect();
+    services.sort_by(|a, b| b.cost.total_cmp(&a.cost).then_with(|| a.service.cmp(&b.service)));
+    ProfileServices { profile: profile.to_string(), services, error: None }
+}
+
+fn time_period(cli: &Cli) -> DateInterval {
+    DateInterval::builder().start(&cli.start_date).end(&cli.query_end_date).build().expect("start and end are set")
+}
+
+/// Names of the services with usage in the range.
+async fn fetch_names(cli: &Cli, client: &CostExplorerClient) -> Result<Vec<String>, String> {
+    let mut names = Vec::new();
+    let mut next_page_token = None;
+    loop {
+        cli.ce_limiter.acquire().await;
+        let response = client
+            .get_dimension_values()
+            .time_period(time_period(cli))
+            .dimension(Dimension::Service)
+            .context(Context::CostAndUsage)
+            .set_next_page_token(next_page_token)
+            .send()
+            .await
+            .map_err(|e| error_message(&e))?;
+        names.extend(response.dimension_values.into_iter().filter_map(|value| value.value));
+        next_page_token = response.next_page_token;
+        if next_page_token.is_none() {
+            return Ok(names);
+        }
+    }
+}
+
+/// Cost of each service over the whole range, in one monthly query grouped by service.
+async fn fetch_costs(cli: &Cli, client: &CostExplorerClient) -> Result<HashMap<String, f64>, String> {
+    let mut costs: HashMap<String, f64> = HashMap::new();
+    let mut next_page_token = None;
+    loop {
+        cli.ce_limiter.acquire().await;
+        let response = client
+            .get_cost_and_usage()
+            .time_period(time_period(cli))
+            .granularity(Granularity::Monthly)
+            .metrics(COST_METRIC)
+            .group_by(GroupDefinition::builder().r#type(GroupDefinitionType::Dimension).key("SERVICE").build())
+            .set_next_page_token(next_page_token)
+            .send()
+            .await
+            .map_err(|e| error_message(&e))?;
+        for group in response.results_by_time().iter().flat_map(|result| result.groups()) {
+            let cost = group
+                .metrics()
+                .and_then(|m| m.get(COST_METRIC))
+                .and_then(|m| m.amount())
+                .and_then(|a| a.parse::<f64>().ok())
+                .unwrap_or(0.0);
+            *costs.entry(group.keys().join(", ")).or_insert(0.0) += cost;
+        }
+        next_page_token = response.next_page_token;
+        if next_page_token.is_none() {
+            return Ok(costs);
+        }
+    }
+}
+
+fn error_message<E: ProvideErrorMetadata + std::error::Error>(error: &E) -> String {
+    match (error.code(), error.message()) {
+        (Some(code), Some(message)) => format!("{}: {}", code, message),
+        _ => error.to_string(),
+    }
+}
+
+/// Prints one table of services per profile.
+pub fn print(cli: &Cli, results: &[ProfileServices]) {
+    for result in results {
+        if let Some(error) = &result.error {
+            println!("\nServices for Profile {}: unavailable - {}", result.profile, error);
+            continue;
+        }
+        if result.services.is_empty() {
+            println!("\nServices for Profile {} ({} to {}): none", result.profile, cli.start_date, cli.end_date);
+            continue;
+        }
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_DEFAULT);
+        table.set_titles(Row::new(vec![Cell::new("Service").style_spec("bFc"), Cell::new("Total Cost (USD)").style_spec("bFc")]));
+        for service in &result.services {
+            table.add_row(Row::new(vec![Cell::new(&service.service), Cell::new(&format!("{:.2}", service.cost)).style_spec("Fr")]));
+        }
+        println!("\nServices for Profile {} ({} to {}):", result.profile, cli.start_date, cli.end_date);
+        table.printstd();
+    }
+}
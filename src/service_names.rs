@@ -0,0 +1,224 @@
+use clap::ValueEnum;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+
+/// Where `--short-names` abbreviates Cost Explorer service names.
+#[derive(ValueEnum, Clone, Debug, PartialEq)]
+pub enum ShortNames {
+    /// Everywhere, including CSV, JSON, notifications and published metrics.
+    Always,
+    /// Terminal tables and the TUI only; CSV and JSON keep the full names.
+    Tables,
+    /// Never.
+    Never,
+}
+
+/// Cost Explorer's long service names and their abbreviations. Services not listed keep their full name.
+const SHORT_NAMES: &[(&str, &str)] = &[
+    ("Amazon Elastic Compute Cloud - Compute", "EC2"),
+    ("Amazon Simple Storage Service", "S3"),
+    ("Amazon Relational Database Service", "RDS"),
+    ("AWS Lambda", "Lambda"),
+    ("AmazonCloudWatch", "CloudWatch"),
+    ("Amazon DynamoDB", "DynamoDB"),
+    ("Amazon Virtual Private Cloud", "VPC"),
+    ("Amazon Elastic Load Balancing", "ELB"),
+    ("Amazon CloudFront", "CloudFront"),
+    ("Amazon Elastic Container Service", "ECS"),
+    ("Amazon Elastic Container Service for Kubernetes", "EKS"),
+    ("Amazon EC2 Container Registry (ECR)", "ECR"),
+    ("Amazon Elastic File System", "EFS"),
+    ("Amazon ElastiCache", "ElastiCache"),
+    ("Amazon Route 53", "Route 53"),
+    ("Amazon Simple Notification Service", "SNS"),
+    ("Amazon Simple Queue Service", "SQS"),
+    ("Amazon Simple Email Service", "SES"),
+    ("Amazon API Gateway", "API Gateway"),
+    ("Amazon Kinesis", "Kinesis"),
+    ("Amazon Kinesis Firehose", "Firehose"),
+    ("Amazon Managed Streaming for Apache Kafka", "MSK"),
+    ("Amazon OpenSearch Service", "OpenSearch"),
+    ("Amazon Redshift", "Redshift"),
+    ("Amazon Athena", "Athena"),
+    ("Amazon Elastic MapReduce", "EMR"),
+    ("Amazon SageMaker", "SageMaker"),
+    ("Amazon DocumentDB (with MongoDB compatibility)", "DocumentDB"),
+    ("Amazon GuardDuty", "GuardDuty"),
+    ("Amazon Inspector", "Inspector"),
+    ("Amazon Cognito", "Cognito"),
+    ("Amazon Lightsail", "Lightsail"),
+    ("Amazon QuickSight", "QuickSight"),
+    ("AWS Glue", "Glue"),
+    ("AWS Step Functions", "Step Functions"),
+    ("AWS Key Management Service", "KMS"),
+    ("AWS Secrets Manager", "Secrets Manager"),
+    ("AWS Systems Manager", "SSM"),
+    ("AWS CloudTrail", "CloudTrail"),
+    ("AWS Config", "Config"),
+    ("AWS Security Hub", "Security Hub"),
+    ("AWS WAF", "WAF"),
+    ("AWS Backup", "Backup"),
+    ("AWS Direct Connect", "Direct Connect"),
+    ("AWS Data Transfer", "Data Transfer"),
+    ("AWS Cost Explorer", "Cost Explorer"),
+    ("AWS X-Ray", "X-Ray"),
+    ("AWS CodeBuild", "CodeBuild"),
+];
+
+const fn same(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+const fn unique_names() -> bool {
+    let mut i = 0;
+    while i < SHORT_NAMES.len() {
+        let mut j = i + 1;
+        while j < SHORT_NAMES.len() {
+            if same(SHORT_NAMES[i].0, SHORT_NAMES[j].0) || same(SHORT_NAMES[i].1, SHORT_NAMES[j].1) {
+                return false;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    true
+}
+
+// Two services sharing an abbreviation would be indistinguishable in a table, so the build fails instead.
+const _: () = assert!(unique_names(), "SHORT_NAMES has a duplicate long or short name");
+
+/// The abbreviation for a Cost Explorer service name, or the name itself if it has none.
+pub fn short_name(service: &str) -> &str {
+    SHORT_NAMES
+        .iter()
+        .find(|(long, _)| *long == service)
+        .map_or(service, |(_, short)| short)
+}
+
+/// The Cost Explorer service name for an abbreviation such as `EC2`, or the name itself if it isn't one.
+pub fn long_name(name: &str) -> &str {
+    SHORT_NAMES
+        .iter()
+        .find(|(_, short)| short.eq_ignore_ascii_case(name))
+        .map_or(name, |(long, _)| long)
+}
+
+/// Display names from `--service-aliases`, keyed by Cost Explorer service name or a glob where `*`
+/// matches any run of characters and `?` a single one.
+#[derive(Debug, Default, Clone)]
+pub struct ServiceAliases {
+    exact: HashMap<String, String>,
+    /// Glob keys, sorted; the first match wins.
+    globs: Vec<(String, String)>,
+}
+
+impl ServiceAliases {
+    /// Reads the alias map, as TOML when the path ends in `.toml` and JSON otherwise.
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let contents = std::fs::read_to_string(path).map_err(|e| format!("Failed to read --service-aliases file {}: {}", path, e))?;
+        let map: BTreeMap<String, String> = if path.ends_with(".toml") {
+            toml::from_str(&contents).map_err(|e| format!("Invalid --service-aliases file {}: {}", path, e))?
+        } else {
+            serde_json::from_str(&contents).map_err(|e| format!("Invalid --service-aliases file {}: {}", path, e))?
+        };
+        let mut aliases = ServiceAliases::default();
+        for (pattern, alias) in map {
+            if alias.trim().is_empty() {
+                return Err(format!("Empty alias for '{}' in --service-aliases file {}", pattern, path).into());
+            }
+            if pattern.contains(['*', '?']) {
+                aliases.globs.push((pattern, alias));
+            } else {
+                aliases.exact.insert(pattern, alias);
+            }
+        }
+        Ok(aliases)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.exact.is_empty() && self.globs.is_empty()
+    }
+
+    /// The alias of a service: an exact entry first, then the first matching glob.
+    pub fn alias(&self, service: &str) -> Option<&str> {
+        self.exact
+            .get(service)
+            .or_else(|| self.globs.iter().find(|(pattern, _)| glob_match(pattern, service)).map(|(_, alias)| alias))
+            .map(String::as_str)
+    }
+}
+
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let (pattern, text): (Vec<char>, Vec<char>) = (pattern.chars().collect(), text.chars().collect());
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text position it currently absorbs up to, for backtracking
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            star = Some((star_p, star_t + 1));
+            p = star_p + 1;
+            t = star_t + 1;
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn no_two_services_share_a_short_name() {
+        let mut seen = HashSet::new();
+        for (long, short) in SHORT_NAMES {
+            // `long_name` matches abbreviations case-insensitively
+            assert!(seen.insert(short.to_ascii_lowercase()), "{} and another service are both abbreviated {}", long, short);
+            assert!(SHORT_NAMES.iter().all(|(other, _)| other == long || !other.eq_ignore_ascii_case(short)), "{} is another service's name", short);
+        }
+    }
+
+    #[test]
+    fn short_and_long_names_round_trip() {
+        for (long, short) in SHORT_NAMES {
+            assert_eq!(short_name(long), *short);
+            assert_eq!(long_name(short), *long);
+            assert_eq!(long_name(&short.to_ascii_lowercase()), *long);
+        }
+        assert_eq!(short_name("Amazon Bedrock"), "Amazon Bedrock");
+        assert_eq!(long_name("Bedrock"), "Bedrock");
+    }
+
+    #[test]
+    fn aliases_prefer_exact_entries_to_globs() {
+        let dir = crate::tests::temp_dir("service-aliases");
+        let path = dir.join("aliases.toml");
+        std::fs::write(&path, "\"Amazon Elastic Compute Cloud - Compute\" = \"Compute\"\n\"Amazon Elastic *\" = \"Elastic\"\n\"AWS ?lue\" = \"ETL\"\n").unwrap();
+        let aliases = ServiceAliases::load(path.to_str().unwrap()).unwrap();
+
+        assert_eq!(aliases.alias("Amazon Elastic Compute Cloud - Compute"), Some("Compute"));
+        assert_eq!(aliases.alias("Amazon Elastic File System"), Some("Elastic"));
+        assert_eq!(aliases.alias("AWS Glue"), Some("ETL"));
+        assert_eq!(aliases.alias("AWS Lambda"), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,254 @@
+use crate::alerts::Alert;
+use crate::{AccountCostData, ReportMetadata};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::error::Error;
+
+const TOP_ACCOUNTS: usize = 5;
+
+/// Builds the Block Kit payload summarizing a finished report. The change compares the latest two
+/// complete periods; the in-progress `partial_period` is shown on its own.
+pub fn build_message(
+    metadata: &ReportMetadata,
+    accounts: &[AccountCostData],
+    global_period_totals: &BTreeMap<String, f64>,
+    partial_period: Option<&str>,
+    total_cost: f64,
+    alerts: &[Alert],
+) -> Value {
+    let mut blocks = vec![json!({
+        "type": "header",
+        "text": {
+            "type": "plain_text",
+            "text": format!("AWS cost report {} to {}", metadata.start_date, metadata.end_date),
+        }
+    })];
+
+    let mut summary = format!("*Total cost:* ${:.2}", total_cost);
+    let mut periods = global_period_totals.iter().rev().filter(|(period, _)| Some(period.as_str()) != partial_period);
+    if let (Some((latest, latest_cost)), Some((previous, previous_cost))) = (periods.next(), periods.next()) {
+        let change = if *previous_cost == 0.0 {
+            if *latest_cost > 0.0 { "new".to_string() } else { "n/a".to_string() }
+        } else {
+            format!("{:+.1}%", (latest_cost - previous_cost) / previous_cost * 100.0)
+        };
+        summary.push_str(&format!(
+            "\n*Change vs previous period:* {} ({} ${:.2} vs {} ${:.2})",
+            change, latest, latest_cost, previous, previous_cost
+        ));
+    }
+    if let Some((partial, partial_cost)) = partial_period.and_then(|partial| global_period_totals.get_key_value(partial)) {
+        summary.push_str(&format!("\n*{} so far (in progress):* ${:.2}", partial, partial_cost));
+    }
+    blocks.push(json!({ "type": "section", "text": { "type": "mrkdwn", "text": summary } }));
+
+    if !alerts.is_empty() {
+        let lines: Vec<String> = alerts.iter().map(|alert| format!(":rotating_light: {}", alert.message())).collect();
+        blocks.push(json!({
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": format!("*ALERT*\n{}", lines.join("\n")) }
+        }));
+    }
+
+    let mut ranked: Vec<&AccountCostData> = accounts.iter().collect();
+    ranked.sort_by(|a, b| {
+        b.total_cost
+            .partial_cmp(&a.total_cost)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.account_id.cmp(&b.account_id))
+    });
+    if !ranked.is_empty() {
+        let lines: Vec<String> = ranked
+            .iter()
+            .take(TOP_ACCOUNTS)
+            .enumerate()
+            .map(|(i, account)| {
+                format!("{}. {} ({}): ${:.2}", i + 1, account.account_name, account.account_id, account.total_cost)
+            })
+            .collect();
+        blocks.push(json!({
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": format!("*Top accounts by cost*\n{}", lines.join("\n")) }
+        }));
+    }
+
+    json!({
+        "text": format!("AWS cost report {} to {}: ${:.2}", metadata.start_date, metadata.end_date, total_cost),
+        "blocks": blocks,
+    })
+}
+
+pub async fn post_webhook(client: &reqwest::Client, url: &str, message: &Value) -> Result<(), Box<dyn Error>> {
+    let response = client.post(url).json(message).send().await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Slack webhook returned {}: {}", status, body).into());
+    }
+    Ok(())
+}
+
+/// Checks the `ok` flag of a Slack Web API response.
+fn slack_api_result(body: Value) -> Result<Value, Box<dyn Error>> {
+    if body["ok"].as_bool() == Some(true) {
+        Ok(body)
+    } else {
+        Err(format!("Slack API error: {}", body["error"].as_str().unwrap_or("unknown error")).into())
+    }
+}
+
+/// Uploads a file to a channel using the external upload flow (requires a bot token with `files:write`).
+pub async fn upload_file(
+    client: &reqwest::Client,
+    token: &str,
+    channel: &str,
+    path: &str,
+    title: &str,
+) -> Result<(), Box<dyn Error>> {
+    let bytes = std::fs::read(path)?;
+    let filename = std::path::Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "chart.png".to_string());
+
+    let length = bytes.len().to_string();
+    let ticket = slack_api_result(
+        client
+            .post("https://slack.com/api/files.getUploadURLExternal")
+            .bearer_auth(token)
+            .form(&[("filename", filename.as_str()), ("length", length.as_str())])
+            .send()
+            .await?
+            .json()
+            .await?,
+    )?;
+    let upload_url = ticket["upload_url"].as_str().ok_or("Slack API response missing upload_url")?;
+    let file_id = ticket["file_id"].as_str().ok_or("Slack API response missing file_id")?;
+
+    let upload = client.post(upload_url).body(bytes).send().await?;
+    if !upload.status().is_success() {
+        return Err(format!("Slack file upload returned {}", upload.status()).into());
+    }
+
+    slack_api_result(
+        client
+            .post("https://slack.com/api/files.completeUploadExternal")
+            .bearer_auth(token)
+            .json(&json!({ "files": [{ "id": file_id, "title": title }], "channel_id": channel }))
+            .send()
+            .await?
+            .json()
+            .await?,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{account_costs, cli, monthly, report};
+    use crate::Report;
+
+    /// The report of seven accounts costing 10 to 60 a month from January to March 2024, two of
+    /// them tied at 40.
+    fn seven_accounts() -> Report {
+        let cli = cli(&["--start-date", "2024-01-01", "--end-date", "2024-04-01"]);
+        let accounts: Vec<_> = [("666666666666", 10.0), ("555555555555", 20.0), ("444444444444", 30.0), ("333333333333", 40.0), ("222222222222", 50.0), ("111111111111", 60.0), ("777777777777", 40.0)]
+            .into_iter()
+            .map(|(account_id, cost)| account_costs("prod", account_id, &[("Amazon EC2", monthly(&[cost; 3]))]))
+            .collect();
+        report(&cli, &accounts)
+    }
+
+    fn totals(costs: &[(&str, f64)]) -> BTreeMap<String, f64> {
+        costs.iter().map(|(period, cost)| (period.to_string(), *cost)).collect()
+    }
+
+    fn section(message: &Value, index: usize) -> &str {
+        message["blocks"][index]["text"]["text"].as_str().unwrap()
+    }
+
+    #[test]
+    fn header_and_total_describe_the_report() {
+        let report = seven_accounts();
+        let message = build_message(&report.metadata, &report.accounts, &report.global_period_totals, None, report.total_cost, &[]);
+
+        assert_eq!(message["text"], "AWS cost report 2024-01-01 to 2024-04-01: $750.00");
+        assert_eq!(message["blocks"][0]["type"], "header");
+        assert_eq!(message["blocks"][0]["text"]["text"], "AWS cost report 2024-01-01 to 2024-04-01");
+        assert_eq!(section(&message, 1), "*Total cost:* $750.00\n*Change vs previous period:* +0.0% (2024-03-01 $250.00 vs 2024-02-01 $250.00)");
+    }
+
+    #[test]
+    fn change_compares_the_latest_complete_periods() {
+        let report = seven_accounts();
+        let global = totals(&[("2024-01-01", 100.0), ("2024-02-01", 150.0), ("2024-03-01", 40.0)]);
+
+        let message = build_message(&report.metadata, &report.accounts, &global, Some("2024-03-01"), 290.0, &[]);
+        assert_eq!(
+            section(&message, 1),
+            "*Total cost:* $290.00\n*Change vs previous period:* +50.0% (2024-02-01 $150.00 vs 2024-01-01 $100.00)\n*2024-03-01 so far (in progress):* $40.00"
+        );
+
+        // Without a partial period the latest one is compared as it is
+        let message = build_message(&report.metadata, &report.accounts, &global, None, 290.0, &[]);
+        assert!(section(&message, 1).contains("*Change vs previous period:* -73.3% (2024-03-01 $40.00 vs 2024-02-01 $150.00)"));
+    }
+
+    #[test]
+    fn change_from_a_zero_period_is_new_or_not_applicable() {
+        let report = seven_accounts();
+        let message = build_message(&report.metadata, &report.accounts, &totals(&[("2024-01-01", 0.0), ("2024-02-01", 25.0)]), None, 25.0, &[]);
+        assert!(section(&message, 1).ends_with("*Change vs previous period:* new (2024-02-01 $25.00 vs 2024-01-01 $0.00)"));
+
+        let message = build_message(&report.metadata, &report.accounts, &totals(&[("2024-01-01", 0.0), ("2024-02-01", 0.0)]), None, 0.0, &[]);
+        assert!(section(&message, 1).ends_with("*Change vs previous period:* n/a (2024-02-01 $0.00 vs 2024-01-01 $0.00)"));
+
+        // A single period has nothing to compare with
+        let message = build_message(&report.metadata, &report.accounts, &totals(&[("2024-01-01", 5.0)]), None, 5.0, &[]);
+        assert_eq!(section(&message, 1), "*Total cost:* $5.00");
+    }
+
+    #[test]
+    fn top_accounts_are_the_five_costliest_with_ties_by_account_id() {
+        let report = seven_accounts();
+        let message = build_message(&report.metadata, &report.accounts, &report.global_period_totals, None, report.total_cost, &[]);
+
+        assert_eq!(message["blocks"].as_array().unwrap().len(), 3);
+        assert_eq!(
+            section(&message, 2),
+            "*Top accounts by cost*\n\
+             1. Account-111111111111 (111111111111): $180.00\n\
+             2. Account-222222222222 (222222222222): $150.00\n\
+             3. Account-333333333333 (333333333333): $120.00\n\
+             4. Account-777777777777 (777777777777): $120.00\n\
+             5. Account-444444444444 (444444444444): $90.00"
+        );
+    }
+
+    #[test]
+    fn alerts_get_a_section_before_the_top_accounts() {
+        let report = seven_accounts();
+        let alerts = [
+            Alert { scope: "global".to_string(), profile: None, account_id: None, account_name: None, period: "2024-03-01".to_string(), cost: 250.0, threshold: 200.0 },
+            Alert {
+                scope: "account".to_string(),
+                profile: Some("prod".to_string()),
+                account_id: Some("111111111111".to_string()),
+                account_name: Some("Account-111111111111".to_string()),
+                period: "2024-03-01".to_string(),
+                cost: 60.0,
+                threshold: 50.0,
+            },
+        ];
+        let message = build_message(&report.metadata, &report.accounts, &report.global_period_totals, None, report.total_cost, &alerts);
+
+        assert_eq!(
+            section(&message, 2),
+            "*ALERT*\n\
+             :rotating_light: Total cost $250.00 in 2024-03-01, over --alert-threshold $200.00\n\
+             :rotating_light: Profile prod Account 111111111111 (Account-111111111111) cost $60.00 in 2024-03-01, over --account-alert-threshold $50.00"
+        );
+        assert!(section(&message, 3).starts_with("*Top accounts by cost*"));
+    }
+}
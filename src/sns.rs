@@ -0,0 +1,75 @@
+use crate::{AccountCostData, ReportMetadata};
+use aws_config::SdkConfig;
+use aws_sdk_sns::types::MessageAttributeValue;
+use aws_sdk_sns::Client as SnsClient;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+
+/// JSON document published to the topic: report metadata, global totals, per-account totals and alerts.
+pub fn build_message(
+    metadata: &ReportMetadata,
+    accounts: &[AccountCostData],
+    total_cost: f64,
+    average_period_cost: f64,
+    alerts: &[String],
+) -> Value {
+    let account_totals: Vec<Value> = accounts
+        .iter()
+        .map(|account| {
+            json!({
+                "profile": account.profile,
+                "account_id": account.account_id,
+                "account_name": account.account_name,
+                "total_cost": account.total_cost,
+            })
+        })
+        .collect();
+
+    json!({
+        "metadata": metadata,
+        "global_summary": {
+            "total_cost": total_cost,
+            "average_monthly_cost": average_period_cost,
+        },
+        "accounts": account_totals,
+        "alerts": alerts,
+    })
+}
+
+/// Attributes subscribers can match in filter policies (e.g. only runs with `alert_count > 0`).
+pub fn message_attributes(total_cost: f64, alert_count: usize) -> Result<HashMap<String, MessageAttributeValue>, Box<dyn Error>> {
+    let mut attributes = HashMap::new();
+    attributes.insert(
+        "total_cost".to_string(),
+        MessageAttributeValue::builder()
+            .data_type("Number")
+            .string_value(format!("{:.2}", total_cost))
+            .build()?,
+    );
+    attributes.insert(
+        "alert_count".to_string(),
+        MessageAttributeValue::builder()
+            .data_type("Number")
+            .string_value(alert_count.to_string())
+            .build()?,
+    );
+    Ok(attributes)
+}
+
+pub async fn publish(
+    config: &SdkConfig,
+    topic_arn: &str,
+    message: &Value,
+    attributes: HashMap<String, MessageAttributeValue>,
+) -> Result<String, Box<dyn Error>> {
+    let response = SnsClient::new(config)
+        .publish()
+        .topic_arn(topic_arn)
+        .subject("AWS cost report")
+        .message(serde_json::to_string(message)?)
+        .set_message_attributes(Some(attributes))
+        .send()
+        .await?;
+    Ok(response.message_id.unwrap_or_default())
+}
@@ -0,0 +1,233 @@
+use crate::{cost_explorer_client, load_discovery_config, Cli, Report};
+use aws_sdk_costexplorer::error::ProvideErrorMetadata;
+use aws_sdk_costexplorer::types::{
+    AccountScope, LookbackPeriodInDays, PaymentOption, SavingsPlansPurchaseRecommendationDetail, SupportedSavingsPlansType, TermInYears,
+};
+use clap::ValueEnum;
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Commitment term of `--sp-recommendations`.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum SpTerm {
+    OneYear,
+    ThreeYears,
+}
+
+/// How a recommended Savings Plan is paid for.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum SpPaymentOption {
+    #[value(name = "no-upfront")]
+    None,
+    #[value(name = "partial-upfront")]
+    Partial,
+    #[value(name = "all-upfront")]
+    All,
+}
+
+/// Days of usage a recommendation is based on; Cost Explorer only offers these three.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum SpLookback {
+    #[value(name = "7")]
+    Seven,
+    #[value(name = "30")]
+    Thirty,
+    #[value(name = "60")]
+    Sixty,
+}
+
+impl SpTerm {
+    fn api_value(self) -> TermInYears {
+        match self {
+            SpTerm::OneYear => TermInYears::OneYear,
+            SpTerm::ThreeYears => TermInYears::ThreeYears,
+        }
+    }
+}
+
+impl SpPaymentOption {
+    fn api_value(self) -> PaymentOption {
+        match self {
+            SpPaymentOption::None => PaymentOption::NoUpfront,
+            SpPaymentOption::Partial => PaymentOption::PartialUpfront,
+            SpPaymentOption::All => PaymentOption::AllUpfront,
+        }
+    }
+}
+
+impl SpLookback {
+    pub fn days(self) -> u32 {
+        match self {
+            SpLookback::Seven => 7,
+            SpLookback::Thirty => 30,
+            SpLookback::Sixty => 60,
+        }
+    }
+
+    fn api_value(self) -> LookbackPeriodInDays {
+        match self {
+            SpLookback::Seven => LookbackPeriodInDays::SevenDays,
+            SpLookback::Thirty => LookbackPeriodInDays::ThirtyDays,
+            SpLookback::Sixty => LookbackPeriodInDays::SixtyDays,
+        }
+    }
+}
+
+/// One linked account's share of a payer-level recommendation.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccountContribution {
+    pub account_id: String,
+    /// Name from the report, for accounts it covers.
+    pub account_name: Option<String>,
+    pub hourly_commitment: f64,
+    pub upfront_cost: f64,
+    pub estimated_monthly_savings: f64,
+    pub estimated_savings_percent: f64,
+    pub estimated_utilization_percent: f64,
+}
+
+/// The Compute Savings Plans purchase Cost Explorer recommends for one profile's payer account.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SpRecommendation {
+    pub profile: String,
+    /// `one-year` or `three-years`.
+    pub term: String,
+    /// `no-upfront`, `partial-upfront` or `all-upfront`.
+    pub payment_option: String,
+    pub lookback_days: u32,
+    pub generated_at: Option<String>,
+    pub hourly_commitment: f64,
+    pub upfront_cost: f64,
+    pub estimated_monthly_savings: f64,
+    pub estimated_savings_percent: f64,
+    /// On-Demand spend over the lookback period that the recommendation is based on.
+    pub current_on_demand_spend: f64,
+    /// Share of that On-Demand spend the commitment would cover.
+    pub estimated_coverage_percent: Option<f64>,
+    /// Share of the commitment the usage would consume, averaged over the accounts by commitment.
+    pub estimated_utilization_percent: Option<f64>,
+    /// Months of savings that pay back the upfront cost; 0 without an upfront payment.
+    pub break_even_months: Option<f64>,
+    pub accounts: Vec<AccountContribution>,
+    /// Why there is no recommendation: too little On-Demand usage, or the request failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+fn amount(value: Option<&str>) -> f64 {
+    value.and_then(|value| value.parse::<f64>().ok()).unwrap_or(0.0)
+}
+
+/// Requests every profile's payer-level recommendation concurrently.
+pub async fn collect(cli: &Cli, profiles: &[String], report: &Report) -> Vec<SpRecommendation> {
+    let names: HashMap<&str, &str> = report
+        .accounts
+        .iter()
+        .map(|account| (account.account_id.as_str(), account.account_name.as_str()))
+        .collect();
+    join_all(profiles.iter().map(|profile| recommend(cli, profile, &names))).await
+}
+
+async fn recommend(cli: &Cli, profile: &str, names: &HashMap<&str, &str>) -> SpRecommendation {
+    let mut recommendation = SpRecommendation {
+        profile: profile.to_string(),
+        term: cli.sp_term.to_possible_value().map(|value| value.get_name().to_string()).unwrap_or_default(),
+        payment_option: cli.sp_payment_option.to_possible_value().map(|value| value.get_name().to_string()).unwrap_or_default(),
+        lookback_days: cli.sp_lookback_days.days(),
+        generated_at: None,
+        hourly_commitment: 0.0,
+        upfront_cost: 0.0,
+        estimated_monthly_savings: 0.0,
+        estimated_savings_percent: 0.0,
+        current_on_demand_spend: 0.0,
+        estimated_coverage_percent: None,
+        estimated_utilization_percent: None,
+        break_even_months: None,
+        accounts: Vec::new(),
+        note: None,
+    };
+    let config = load_discovery_config(cli, profile).await;
+    let client = cost_explorer_client(cli, &config);
+    let mut details: Vec<SavingsPlansPurchaseRecommendationDetail> = Vec::new();
+    let mut next_page_token = None;
+    loop {
+        cli.ce_limiter.acquire().await;
+        let response = client
+            .get_savings_plans_purchase_recommendation()
+            .savings_plans_type(SupportedSavingsPlansType::ComputeSp)
+            .term_in_years(cli.sp_term.api_value())
+            .payment_option(cli.sp_payment_option.api_value())
+            .lookback_period_in_days(cli.sp_lookback_days.api_value())
+            .account_scope(AccountScope::Payer)
+            .set_next_page_token(next_page_token)
+            .send()
+            .await;
+        let response = match response {
+            Ok(response) => response,
+            Err(e) => {
+                recommendation.note = Some(match (e.code(), e.message()) {
+                    (Some("DataUnavailableException"), _) => insufficient_usage(cli),
+                    (Some(code), Some(message)) => format!("unavailable - {}: {}", code, message),
+                    _ => format!("unavailable - {}", e),
+                });
+                return recommendation;
+            }
+        };
+        if recommendation.generated_at.is_none() {
+            recommendation.generated_at = response.metadata().and_then(|metadata| metadata.generation_timestamp()).map(str::to_string);
+        }
+        if let Some(purchase) = response.savings_plans_purchase_recommendation() {
+            if let Some(summary) = purchase.savings_plans_purchase_recommendation_summary() {
+                recommendation.hourly_commitment = amount(summary.hourly_commitment_to_purchase());
+                recommendation.estimated_monthly_savings = amount(summary.estimated_monthly_savings_amount());
+                recommendation.estimated_savings_percent = amount(summary.estimated_savings_percentage());
+                recommendation.current_on_demand_spend = amount(summary.current_on_demand_spend());
+            }
+            details.extend(purchase.savings_plans_purchase_recommendation_details().iter().cloned());
+        }
+        next_page_token = response.next_page_token().map(str::to_string);
+        if next_page_token.is_none() {
+            break;
+        }
+    }
+    if details.is_empty() || recommendation.hourly_commitment <= 0.0 {
+        recommendation.note = Some(insufficient_usage(cli));
+        return recommendation;
+    }
+
+    let remaining_on_demand: f64 = details.iter().map(|detail| amount(detail.estimated_on_demand_cost())).sum();
+    recommendation.accounts = details
+        .iter()
+        .map(|detail| {
+            let account_id = detail.account_id().unwrap_or_default().to_string();
+            AccountContribution {
+                account_name: names.get(account_id.as_str()).map(|name| name.to_string()),
+                account_id,
+                hourly_commitment: amount(detail.hourly_commitment_to_purchase()),
+                upfront_cost: amount(detail.upfront_cost()),
+                estimated_monthly_savings: amount(detail.estimated_monthly_savings_amount()),
+                estimated_savings_percent: amount(detail.estimated_savings_percentage()),
+                estimated_utilization_percent: amount(detail.estimated_average_utilization()),
+            }
+        })
+        .collect();
+    recommendation.accounts.sort_by(|a, b| b.estimated_monthly_savings.total_cmp(&a.estimated_monthly_savings).then_with(|| a.account_id.cmp(&b.account_id)));
+    recommendation.upfront_cost = recommendation.accounts.iter().map(|account| account.upfront_cost).sum();
+    let committed: f64 = recommendation.accounts.iter().map(|account| account.hourly_commitment).sum();
+    recommendation.estimated_utilization_percent = (committed > 0.0).then(|| {
+        recommendation.accounts.iter().map(|account| account.estimated_utilization_percent * account.hourly_commitment).sum::<f64>() / committed
+    });
+    recommendation.estimated_coverage_percent = (recommendation.current_on_demand_spend > 0.0)
+        .then(|| ((1.0 - remaining_on_demand / recommendation.current_on_demand_spend) * 100.0).clamp(0.0, 100.0));
+    recommendation.break_even_months = (recommendation.estimated_monthly_savings > 0.0)
+        .then(|| recommendation.upfront_cost / recommendation.estimated_monthly_savings);
+    recommendation
+}
+
+fn insufficient_usage(cli: &Cli) -> String {
+    format!(
+        "no recommendation: not enough On-Demand compute usage in the last {} days to recommend a commitment",
+        cli.sp_lookback_days.days()
+    )
+}
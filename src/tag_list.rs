@@ -0,0 +1,190 @@
+use crate::{cost_explorer_client, load_discovery_config, Cli, COST_METRIC};
+use aws_sdk_costexplorer::error::ProvideErrorMetadata;
+use aws_sdk_costexplorer::types::{DateInterval, Granularity, GroupDefinition, GroupDefinitionType};
+use aws_sdk_costexplorer::Client as CostExplorerClient;
+use futures::future::join_all;
+use prettytable::{format, Cell, Row, Table};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// The cost allocation tag keys of one profile, or why they couldn't be listed.
+#[derive(Serialize, Debug)]
+pub struct ProfileTagKeys {
+    pub profile: String,
+    /// Alphabetical.
+    pub tag_keys: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A value of the tag key with its cost over the range.
+#[derive(Serialize, Debug)]
+pub struct TagValueEntry {
+    pub value: String,
+    pub cost: f64,
+}
+
+/// The values of a tag key in one profile, or why they couldn't be listed.
+#[derive(Serialize, Debug)]
+pub struct ProfileTagValues {
+    pub profile: String,
+    /// Highest cost first.
+    pub values: Vec<TagValueEntry>,
+    /// Cost of resources without the tag.
+    pub untagged_cost: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Lists every profile's tag keys concurrently. A failing profile gets an error entry instead of
+/// stopping the others.
+pub async fn list_keys(cli: &Cli, profiles: &[String]) -> Vec<ProfileTagKeys> {
+    join_all(profiles.iter().map(|profile| list_profile_keys(cli, profile))).await
+}
+
+async fn list_profile_keys(cli: &Cli, profile: &str) -> ProfileTagKeys {
+    let config = load_discovery_config(cli, profile).await;
+    let client = cost_explorer_client(cli, &config);
+    match fetch_tags(cli, &client, None).await {
+        Ok(mut tag_keys) => {
+            tag_keys.sort();
+            ProfileTagKeys { profile: profile.to_string(), tag_keys, error: None }
+        }
+        Err(error) => ProfileTagKeys { profile: profile.to_string(), tag_keys: Vec::new(), error: Some(error) },
+    }
+}
+
+/// Lists every profile's values of `key` concurrently, with their costs.
+pub async fn list_values(cli: &Cli, profiles: &[String], key: &str) -> Vec<ProfileTagValues> {
+    join_all(profiles.iter().map(|profile| list_profile_values(cli, profile, key))).await
+}
+
+async fn list_profile_values(cli: &Cli, profile: &str, key: &str) -> ProfileTagValues {
+    let config = load_discovery_config(cli, profile).await;
+    let client = cost_explorer_client(cli, &config);
+    let fetched = match fetch_tags(cli, &client, Some(key)).await {
+        Ok(values) => fetch_costs(cli, &client, key).await.map(|costs| (values, costs)),
+        Err(e) => Err(e),
+    };
+    let (values, mut costs) = match fetched {
+        Ok(fetched) => fetched,
+        Err(error) => return ProfileTagValues { profile: profile.to_string(), values: Vec::new(), untagged_cost: 0.0, error: Some(error) },
+    };
+    let untagged_cost = costs.remove("").unwrap_or(0.0);
+    let mut values: Vec<TagValueEntry> = values
+        .into_iter()
+        .filter(|value| !value.is_empty())
+        .map(|value| TagValueEntry { cost: costs.get(&value).copied().unwrap_or(0.0), value })
+        .collect();
+    values.sort_by(|a, b| b.cost.total_cmp(&a.cost).then_with(|| a.value.cmp(&b.value)));
+    ProfileTagValues { profile: profile.to_string(), values, untagged_cost, error: None }
+}
+
+fn time_period(cli: &Cli) -> DateInterval {
+    DateInterval::builder().start(&cli.start_date).end(&cli.query_end_date).build().expect("start and end are set")
+}
+
+/// Tag keys with usage in the range, or the values of `key`.
+async fn fetch_tags(cli: &Cli, client: &CostExplorerClient, key: Option<&str>) -> Result<Vec<String>, String> {
+    let mut tags = Vec::new();
+    let mut next_page_token = None;
+    loop {
+        cli.ce_limiter.acquire().await;
+        let response = client
+            .get_tags()
+            .time_period(time_period(cli))
+            .set_tag_key(key.map(str::to_string))
+            .set_next_page_token(next_page_token)
+            .send()
+            .await
+            .map_err(|e| error_message(&e))?;
+        tags.extend(response.tags);
+        next_page_token = response.next_page_token;
+        if next_page_token.is_none() {
+            return Ok(tags);
+        }
+    }
+}
+
+/// Cost of each value of `key` over the range, in one monthly query grouped by the tag. Untagged
+/// costs are under the empty value.
+async fn fetch_costs(cli: &Cli, client: &CostExplorerClient, key: &str) -> Result<HashMap<String, f64>, String> {
+    let mut costs: HashMap<String, f64> = HashMap::new();
+    let mut next_page_token = None;
+    loop {
+        cli.ce_limiter.acquire().await;
+        let response = client
+            .get_cost_and_usage()
+            .time_period(time_period(cli))
+            .granularity(Granularity::Monthly)
+            .metrics(COST_METRIC)
+            .group_by(GroupDefinition::builder().r#type(GroupDefinitionType::Tag).key(key).build())
+            .set_next_page_token(next_page_token)
+            .send()
+            .await
+            .map_err(|e| error_message(&e))?;
+        for group in response.results_by_time().iter().flat_map(|result| result.groups()) {
+            let cost = group
+                .metrics()
+                .and_then(|m| m.get(COST_METRIC))
+                .and_then(|m| m.amount())
+                .and_then(|a| a.parse::<f64>().ok())
+                .unwrap_or(0.0);
+            // Groups are keyed `key$value`
+            let value = group.keys().first().map_or("", |tag| tag.split_once('$').map_or(tag.as_str(), |(_, value)| value));
+            *costs.entry(value.to_string()).or_insert(0.0) += cost;
+        }
+        next_page_token = response.next_page_token;
+        if next_page_token.is_none() {
+            return Ok(costs);
+        }
+    }
+}
+
+fn error_message<E: ProvideErrorMetadata + std::error::Error>(error: &E) -> String {
+    match (error.code(), error.message()) {
+        (Some(code), Some(message)) => format!("{}: {}", code, message),
+        _ => error.to_string(),
+    }
+}
+
+/// Prints the tag keys of each profile, one per line.
+pub fn print_keys(cli: &Cli, results: &[ProfileTagKeys]) {
+    for result in results {
+        if let Some(error) = &result.error {
+            println!("\nCost Allocation Tags for Profile {}: unavailable - {}", result.profile, error);
+            continue;
+        }
+        if result.tag_keys.is_empty() {
+            println!("\nCost Allocation Tags for Profile {} ({} to {}): none", result.profile, cli.start_date, cli.end_date);
+            continue;
+        }
+        println!("\nCost Allocation Tags for Profile {} ({} to {}):", result.profile, cli.start_date, cli.end_date);
+        for key in &result.tag_keys {
+            println!("  {}", key);
+        }
+    }
+}
+
+/// Prints one table of tag values per profile, with the untagged cost below it.
+pub fn print_values(cli: &Cli, key: &str, results: &[ProfileTagValues]) {
+    for result in results {
+        if let Some(error) = &result.error {
+            println!("\nValues of Tag {} for Profile {}: unavailable - {}", key, result.profile, error);
+            continue;
+        }
+        if result.values.is_empty() {
+            println!("\nValues of Tag {} for Profile {} ({} to {}): none", key, result.profile, cli.start_date, cli.end_date);
+        } else {
+            let mut table = Table::new();
+            table.set_format(*format::consts::FORMAT_DEFAULT);
+            table.set_titles(Row::new(vec![Cell::new("Value").style_spec("bFc"), Cell::new("Total Cost (USD)").style_spec("bFc")]));
+            for value in &result.values {
+                table.add_row(Row::new(vec![Cell::new(&value.value), Cell::new(&format!("{:.2}", value.cost)).style_spec("Fr")]));
+            }
+            println!("\nValues of Tag {} for Profile {} ({} to {}):", key, result.profile, cli.start_date, cli.end_date);
+            table.printstd();
+        }
+        println!("Untagged: ${:.2}", result.untagged_cost);
+    }
+}
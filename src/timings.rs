@@ -0,0 +1,104 @@
+use prettytable::{format, Cell, Row, Table};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Wall-clock duration of one phase of the run, for `--timings`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Timing {
+    /// `credential_check`, `config_load`, `discovery`, `cost_query`, `render` or `write_outputs`.
+    pub phase: String,
+    pub profile: Option<String>,
+    /// Set for `cost_query`, which is timed per account.
+    pub account_id: Option<String>,
+    pub seconds: f64,
+}
+
+/// Collects phase durations from every task of the run; recording is a no-op unless enabled.
+#[derive(Debug, Default, Clone)]
+pub struct Timings {
+    entries: Option<Arc<Mutex<Vec<Timing>>>>,
+}
+
+impl Timings {
+    pub fn enabled() -> Self {
+        Timings { entries: Some(Arc::default()) }
+    }
+
+    /// Times a phase until the returned span is dropped, so early returns and `continue`s are covered.
+    pub fn span(&self, phase: &'static str, profile: Option<&str>, account_id: Option<&str>) -> Span {
+        Span {
+            timings: self.entries.clone(),
+            phase,
+            profile: profile.map(str::to_string),
+            account_id: account_id.map(str::to_string),
+            start: Instant::now(),
+        }
+    }
+
+    /// Durations recorded so far, in the order the phases finished.
+    pub fn entries(&self) -> Vec<Timing> {
+        self.entries.as_ref().map(|e| e.lock().unwrap().clone()).unwrap_or_default()
+    }
+
+    /// Prints per-phase totals and then every recorded duration to stderr.
+    pub fn print_summary(&self) {
+        let entries = self.entries();
+        if self.entries.is_none() || entries.is_empty() {
+            return;
+        }
+        let mut totals: Vec<(&str, f64, usize)> = Vec::new();
+        for entry in &entries {
+            match totals.iter_mut().find(|(phase, ..)| *phase == entry.phase) {
+                Some(total) => {
+                    total.1 += entry.seconds;
+                    total.2 += 1;
+                }
+                None => totals.push((&entry.phase, entry.seconds, 1)),
+            }
+        }
+
+        let mut summary = Table::new();
+        summary.set_format(*format::consts::FORMAT_DEFAULT);
+        summary.set_titles(Row::new(vec![Cell::new("Phase"), Cell::new("Count"), Cell::new("Total (s)")]));
+        for (phase, seconds, count) in totals {
+            summary.add_row(Row::new(vec![Cell::new(phase), Cell::new(&count.to_string()), Cell::new(&format!("{:.2}", seconds))]));
+        }
+        let mut detail = Table::new();
+        detail.set_format(*format::consts::FORMAT_DEFAULT);
+        detail.set_titles(Row::new(vec![Cell::new("Phase"), Cell::new("Profile"), Cell::new("Account ID"), Cell::new("Seconds")]));
+        for entry in &entries {
+            detail.add_row(Row::new(vec![
+                Cell::new(&entry.phase),
+                Cell::new(entry.profile.as_deref().unwrap_or("")),
+                Cell::new(entry.account_id.as_deref().unwrap_or("")),
+                Cell::new(&format!("{:.2}", entry.seconds)),
+            ]));
+        }
+        eprintln!("\nTimings:");
+        let _ = summary.print(&mut std::io::stderr());
+        let _ = detail.print(&mut std::io::stderr());
+    }
+}
+
+/// A phase being timed; records its duration when dropped.
+pub struct Span {
+    timings: Option<Arc<Mutex<Vec<Timing>>>>,
+    phase: &'static str,
+    profile: Option<String>,
+    account_id: Option<String>,
+    start: Instant,
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some(timings) = &self.timings {
+            timings.lock().unwrap().push(Timing {
+                phase: self.phase.to_string(),
+                profile: self.profile.take(),
+                account_id: self.account_id.take(),
+                seconds: self.start.elapsed().as_secs_f64(),
+            });
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use crate::optimization_hub::ProfileNote;
+use crate::partition::Partition;
+use crate::{load_discovery_config, Cli, Report};
+use aws_config::{Region, SdkConfig};
+use aws_sdk_support::error::ProvideErrorMetadata;
+use aws_sdk_support::Client as SupportClient;
+use aws_sdk_sts::Client as StsClient;
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Shown for profiles whose account lacks the support plan the Trusted Advisor API requires.
+pub const SUPPORT_PLAN_REQUIRED: &str =
+    "needs a Business, Enterprise On-Ramp or Enterprise Support plan for the Trusted Advisor API";
+
+/// Category of the cost optimization checks, such as idle load balancers and unassociated Elastic IPs.
+const COST_CATEGORY: &str = "cost_optimizing";
+
+/// One cost optimization check that flagged resources.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AdvisorCheck {
+    pub name: String,
+    /// `warning` or `error`.
+    pub status: String,
+    pub flagged_resources: u64,
+    pub estimated_monthly_savings: f64,
+}
+
+/// Trusted Advisor cost checks of one reported account.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccountAdvice {
+    pub profile: String,
+    pub account_id: String,
+    pub account_name: String,
+    pub flagged_resources: u64,
+    pub estimated_monthly_savings: f64,
+    /// Highest savings first.
+    pub checks: Vec<AdvisorCheck>,
+}
+
+/// `--trusted-advisor` cost optimization findings for the reported accounts.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrustedAdvisor {
+    pub flagged_resources: u64,
+    pub estimated_monthly_savings: f64,
+    /// Highest savings first.
+    pub accounts: Vec<AccountAdvice>,
+    pub notes: Vec<ProfileNote>,
+}
+
+/// Reads every profile's cost checks concurrently. Trusted Advisor only reports on the caller's own
+/// account, so each profile contributes that account, when the report covers it.
+pub async fn collect(cli: &Cli, profiles: &[String], report: &Report) -> TrustedAdvisor {
+    let fetched = join_all(profiles.iter().map(|profile| fetch_profile(cli, profile))).await;
+    let accounts: HashMap<&str, &str> = report
+        .accounts
+        .iter()
+        .map(|account| (account.account_id.as_str(), account.account_name.as_str()))
+        .collect();
+
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut advice: Vec<AccountAdvice> = Vec::new();
+    let mut notes = Vec::new();
+    for (profile, result) in profiles.iter().zip(fetched) {
+        let (account_id, checks) = match result {
+            Ok(fetched) => fetched,
+            Err(note) => {
+                notes.push(ProfileNote { profile: profile.clone(), note });
+                continue;
+            }
+        };
+        let Some(account_name) = accounts.get(account_id.as_str()) else {
+            notes.push(ProfileNote { profile: profile.clone(), note: "its account is not among the reported accounts".to_string() });
+            continue;
+        };
+        if !seen.insert(account_id.clone()) {
+            continue;
+        }
+        advice.push(AccountAdvice {
+            profile: profile.clone(),
+            account_name: account_name.to_string(),
+            account_id,
+            flagged_resources: checks.iter().map(|check| check.flagged_resources).sum(),
+            estimated_monthly_savings: checks.iter().fold(0.0, |total, check| total + check.estimated_monthly_savings),
+            checks,
+        });
+    }
+    advice.sort_by(|a, b| b.estimated_monthly_savings.total_cmp(&a.estimated_monthly_savings).then_with(|| a.account_id.cmp(&b.account_id)));
+    TrustedAdvisor {
+        flagged_resources: advice.iter().map(|account| account.flagged_resources).sum(),
+        estimated_monthly_savings: advice.iter().fold(0.0, |total, account| total + account.estimated_monthly_savings),
+        accounts: advice,
+        notes,
+    }
+}
+
+/// Support client for the endpoint region of the profile's partition, the only one the API answers in.
+fn support_client(config: &SdkConfig) -> SupportClient {
+    let builder = aws_sdk_support::config::Builder::from(config).region(Region::new(Partition::of(config).endpoint_region()));
+    SupportClient::from_conf(builder.build())
+}
+
+/// The caller's account ID with its cost checks that flagged resources. Errors come back as a one-line note.
+async fn fetch_profile(cli: &Cli, profile: &str) -> Result<(String, Vec<AdvisorCheck>), String> {
+    let config = load_discovery_config(cli, profile).await;
+    let account_id = StsClient::new(&config)
+        .get_caller_identity()
+        .send()
+        .await
+        .ok()
+        .and_then(|identity| identity.account)
+        .ok_or_else(|| "unavailable - cannot identify the profile's account".to_string())?;
+    let client = support_client(&config);
+    let described = client.describe_trusted_advisor_checks().language("en").send().await.map_err(|e| note(e.code(), e.message(), &e))?;
+    let names: HashMap<&str, &str> = described
+        .checks()
+        .iter()
+        .filter(|check| check.category() == COST_CATEGORY)
+        .map(|check| (check.id(), check.name()))
+        .collect();
+    if names.is_empty() {
+        return Ok((account_id, Vec::new()));
+    }
+    let summaries = client
+        .describe_trusted_advisor_check_summaries()
+        .set_check_ids(Some(names.keys().map(|id| Some(id.to_string())).collect()))
+        .send()
+        .await
+        .map_err(|e| note(e.code(), e.message(), &e))?;
+
+    let mut checks: Vec<AdvisorCheck> = summaries
+        .summaries()
+        .iter()
+        .filter_map(|summary| {
+            let flagged = summary.resources_summary().map_or(0, |resources| resources.resources_flagged()).max(0) as u64;
+            (flagged > 0).then(|| AdvisorCheck {
+                name: names.get(summary.check_id()).copied().unwrap_or(summary.check_id()).to_string(),
+                status: summary.status().to_string(),
+                flagged_resources: flagged,
+                estimated_monthly_savings: summary
+                    .category_specific_summary()
+                    .and_then(|category| category.cost_optimizing())
+                    .map_or(0.0, |cost| cost.estimated_monthly_savings()),
+            })
+        })
+        .collect();
+    checks.sort_by(|a, b| b.estimated_monthly_savings.total_cmp(&a.estimated_monthly_savings).then_with(|| a.name.cmp(&b.name)));
+    Ok((account_id, checks))
+}
+
+/// Basic and Developer support plans get `SubscriptionRequiredException`.
+fn note(code: Option<&str>, message: Option<&str>, error: &dyn std::fmt::Display) -> String {
+    match (code, message) {
+        (Some("SubscriptionRequiredException"), _) => SUPPORT_PLAN_REQUIRED.to_string(),
+        (Some(code), Some(message)) => format!("unavailable - {}: {}", code, message),
+        _ => format!("unavailable - {}", error),
+    }
+}
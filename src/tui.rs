@@ -0,0 +1,323 @@
+use crate::{AccountCostData, Report};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::{Block, Cell, List, ListItem, ListState, Paragraph, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::BTreeMap;
+use std::error::Error;
+
+/// Service table period columns shown at once; left/right scroll through the rest.
+const VISIBLE_PERIODS: usize = 6;
+
+#[derive(PartialEq)]
+enum AccountSort {
+    CostDesc,
+    Name,
+}
+
+/// Period totals and per-service breakdown for the selected account, optionally rolled up to months.
+struct AccountView {
+    periods: Vec<String>,
+    totals: BTreeMap<String, f64>,
+    services: Vec<(String, BTreeMap<String, f64>, f64)>,
+}
+
+struct App<'a> {
+    report: &'a Report,
+    /// Abbreviate service names (`--short-names` other than `never`).
+    short_names: bool,
+    order: Vec<usize>,
+    sort: AccountSort,
+    selected: ListState,
+    period_offset: usize,
+    monthly_rollup: bool,
+    filter: String,
+    editing_filter: bool,
+    status: String,
+}
+
+impl<'a> App<'a> {
+    fn new(report: &'a Report, short_names: bool) -> Self {
+        let mut app = App {
+            report,
+            short_names,
+            order: (0..report.accounts.len()).collect(),
+            sort: AccountSort::CostDesc,
+            selected: ListState::default(),
+            period_offset: 0,
+            monthly_rollup: false,
+            filter: String::new(),
+            editing_filter: false,
+            status: String::new(),
+        };
+        app.sort_accounts();
+        app.selected.select(if report.accounts.is_empty() { None } else { Some(0) });
+        app
+    }
+
+    fn sort_accounts(&mut self) {
+        let accounts = &self.report.accounts;
+        match self.sort {
+            AccountSort::CostDesc => self.order.sort_by(|a, b| {
+                accounts[*b]
+                    .total_cost
+                    .partial_cmp(&accounts[*a].total_cost)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+                    .then_with(|| accounts[*a].account_id.cmp(&accounts[*b].account_id))
+            }),
+            AccountSort::Name => self.order.sort_by(|a, b| {
+                accounts[*a]
+                    .account_name
+                    .cmp(&accounts[*b].account_name)
+                    .then_with(|| accounts[*a].account_id.cmp(&accounts[*b].account_id))
+            }),
+        }
+    }
+
+    fn selected_account(&self) -> Option<&'a AccountCostData> {
+        self.selected
+            .selected()
+            .and_then(|i| self.order.get(i))
+            .map(|i| &self.report.accounts[*i])
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        if self.order.is_empty() {
+            return;
+        }
+        let current = self.selected.selected().unwrap_or(0) as isize;
+        let next = (current + delta).clamp(0, self.order.len() as isize - 1);
+        self.selected.select(Some(next as usize));
+        self.period_offset = 0;
+    }
+
+    fn account_view(&self, account: &AccountCostData) -> AccountView {
+        let bucket = |period: &str| -> String {
+            let is_dated = period.get(..10).is_some_and(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").is_ok());
+            if self.monthly_rollup && is_dated {
+                period[..7].to_string()
+            } else {
+                period.to_string()
+            }
+        };
+
+        let mut totals: BTreeMap<String, f64> = BTreeMap::new();
+        for entry in &account.cost_trend {
+            *totals.entry(bucket(&entry.month)).or_insert(0.0) += entry.total_cost;
+        }
+        let periods: Vec<String> = totals.keys().cloned().collect();
+
+        let filter = self.filter.to_lowercase();
+        let services = account
+            .service_consumption
+            .iter()
+            .map(|s| (s, if self.short_names { crate::service_names::short_name(&s.service) } else { s.service.as_str() }))
+            .filter(|(s, label)| filter.is_empty() || s.service.to_lowercase().contains(&filter) || label.to_lowercase().contains(&filter))
+            .map(|(s, label)| {
+                let mut costs: BTreeMap<String, f64> = BTreeMap::new();
+                for (period, cost) in &s.monthly_costs {
+                    *costs.entry(bucket(period)).or_insert(0.0) += cost;
+                }
+                (label.to_string(), costs, s.total_cost)
+            })
+            .collect();
+
+        AccountView { periods, totals, services }
+    }
+
+    /// Writes the selected account's service table, as currently filtered and rolled up, to a CSV file.
+    fn export_current_view(&mut self) {
+        let Some(account) = self.selected_account() else {
+            return;
+        };
+        let view = self.account_view(account);
+        let path = format!("tui_export_profile_{}_account_{}.csv", account.profile, account.account_id);
+        let result = (|| -> Result<(), Box<dyn Error>> {
+            let mut writer = csv::Writer::from_path(&path)?;
+            let mut headers = vec!["Service".to_string()];
+            headers.extend(view.periods.iter().cloned());
+            headers.push("Total Cost (USD)".to_string());
+            writer.write_record(&headers)?;
+            for (service, costs, total) in &view.services {
+                let mut row = vec![service.clone()];
+                row.extend(view.periods.iter().map(|p| format!("{:.2}", costs.get(p).unwrap_or(&0.0))));
+                row.push(format!("{:.2}", total));
+                writer.write_record(&row)?;
+            }
+            writer.flush()?;
+            Ok(())
+        })();
+        self.status = match result {
+            Ok(()) => format!("Exported current view to {}", path),
+            Err(e) => format!("Export failed: {}", e),
+        };
+    }
+
+    /// Handles one key press, returning false when the user quits.
+    fn handle_key(&mut self, code: KeyCode) -> bool {
+        if self.editing_filter {
+            match code {
+                KeyCode::Enter | KeyCode::Esc => self.editing_filter = false,
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Char(c) => self.filter.push(c),
+                _ => {}
+            }
+            return true;
+        }
+
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Right | KeyCode::Char('l') => self.period_offset += 1,
+            KeyCode::Left | KeyCode::Char('h') => self.period_offset = self.period_offset.saturating_sub(1),
+            KeyCode::Char('s') => {
+                let selected = self.selected_account().map(|a| (a.profile.clone(), a.account_id.clone()));
+                self.sort = if self.sort == AccountSort::CostDesc { AccountSort::Name } else { AccountSort::CostDesc };
+                self.sort_accounts();
+                if let Some((profile, account_id)) = selected {
+                    let position = self.order.iter().position(|i| {
+                        let a = &self.report.accounts[*i];
+                        a.profile == profile && a.account_id == account_id
+                    });
+                    self.selected.select(position);
+                }
+            }
+            KeyCode::Char('m') => {
+                self.monthly_rollup = !self.monthly_rollup;
+                self.period_offset = 0;
+            }
+            KeyCode::Char('/') => {
+                self.editing_filter = true;
+                self.filter.clear();
+            }
+            KeyCode::Char('e') => self.export_current_view(),
+            _ => {}
+        }
+        true
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let outer = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(1)])
+            .split(frame.area());
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
+            .split(outer[0]);
+
+        let items: Vec<ListItem> = self
+            .order
+            .iter()
+            .map(|i| {
+                let account = &self.report.accounts[*i];
+                ListItem::new(format!("{} ({})  ${:.2}", account.account_name, account.account_id, account.total_cost))
+            })
+            .collect();
+        let sort_label = match self.sort {
+            AccountSort::CostDesc => "by cost",
+            AccountSort::Name => "by name",
+        };
+        let list = List::new(items)
+            .block(Block::bordered().title(format!("Accounts ({})", sort_label)))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, columns[0], &mut self.selected);
+
+        let detail = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(columns[1]);
+
+        if let Some(account) = self.selected_account() {
+            let view = self.account_view(account);
+
+            let mut previous: Option<f64> = None;
+            let trend_rows: Vec<Row> = view
+                .periods
+                .iter()
+                .map(|period| {
+                    let cost = view.totals.get(period).copied().unwrap_or(0.0);
+                    let change = match previous {
+                        Some(prev) if prev != 0.0 => format!("{:+.1}", (cost - prev) / prev * 100.0),
+                        Some(_) if cost > 0.0 => "new".to_string(),
+                        Some(_) => "n/a".to_string(),
+                        None => String::new(),
+                    };
+                    previous = Some(cost);
+                    Row::new(vec![Cell::from(period.clone()), Cell::from(format!("{:.2}", cost)), Cell::from(change)])
+                })
+                .collect();
+            let trend = Table::new(trend_rows, [Constraint::Length(22), Constraint::Length(16), Constraint::Length(12)])
+                .header(Row::new(vec!["Period", "Total Cost (USD)", "Change (%)"]).style(Style::default().add_modifier(Modifier::BOLD)))
+                .block(Block::bordered().title(format!(
+                    "Cost trend: {} ({}){}",
+                    account.account_name,
+                    account.account_id,
+                    if self.monthly_rollup { " [monthly roll-up]" } else { "" }
+                )));
+            frame.render_widget(trend, detail[0]);
+
+            let offset = self.period_offset.min(view.periods.len().saturating_sub(1));
+            let visible: Vec<&String> = view.periods.iter().skip(offset).take(VISIBLE_PERIODS).collect();
+            let mut header = vec!["Service".to_string()];
+            header.extend(visible.iter().map(|p| p.to_string()));
+            header.push("Total".to_string());
+            let service_rows: Vec<Row> = view
+                .services
+                .iter()
+                .map(|(service, costs, total)| {
+                    let mut cells = vec![Cell::from(service.clone())];
+                    cells.extend(visible.iter().map(|p| Cell::from(format!("{:.2}", costs.get(*p).unwrap_or(&0.0)))));
+                    cells.push(Cell::from(format!("{:.2}", total)));
+                    Row::new(cells)
+                })
+                .collect();
+            let mut widths = vec![Constraint::Min(24)];
+            widths.extend(visible.iter().map(|_| Constraint::Length(12)));
+            widths.push(Constraint::Length(12));
+            let title = if self.filter.is_empty() {
+                "Services".to_string()
+            } else {
+                format!("Services matching '{}'", self.filter)
+            };
+            let services = Table::new(service_rows, widths)
+                .header(Row::new(header).style(Style::default().add_modifier(Modifier::BOLD)))
+                .block(Block::bordered().title(title));
+            frame.render_widget(services, detail[1]);
+        }
+
+        let footer = if self.editing_filter {
+            Paragraph::new(format!("/{}", self.filter)).style(Style::default().fg(Color::Yellow))
+        } else if !self.status.is_empty() {
+            Paragraph::new(self.status.clone()).style(Style::default().fg(Color::Green))
+        } else {
+            Paragraph::new("q quit  ↑/↓ account  ←/→ periods  s sort  m monthly roll-up  / filter services  e export CSV")
+                .style(Style::default().fg(Color::DarkGray))
+        };
+        frame.render_widget(footer, outer[1]);
+    }
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, app: &mut App) -> std::io::Result<()> {
+    loop {
+        terminal.draw(|frame| app.draw(frame))?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press {
+                app.status.clear();
+                if !app.handle_key(key.code) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Opens the interactive report browser over already-collected data.
+pub fn run(report: &Report, short_names: bool) -> std::io::Result<()> {
+    let mut app = App::new(report, short_names);
+    ratatui::run(|terminal| event_loop(terminal, &mut app))
+}
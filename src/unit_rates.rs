@@ -0,0 +1,97 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Cost Explorer metric of usage amounts, in each usage type's own unit (`GB-Mo`, `GB`, `Hrs`, ...).
+pub const USAGE_METRIC: &str = "UsageQuantity";
+
+/// Cost and usage of one `--unit-rates` usage type by period.
+#[derive(Debug, Default, Clone)]
+pub struct UsageSamples {
+    pub costs: BTreeMap<String, f64>,
+    pub quantities: BTreeMap<String, f64>,
+    /// Every unit the quantities were reported in; a rate needs exactly one.
+    pub units: BTreeSet<String>,
+}
+
+/// A usage type's effective rate in one period.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UnitRatePeriod {
+    pub period: String,
+    pub cost: f64,
+    pub quantity: f64,
+    /// Cost per unit; absent when the quantity is zero or its unit is unknown.
+    pub rate: Option<f64>,
+    /// Change of the rate against the previous period that has one.
+    pub change_percent: Option<f64>,
+    /// `change_percent` is over `--unit-rate-threshold` either way.
+    pub exceeds_threshold: bool,
+}
+
+/// Effective rate of one `--unit-rates` usage type for one account.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UnitRate {
+    pub usage_type: String,
+    /// Unit of the quantities, such as `GB-Mo`; absent when periods report different units, in which
+    /// case no rate is given.
+    pub unit: Option<String>,
+    pub periods: Vec<UnitRatePeriod>,
+    /// Some period's rate changed by more than `--unit-rate-threshold`.
+    pub exceeds_threshold: bool,
+    /// With `--price-context`, the first-tier On-Demand list price per unit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub list_price: Option<f64>,
+    /// How far the latest rate is below `list_price`; negative when it is above.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discount_percent: Option<f64>,
+}
+
+/// Whether a usage type is one of the `--unit-rates` usage types, ignoring its region prefix.
+pub fn is_selected(usage_type: &str, selected: &[String]) -> bool {
+    selected.iter().any(|suffix| crate::presets::usage_type_matches(usage_type, suffix))
+}
+
+/// Rates of each usage type over `periods`, flagging changes above `threshold_percent`.
+pub fn unit_rates(samples: &BTreeMap<String, UsageSamples>, periods: &[String], threshold_percent: f64) -> Vec<UnitRate> {
+    samples
+        .iter()
+        .map(|(usage_type, samples)| {
+            // Cost Explorer reports `N/A` for quantities that can't be added up
+            let unit = match samples.units.iter().collect::<Vec<_>>().as_slice() {
+                [unit] if !unit.is_empty() && unit.as_str() != "N/A" => Some(unit.to_string()),
+                _ => None,
+            };
+            let mut previous_rate: Option<f64> = None;
+            let periods: Vec<UnitRatePeriod> = periods
+                .iter()
+                .filter(|period| samples.costs.contains_key(*period) || samples.quantities.contains_key(*period))
+                .map(|period| {
+                    let cost = samples.costs.get(period).copied().unwrap_or(0.0);
+                    let quantity = samples.quantities.get(period).copied().unwrap_or(0.0);
+                    let rate = unit.as_ref().filter(|_| quantity != 0.0).map(|_| cost / quantity);
+                    let change_percent = match (previous_rate, rate) {
+                        (Some(previous), Some(rate)) if previous != 0.0 => Some((rate - previous) / previous.abs() * 100.0),
+                        _ => None,
+                    };
+                    previous_rate = rate.or(previous_rate);
+                    UnitRatePeriod {
+                        period: period.clone(),
+                        cost,
+                        quantity,
+                        rate,
+                        change_percent,
+                        exceeds_threshold: change_percent.is_some_and(|change| change.abs() > threshold_percent),
+                    }
+                })
+                .collect();
+            UnitRate {
+                usage_type: usage_type.clone(),
+                unit,
+                exceeds_threshold: periods.iter().any(|period| period.exceeds_threshold),
+                periods,
+                list_price: None,
+                discount_percent: None,
+            }
+        })
+        .filter(|rate| !rate.periods.is_empty())
+        .collect()
+}
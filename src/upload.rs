@@ -0,0 +1,104 @@
+use aws_config::SdkConfig;
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::ServerSideEncryption;
+use aws_sdk_s3::Client as S3Client;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+pub fn is_s3_uri(target: &str) -> bool {
+    target.starts_with("s3://")
+}
+
+/// Splits `s3://bucket/key` into its bucket and key.
+fn parse_s3_uri(uri: &str) -> Result<(String, String), String> {
+    let rest = uri
+        .strip_prefix("s3://")
+        .ok_or_else(|| format!("Not an S3 URI: {}", uri))?;
+    match rest.split_once('/') {
+        Some((bucket, key)) if !bucket.is_empty() && !key.is_empty() => Ok((bucket.to_string(), key.to_string())),
+        _ => Err(format!("S3 URI must include a bucket and key: {}", uri)),
+    }
+}
+
+/// Picks the Content-Type (and Content-Encoding for gzip) to store with an uploaded report.
+fn content_type_for(key: &str) -> (&'static str, Option<&'static str>) {
+    let (base, encoding) = match key.strip_suffix(".gz") {
+        Some(base) => (base, Some("gzip")),
+        None => (key, None),
+    };
+    let content_type = match Path::new(base).extension().and_then(|e| e.to_str()) {
+        Some("csv") => "text/csv",
+        Some("tsv") => "text/tab-separated-values",
+        Some("json") => "application/json",
+        Some("png") => "image/png",
+        _ => "application/octet-stream",
+    };
+    (content_type, encoding)
+}
+
+/// Stages report files locally and uploads them to S3 once they are complete.
+pub struct S3Uploader {
+    client: S3Client,
+    sse_kms_key_id: Option<String>,
+    staging_dir: PathBuf,
+}
+
+impl S3Uploader {
+    pub fn new(config: &SdkConfig, sse_kms_key_id: Option<String>) -> std::io::Result<Self> {
+        let staging_dir = std::env::temp_dir().join(format!("aws-cost-cli-{}", std::process::id()));
+        std::fs::create_dir_all(&staging_dir)?;
+        Ok(S3Uploader {
+            client: S3Client::new(config),
+            sse_kms_key_id,
+            staging_dir,
+        })
+    }
+
+    /// Local file to write before uploading `target`.
+    pub fn staging_path(&self, target: &str) -> String {
+        let file_name = target.rsplit('/').next().unwrap_or(target);
+        self.staging_dir.join(file_name).to_string_lossy().into_owned()
+    }
+
+    pub async fn upload(&self, local_path: &str, target: &str) -> Result<(), Box<dyn Error>> {
+        let (bucket, key) = parse_s3_uri(target)?;
+        let (content_type, content_encoding) = content_type_for(&key);
+        let body = ByteStream::from_path(local_path).await?;
+
+        let mut request = self
+            .client
+            .put_object()
+            .bucket(&bucket)
+            .key(&key)
+            .content_type(content_type)
+            .set_content_encoding(content_encoding.map(str::to_string))
+            .body(body);
+        if let Some(kms_key_id) = &self.sse_kms_key_id {
+            request = request
+                .server_side_encryption(ServerSideEncryption::AwsKms)
+                .ssekms_key_id(kms_key_id);
+        }
+
+        match request.send().await {
+            Ok(_) => {
+                let _ = std::fs::remove_file(local_path);
+                Ok(())
+            }
+            Err(e) if e.code() == Some("AccessDenied") => Err(format!(
+                "Upload to {} was denied: {}. Check s3:PutObject on the bucket{}.",
+                target,
+                e.message().unwrap_or("access denied"),
+                if self.sse_kms_key_id.is_some() { " and kms:GenerateDataKey on the KMS key" } else { "" }
+            )
+            .into()),
+            Err(e) => Err(format!("Upload to {} failed: {}", target, e).into()),
+        }
+    }
+}
+
+impl Drop for S3Uploader {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir(&self.staging_dir);
+    }
+}
@@ -0,0 +1,291 @@
+use crate::cur::CostSource;
+use crate::{Cli, GranularityOption};
+use clap::error::ErrorKind;
+use clap::CommandFactory;
+
+/// Checks flag combinations clap's own attributes can't express, after `--month`/`--mtd`/`--ytd`
+/// have settled the range and granularity. Add new cross-flag constraints here.
+pub fn validate(cli: &Cli) -> Result<(), clap::Error> {
+    if cli.tag_value.is_some() && cli.tag_key.is_none() {
+        return Err(fail(ErrorKind::MissingRequiredArgument, "--tag-value requires --tag-key"));
+    }
+    if let Some(fallback) = &cli.fallback_granularity {
+        if cli.query_granularity() != GranularityOption::Hourly {
+            return Err(fail(
+                ErrorKind::ArgumentConflict,
+                "--fallback-granularity only applies to hourly queries (--granularity hourly, or daily with --timezone)",
+            ));
+        }
+        if *fallback == GranularityOption::Hourly {
+            return Err(fail(ErrorKind::InvalidValue, "--fallback-granularity must be a granularity other than hourly"));
+        }
+    }
+    if cli.source == CostSource::Cur && cli.cur_path.is_none() {
+        return Err(fail(ErrorKind::MissingRequiredArgument, "--source cur requires --cur-path"));
+    }
+    if cli.source != CostSource::Cur && cli.cur_path.is_some() {
+        return Err(fail(ErrorKind::ArgumentConflict, "--cur-path only applies to --source cur"));
+    }
+    let athena_flags = [&cli.athena_database, &cli.athena_table, &cli.athena_output];
+    if cli.source == CostSource::Athena && athena_flags.iter().any(|flag| flag.is_none()) {
+        return Err(fail(ErrorKind::MissingRequiredArgument, "--source athena requires --athena-database, --athena-table and --athena-output"));
+    }
+    if cli.source != CostSource::Athena && athena_flags.iter().any(|flag| flag.is_some()) {
+        return Err(fail(ErrorKind::ArgumentConflict, "--athena-database, --athena-table and --athena-output only apply to --source athena"));
+    }
+    if cli.athena_output.as_deref().is_some_and(|output| !output.starts_with("s3://")) {
+        return Err(fail(ErrorKind::InvalidValue, "--athena-output must be an s3://bucket/prefix"));
+    }
+    if cli.source != CostSource::Api
+        && (cli.preset.is_some() || cli.unit_rates.is_some() || cli.amortization_delta || cli.compare_metrics.is_some() || cli.cache_dir.is_some())
+    {
+        let source = if cli.source == CostSource::Cur { "cur" } else { "athena" };
+        return Err(fail(
+            ErrorKind::ArgumentConflict,
+            &format!("--source {} can't be combined with --preset, --unit-rates, --amortization-delta, --compare-metrics or --cache-dir, which need Cost Explorer", source),
+        ));
+    }
+    if cli.show_credits && cli.tag_key.is_some() && cli.tag_value.is_none() {
+        return Err(fail(
+            ErrorKind::ArgumentConflict,
+            "--show-credits can't be combined with --tag-key without --tag-value: Cost Explorer groups by at most two dimensions",
+        ));
+    }
+    if let Some(metrics) = &cli.compare_metrics {
+        if metrics.len() != 2 || metrics[0] == metrics[1] {
+            return Err(fail(ErrorKind::InvalidValue, "--compare-metrics takes two different metrics, e.g. unblended,blended"));
+        }
+    }
+    if cli.compare_threshold < 0.0 {
+        return Err(fail(ErrorKind::ValueValidation, "--compare-threshold must not be negative"));
+    }
+    if cli.unit_rates.is_some() && cli.preset.is_some() {
+        return Err(fail(
+            ErrorKind::ArgumentConflict,
+            "--unit-rates can't be combined with --preset, whose filter leaves out most usage types",
+        ));
+    }
+    if cli.unit_rates.as_ref().is_some_and(|usage_types| usage_types.iter().any(|usage_type| usage_type.trim().is_empty())) {
+        return Err(fail(ErrorKind::InvalidValue, "--unit-rates takes a comma-separated list of usage types"));
+    }
+    if cli.unit_rate_threshold < 0.0 {
+        return Err(fail(ErrorKind::ValueValidation, "--unit-rate-threshold must not be negative"));
+    }
+    if cli.free_tier_threshold < 0.0 {
+        return Err(fail(ErrorKind::ValueValidation, "--free-tier-threshold must not be negative"));
+    }
+    if cli.burn_rate_threshold < 0.0 {
+        return Err(fail(ErrorKind::ValueValidation, "--burn-rate-threshold must not be negative"));
+    }
+    if cli.composition_threshold < 0.0 {
+        return Err(fail(ErrorKind::ValueValidation, "--composition-threshold must not be negative"));
+    }
+    if cli.per_day && cli.granularity != GranularityOption::Monthly {
+        return Err(fail(
+            ErrorKind::ArgumentConflict,
+            &format!("--per-day only applies to --granularity monthly, not {}", cli.granularity.name()),
+        ));
+    }
+    if !(0.0..=100.0).contains(&cli.weekend_target_percent) {
+        return Err(fail(ErrorKind::ValueValidation, "--weekend-target-percent must be between 0 and 100"));
+    }
+    if [cli.alert_threshold, cli.account_alert_threshold].into_iter().flatten().any(|threshold| threshold < 0.0) {
+        return Err(fail(ErrorKind::ValueValidation, "--alert-threshold and --account-alert-threshold must not be negative"));
+    }
+    if let Some(format) = cli.date_format.as_deref().filter(|format| *format != "iso") {
+        if chrono::format::StrftimeItems::new(format).any(|item| item == chrono::format::Item::Error) {
+            return Err(fail(ErrorKind::ValueValidation, &format!("--date-format '{}' is not a valid strftime pattern", format)));
+        }
+    }
+    if cli.sns_profile.is_some() && cli.sns_topic_arn.is_none() {
+        return Err(fail(ErrorKind::MissingRequiredArgument, "--sns-profile requires --sns-topic-arn"));
+    }
+    if cli.history_profile.is_some() && cli.dynamodb_table.is_none() {
+        return Err(fail(ErrorKind::MissingRequiredArgument, "--history-profile requires --dynamodb-table"));
+    }
+    if cli.chart && cli.json && cli.chart_dir.is_none() {
+        return Err(fail(
+            ErrorKind::MissingRequiredArgument,
+            "--chart with --json requires --chart-dir: the JSON output doesn't say where the chart PNGs are written",
+        ));
+    }
+    let has_s3_target = [cli.csv.as_deref(), cli.json_out.as_deref(), cli.chart_dir.as_deref()]
+        .into_iter()
+        .flatten()
+        .any(crate::upload::is_s3_uri);
+    if !has_s3_target {
+        if cli.upload_profile.is_some() {
+            return Err(fail(ErrorKind::MissingRequiredArgument, "--upload-profile requires an s3:// --csv, --json-out or --chart-dir target"));
+        }
+        if cli.sse_kms_key_id.is_some() {
+            return Err(fail(ErrorKind::MissingRequiredArgument, "--sse-kms-key-id requires an s3:// --csv, --json-out or --chart-dir target"));
+        }
+    }
+    if let (Some(included), Some(excluded)) = (&cli.account_id, &cli.exclude_accounts) {
+        if let Some(id) = included.iter().find(|id| excluded.contains(id)) {
+            return Err(fail(ErrorKind::ArgumentConflict, &format!("Account {} is given to both --account-id and --exclude-accounts", id)));
+        }
+    }
+    Ok(())
+}
+
+fn fail(kind: ErrorKind, message: &str) -> clap::Error {
+    Cli::command().error(kind, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::Preset;
+    use crate::tests::cli;
+
+    const RANGE: [&str; 4] = ["--start-date", "2025-01-01", "--end-date", "2025-04-01"];
+
+    fn parse(args: &[&str]) -> Cli {
+        cli(&RANGE.iter().chain(args).copied().collect::<Vec<_>>())
+    }
+
+    /// Asserts that `cli` fails validation with `kind` and a message containing `expected`.
+    fn assert_rejected(cli: &Cli, kind: ErrorKind, expected: &str) {
+        let error = validate(cli).unwrap_err();
+        let message = error.render().to_string();
+        assert_eq!(error.kind(), kind, "{}", message);
+        assert!(message.contains(expected), "{}", message);
+    }
+
+    #[test]
+    fn plain_report_is_valid() {
+        assert!(validate(&parse(&[])).is_ok());
+        assert!(validate(&parse(&["--tag-key", "team", "--tag-value", "data"])).is_ok());
+    }
+
+    #[test]
+    fn tag_value_requires_tag_key() {
+        assert_rejected(&parse(&["--tag-value", "prod"]), ErrorKind::MissingRequiredArgument, "--tag-value requires --tag-key");
+    }
+
+    #[test]
+    fn fallback_granularity_only_applies_to_hourly_queries() {
+        assert_rejected(&parse(&["--fallback-granularity", "daily"]), ErrorKind::ArgumentConflict, "only applies to hourly queries");
+        assert!(validate(&parse(&["--granularity", "hourly", "--fallback-granularity", "daily"])).is_ok());
+        assert_rejected(
+            &parse(&["--granularity", "hourly", "--fallback-granularity", "hourly"]),
+            ErrorKind::InvalidValue,
+            "other than hourly",
+        );
+    }
+
+    #[test]
+    fn cur_source_and_cur_path_go_together() {
+        assert_rejected(&parse(&["--source", "cur"]), ErrorKind::MissingRequiredArgument, "--source cur requires --cur-path");
+        assert_rejected(&parse(&["--cur-path", "cur/"]), ErrorKind::ArgumentConflict, "--cur-path only applies to --source cur");
+        assert!(validate(&parse(&["--source", "cur", "--cur-path", "cur/"])).is_ok());
+    }
+
+    #[test]
+    fn athena_source_needs_all_of_its_flags() {
+        let athena = ["--athena-database", "cur", "--athena-table", "cur_report", "--athena-output", "s3://results/prefix"];
+        assert_rejected(&parse(&["--source", "athena", "--athena-database", "cur"]), ErrorKind::MissingRequiredArgument, "requires --athena-database");
+        assert_rejected(&parse(&athena), ErrorKind::ArgumentConflict, "only apply to --source athena");
+        assert!(validate(&parse(&[&["--source", "athena"][..], &athena].concat())).is_ok());
+        let local_output = ["--source", "athena", "--athena-database", "cur", "--athena-table", "cur_report", "--athena-output", "results/"];
+        assert_rejected(&parse(&local_output), ErrorKind::InvalidValue, "--athena-output must be an s3://bucket/prefix");
+    }
+
+    #[test]
+    fn report_sources_other_than_cost_explorer_reject_its_features() {
+        for flags in [&["--unit-rates", "NatGateway-Bytes"][..], &["--amortization-delta"], &["--compare-metrics", "unblended,blended"], &["--cache-dir", "cache"]] {
+            let cli = parse(&[&["--source", "cur", "--cur-path", "cur/"][..], flags].concat());
+            assert_rejected(&cli, ErrorKind::ArgumentConflict, "--source cur can't be combined with");
+        }
+        let mut cli = parse(&["--source", "cur", "--cur-path", "cur/"]);
+        cli.preset = Some(Preset::Ec2);
+        assert_rejected(&cli, ErrorKind::ArgumentConflict, "which need Cost Explorer");
+    }
+
+    #[test]
+    fn show_credits_needs_a_tag_value_with_a_tag_key() {
+        assert_rejected(&parse(&["--show-credits", "--tag-key", "team"]), ErrorKind::ArgumentConflict, "at most two dimensions");
+        assert!(validate(&parse(&["--show-credits", "--tag-key", "team", "--tag-value", "data"])).is_ok());
+    }
+
+    #[test]
+    fn compare_metrics_takes_two_different_metrics() {
+        for metrics in ["unblended", "unblended,unblended", "unblended,blended,amortized"] {
+            assert_rejected(&parse(&["--compare-metrics", metrics]), ErrorKind::InvalidValue, "two different metrics");
+        }
+    }
+
+    #[test]
+    fn unit_rates_reject_a_preset_and_empty_usage_types() {
+        let mut cli = parse(&["--unit-rates", "NatGateway-Bytes"]);
+        cli.preset = Some(Preset::DataTransfer);
+        assert_rejected(&cli, ErrorKind::ArgumentConflict, "--unit-rates can't be combined with --preset");
+        assert_rejected(&parse(&["--unit-rates", "NatGateway-Bytes, "]), ErrorKind::InvalidValue, "comma-separated list of usage types");
+    }
+
+    #[test]
+    fn thresholds_must_not_be_negative() {
+        for flag in [
+            "--compare-threshold",
+            "--unit-rate-threshold",
+            "--free-tier-threshold",
+            "--burn-rate-threshold",
+            "--composition-threshold",
+            "--alert-threshold",
+            "--account-alert-threshold",
+        ] {
+            let error = validate(&parse(&[&format!("{}=-1", flag)])).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::ValueValidation, "{}", flag);
+            assert!(error.render().to_string().contains("must not be negative"), "{}", flag);
+        }
+    }
+
+    #[test]
+    fn per_day_only_applies_to_monthly_reports() {
+        assert_rejected(&parse(&["--per-day", "--granularity", "daily"]), ErrorKind::ArgumentConflict, "--per-day only applies to --granularity monthly, not daily");
+    }
+
+    #[test]
+    fn weekend_target_percent_is_a_percentage() {
+        for percent in ["-1", "101"] {
+            assert_rejected(&parse(&[&format!("--weekend-target-percent={}", percent)]), ErrorKind::ValueValidation, "between 0 and 100");
+        }
+    }
+
+    #[test]
+    fn date_format_must_be_a_strftime_pattern() {
+        assert_rejected(&parse(&["--date-format", "%Q"]), ErrorKind::ValueValidation, "'%Q' is not a valid strftime pattern");
+        assert!(validate(&parse(&["--date-format", "iso"])).is_ok());
+        assert!(validate(&parse(&["--date-format", "%Y/%m"])).is_ok());
+    }
+
+    #[test]
+    fn notification_profiles_require_their_targets() {
+        assert_rejected(&parse(&["--sns-profile", "ops"]), ErrorKind::MissingRequiredArgument, "--sns-profile requires --sns-topic-arn");
+        assert_rejected(&parse(&["--history-profile", "ops"]), ErrorKind::MissingRequiredArgument, "--history-profile requires --dynamodb-table");
+    }
+
+    #[test]
+    fn chart_with_json_requires_a_chart_dir() {
+        assert_rejected(&parse(&["--chart", "--json"]), ErrorKind::MissingRequiredArgument, "--chart with --json requires --chart-dir");
+        assert!(validate(&parse(&["--chart", "--json", "--chart-dir", "charts"])).is_ok());
+        assert!(validate(&parse(&["--chart"])).is_ok());
+    }
+
+    #[test]
+    fn upload_options_require_an_s3_target() {
+        assert_rejected(&parse(&["--upload-profile", "ops", "--csv", "report.csv"]), ErrorKind::MissingRequiredArgument, "--upload-profile requires an s3://");
+        assert_rejected(&parse(&["--sse-kms-key-id", "key"]), ErrorKind::MissingRequiredArgument, "--sse-kms-key-id requires an s3://");
+        assert!(validate(&parse(&["--upload-profile", "ops", "--json-out", "s3://bucket/report.json"])).is_ok());
+    }
+
+    #[test]
+    fn an_account_cant_be_both_included_and_excluded() {
+        assert_rejected(
+            &parse(&["--account-id", "111111111111,222222222222", "--exclude-accounts", "222222222222"]),
+            ErrorKind::ArgumentConflict,
+            "Account 222222222222 is given to both --account-id and --exclude-accounts",
+        );
+    }
+}
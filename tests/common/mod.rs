@@ -0,0 +1,198 @@
+//! A canned STS, Organizations and Cost Explorer endpoint for running the binary end to end with
+//! `--endpoint-url` and `--static-credentials`, and the profile files it runs with.
+
+// Each test file uses only some of the helpers
+#![allow(dead_code)]
+
+use chrono::{Datelike, Months, NaiveDate};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::{Arc, Mutex};
+
+pub const MANAGEMENT_ACCOUNT: &str = "111111111111";
+pub const MEMBER_ACCOUNT: &str = "222222222222";
+
+/// Profiles in this region fail the credential check, as if their keys had been revoked.
+pub const REVOKED_REGION: &str = "eu-west-1";
+
+/// Monthly cost of each service in each account, for every month of the requested range.
+pub const SERVICE_COSTS: &[(&str, &str, f64)] = &[
+    (MANAGEMENT_ACCOUNT, "Amazon Elastic Compute Cloud - Compute", 100.0),
+    (MANAGEMENT_ACCOUNT, "Amazon Simple Storage Service", 10.0),
+    (MEMBER_ACCOUNT, "Amazon Elastic Compute Cloud - Compute", 50.0),
+];
+
+/// A mock AWS endpoint on a local port, serving each connection on its own thread.
+pub struct MockAws {
+    pub url: String,
+    /// `X-Amz-Target` (or STS `Action`) of every request received.
+    pub operations: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockAws {
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let operations = Arc::new(Mutex::new(Vec::new()));
+        let recorded = operations.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let recorded = recorded.clone();
+                std::thread::spawn(move || serve(stream, &recorded));
+            }
+        });
+        MockAws { url, operations }
+    }
+
+    pub fn count(&self, operation: &str) -> usize {
+        self.operations.lock().unwrap().iter().filter(|received| received.as_str() == operation).count()
+    }
+}
+
+fn serve(stream: TcpStream, operations: &Mutex<Vec<String>>) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut stream = stream;
+    // The SDK keeps connections alive, so serve requests until the client hangs up
+    loop {
+        let mut headers = Vec::new();
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                return;
+            }
+            if line == "\r\n" {
+                break;
+            }
+            headers.push(line.trim_end().to_string());
+        }
+        let header = |name: &str| {
+            headers.iter().find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                key.eq_ignore_ascii_case(name).then(|| value.trim().to_string())
+            })
+        };
+        let length: usize = header("content-length").and_then(|length| length.parse().ok()).unwrap_or(0);
+        let mut body = vec![0; length];
+        if reader.read_exact(&mut body).is_err() {
+            return;
+        }
+        let body = String::from_utf8_lossy(&body).to_string();
+        let authorization = header("authorization").unwrap_or_default();
+        let (operation, status, content_type, response) = match header("x-amz-target") {
+            Some(target) => {
+                let (status, response) = json_response(&target, &body);
+                (target, status, "application/x-amz-json-1.1", response)
+            }
+            None => {
+                let (status, response) = sts_response(&body, &authorization);
+                ("STS.GetCallerIdentity".to_string(), status, "text/xml", response)
+            }
+        };
+        operations.lock().unwrap().push(operation);
+        let reply = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nx-amzn-RequestId: mock\r\n\r\n{}",
+            status,
+            content_type,
+            response.len(),
+            response
+        );
+        if stream.write_all(reply.as_bytes()).is_err() {
+            return;
+        }
+    }
+}
+
+fn sts_response(body: &str, authorization: &str) -> (&'static str, String) {
+    if !body.contains("Action=GetCallerIdentity") {
+        return ("400 Bad Request", format!("<ErrorResponse><Error><Code>InvalidAction</Code><Message>{}</Message></Error></ErrorResponse>", body));
+    }
+    if authorization.contains(&format!("/{}/", REVOKED_REGION)) {
+        let error = "<ErrorResponse><Error><Type>Sender</Type><Code>InvalidClientTokenId</Code><Message>The security token included in the request is invalid.</Message></Error><RequestId>mock</RequestId></ErrorResponse>";
+        return ("403 Forbidden", error.to_string());
+    }
+    let identity = format!(
+        "<GetCallerIdentityResponse><GetCallerIdentityResult><Arn>arn:aws:iam::{0}:user/ci</Arn><UserId>AIDAMOCK</UserId><Account>{0}</Account></GetCallerIdentityResult><ResponseMetadata><RequestId>mock</RequestId></ResponseMetadata></GetCallerIdentityResponse>",
+        MANAGEMENT_ACCOUNT
+    );
+    ("200 OK", identity)
+}
+
+fn json_response(target: &str, body: &str) -> (&'static str, String) {
+    let request: Value = serde_json::from_str(body).unwrap_or(Value::Null);
+    let response = match target.rsplit('.').next().unwrap_or_default() {
+        "ListAccounts" => json!({
+            "Accounts": [
+                { "Id": MANAGEMENT_ACCOUNT, "Name": "Management", "Status": "ACTIVE", "Arn": format!("arn:aws:organizations::{0}:account/o-mock/{0}", MANAGEMENT_ACCOUNT) },
+                { "Id": MEMBER_ACCOUNT, "Name": "Workloads", "Status": "ACTIVE", "Arn": format!("arn:aws:organizations::{0}:account/o-mock/{1}", MANAGEMENT_ACCOUNT, MEMBER_ACCOUNT) },
+            ]
+        }),
+        "GetCostAndUsage" => cost_and_usage(&request),
+        _ => {
+            let error = json!({ "__type": "UnknownOperationException", "message": format!("mock doesn't implement {}", target) });
+            return ("400 Bad Request", error.to_string());
+        }
+    };
+    ("200 OK", response.to_string())
+}
+
+/// `SERVICE_COSTS` of the filtered account for every month of the range; other groupings are empty.
+fn cost_and_usage(request: &Value) -> Value {
+    let date = |key: &str| NaiveDate::parse_from_str(request["TimePeriod"][key].as_str().unwrap_or_default(), "%Y-%m-%d").unwrap();
+    let (start, end) = (date("Start"), date("End"));
+    let grouped_by_service = request["GroupBy"].as_array().is_some_and(|groups| groups.iter().any(|group| group["Key"] == "SERVICE"));
+    let filter = request["Filter"].to_string();
+    let account = [MANAGEMENT_ACCOUNT, MEMBER_ACCOUNT].into_iter().find(|account| filter.contains(account));
+    let mut results = Vec::new();
+    let mut month = start.with_day(1).unwrap();
+    while month < end {
+        let next = month + Months::new(1);
+        let groups: Vec<Value> = SERVICE_COSTS
+            .iter()
+            .filter(|(owner, _, _)| grouped_by_service && Some(*owner) == account)
+            .map(|(_, service, cost)| json!({ "Keys": [service], "Metrics": { "UnblendedCost": { "Amount": cost.to_string(), "Unit": "USD" } } }))
+            .collect();
+        results.push(json!({
+            "TimePeriod": { "Start": month.max(start).to_string(), "End": next.min(end).to_string() },
+            "Total": {},
+            "Groups": groups,
+            "Estimated": false,
+        }));
+        month = next;
+    }
+    json!({ "ResultsByTime": results, "DimensionValueAttributes": [] })
+}
+
+/// A home directory whose `~/.aws/config` has a profile for each `(name, region)`.
+pub fn home_with_profiles(name: &str, profiles: &[(&str, &str)]) -> PathBuf {
+    let home = std::env::temp_dir().join(format!("aws-cost-cli-it-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&home);
+    std::fs::create_dir_all(home.join(".aws")).unwrap();
+    let config: String = profiles.iter().map(|(profile, region)| format!("[profile {}]\nregion = {}\n\n", profile, region)).collect();
+    std::fs::write(home.join(".aws").join("config"), config).unwrap();
+    home
+}
+
+/// The binary with `home` as the home directory and no AWS settings inherited from the environment.
+pub fn cli_command(home: &Path, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_aws-cost-cli"));
+    for (variable, _) in std::env::vars() {
+        if variable.starts_with("AWS_") {
+            command.env_remove(variable);
+        }
+    }
+    command.env("HOME", home).env("USERPROFILE", home).env("AWS_EC2_METADATA_DISABLED", "true").args(args);
+    command
+}
+
+/// Runs the binary as `cli_command` sets it up and waits for it to exit.
+pub fn run_cli(home: &Path, args: &[&str]) -> Output {
+    cli_command(home, args).output().unwrap()
+}
+
+/// Arguments pointing every client at `mock` with dummy credentials.
+pub fn mock_args<'a>(mock: &'a MockAws, profiles: &'a str) -> Vec<&'a str> {
+    vec!["--profiles", profiles, "--endpoint-url", &mock.url, "--static-credentials", "--no-pager", "--no-alias-lookup"]
+}
@@ -0,0 +1,92 @@
+#![cfg(feature = "integration")]
+
+mod common;
+
+use common::{home_with_profiles, mock_args, run_cli, MockAws, REVOKED_REGION};
+use std::process::Output;
+
+const RANGE: [&str; 4] = ["--start-date", "2024-01-01", "--end-date", "2024-04-01"];
+
+fn assert_exit(output: &Output, code: i32) {
+    assert_eq!(output.status.code(), Some(code), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn complete_report_exits_0() {
+    let mock = MockAws::start();
+    let home = home_with_profiles("exit-success", &[("management", "us-east-1")]);
+    assert_exit(&run_cli(&home, &[&mock_args(&mock, "management")[..], &RANGE, &["--json"]].concat()), 0);
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn invalid_arguments_exit_5_without_requests() {
+    let mock = MockAws::start();
+    let home = home_with_profiles("exit-invalid", &[("management", "us-east-1")]);
+    let args = [&mock_args(&mock, "management")[..], &["--start-date", "2024-13-01", "--end-date", "2024-04-01"]].concat();
+    assert_exit(&run_cli(&home, &args), 5);
+    assert_exit(&run_cli(&home, &[&mock_args(&mock, "management")[..], &["--granularity", "yearly"]].concat()), 5);
+    assert!(mock.operations.lock().unwrap().is_empty());
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn check_exits_0_when_every_profile_passes() {
+    let mock = MockAws::start();
+    let home = home_with_profiles("exit-check-ok", &[("management", "us-east-1"), ("audit", "us-east-2")]);
+    assert_exit(&run_cli(&home, &[&mock_args(&mock, "management,audit")[..], &["--check"]].concat()), 0);
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn check_exits_4_when_some_profiles_fail() {
+    let mock = MockAws::start();
+    let home = home_with_profiles("exit-check-partial", &[("management", "us-east-1"), ("revoked", REVOKED_REGION)]);
+    let output = run_cli(&home, &[&mock_args(&mock, "management,revoked")[..], &["--check"]].concat());
+    assert_exit(&output, 4);
+    // Only the credential check is run
+    assert!(mock.operations.lock().unwrap().iter().all(|operation| operation == "STS.GetCallerIdentity"));
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn check_exits_6_when_every_profile_fails() {
+    let mock = MockAws::start();
+    let home = home_with_profiles("exit-check-failed", &[("revoked", REVOKED_REGION)]);
+    assert_exit(&run_cli(&home, &[&mock_args(&mock, "revoked")[..], &["--check"]].concat()), 6);
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn report_exits_4_when_some_profiles_fail() {
+    let mock = MockAws::start();
+    let home = home_with_profiles("exit-report-partial", &[("management", "us-east-1"), ("revoked", REVOKED_REGION)]);
+    assert_exit(&run_cli(&home, &[&mock_args(&mock, "management,revoked")[..], &RANGE, &["--json"]].concat()), 4);
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn failed_json_runs_print_an_incomplete_document_with_metadata() {
+    let mock = MockAws::start();
+    let home = home_with_profiles("exit-json-failed", &[("revoked", REVOKED_REGION)]);
+    let output = run_cli(&home, &[&mock_args(&mock, "revoked")[..], &RANGE, &["--json"]].concat());
+    assert_exit(&output, 6);
+
+    let document: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(document["complete"], false);
+    assert_eq!(document["metadata"]["start_date"], "2024-01-01");
+    assert_eq!(document["metadata"]["end_date"], "2024-04-01");
+    assert_eq!(document["metadata"]["profiles"], serde_json::json!(["revoked"]));
+    let phases: Vec<&str> = document["errors"].as_array().unwrap().iter().map(|error| error["phase"].as_str().unwrap()).collect();
+    assert_eq!(phases, ["credential_check", "credential_check"]);
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn report_exits_6_when_every_profile_fails() {
+    let mock = MockAws::start();
+    let home = home_with_profiles("exit-report-failed", &[("revoked", REVOKED_REGION)]);
+    assert_exit(&run_cli(&home, &[&mock_args(&mock, "revoked")[..], &RANGE].concat()), 6);
+    assert_eq!(mock.count("AWSInsightsIndexService.GetCostAndUsage"), 0);
+    std::fs::remove_dir_all(&home).unwrap();
+}
@@ -0,0 +1,44 @@
+#![cfg(feature = "integration")]
+
+mod common;
+
+use common::{home_with_profiles, mock_args, run_cli, MockAws, MANAGEMENT_ACCOUNT, REVOKED_REGION};
+use serde_json::Value;
+
+const RANGE: [&str; 4] = ["--start-date", "2024-01-01", "--end-date", "2024-04-01"];
+
+#[test]
+fn dry_run_lists_the_queries_without_making_them() {
+    let mock = MockAws::start();
+    let home = home_with_profiles("plan", &[("management", "us-east-1")]);
+    let output = run_cli(&home, &[&mock_args(&mock, "management")[..], &RANGE, &["--dry-run", "--json"]].concat());
+
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    let plan: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(plan["profiles"][0]["accounts"].as_array().unwrap().len(), 2);
+    assert_eq!(plan["profiles"][0]["accounts"][0]["account_id"], MANAGEMENT_ACCOUNT);
+    assert_eq!(plan["errors"], Value::Array(Vec::new()));
+    assert_eq!(mock.count("AWSInsightsIndexService.GetCostAndUsage"), 0);
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn dry_run_reports_profiles_that_failed_the_credential_check() {
+    let mock = MockAws::start();
+    let home = home_with_profiles("plan-partial", &[("management", "us-east-1"), ("revoked", REVOKED_REGION)]);
+    // --plan is an alias of --dry-run
+    let args = [&mock_args(&mock, "management,revoked")[..], &RANGE, &["--plan"]].concat();
+
+    let output = run_cli(&home, &[&args[..], &["--json"]].concat());
+    assert_eq!(output.status.code(), Some(4), "{}", String::from_utf8_lossy(&output.stderr));
+    let plan: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(plan["profiles"].as_array().unwrap().len(), 1);
+    assert_eq!(plan["errors"][0]["profile"], "revoked");
+    assert_eq!(plan["errors"][0]["phase"], "credential_check");
+
+    let output = run_cli(&home, &args);
+    assert_eq!(output.status.code(), Some(4));
+    let text = String::from_utf8_lossy(&output.stdout);
+    assert!(text.contains("Not planned:\n  profile revoked (credential_check): InvalidClientTokenId"), "{}", text);
+    std::fs::remove_dir_all(&home).unwrap();
+}
@@ -0,0 +1,69 @@
+#![cfg(feature = "integration")]
+
+mod common;
+
+use common::{home_with_profiles, mock_args, run_cli, MockAws, MANAGEMENT_ACCOUNT, MEMBER_ACCOUNT};
+use serde_json::Value;
+
+const RANGE: [&str; 4] = ["--start-date", "2024-01-01", "--end-date", "2024-04-01"];
+
+#[test]
+fn json_report_covers_every_account_of_the_organization() {
+    let mock = MockAws::start();
+    let home = home_with_profiles("json-report", &[("management", "us-east-1")]);
+    let output = run_cli(&home, &[&mock_args(&mock, "management")[..], &RANGE, &["--json"]].concat());
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["complete"], true);
+    let accounts = report["accounts"].as_array().unwrap();
+    let totals: Vec<(&str, f64)> = accounts.iter().map(|account| (account["account_id"].as_str().unwrap(), account["total_cost"].as_f64().unwrap())).collect();
+    assert_eq!(totals, [(MANAGEMENT_ACCOUNT, 330.0), (MEMBER_ACCOUNT, 150.0)]);
+    assert_eq!(accounts[0]["account_name"], "Management");
+    assert_eq!(accounts[0]["cost_trend"].as_array().unwrap().len(), 3);
+    assert_eq!(mock.count("STS.GetCallerIdentity"), 1);
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn csv_exports_match_the_mocked_costs() {
+    let mock = MockAws::start();
+    let home = home_with_profiles("csv-report", &[("management", "us-east-1")]);
+    let base = home.join("report");
+    let output = run_cli(&home, &[&mock_args(&mock, "management")[..], &RANGE, &["--csv", base.to_str().unwrap()]].concat());
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let unified = std::fs::read_to_string(home.join("report_unified_view.csv")).unwrap();
+    let total = unified.lines().last().unwrap();
+    assert!(total.starts_with("TOTAL,") && total.ends_with(",480.00"), "{}", unified);
+    let summary = home.join(format!("report_service_summary_profile_management_account_{}.csv", MANAGEMENT_ACCOUNT));
+    assert!(std::fs::read_to_string(summary).unwrap().contains("Amazon Simple Storage Service,10.00,10.00,10.00,30.00"));
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn accounts_reached_through_two_profiles_are_reported_once() {
+    let mock = MockAws::start();
+    let home = home_with_profiles("overlap", &[("management", "us-east-1"), ("audit", "us-east-2")]);
+    for extra in [&[][..], &["--account-id", MEMBER_ACCOUNT]] {
+        let output = run_cli(&home, &[&mock_args(&mock, "management,audit")[..], &RANGE, &["--json"], extra].concat());
+
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+        let accounts: Vec<(&str, &str)> = report["accounts"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|account| (account["profile"].as_str().unwrap(), account["account_id"].as_str().unwrap()))
+            .collect();
+        let costs: f64 = report["accounts"].as_array().unwrap().iter().map(|account| account["total_cost"].as_f64().unwrap()).sum();
+        if extra.is_empty() {
+            assert_eq!(accounts, [("management", MANAGEMENT_ACCOUNT), ("management", MEMBER_ACCOUNT)]);
+            assert_eq!(costs, 480.0);
+        } else {
+            assert_eq!(accounts, [("management", MEMBER_ACCOUNT)]);
+            assert_eq!(costs, 150.0);
+        }
+    }
+    std::fs::remove_dir_all(&home).unwrap();
+}
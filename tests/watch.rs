@@ -0,0 +1,58 @@
+#![cfg(all(feature = "integration", unix))]
+
+mod common;
+
+use common::{cli_command, home_with_profiles, mock_args, MockAws};
+use std::process::{Output, Stdio};
+use std::time::{Duration, Instant};
+
+const RANGE: [&str; 4] = ["--start-date", "2024-01-01", "--end-date", "2024-04-01"];
+const COST_QUERY: &str = "AWSInsightsIndexService.GetCostAndUsage";
+const LIST_ACCOUNTS: &str = "AWSOrganizationsV20161128.ListAccounts";
+
+/// Runs `--watch 1s` until `refreshes` refreshes have been written after the first report, then stops it with Ctrl-C.
+fn watch(mock: &MockAws, name: &str, extra: &[&str], refreshes: usize) -> Output {
+    let home = home_with_profiles(name, &[("management", "us-east-1")]);
+    let args = [&mock_args(mock, "management")[..], &RANGE, &["--watch", "1s", "--json"], extra].concat();
+    let child = cli_command(&home, &args).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().unwrap();
+    let deadline = Instant::now() + Duration::from_secs(30);
+    // The next refresh's discovery starts once the previous report is written
+    while mock.count(LIST_ACCOUNTS) < refreshes + 2 && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    std::process::Command::new("kill").args(["-INT", &child.id().to_string()]).status().unwrap();
+    let pid = child.id();
+    let output = child.wait_with_output().unwrap();
+    // The temporary cache standing in for --cache-dir is removed on exit
+    assert!(!std::env::temp_dir().join(format!("aws-cost-cli-watch-{}", pid)).exists());
+    std::fs::remove_dir_all(&home).unwrap();
+    output
+}
+
+#[test]
+fn refreshes_read_settled_periods_from_the_cache() {
+    let mock = MockAws::start();
+    let output = watch(&mock, "watch-cached", &[], 2);
+
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    // Cost Explorer is only queried on the first run; refreshes take the months it returned from the cache
+    let operations = mock.operations.lock().unwrap().clone();
+    let mut refreshes = operations.iter().skip_while(|operation| *operation != LIST_ACCOUNTS).skip(1).skip_while(|operation| *operation == COST_QUERY);
+    assert!(mock.count(COST_QUERY) > 0);
+    assert!(refreshes.all(|operation| operation == LIST_ACCOUNTS), "{:?}", operations);
+    let reports = String::from_utf8_lossy(&output.stdout);
+    assert!(reports.matches("\"complete\": true").count() >= 3, "{}", reports);
+}
+
+#[test]
+fn refreshes_query_periods_that_can_still_change() {
+    let mock = MockAws::start();
+    // Every period counts as recent, so none is taken from the cache
+    let output = watch(&mock, "watch-recent", &["--cache-recent-days", "100000"], 2);
+
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    let operations = mock.operations.lock().unwrap().clone();
+    let runs: Vec<usize> = operations.split(|operation| operation == LIST_ACCOUNTS).map(|run| run.iter().filter(|operation| *operation == COST_QUERY).count()).collect();
+    // The first run and the first refresh query the same; the last refresh may have been interrupted
+    assert!(runs[1] > 0 && runs[1] == runs[2], "{:?}", operations);
+}